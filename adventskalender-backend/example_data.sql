@@ -1,15 +1,16 @@
--- add some example /demo users for the participants
-INSERT INTO participants VALUES (DEFAULT, 'First', 'User', NULL);
-INSERT INTO participants VALUES (DEFAULT, 'Second', 'User', NULL);
-INSERT INTO participants VALUES (DEFAULT, 'Third', 'User', NULL);
-INSERT INTO participants VALUES (DEFAULT, 'Fourth', 'User', NULL);
-INSERT INTO participants VALUES (DEFAULT, 'Fifth', 'User', NULL);
-INSERT INTO participants VALUES (DEFAULT, 'Sixth', 'User', NULL);
-INSERT INTO participants VALUES (DEFAULT, 'Seventh', 'User', NULL);
-INSERT INTO participants VALUES (DEFAULT, 'Eighth', 'User', NULL);
-INSERT INTO participants VALUES (DEFAULT, 'Nineth', 'User', NULL);
-INSERT INTO participants VALUES (DEFAULT, 'Tenth', 'User', NULL);
-INSERT INTO participants VALUES (DEFAULT, 'Won', 'User', '2021-11-01');
+-- add some example /demo users for the participants (to the default raffle created by the migrations)
+INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('First', 'User', 1);
+INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Second', 'User', 1);
+INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Third', 'User', 1);
+INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Fourth', 'User', 1);
+INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Fifth', 'User', 1);
+INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Sixth', 'User', 1);
+INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Seventh', 'User', 1);
+INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Eighth', 'User', 1);
+INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Nineth', 'User', 1);
+INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Tenth', 'User', 1);
+INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Won', 'User', 1);
+INSERT INTO wins (participant_id, won_on) SELECT id, '2021-11-01' FROM participants WHERE first_name = 'Won' AND last_name = 'User';
 
 -- insert the demo users for authentication
 INSERT INTO users VALUES (DEFAULT, 'demouser', '$2y$10$biaBP9HfkEp7oS.6Z6zOdOFTWFhO6hLQF2dXX3o0c9azOtE5sAzsW'); -- demopassword
\ No newline at end of file
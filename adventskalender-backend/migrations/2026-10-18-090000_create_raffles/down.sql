@@ -0,0 +1,23 @@
+-- participants of different raffles may share a name, so just keep one of them (preferably the winner) before the
+-- names have to be globally unique again
+DELETE
+FROM participants
+WHERE id IN (SELECT id
+             FROM (SELECT id,
+                          ROW_NUMBER() OVER (
+                              PARTITION BY first_name, last_name
+                              ORDER BY won_on IS NULL, id
+                              ) AS position
+                   FROM participants) AS numbered
+             WHERE position > 1);
+
+-- the names of the participants have to be globally unique again
+DROP INDEX participants_unique_name;
+CREATE UNIQUE INDEX participants_unique_name ON participants (first_name, last_name);
+
+-- remove the association of the participants with the raffles
+ALTER TABLE participants
+    DROP COLUMN raffle_id;
+
+-- and finally remove the raffles
+DROP TABLE raffles;
@@ -0,0 +1,36 @@
+-- the table which holds the raffles (e.g. one per year or per department)
+CREATE TABLE raffles
+(
+    id         SERIAL PRIMARY KEY,
+    name       VARCHAR(64) NOT NULL,
+    start_date DATE        NOT NULL,
+    end_date   DATE        NOT NULL,
+    archived   BOOLEAN     NOT NULL DEFAULT FALSE,
+    CONSTRAINT raffles_valid_date_range CHECK (start_date <= end_date)
+);
+
+-- the name of a raffle should always be unique
+CREATE UNIQUE INDEX raffles_unique_name ON raffles (name);
+
+-- all existing participants and winners are moved into a default raffle which covers the advent
+-- days of the year the existing winners were picked in (or the current year if nobody won so far)
+INSERT INTO raffles (name, start_date, end_date)
+SELECT 'Default raffle',
+       LEAST(make_date(raffle_year, 12, 1), COALESCE(first_win, make_date(raffle_year, 12, 1))),
+       GREATEST(make_date(raffle_year, 12, 24), COALESCE(last_win, make_date(raffle_year, 12, 24)))
+FROM (SELECT EXTRACT(YEAR FROM COALESCE(MAX(won_on), CURRENT_DATE))::INT AS raffle_year,
+             MIN(won_on)                                                  AS first_win,
+             MAX(won_on)                                                  AS last_win
+      FROM participants) AS existing_winners;
+
+-- every participant belongs to exactly one raffle
+ALTER TABLE participants
+    ADD COLUMN raffle_id INT4 REFERENCES raffles (id) ON DELETE CASCADE;
+UPDATE participants
+SET raffle_id = (SELECT id FROM raffles WHERE name = 'Default raffle');
+ALTER TABLE participants
+    ALTER COLUMN raffle_id SET NOT NULL;
+
+-- the names of the participants just have to be unique within a raffle
+DROP INDEX participants_unique_name;
+CREATE UNIQUE INDEX participants_unique_name ON participants (raffle_id, first_name, last_name);
@@ -0,0 +1,6 @@
+-- remove the calendar settings of the raffles again
+ALTER TABLE raffles
+    DROP CONSTRAINT raffles_positive_winners_per_day,
+    DROP COLUMN winners_per_day,
+    DROP COLUMN excluded_dates,
+    DROP COLUMN exclude_weekends;
@@ -0,0 +1,6 @@
+-- every raffle defines on which days winners can be picked and how many winners there are per day
+ALTER TABLE raffles
+    ADD COLUMN exclude_weekends BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN excluded_dates   DATE[]  NOT NULL DEFAULT '{}',
+    ADD COLUMN winners_per_day  INT4    NOT NULL DEFAULT 5,
+    ADD CONSTRAINT raffles_positive_winners_per_day CHECK (winners_per_day > 0);
@@ -0,0 +1,6 @@
+-- remove the link between the winners and their draws
+ALTER TABLE participants
+    DROP COLUMN draw_id;
+
+-- and the draws themselves
+DROP TABLE draws;
@@ -0,0 +1,18 @@
+-- every pick of winners is recorded as a draw which can be replayed to verify that it was fair
+CREATE TABLE draws
+(
+    id                SERIAL      PRIMARY KEY,
+    raffle_id         INT4        NOT NULL REFERENCES raffles (id) ON DELETE CASCADE,
+    drawn_for         DATE        NOT NULL,
+    drawn_at          TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    drawn_by          INT4        REFERENCES users (id),
+    algorithm_version INT4        NOT NULL,
+    seed              BYTEA       NOT NULL,
+    candidate_ids     INT4[]      NOT NULL,
+    candidates_hash   VARCHAR(64) NOT NULL,
+    winner_ids        INT4[]      NOT NULL
+);
+
+-- the winners are linked to the draw which selected them
+ALTER TABLE participants
+    ADD COLUMN draw_id INT4 REFERENCES draws (id) ON DELETE SET NULL;
@@ -0,0 +1,2 @@
+-- remove the commitments of the draws again
+DROP TABLE draw_commitments;
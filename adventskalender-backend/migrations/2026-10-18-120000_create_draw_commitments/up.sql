@@ -0,0 +1,15 @@
+-- commitments to the seed of a draw which are published before the draw is performed
+CREATE TABLE draw_commitments
+(
+    id              SERIAL      PRIMARY KEY,
+    raffle_id       INT4        NOT NULL REFERENCES raffles (id) ON DELETE CASCADE,
+    drawn_for       DATE        NOT NULL,
+    winner_count    INT4        NOT NULL CHECK (winner_count > 0),
+    seed            BYTEA       NOT NULL,
+    seed_hash       VARCHAR(64) NOT NULL,
+    candidates_hash VARCHAR(64) NOT NULL,
+    committed_at    TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    committed_by    INT4        REFERENCES users (id),
+    revealed_at     TIMESTAMP,
+    draw_id         INT4        REFERENCES draws (id) ON DELETE SET NULL
+);
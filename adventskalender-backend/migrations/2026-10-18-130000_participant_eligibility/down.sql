@@ -0,0 +1,22 @@
+-- remove the recorded inputs of the rule-based draws
+ALTER TABLE draws
+    DROP COLUMN rules,
+    DROP COLUMN group_capacities,
+    DROP COLUMN candidate_groups,
+    DROP COLUMN candidate_weights;
+
+-- remove the eligibility settings of the raffles
+ALTER TABLE raffles
+    DROP CONSTRAINT raffles_positive_max_winners_per_department,
+    DROP COLUMN max_winners_per_department,
+    DROP COLUMN use_tickets;
+
+-- remove the absences of the participants
+DROP TABLE participant_absences;
+
+-- and the eligibility settings of the participants
+ALTER TABLE participants
+    DROP CONSTRAINT participants_positive_tickets,
+    DROP COLUMN blocked,
+    DROP COLUMN department,
+    DROP COLUMN tickets;
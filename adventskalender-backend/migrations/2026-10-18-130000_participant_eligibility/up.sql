@@ -0,0 +1,31 @@
+-- participants can have more than one ticket, belong to a department and be blocked from winning
+ALTER TABLE participants
+    ADD COLUMN tickets    INT4        NOT NULL DEFAULT 1,
+    ADD COLUMN department VARCHAR(64),
+    ADD COLUMN blocked    BOOLEAN     NOT NULL DEFAULT FALSE,
+    ADD CONSTRAINT participants_positive_tickets CHECK (tickets > 0);
+
+-- participants who are absent cannot win on the days of their absence
+CREATE TABLE participant_absences
+(
+    id             SERIAL PRIMARY KEY,
+    participant_id INT4 NOT NULL REFERENCES participants (id) ON DELETE CASCADE,
+    start_date     DATE NOT NULL,
+    end_date       DATE NOT NULL,
+    CONSTRAINT participant_absences_valid_date_range CHECK (start_date <= end_date)
+);
+CREATE INDEX participant_absences_participant ON participant_absences (participant_id);
+
+-- every raffle defines if the tickets are used as weights and how many winners a department can
+-- have per day
+ALTER TABLE raffles
+    ADD COLUMN use_tickets                BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN max_winners_per_department INT4,
+    ADD CONSTRAINT raffles_positive_max_winners_per_department CHECK (max_winners_per_department > 0);
+
+-- the inputs of the rule-based draws (and the applied rules) are recorded with the draw
+ALTER TABLE draws
+    ADD COLUMN candidate_weights INT4[],
+    ADD COLUMN candidate_groups  INT4[],
+    ADD COLUMN group_capacities  INT4[],
+    ADD COLUMN rules             TEXT;
@@ -0,0 +1,2 @@
+-- remove the history of replaced winners again
+DROP TABLE winner_replacements;
@@ -0,0 +1,19 @@
+-- the history of winners who were replaced by a substitute (the original win is kept here)
+CREATE TABLE winner_replacements
+(
+    id                 SERIAL      PRIMARY KEY,
+    raffle_id          INT4        NOT NULL REFERENCES raffles (id) ON DELETE CASCADE,
+    participant_id     INT4        NOT NULL REFERENCES participants (id) ON DELETE CASCADE,
+    won_on             DATE        NOT NULL,
+    picked_by          INT4        REFERENCES users (id),
+    picking_time       TIMESTAMP,
+    present_identifier VARCHAR(1),
+    draw_id            INT4        REFERENCES draws (id) ON DELETE SET NULL,
+    reason             VARCHAR(16) NOT NULL CHECK (reason IN ('declined', 'absent', 'duplicate')),
+    replaced_at        TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    replaced_by        INT4        REFERENCES users (id),
+    substitute_id      INT4        REFERENCES participants (id) ON DELETE SET NULL,
+    substitute_draw_id INT4        REFERENCES draws (id) ON DELETE SET NULL
+);
+
+CREATE INDEX winner_replacements_participant_id ON winner_replacements (participant_id);
@@ -0,0 +1,54 @@
+-- restore the columns of the current winners on the participants
+ALTER TABLE participants
+    ADD COLUMN won_on             DATE DEFAULT NULL,
+    ADD COLUMN picked_by          INT4 REFERENCES users (id),
+    ADD COLUMN picking_time       TIMESTAMP,
+    ADD COLUMN present_identifier VARCHAR(1) DEFAULT NULL,
+    ADD COLUMN draw_id            INT4 REFERENCES draws (id) ON DELETE SET NULL;
+
+UPDATE participants
+SET won_on             = wins.won_on,
+    picked_by          = wins.picked_by,
+    picking_time       = wins.picked_at,
+    present_identifier = wins.present_identifier,
+    draw_id            = wins.draw_id
+FROM wins
+WHERE wins.participant_id = participants.id
+  AND wins.status = 'active';
+
+-- restore the copied values of the replaced wins
+ALTER TABLE winner_replacements
+    ADD COLUMN participant_id     INT4 REFERENCES participants (id) ON DELETE CASCADE,
+    ADD COLUMN won_on             DATE,
+    ADD COLUMN picked_by          INT4 REFERENCES users (id),
+    ADD COLUMN picking_time       TIMESTAMP,
+    ADD COLUMN present_identifier VARCHAR(1),
+    ADD COLUMN draw_id            INT4 REFERENCES draws (id) ON DELETE SET NULL,
+    ADD COLUMN substitute_id      INT4 REFERENCES participants (id) ON DELETE SET NULL,
+    ADD COLUMN substitute_draw_id INT4 REFERENCES draws (id) ON DELETE SET NULL;
+
+UPDATE winner_replacements
+SET participant_id     = wins.participant_id,
+    won_on             = wins.won_on,
+    picked_by          = wins.picked_by,
+    picking_time       = wins.picked_at,
+    present_identifier = wins.present_identifier,
+    draw_id            = wins.draw_id
+FROM wins
+WHERE wins.id = winner_replacements.win_id;
+
+UPDATE winner_replacements
+SET substitute_id      = wins.participant_id,
+    substitute_draw_id = wins.draw_id
+FROM wins
+WHERE wins.id = winner_replacements.substitute_win_id;
+
+ALTER TABLE winner_replacements
+    ALTER COLUMN participant_id SET NOT NULL,
+    ALTER COLUMN won_on SET NOT NULL,
+    DROP COLUMN win_id,
+    DROP COLUMN substitute_win_id;
+
+CREATE INDEX winner_replacements_participant_id ON winner_replacements (participant_id);
+
+DROP TABLE wins;
@@ -0,0 +1,65 @@
+-- every win of a participant is stored as its own row instead of overwriting the columns of the
+-- participant, so the history of the winners (and their presents) is kept
+CREATE TABLE wins
+(
+    id                 SERIAL      PRIMARY KEY,
+    participant_id     INT4        NOT NULL REFERENCES participants (id) ON DELETE CASCADE,
+    won_on             DATE        NOT NULL,
+    picked_by          INT4        REFERENCES users (id),
+    picked_at          TIMESTAMP,
+    present_identifier VARCHAR(1),
+    status             VARCHAR(16) NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'revoked', 'replaced', 'superseded')),
+    revoked_at         TIMESTAMP,
+    draw_id            INT4        REFERENCES draws (id) ON DELETE SET NULL,
+    CHECK ((status = 'active') = (revoked_at IS NULL))
+);
+
+-- a participant can just have a single active win
+CREATE UNIQUE INDEX wins_active_participant_id ON wins (participant_id) WHERE status = 'active';
+CREATE INDEX wins_won_on ON wins (won_on);
+
+-- migrate the current winners and the winners which were replaced so far
+INSERT INTO wins (participant_id, won_on, picked_by, picked_at, present_identifier, draw_id)
+SELECT id, won_on, picked_by, picking_time, present_identifier, draw_id
+FROM participants
+WHERE won_on IS NOT NULL;
+
+INSERT INTO wins (participant_id, won_on, picked_by, picked_at, present_identifier, status, revoked_at, draw_id)
+SELECT participant_id, won_on, picked_by, picking_time, present_identifier, 'replaced', replaced_at, draw_id
+FROM winner_replacements;
+
+-- the replacements just link the replaced win and the win of the substitute (a replaced winner
+-- cannot win again, so there is just a single replaced win per participant)
+ALTER TABLE winner_replacements
+    ADD COLUMN win_id            INT4 REFERENCES wins (id) ON DELETE CASCADE,
+    ADD COLUMN substitute_win_id INT4 REFERENCES wins (id) ON DELETE SET NULL;
+
+UPDATE winner_replacements
+SET win_id = wins.id
+FROM wins
+WHERE wins.participant_id = winner_replacements.participant_id
+  AND wins.status = 'replaced';
+
+UPDATE winner_replacements
+SET substitute_win_id = wins.id
+FROM wins
+WHERE wins.participant_id = winner_replacements.substitute_id
+  AND wins.draw_id = winner_replacements.substitute_draw_id;
+
+ALTER TABLE winner_replacements
+    ALTER COLUMN win_id SET NOT NULL,
+    DROP COLUMN participant_id,
+    DROP COLUMN won_on,
+    DROP COLUMN picked_by,
+    DROP COLUMN picking_time,
+    DROP COLUMN present_identifier,
+    DROP COLUMN draw_id,
+    DROP COLUMN substitute_id,
+    DROP COLUMN substitute_draw_id;
+
+ALTER TABLE participants
+    DROP COLUMN won_on,
+    DROP COLUMN picked_by,
+    DROP COLUMN picking_time,
+    DROP COLUMN present_identifier,
+    DROP COLUMN draw_id;
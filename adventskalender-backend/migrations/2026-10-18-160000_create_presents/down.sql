@@ -0,0 +1,8 @@
+ALTER TABLE wins ADD COLUMN present_identifier VARCHAR(1);
+UPDATE wins
+SET present_identifier = LEFT(presents.identifier, 1)
+FROM presents
+WHERE presents.id = wins.present_id;
+DROP INDEX wins_present_id;
+ALTER TABLE wins DROP COLUMN present_id;
+DROP TABLE presents;
@@ -0,0 +1,36 @@
+-- the presents of a raffle day together with the number of pieces which can be handed out
+CREATE TABLE presents
+(
+    id           SERIAL       PRIMARY KEY,
+    raffle_id    INT4         NOT NULL REFERENCES raffles (id) ON DELETE CASCADE,
+    available_on DATE         NOT NULL,
+    identifier   VARCHAR(8)   NOT NULL,
+    name         VARCHAR(128) NOT NULL,
+    description  TEXT,
+    sponsor      VARCHAR(128),
+    quantity     INT4         NOT NULL CHECK (quantity >= 0),
+    UNIQUE (raffle_id, available_on, identifier)
+);
+
+-- every identifier which was assigned so far becomes a present of its day (each identifier was
+-- unique per day, so there is a single piece of it unless it was handed out more often)
+INSERT INTO presents (raffle_id, available_on, identifier, name, quantity)
+SELECT participants.raffle_id, wins.won_on, wins.present_identifier, wins.present_identifier,
+       GREATEST(1, COUNT(*) FILTER (WHERE wins.status = 'active'))
+FROM wins
+         JOIN participants ON participants.id = wins.participant_id
+WHERE wins.present_identifier IS NOT NULL
+GROUP BY participants.raffle_id, wins.won_on, wins.present_identifier;
+
+-- the wins reference the presents instead of storing a free-form identifier
+ALTER TABLE wins ADD COLUMN present_id INT4 REFERENCES presents (id);
+UPDATE wins
+SET present_id = presents.id
+FROM participants,
+     presents
+WHERE participants.id = wins.participant_id
+  AND presents.raffle_id = participants.raffle_id
+  AND presents.available_on = wins.won_on
+  AND presents.identifier = wins.present_identifier;
+ALTER TABLE wins DROP COLUMN present_identifier;
+CREATE INDEX wins_present_id ON wins (present_id);
@@ -0,0 +1,6 @@
+ALTER TABLE participants
+    DROP COLUMN present_preferences;
+
+ALTER TABLE raffles
+    DROP CONSTRAINT raffles_valid_present_assignment,
+    DROP COLUMN present_assignment;
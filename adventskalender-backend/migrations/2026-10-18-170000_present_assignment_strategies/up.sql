@@ -0,0 +1,8 @@
+-- every raffle defines if (and how) presents are assigned automatically to the picked winners
+ALTER TABLE raffles
+    ADD COLUMN present_assignment VARCHAR(16) NOT NULL DEFAULT 'manual',
+    ADD CONSTRAINT raffles_valid_present_assignment CHECK (present_assignment IN ('manual', 'random', 'ordered', 'preference'));
+
+-- the identifiers of the presents a participant would like to get (the most preferred one first)
+ALTER TABLE participants
+    ADD COLUMN present_preferences VARCHAR(8)[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,2 @@
+ALTER TABLE raffles
+    DROP COLUMN scheduled_draws;
@@ -0,0 +1,3 @@
+-- raffles can let the backend pick the winners of each day at the configured time
+ALTER TABLE raffles
+    ADD COLUMN scheduled_draws BOOLEAN NOT NULL DEFAULT FALSE;
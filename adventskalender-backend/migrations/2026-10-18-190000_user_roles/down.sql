@@ -0,0 +1,3 @@
+ALTER TABLE users
+    DROP CONSTRAINT users_valid_role,
+    DROP COLUMN role;
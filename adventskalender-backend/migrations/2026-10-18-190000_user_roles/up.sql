@@ -0,0 +1,7 @@
+-- every user has a role which defines what the user is allowed to do. The existing users keep all
+-- permissions they had so far, new users can just look at the results by default
+ALTER TABLE users
+    ADD COLUMN role VARCHAR(16) NOT NULL DEFAULT 'admin',
+    ADD CONSTRAINT users_valid_role CHECK (role IN ('admin', 'operator', 'viewer'));
+ALTER TABLE users
+    ALTER COLUMN role SET DEFAULT 'viewer';
@@ -0,0 +1,4 @@
+-- disabled users can neither log in nor use the tokens they got before. They are kept (instead of
+-- being deleted) if they are referenced by the history of the raffles
+ALTER TABLE users
+    ADD COLUMN disabled BOOLEAN NOT NULL DEFAULT false;
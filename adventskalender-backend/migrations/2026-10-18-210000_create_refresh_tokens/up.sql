@@ -0,0 +1,16 @@
+-- the refresh tokens which can be exchanged for a new access token. Only the SHA-256 hash of a
+-- token is stored. All tokens issued for one login share the same family, so the whole chain can
+-- be revoked if a token which was already used is presented again
+CREATE TABLE refresh_tokens
+(
+    id         SERIAL      PRIMARY KEY,
+    user_id    INT4        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    family     VARCHAR(32) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    issued_at  TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP   NOT NULL,
+    used_at    TIMESTAMP,
+    revoked_at TIMESTAMP
+);
+
+CREATE INDEX refresh_tokens_family_idx ON refresh_tokens (family);
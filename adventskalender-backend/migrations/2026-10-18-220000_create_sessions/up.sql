@@ -0,0 +1,21 @@
+-- every login starts a session which is referenced by the tokens issued for it, so all tokens of a
+-- login can be revoked at once (e.g. to log out a lost device)
+CREATE TABLE sessions
+(
+    id           VARCHAR(32)  PRIMARY KEY,
+    user_id      INT4         NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at   TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at   TIMESTAMP    NOT NULL,
+    user_agent   VARCHAR(256),
+    ip_address   VARCHAR(64),
+    revoked_at   TIMESTAMP
+);
+
+CREATE INDEX sessions_user_id_idx ON sessions (user_id);
+
+-- the family of a refresh token is the session it was issued for. The existing refresh tokens do
+-- not belong to a session and are dropped, so their users have to log in again
+DELETE FROM refresh_tokens;
+ALTER TABLE refresh_tokens
+    ADD CONSTRAINT refresh_tokens_family_fkey FOREIGN KEY (family) REFERENCES sessions (id) ON DELETE CASCADE;
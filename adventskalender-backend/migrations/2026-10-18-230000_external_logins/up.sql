@@ -0,0 +1,4 @@
+-- the subject of the users who log in through the external OpenID Connect provider. Users which
+-- were created for an external login have no password (an empty hash)
+ALTER TABLE users
+    ADD COLUMN external_subject VARCHAR(255) UNIQUE;
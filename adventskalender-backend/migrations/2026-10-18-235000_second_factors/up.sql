@@ -0,0 +1,23 @@
+-- the TOTP secrets of the users (encrypted with the totp_encryption_key). A secret is just used
+-- for the login after it was confirmed with a first code. The last used time step prevents that a
+-- code is used twice
+CREATE TABLE second_factors
+(
+    user_id        INT4         PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    secret         VARCHAR(128) NOT NULL,
+    created_at     TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    confirmed_at   TIMESTAMP,
+    last_used_step INT8
+);
+
+-- the recovery codes which can be used once instead of a TOTP code (e.g. if the device is lost).
+-- Only the SHA-256 hash of a code is stored
+CREATE TABLE recovery_codes
+(
+    id        SERIAL      PRIMARY KEY,
+    user_id   INT4        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at   TIMESTAMP
+);
+
+CREATE INDEX recovery_codes_user_id_idx ON recovery_codes (user_id);
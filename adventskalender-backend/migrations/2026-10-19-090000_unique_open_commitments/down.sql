@@ -0,0 +1,2 @@
+-- allow several open commitments per raffle and day again
+DROP INDEX draw_commitments_open_raffle_id_drawn_for;
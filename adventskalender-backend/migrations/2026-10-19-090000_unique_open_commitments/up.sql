@@ -0,0 +1,3 @@
+-- just one commitment per raffle and day can be open, so a draw cannot be re-rolled by committing
+-- again before revealing (fails if there already are several open commitments for the same day)
+CREATE UNIQUE INDEX draw_commitments_open_raffle_id_drawn_for ON draw_commitments (raffle_id, drawn_for) WHERE revealed_at IS NULL;
@@ -0,0 +1,3 @@
+-- remove the flag which forces the users to change their password
+ALTER TABLE users
+    DROP COLUMN must_change_password;
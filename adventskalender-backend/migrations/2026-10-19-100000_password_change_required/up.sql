@@ -0,0 +1,3 @@
+-- a password which was reset by an administrator has to be changed by the user after the next login
+ALTER TABLE users
+    ADD COLUMN must_change_password BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,4 @@
+-- delete the wins together with their participant again
+ALTER TABLE wins
+    DROP CONSTRAINT wins_participant_id_fkey,
+    ADD CONSTRAINT wins_participant_id_fkey FOREIGN KEY (participant_id) REFERENCES participants (id) ON DELETE CASCADE;
@@ -0,0 +1,5 @@
+-- the wins are the history of the raffle (including the revoked and replaced ones), so a participant
+-- who won at any time cannot be deleted anymore
+ALTER TABLE wins
+    DROP CONSTRAINT wins_participant_id_fkey,
+    ADD CONSTRAINT wins_participant_id_fkey FOREIGN KEY (participant_id) REFERENCES participants (id) ON DELETE RESTRICT;
@@ -0,0 +1,3 @@
+-- remove the point in time at which the scheduled draws were enabled
+ALTER TABLE raffles
+    DROP COLUMN scheduled_draws_enabled_at;
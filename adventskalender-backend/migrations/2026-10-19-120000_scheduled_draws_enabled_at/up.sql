@@ -0,0 +1,6 @@
+-- the scheduled draws just catch up the days which were due after they were enabled for a raffle
+ALTER TABLE raffles
+    ADD COLUMN scheduled_draws_enabled_at TIMESTAMP;
+UPDATE raffles
+SET scheduled_draws_enabled_at = timezone('UTC', CURRENT_TIMESTAMP)
+WHERE scheduled_draws;
@@ -0,0 +1,2 @@
+-- remove the function which takes an advisory lock with a timeout
+DROP FUNCTION advisory_lock_with_timeout(INT4, INT4, TEXT);
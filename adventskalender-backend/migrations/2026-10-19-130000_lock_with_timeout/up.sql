@@ -0,0 +1,14 @@
+-- take a session-level advisory lock, but wait at most for the supplied time. Just a lock which
+-- is not available in time (lock_not_available, SQLSTATE 55P03) is reported as false, every other
+-- error is raised as usual
+CREATE FUNCTION advisory_lock_with_timeout(namespace INT4, key INT4, timeout TEXT) RETURNS BOOLEAN AS
+$$
+BEGIN
+    PERFORM set_config('lock_timeout', timeout, true);
+    PERFORM pg_advisory_lock(namespace, key);
+    RETURN true;
+EXCEPTION
+    WHEN lock_not_available THEN
+        RETURN false;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,159 @@
+use crate::models::{NewRaffle, Raffle};
+use chrono::{Datelike, NaiveDate, Weekday};
+use std::fmt::{self, Display, Formatter};
+
+/// The days of a raffle on which winners can be picked and how many winners are picked per day.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaffleCalendar {
+    /// The first day on which winners can be picked.
+    pub start_date: NaiveDate,
+    /// The last day on which winners can be picked.
+    pub end_date: NaiveDate,
+    /// A flag which indicates that no winners are picked on saturdays and sundays.
+    pub exclude_weekends: bool,
+    /// Single days (e.g. holidays) within the date range on which no winners are picked.
+    pub excluded_dates: Vec<NaiveDate>,
+    /// The number of winners which are picked on each valid day.
+    pub winners_per_day: usize,
+}
+
+/// The reasons why winners cannot be picked for a day.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PickRefusal {
+    /// The day is not part of the date range of the raffle.
+    OutOfRange,
+    /// The day is within the date range but excluded (weekend or holiday).
+    ExcludedDay,
+    /// Picking the requested number of winners would exceed the quota of the day.
+    QuotaExceeded {
+        /// The number of winners which can still be picked for the day.
+        remaining: usize,
+    },
+}
+
+impl Display for PickRefusal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            PickRefusal::OutOfRange => write!(f, "the day is not part of the raffle"),
+            PickRefusal::ExcludedDay => write!(f, "the day is excluded from the raffle"),
+            PickRefusal::QuotaExceeded { remaining } => write!(
+                f,
+                "the quota of the day would be exceeded ({} winners remaining)",
+                remaining
+            ),
+        }
+    }
+}
+
+impl From<&Raffle> for RaffleCalendar {
+    fn from(raffle: &Raffle) -> Self {
+        RaffleCalendar {
+            start_date: raffle.start_date,
+            end_date: raffle.end_date,
+            exclude_weekends: raffle.exclude_weekends,
+            excluded_dates: raffle.excluded_dates.clone(),
+            winners_per_day: usize::try_from(raffle.winners_per_day).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&NewRaffle> for RaffleCalendar {
+    fn from(raffle: &NewRaffle) -> Self {
+        RaffleCalendar {
+            start_date: raffle.start_date,
+            end_date: raffle.end_date,
+            exclude_weekends: raffle.exclude_weekends,
+            excluded_dates: raffle.excluded_dates.clone(),
+            winners_per_day: usize::try_from(raffle.winners_per_day).unwrap_or_default(),
+        }
+    }
+}
+
+impl RaffleCalendar {
+    /// Check if winners can be picked on the supplied day at all.
+    pub fn check_day(&self, date: NaiveDate) -> Result<(), PickRefusal> {
+        if date < self.start_date || date > self.end_date {
+            return Err(PickRefusal::OutOfRange);
+        }
+        if self.exclude_weekends && matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            return Err(PickRefusal::ExcludedDay);
+        }
+        if self.excluded_dates.contains(&date) {
+            return Err(PickRefusal::ExcludedDay);
+        }
+        Ok(())
+    }
+
+    /// Check if `count` additional winners can be picked for the supplied day if `already_won`
+    /// winners were already stored for it.
+    pub fn check_pick(
+        &self,
+        date: NaiveDate,
+        count: usize,
+        already_won: usize,
+    ) -> Result<(), PickRefusal> {
+        self.check_day(date)?;
+        let remaining = self.winners_per_day.saturating_sub(already_won);
+        if count > remaining {
+            return Err(PickRefusal::QuotaExceeded { remaining });
+        }
+        Ok(())
+    }
+
+    /// Get all days of the raffle on which winners can be picked.
+    pub fn valid_days(&self) -> Vec<NaiveDate> {
+        self.start_date
+            .iter_days()
+            .take_while(|date| *date <= self.end_date)
+            .filter(|date| self.check_day(*date).is_ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advent_calendar() -> RaffleCalendar {
+        RaffleCalendar {
+            start_date: NaiveDate::from_ymd_opt(2026, 12, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2026, 12, 24).unwrap(),
+            exclude_weekends: true,
+            excluded_dates: vec![NaiveDate::from_ymd_opt(2026, 12, 8).unwrap()],
+            winners_per_day: 3,
+        }
+    }
+
+    #[test]
+    fn days_outside_the_range_and_excluded_days_are_refused() {
+        let calendar = advent_calendar();
+        let day = |day| NaiveDate::from_ymd_opt(2026, 12, day).unwrap();
+        assert_eq!(calendar.check_day(day(1)), Ok(()));
+        assert_eq!(calendar.check_day(day(24)), Ok(()));
+        assert_eq!(calendar.check_day(day(25)), Err(PickRefusal::OutOfRange));
+        assert_eq!(
+            calendar.check_day(NaiveDate::from_ymd_opt(2026, 11, 30).unwrap()),
+            Err(PickRefusal::OutOfRange)
+        );
+        // the 5th of december 2026 is a saturday
+        assert_eq!(calendar.check_day(day(5)), Err(PickRefusal::ExcludedDay));
+        assert_eq!(calendar.check_day(day(8)), Err(PickRefusal::ExcludedDay));
+        assert_eq!(calendar.valid_days().len(), 17);
+    }
+
+    #[test]
+    fn picks_exceeding_the_quota_are_refused() {
+        let calendar = advent_calendar();
+        let day = NaiveDate::from_ymd_opt(2026, 12, 2).unwrap();
+        assert_eq!(calendar.check_pick(day, 3, 0), Ok(()));
+        assert_eq!(calendar.check_pick(day, 1, 2), Ok(()));
+        assert_eq!(
+            calendar.check_pick(day, 2, 2),
+            Err(PickRefusal::QuotaExceeded { remaining: 1 })
+        );
+        assert_eq!(
+            calendar.check_pick(day, 1, 4),
+            Err(PickRefusal::QuotaExceeded { remaining: 0 })
+        );
+    }
+}
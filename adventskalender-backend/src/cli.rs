@@ -0,0 +1,410 @@
+use crate::config::Configuration;
+use crate::export::ExportFormat;
+use crate::guards::Role;
+use clap::{Parser, Subcommand};
+use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use diesel::PgConnection;
+use std::path::PathBuf;
+
+/// The number of attempts the commands make to connect to the database server.
+const DATABASE_CONNECTION_ATTEMPTS: u32 = 3;
+
+/// The number of connections the commands keep open to the database server.
+const DATABASE_POOL_SIZE: u32 = 2;
+
+/// The backend of the adventskalender. Without a command, the server is started.
+#[derive(Parser)]
+#[command(version = env!("VERGEN_GIT_DESCRIBE"))]
+pub struct Cli {
+    /// The TOML file with the configuration (the environment variables override its settings)
+    #[arg(long, global = true, env = "ADVENTSKALENDER_CONFIG_FILE")]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Start the server (the default if no command is supplied)
+    Serve,
+    /// Apply all pending migrations to the database
+    Migrate,
+    /// Create a new user (the password is asked for interactively)
+    CreateUser {
+        /// The name the new user logs in with
+        username: String,
+        /// The role of the new user (viewer, operator or admin)
+        #[arg(long, default_value = "viewer", value_parser = parse_role)]
+        role: Role,
+        /// Read the password from the first line of the standard input instead of asking for it
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Set a new password for an existing user (the password is asked for interactively)
+    ResetPassword {
+        /// The name of the user
+        username: String,
+        /// Read the password from the first line of the standard input instead of asking for it
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Add the participants of a CSV or JSON file (selected by the file extension) to a raffle
+    ImportParticipants {
+        /// The id of the raffle the participants are added to
+        #[arg(long)]
+        raffle: i32,
+        /// Just validate the file without adding any participant
+        #[arg(long)]
+        dry_run: bool,
+        /// The file with the participants
+        file: PathBuf,
+    },
+    /// Export the winners of a raffle
+    ExportWinners {
+        /// The id of the raffle
+        #[arg(long)]
+        raffle: i32,
+        /// The format of the export (csv or jsonl)
+        #[arg(long, default_value = "csv", value_parser = parse_export_format)]
+        format: ExportFormat,
+        /// The file the export is written to (the standard output if not supplied)
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Create a new key for signing the tokens (the previous keys stay valid until their tokens expired)
+    RotateKey,
+    /// Check the configuration, the keys and the connection to the database
+    CheckConfig,
+}
+
+fn parse_role(value: &str) -> Result<Role, String> {
+    match value {
+        "viewer" | "operator" | "admin" => Ok(Role::from_name(value)),
+        _ => Err("the role has to be one of viewer, operator or admin".to_string()),
+    }
+}
+
+fn parse_export_format(value: &str) -> Result<ExportFormat, String> {
+    match value {
+        "csv" => Ok(ExportFormat::Csv),
+        "jsonl" => Ok(ExportFormat::JsonLines),
+        _ => Err("the format has to be one of csv or jsonl".to_string()),
+    }
+}
+
+/// Connect to the configured database.
+fn connect(configuration: &Configuration) -> Result<Pool<ConnectionManager<PgConnection>>, String> {
+    use crate::fairings::connect_to_database;
+
+    connect_to_database(
+        &configuration.database_connection_url,
+        DATABASE_POOL_SIZE,
+        DATABASE_CONNECTION_ATTEMPTS,
+    )
+}
+
+/// Get a single connection to the configured database.
+fn database_connection(
+    configuration: &Configuration,
+) -> Result<PooledConnection<ConnectionManager<PgConnection>>, String> {
+    connect(configuration)?
+        .get()
+        .map_err(|error| format!("Could not connect to the database: {}", error))
+}
+
+/// Ask for a new password (or read it from the standard input) and ensure that it follows the
+/// same rules as the passwords which are set through the API.
+fn read_new_password(from_stdin: bool) -> Result<String, String> {
+    use crate::users::check_new_password;
+
+    let (first_time, second_time) = if from_stdin {
+        let mut password = String::new();
+        std::io::stdin()
+            .read_line(&mut password)
+            .map_err(|error| format!("Could not read the password: {}", error))?;
+        let password = password.trim_end_matches(['\r', '\n']).to_string();
+        (password.clone(), password)
+    } else {
+        let first_time = rpassword::prompt_password("New password: ")
+            .map_err(|error| format!("Could not read the password: {}", error))?;
+        let second_time = rpassword::prompt_password("Repeat the new password: ")
+            .map_err(|error| format!("Could not read the password: {}", error))?;
+        (first_time, second_time)
+    };
+
+    check_new_password(&first_time, &second_time)
+        .map_err(|rejection| format!("The password was rejected since {}", rejection))?;
+    Ok(first_time)
+}
+
+/// Look up a raffle and ensure that its participants can be changed.
+fn lookup_writable_raffle(
+    connection: &mut PgConnection,
+    raffle_id: i32,
+) -> Result<crate::models::Raffle, String> {
+    let raffle = lookup_raffle(connection, raffle_id)?;
+    if raffle.archived {
+        return Err(format!(
+            "The raffle {} is archived and cannot be changed",
+            raffle_id
+        ));
+    }
+    Ok(raffle)
+}
+
+fn lookup_raffle(
+    connection: &mut PgConnection,
+    raffle_id: i32,
+) -> Result<crate::models::Raffle, String> {
+    use crate::schema::raffles::dsl::raffles;
+    use diesel::{OptionalExtension, QueryDsl, RunQueryDsl};
+
+    raffles
+        .find(raffle_id)
+        .first::<crate::models::Raffle>(connection)
+        .optional()
+        .map_err(|error| format!("Could not look up the raffle: {}", error))?
+        .ok_or(format!("There is no raffle with the id {}", raffle_id))
+}
+
+/// Apply all pending migrations to the database.
+pub fn migrate(configuration: &Configuration) -> Result<(), String> {
+    use crate::run_migrations;
+
+    let mut connection = database_connection(configuration)?;
+    let applied_migrations = run_migrations(&mut connection)?;
+    println!("Applied {} migrations", applied_migrations);
+    Ok(())
+}
+
+/// Create a new user with the supplied role.
+pub fn create_user(
+    configuration: &Configuration,
+    username: &str,
+    role: Role,
+    password_stdin: bool,
+) -> Result<(), String> {
+    use crate::users::{hash_password, insert_user, is_valid_username};
+    use crate::{log_action, Action};
+
+    if !is_valid_username(username) {
+        return Err(format!("The name '{}' cannot be used for a user", username));
+    }
+    let password = read_new_password(password_stdin)?;
+    let hashed_password = hash_password(&password)
+        .map_err(|error| format!("Could not hash the password: {}", error))?;
+
+    let mut connection = database_connection(configuration)?;
+    let created_user = insert_user(&mut connection, username, hashed_password, role)
+        .map_err(|error| format!("Could not create the user '{}': {}", username, error))?;
+    log_action(
+        &mut connection,
+        None,
+        Action::UserCreated,
+        Some(format!(
+            "The user '{}' was created with the id {} and the role {} on the command line",
+            created_user.username, created_user.id, created_user.role
+        )),
+    );
+
+    println!(
+        "Created the user '{}' with the id {} and the role {}",
+        created_user.username, created_user.id, created_user.role
+    );
+    Ok(())
+}
+
+/// Set a new password for an existing user.
+pub fn reset_password(
+    configuration: &Configuration,
+    username: &str,
+    password_stdin: bool,
+) -> Result<(), String> {
+    use crate::users::hash_password;
+    use crate::{log_action, lookup_user_by_name, Action};
+
+    let mut connection = database_connection(configuration)?;
+    let user = lookup_user_by_name(&mut connection, username.to_string())
+        .map_err(|_| format!("There is no user with the name '{}'", username))?;
+
+    let password = read_new_password(password_stdin)?;
+    let hashed_password = hash_password(&password)
+        .map_err(|error| format!("Could not hash the password: {}", error))?;
+    crate::users::reset_password(&mut connection, user.id, hashed_password)
+        .map_err(|error| format!("Could not store the new password: {}", error))?;
+    log_action(
+        &mut connection,
+        None,
+        Action::PasswordReset,
+        Some(format!(
+            "The password of the user '{}' with the id {} was reset on the command line",
+            user.username, user.id
+        )),
+    );
+
+    println!("The password of the user '{}' was reset", user.username);
+    Ok(())
+}
+
+/// Add the participants of a CSV or JSON file to a raffle.
+pub fn import_participants(
+    configuration: &Configuration,
+    raffle_id: i32,
+    file: &PathBuf,
+    dry_run: bool,
+) -> Result<(), String> {
+    use crate::import::{store_participants, ImportFormat};
+    use crate::{log_action, Action};
+
+    let import_format = match file.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.eq_ignore_ascii_case("json") => ImportFormat::Json,
+        _ => ImportFormat::Csv,
+    };
+    let import_data = std::fs::read_to_string(file)
+        .map_err(|error| format!("Could not read {}: {}", file.display(), error))?;
+
+    let mut connection = database_connection(configuration)?;
+    lookup_writable_raffle(&mut connection, raffle_id)?;
+    let import_report = store_participants(
+        &mut connection,
+        raffle_id,
+        &import_data,
+        import_format,
+        dry_run,
+    )
+    .map_err(|error| format!("Could not import the participants: {}", error))?;
+
+    for error in &import_report.errors {
+        eprintln!("Row {}: {}", error.row, error.message);
+    }
+    if !import_report.errors.is_empty() {
+        return Err(format!(
+            "{} of {} rows are invalid ({} rows are valid), no participant was imported",
+            import_report.errors.len(),
+            import_report.total_rows,
+            import_report.valid_rows
+        ));
+    }
+    if dry_run {
+        println!(
+            "All {} participants can be imported",
+            import_report.total_rows
+        );
+        return Ok(());
+    }
+
+    log_action(
+        &mut connection,
+        None,
+        Action::ParticipantsImported,
+        Some(format!(
+            "{} participants were added to the raffle {} by a bulk import on the command line",
+            import_report.imported, raffle_id
+        )),
+    );
+    println!(
+        "Imported {} participants into the raffle {}",
+        import_report.imported, raffle_id
+    );
+    Ok(())
+}
+
+/// Export the winners of a raffle to a file or the standard output.
+pub async fn export_winners(
+    configuration: &Configuration,
+    raffle_id: i32,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+) -> Result<(), String> {
+    use crate::export::export_stream;
+    use rocket::futures::StreamExt;
+    use std::io::Write;
+
+    let db_connection_pool = connect(configuration)?;
+    let mut connection = db_connection_pool
+        .get()
+        .map_err(|error| format!("Could not connect to the database: {}", error))?;
+    lookup_raffle(&mut connection, raffle_id)?;
+    drop(connection);
+
+    let mut writer: Box<dyn Write + Send> = match &output {
+        Some(path) => Box::new(
+            std::fs::File::create(path)
+                .map_err(|error| format!("Could not create {}: {}", path.display(), error))?,
+        ),
+        None => Box::new(std::io::stdout()),
+    };
+    let incomplete_export =
+        || "Could not fetch the winners from the database, the export is incomplete".to_string();
+    let mut lines = export_stream(db_connection_pool.into(), raffle_id, true, format)
+        .await
+        .map_err(|()| incomplete_export())?;
+    while let Some(line) = lines.next().await {
+        let line = line.map_err(|()| incomplete_export())?;
+        writer
+            .write_all(line.as_bytes())
+            .map_err(|error| format!("Could not write the export: {}", error))?;
+    }
+    writer
+        .flush()
+        .map_err(|error| format!("Could not write the export: {}", error))
+}
+
+/// Create a new key which signs the tokens from now on and remove the retired keys which are not
+/// needed anymore. Running servers pick up the new key on their own.
+pub fn rotate_key(configuration: &Configuration) -> Result<(), String> {
+    use crate::keys::{key_retention, KeyStore};
+
+    let mut key_store = KeyStore::load(&configuration.key_directory)?;
+    let key = key_store.rotate()?;
+    println!(
+        "Stored the new key {} in {}. Running servers sign the tokens with it within a minute, the tokens issued so far stay valid",
+        key.id,
+        configuration.key_directory.display()
+    );
+    for key_id in key_store.prune(key_retention(configuration.token_lifetime_in_seconds))? {
+        println!("Removed the retired key {}", key_id);
+    }
+    Ok(())
+}
+
+/// Check the keys and the connection to the database without starting the server (the
+/// configuration itself was already validated while loading it).
+pub fn check_config(configuration: &Configuration) -> Result<(), String> {
+    use crate::discovery::OpenIdConfiguration;
+    use crate::keys::KeyStore;
+    use crate::MIGRATIONS;
+    use diesel_migrations::MigrationHarness;
+
+    println!("The configuration is valid");
+
+    let openid_configuration = OpenIdConfiguration::for_api_host(&configuration.api_host)?;
+    println!(
+        "The tokens are issued by {} and can be validated with the keys of {}",
+        openid_configuration.issuer, openid_configuration.jwks_uri
+    );
+
+    let key_store = KeyStore::load(&configuration.key_directory)?;
+    match key_store.active_key() {
+        Some(key) => println!(
+            "The key directory {} contains {} valid keys, the active key is {}",
+            configuration.key_directory.display(),
+            key_store.keys().len(),
+            key.id
+        ),
+        None => println!(
+            "The key directory {} contains no keys, a key is generated on the first start",
+            configuration.key_directory.display()
+        ),
+    }
+
+    let mut connection = database_connection(configuration)?;
+    let pending_migrations = connection
+        .pending_migrations(MIGRATIONS)
+        .map_err(|error| format!("Could not check the database migrations: {}", error))?;
+    println!(
+        "The database is reachable and has {} pending migrations",
+        pending_migrations.len()
+    );
+    Ok(())
+}
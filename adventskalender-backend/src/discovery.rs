@@ -0,0 +1,143 @@
+use serde::Serialize;
+use url::Url;
+
+/// The path all API routes are mounted below.
+pub const API_BASE_PATH: &str = "/v1";
+
+/// The path the discovery document and the keys are mounted below.
+pub const WELL_KNOWN_BASE_PATH: &str = "/.well-known";
+
+/// The metadata other services need to validate the tokens we issue (e.g. with a standard OpenID
+/// Connect library). See https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderMetadata
+/// for more information.
+///
+/// There is no `authorization_endpoint`, since the tokens are just issued for a username and a
+/// password (or a refresh token) and not by redirecting the user.
+#[derive(Clone, Default, Serialize)]
+pub struct OpenIdConfiguration {
+    /// The issuer of the tokens (the `iss` claim).
+    pub issuer: String,
+    /// The endpoint which issues the tokens.
+    pub token_endpoint: String,
+    /// The endpoint which returns the information of the user the token was issued for.
+    pub userinfo_endpoint: String,
+    /// The endpoint which revokes the session of a token.
+    pub revocation_endpoint: String,
+    /// The document with the keys which validate the signature of the tokens.
+    pub jwks_uri: String,
+    /// The tokens are returned directly and not as a code which has to be exchanged.
+    pub response_types_supported: Vec<String>,
+    /// The ways the tokens can be requested.
+    pub grant_types_supported: Vec<String>,
+    /// The subject is the username, which is the same for all audiences.
+    pub subject_types_supported: Vec<String>,
+    /// The algorithms the tokens are signed with.
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    /// The claims the tokens contain.
+    pub claims_supported: Vec<String>,
+}
+
+impl OpenIdConfiguration {
+    /// Create the discovery document for an API which is reachable at the supplied host. The
+    /// endpoints are derived from the routes which serve them, so they always match the mounted
+    /// routes.
+    pub fn for_api_host(api_host: &str) -> Result<OpenIdConfiguration, String> {
+        use rocket::uri;
+
+        let base_url = match Url::parse(api_host) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+            _ => {
+                return Err(format!(
+                    "The API host '{}' is not an http or https URL",
+                    api_host
+                ))
+            }
+        };
+        if base_url.path() != "/" || base_url.query().is_some() || base_url.fragment().is_some() {
+            return Err(format!(
+                "The API host '{}' must not contain a path, a query or a fragment",
+                api_host
+            ));
+        }
+
+        let endpoint = |base_path: &str, route_path: String| {
+            base_url
+                .join(&format!("{}{}", base_path, route_path))
+                .map(|url| url.to_string())
+                .map_err(|error| {
+                    format!(
+                        "Could not build the URL of the endpoint {}{}: {}",
+                        base_path, route_path, error
+                    )
+                })
+        };
+
+        Ok(OpenIdConfiguration {
+            // the issuer has to be exactly the value of the `iss` claim of the tokens
+            issuer: api_host.to_string(),
+            token_endpoint: endpoint(
+                API_BASE_PATH,
+                uri!(crate::routes::get_login_token).to_string(),
+            )?,
+            userinfo_endpoint: endpoint(
+                API_BASE_PATH,
+                uri!(crate::routes::get_current_user).to_string(),
+            )?,
+            revocation_endpoint: endpoint(API_BASE_PATH, uri!(crate::routes::logout).to_string())?,
+            jwks_uri: endpoint(
+                WELL_KNOWN_BASE_PATH,
+                uri!(crate::routes::get_jwks).to_string(),
+            )?,
+            response_types_supported: vec!["token".to_string()],
+            grant_types_supported: vec!["password".to_string(), "refresh_token".to_string()],
+            subject_types_supported: vec!["public".to_string()],
+            id_token_signing_alg_values_supported: vec!["EdDSA".to_string()],
+            claims_supported: [
+                "iss", "sub", "aud", "exp", "iat", "nbf", "jti", "sid", "role",
+            ]
+            .iter()
+            .map(|claim| claim.to_string())
+            .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_are_derived_from_the_routes() {
+        let configuration =
+            OpenIdConfiguration::for_api_host("https://adventskalender.example.com").unwrap();
+        assert_eq!(configuration.issuer, "https://adventskalender.example.com");
+        assert_eq!(
+            configuration.token_endpoint,
+            "https://adventskalender.example.com/v1/auth/token"
+        );
+        assert_eq!(
+            configuration.userinfo_endpoint,
+            "https://adventskalender.example.com/v1/auth/me"
+        );
+        assert_eq!(
+            configuration.revocation_endpoint,
+            "https://adventskalender.example.com/v1/auth/logout"
+        );
+        assert_eq!(
+            configuration.jwks_uri,
+            "https://adventskalender.example.com/.well-known/jwks.json"
+        );
+        assert_eq!(
+            configuration.id_token_signing_alg_values_supported,
+            vec!["EdDSA"]
+        );
+    }
+
+    #[test]
+    fn invalid_api_hosts_are_rejected() {
+        assert!(OpenIdConfiguration::for_api_host("not a url").is_err());
+        assert!(OpenIdConfiguration::for_api_host("ftp://example.com").is_err());
+        assert!(OpenIdConfiguration::for_api_host("https://example.com/api").is_err());
+        assert!(OpenIdConfiguration::for_api_host("http://localhost:5479/").is_ok());
+    }
+}
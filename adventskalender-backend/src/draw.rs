@@ -0,0 +1,369 @@
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::Serialize;
+
+/// The version of the algorithm which is used for drawing winners. It is stored with every draw
+/// and has to be increased whenever the result of [`draw_weighted_winners`] changes for the same
+/// input. Draws of older versions can still be verified.
+pub const DRAW_ALGORITHM_VERSION: i32 = 2;
+
+/// The first version of the algorithm which drew all candidates with the same chance and without
+/// any limits per department (see [`draw_winners`]).
+pub const UNWEIGHTED_DRAW_ALGORITHM_VERSION: i32 = 1;
+
+/// The number of bytes of the seed of a draw.
+pub const SEED_LENGTH: usize = 32;
+
+/// The stream of the ChaCha20 generator which is used to assign the presents to the drawn winners.
+const PRESENT_STREAM: u64 = 1;
+
+/// Generate a fresh seed for a draw with the system's secure random number generator.
+pub fn generate_seed() -> Result<[u8; SEED_LENGTH], ring::error::Unspecified> {
+    use ring::rand::{SecureRandom, SystemRandom};
+
+    let mut seed = [0u8; SEED_LENGTH];
+    SystemRandom::new().fill(&mut seed)?;
+    Ok(seed)
+}
+
+/// Encode the supplied bytes as a lowercase hex string.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Calculate the SHA-256 hash (as hex string) of the ordered list of candidate ids. Each id is
+/// hashed as a big-endian 32 bit integer, so the hash can be reproduced with other tools as well.
+pub fn candidates_hash(candidate_ids: &[i32]) -> String {
+    use ring::digest::{Context, SHA256};
+
+    let mut context = Context::new(&SHA256);
+    for candidate_id in candidate_ids {
+        context.update(&candidate_id.to_be_bytes());
+    }
+    to_hex(context.finish().as_ref())
+}
+
+/// Calculate the SHA-256 hash (as hex string) of a seed which is published as the commitment to a
+/// draw before the seed itself is revealed.
+pub fn seed_hash(seed: &[u8]) -> String {
+    use ring::digest::{digest, SHA256};
+
+    to_hex(digest(&SHA256, seed).as_ref())
+}
+
+/// Get a uniformly distributed number in the range `0..bound` from the random number generator.
+/// Values which would cause a modulo bias are rejected, so the result just depends on the stream
+/// of the generator and not on the sampling implementation of a specific `rand` version.
+fn uniform_index(rng: &mut ChaCha20Rng, bound: usize) -> usize {
+    let bound = bound as u64;
+    let zone = u64::MAX - (u64::MAX % bound);
+    loop {
+        let value = rng.next_u64();
+        if value < zone {
+            return (value % bound) as usize;
+        }
+    }
+}
+
+/// Draw `count` winners from the ordered list of candidates. The draw is a partial Fisher-Yates
+/// shuffle driven by a ChaCha20 generator seeded with `seed`, so the same seed and candidates
+/// always result in the same winners (in the same order).
+pub fn draw_winners(seed: &[u8; SEED_LENGTH], candidate_ids: &[i32], count: usize) -> Vec<i32> {
+    let mut rng = ChaCha20Rng::from_seed(*seed);
+    partial_shuffle(&mut rng, candidate_ids, count)
+}
+
+/// Select `count` of the supplied ids with a partial Fisher-Yates shuffle.
+fn partial_shuffle(rng: &mut ChaCha20Rng, ids: &[i32], count: usize) -> Vec<i32> {
+    let mut ids = ids.to_vec();
+    let count = count.min(ids.len());
+    for index in 0..count {
+        let selected = index + uniform_index(rng, ids.len() - index);
+        ids.swap(index, selected);
+    }
+    ids.truncate(count);
+    ids
+}
+
+/// Draw `count` pieces of presents (one entry with the id of the present per piece) with the seed
+/// of a draw. A separate stream of the generator is used, so the presents can be replayed with the
+/// recorded seed as well without influencing the drawn winners.
+pub fn draw_presents(seed: &[u8; SEED_LENGTH], piece_ids: &[i32], count: usize) -> Vec<i32> {
+    let mut rng = ChaCha20Rng::from_seed(*seed);
+    rng.set_stream(PRESENT_STREAM);
+    partial_shuffle(&mut rng, piece_ids, count)
+}
+
+/// A participant who can be drawn as a winner.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    /// The id of the participant.
+    pub id: i32,
+    /// The relative chance of the participant to be drawn (e.g. the number of tickets).
+    pub weight: u32,
+    /// The index of the group (department) of the participant whose number of winners is limited.
+    pub group: Option<usize>,
+}
+
+/// Calculate the SHA-256 hash (as hex string) of the ordered list of weighted candidates and the
+/// capacities of their groups. The number of candidates, then the id, weight and group (or -1) of
+/// each candidate, followed by the number of groups and their capacities are hashed as big-endian
+/// 32 bit integers.
+pub fn weighted_candidates_hash(candidates: &[Candidate], group_capacities: &[usize]) -> String {
+    use ring::digest::{Context, SHA256};
+
+    let mut context = Context::new(&SHA256);
+    context.update(&(candidates.len() as i32).to_be_bytes());
+    for candidate in candidates {
+        context.update(&candidate.id.to_be_bytes());
+        context.update(&(candidate.weight as i32).to_be_bytes());
+        context.update(
+            &candidate
+                .group
+                .map_or(-1, |group| group as i32)
+                .to_be_bytes(),
+        );
+    }
+    context.update(&(group_capacities.len() as i32).to_be_bytes());
+    for capacity in group_capacities {
+        context.update(&(*capacity as i32).to_be_bytes());
+    }
+    to_hex(context.finish().as_ref())
+}
+
+/// Draw up to `count` winners from the ordered list of weighted candidates. Each winner is drawn
+/// with a chance proportional to its weight among the remaining candidates. Once a group reached
+/// its capacity, the remaining candidates of that group cannot be drawn anymore. Less than `count`
+/// winners are returned if there are not enough candidates left.
+pub fn draw_weighted_winners(
+    seed: &[u8; SEED_LENGTH],
+    candidates: &[Candidate],
+    group_capacities: &[usize],
+    count: usize,
+) -> Vec<i32> {
+    let mut rng = ChaCha20Rng::from_seed(*seed);
+    let mut capacities = group_capacities.to_vec();
+    let mut remaining = candidates.to_vec();
+    let mut winners = Vec::with_capacity(count);
+    while winners.len() < count {
+        remaining.retain(|candidate| {
+            candidate.weight > 0
+                && candidate.group.map_or(true, |group| {
+                    capacities.get(group).is_some_and(|left| *left > 0)
+                })
+        });
+        let total_weight = remaining
+            .iter()
+            .map(|candidate| u64::from(candidate.weight))
+            .sum::<u64>();
+        if total_weight == 0 {
+            break;
+        }
+
+        let mut ticket = uniform_index(&mut rng, total_weight as usize) as u64;
+        let mut selected = 0;
+        while ticket >= u64::from(remaining[selected].weight) {
+            ticket -= u64::from(remaining[selected].weight);
+            selected += 1;
+        }
+        let winner = remaining.remove(selected);
+        if let Some(group) = winner.group {
+            capacities[group] -= 1;
+        }
+        winners.push(winner.id);
+    }
+    winners
+}
+
+/// Calculate the hash of the candidates of a draw with the supplied algorithm version.
+pub fn draw_candidates_hash(
+    algorithm_version: i32,
+    candidates: &[Candidate],
+    group_capacities: &[usize],
+) -> String {
+    if algorithm_version == UNWEIGHTED_DRAW_ALGORITHM_VERSION {
+        let candidate_ids = candidates
+            .iter()
+            .map(|candidate| candidate.id)
+            .collect::<Vec<i32>>();
+        candidates_hash(&candidate_ids)
+    } else {
+        weighted_candidates_hash(candidates, group_capacities)
+    }
+}
+
+/// The result of replaying a recorded draw.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DrawVerification {
+    /// A flag which indicates that the stored hash matches the stored list of candidates.
+    pub candidates_hash_matches: bool,
+    /// The winners which were drawn when replaying the draw.
+    pub replayed_winner_ids: Vec<i32>,
+    /// A flag which indicates that the draw could be replayed and resulted in the stored winners.
+    pub verified: bool,
+}
+
+/// Replay a recorded draw and check that it results in the recorded winners.
+pub fn verify_draw(
+    algorithm_version: i32,
+    seed: &[u8],
+    candidates: &[Candidate],
+    group_capacities: &[usize],
+    stored_candidates_hash: &str,
+    winner_ids: &[i32],
+) -> DrawVerification {
+    let candidates_hash_matches =
+        draw_candidates_hash(algorithm_version, candidates, group_capacities)
+            == stored_candidates_hash;
+    let replayed_winner_ids = match <[u8; SEED_LENGTH]>::try_from(seed) {
+        Ok(seed) if algorithm_version == UNWEIGHTED_DRAW_ALGORITHM_VERSION => {
+            let candidate_ids = candidates
+                .iter()
+                .map(|candidate| candidate.id)
+                .collect::<Vec<i32>>();
+            draw_winners(&seed, &candidate_ids, winner_ids.len())
+        }
+        Ok(seed) if algorithm_version == DRAW_ALGORITHM_VERSION => {
+            draw_weighted_winners(&seed, candidates, group_capacities, winner_ids.len())
+        }
+        _ => vec![],
+    };
+    DrawVerification {
+        candidates_hash_matches,
+        verified: candidates_hash_matches && replayed_winner_ids == winner_ids,
+        replayed_winner_ids,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_can_be_replayed_with_the_same_seed() {
+        let candidates = (1..=100).collect::<Vec<i32>>();
+        let seed = [7u8; SEED_LENGTH];
+
+        let winners = draw_winners(&seed, &candidates, 5);
+        assert_eq!(winners, draw_winners(&seed, &candidates, 5));
+        assert_eq!(winners.len(), 5);
+        assert!(winners.iter().all(|winner| candidates.contains(winner)));
+        let mut distinct_winners = winners.clone();
+        distinct_winners.sort();
+        distinct_winners.dedup();
+        assert_eq!(distinct_winners.len(), 5);
+
+        // a different seed should (with overwhelming probability) result in different winners
+        assert_ne!(winners, draw_winners(&[8u8; SEED_LENGTH], &candidates, 5));
+        // and more winners than candidates cannot be drawn
+        assert_eq!(draw_winners(&seed, &[1, 2], 5).len(), 2);
+    }
+
+    #[test]
+    fn candidates_hash_depends_on_the_order() {
+        assert_eq!(
+            candidates_hash(&[]),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_ne!(candidates_hash(&[1, 2, 3]), candidates_hash(&[3, 2, 1]));
+    }
+
+    #[test]
+    fn seed_hash_is_the_sha256_of_the_seed() {
+        assert_eq!(
+            seed_hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_ne!(
+            seed_hash(&[0u8; SEED_LENGTH]),
+            seed_hash(&[1u8; SEED_LENGTH])
+        );
+    }
+
+    fn unweighted(candidate_ids: &[i32]) -> Vec<Candidate> {
+        candidate_ids
+            .iter()
+            .map(|id| Candidate {
+                id: *id,
+                weight: 1,
+                group: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn verification_detects_changed_draws() {
+        let candidates = unweighted(&(1..=20).collect::<Vec<i32>>());
+        let seed = [42u8; SEED_LENGTH];
+        let hash = weighted_candidates_hash(&candidates, &[]);
+        let winners = draw_weighted_winners(&seed, &candidates, &[], 3);
+
+        let verify = |candidates: &[Candidate], winners: &[i32]| {
+            verify_draw(
+                DRAW_ALGORITHM_VERSION,
+                &seed,
+                candidates,
+                &[],
+                &hash,
+                winners,
+            )
+        };
+        assert!(verify(&candidates, &winners).verified);
+        let mut other_winners = winners.clone();
+        other_winners.reverse();
+        assert!(!verify(&candidates, &other_winners).verified);
+        let changed = verify(&candidates[1..], &winners);
+        assert!(!changed.candidates_hash_matches);
+        assert!(!changed.verified);
+        assert!(!verify_draw(0, &seed, &candidates, &[], &hash, &winners).verified);
+    }
+
+    #[test]
+    fn draws_of_the_first_algorithm_version_can_still_be_verified() {
+        let candidate_ids = (1..=20).collect::<Vec<i32>>();
+        let seed = [42u8; SEED_LENGTH];
+        let hash = candidates_hash(&candidate_ids);
+        let winners = draw_winners(&seed, &candidate_ids, 3);
+
+        let verification = verify_draw(
+            UNWEIGHTED_DRAW_ALGORITHM_VERSION,
+            &seed,
+            &unweighted(&candidate_ids),
+            &[],
+            &hash,
+            &winners,
+        );
+        assert!(verification.verified);
+    }
+
+    #[test]
+    fn weights_and_group_capacities_are_respected() {
+        let seed = [3u8; SEED_LENGTH];
+        // candidates without a weight are never drawn
+        let mut candidates = unweighted(&[1, 2, 3]);
+        candidates[1].weight = 0;
+        let winners = draw_weighted_winners(&seed, &candidates, &[], 3);
+        assert_eq!(winners.len(), 2);
+        assert!(!winners.contains(&2));
+
+        // a candidate with nearly all tickets wins (almost) every time
+        let mut candidates = unweighted(&(1..=10).collect::<Vec<i32>>());
+        candidates[4].weight = 1_000_000;
+        let wins = (0..20u8)
+            .filter(|seed| draw_weighted_winners(&[*seed; SEED_LENGTH], &candidates, &[], 1) == [5])
+            .count();
+        assert!(wins >= 19);
+
+        // at most one winner of the first group and none of the second one is drawn
+        let candidates = (1..=10)
+            .map(|id| Candidate {
+                id,
+                weight: 1,
+                group: Some((id % 2) as usize),
+            })
+            .collect::<Vec<Candidate>>();
+        let winners = draw_weighted_winners(&seed, &candidates, &[1, 0], 5);
+        assert_eq!(winners.len(), 1);
+        assert_eq!(winners[0] % 2, 0);
+    }
+}
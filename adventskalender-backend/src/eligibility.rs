@@ -0,0 +1,273 @@
+use crate::draw::Candidate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// The maximum length of the name of a department.
+pub const MAX_DEPARTMENT_LENGTH: usize = 64;
+
+/// A participant who did not win so far together with the values which decide if (and with which
+/// chance) the participant can be drawn.
+#[derive(Clone, Debug)]
+pub struct EligibilityEntry {
+    /// The id of the participant.
+    pub id: i32,
+    /// The number of tickets of the participant.
+    pub tickets: i32,
+    /// The department the participant belongs to.
+    pub department: Option<String>,
+    /// A flag which indicates that the participant must not win.
+    pub blocked: bool,
+}
+
+/// The rules which were applied when the candidates of a draw were selected. They are stored
+/// (as JSON) with every draw for the audit trail.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DrawRules {
+    /// A flag which indicates that the chance of a participant depends on its number of tickets.
+    pub weighted_by_tickets: bool,
+    /// The maximum number of winners per department and day.
+    pub max_winners_per_department: Option<usize>,
+    /// The departments whose number of winners is limited. The groups of the candidates are
+    /// indexes into this list.
+    pub departments: Vec<String>,
+    /// The participants who were excluded since they are on the blocklist.
+    pub blocked_ids: Vec<i32>,
+    /// The participants who were excluded since they are absent on the day of the draw.
+    pub absent_ids: Vec<i32>,
+    /// The participants who were excluded since their department already has enough winners.
+    pub department_limit_ids: Vec<i32>,
+    /// The participants who were excluded since they already won but were replaced.
+    #[serde(default)]
+    pub replaced_ids: Vec<i32>,
+}
+
+/// The candidates of a draw after applying the eligibility rules.
+#[derive(Clone, Debug)]
+pub struct DrawInput {
+    /// The candidates ordered like the supplied participants.
+    pub candidates: Vec<Candidate>,
+    /// The number of winners which can still be drawn per department (indexed like the groups).
+    pub group_capacities: Vec<usize>,
+    /// The rules which were applied.
+    pub rules: DrawRules,
+}
+
+impl DrawInput {
+    /// Get the maximum number of winners which can be drawn from the candidates without exceeding
+    /// the limits of their departments.
+    pub fn max_winners(&self) -> usize {
+        let mut group_sizes = vec![0usize; self.group_capacities.len()];
+        let mut ungrouped = 0;
+        for candidate in self
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.weight > 0)
+        {
+            match candidate.group {
+                Some(group) => group_sizes[group] += 1,
+                None => ungrouped += 1,
+            }
+        }
+        ungrouped
+            + group_sizes
+                .iter()
+                .zip(&self.group_capacities)
+                .map(|(size, capacity)| (*size).min(*capacity))
+                .sum::<usize>()
+    }
+}
+
+/// Normalize the supplied department name. Surrounding whitespace is removed and empty names
+/// are treated as no department. `None` is returned for names which are too long.
+pub fn normalize_department(department: Option<&str>) -> Option<Option<String>> {
+    match department.map(str::trim) {
+        None | Some("") => Some(None),
+        Some(name) if name.chars().count() > MAX_DEPARTMENT_LENGTH => None,
+        Some(name) => Some(Some(name.to_string())),
+    }
+}
+
+/// Select the candidates of a draw from the participants who did not win so far.
+///
+/// Blocked participants, participants in `absent_ids` and former winners who were replaced by a
+/// substitute (`replaced_ids`) are excluded. If `use_tickets` is set,
+/// the tickets of a participant are used as its weight, otherwise all participants have the same
+/// chance. If `max_winners_per_department` is set, `department_winners` (the number of winners per
+/// department which were already picked for the day) is used to limit the number of additional
+/// winners of each department.
+pub fn build_draw_input(
+    entries: &[EligibilityEntry],
+    absent_ids: &HashSet<i32>,
+    replaced_ids: &HashSet<i32>,
+    department_winners: &HashMap<String, usize>,
+    use_tickets: bool,
+    max_winners_per_department: Option<usize>,
+) -> DrawInput {
+    let mut rules = DrawRules {
+        weighted_by_tickets: use_tickets,
+        max_winners_per_department,
+        ..Default::default()
+    };
+
+    // the departments are sorted by their name, so the groups of the candidates are reproducible
+    let mut group_capacities = vec![];
+    if let Some(max_winners) = max_winners_per_department {
+        rules.departments = entries
+            .iter()
+            .filter_map(|entry| entry.department.clone())
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect();
+        group_capacities = rules
+            .departments
+            .iter()
+            .map(|department| {
+                max_winners.saturating_sub(department_winners.get(department).copied().unwrap_or(0))
+            })
+            .collect();
+    }
+
+    let mut candidates = vec![];
+    for entry in entries {
+        if entry.blocked {
+            rules.blocked_ids.push(entry.id);
+            continue;
+        }
+        if absent_ids.contains(&entry.id) {
+            rules.absent_ids.push(entry.id);
+            continue;
+        }
+        if replaced_ids.contains(&entry.id) {
+            rules.replaced_ids.push(entry.id);
+            continue;
+        }
+        let group = match (&entry.department, max_winners_per_department) {
+            (Some(department), Some(_)) => rules.departments.binary_search(department).ok(),
+            _ => None,
+        };
+        if group.is_some_and(|group| group_capacities[group] == 0) {
+            rules.department_limit_ids.push(entry.id);
+            continue;
+        }
+        candidates.push(Candidate {
+            id: entry.id,
+            weight: if use_tickets {
+                u32::try_from(entry.tickets).unwrap_or(0)
+            } else {
+                1
+            },
+            group,
+        });
+    }
+
+    DrawInput {
+        candidates,
+        group_capacities,
+        rules,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: i32, tickets: i32, department: Option<&str>, blocked: bool) -> EligibilityEntry {
+        EligibilityEntry {
+            id,
+            tickets,
+            department: department.map(str::to_string),
+            blocked,
+        }
+    }
+
+    #[test]
+    fn blocked_and_absent_participants_are_excluded() {
+        let entries = vec![
+            entry(1, 3, None, false),
+            entry(2, 1, None, true),
+            entry(3, 1, None, false),
+            entry(4, 1, None, false),
+        ];
+        let input = build_draw_input(
+            &entries,
+            &HashSet::from([3]),
+            &HashSet::from([4]),
+            &HashMap::new(),
+            false,
+            None,
+        );
+        assert_eq!(
+            input.candidates,
+            vec![Candidate {
+                id: 1,
+                weight: 1,
+                group: None
+            }]
+        );
+        assert!(input.group_capacities.is_empty());
+        assert_eq!(input.rules.blocked_ids, vec![2]);
+        assert_eq!(input.rules.absent_ids, vec![3]);
+        assert_eq!(input.rules.replaced_ids, vec![4]);
+
+        let weighted = build_draw_input(
+            &entries,
+            &HashSet::new(),
+            &HashSet::new(),
+            &HashMap::new(),
+            true,
+            None,
+        );
+        assert_eq!(weighted.candidates[0].weight, 3);
+        assert!(weighted.rules.weighted_by_tickets);
+    }
+
+    #[test]
+    fn departments_with_enough_winners_are_excluded() {
+        let entries = vec![
+            entry(1, 1, Some("Sales"), false),
+            entry(2, 1, Some("IT"), false),
+            entry(3, 1, None, false),
+            entry(4, 1, Some("Sales"), false),
+        ];
+        let input = build_draw_input(
+            &entries,
+            &HashSet::new(),
+            &HashSet::new(),
+            &HashMap::from([("Sales".to_string(), 2), ("IT".to_string(), 1)]),
+            false,
+            Some(2),
+        );
+        assert_eq!(input.rules.departments, vec!["IT", "Sales"]);
+        assert_eq!(input.group_capacities, vec![1, 0]);
+        assert_eq!(input.rules.department_limit_ids, vec![1, 4]);
+        let groups = input
+            .candidates
+            .iter()
+            .map(|candidate| (candidate.id, candidate.group))
+            .collect::<Vec<_>>();
+        assert_eq!(groups, vec![(2, Some(0)), (3, None)]);
+        assert_eq!(input.max_winners(), 2);
+
+        let without_winners = build_draw_input(
+            &entries,
+            &HashSet::new(),
+            &HashSet::new(),
+            &HashMap::new(),
+            false,
+            Some(1),
+        );
+        assert_eq!(without_winners.candidates.len(), 4);
+        assert_eq!(without_winners.max_winners(), 3);
+    }
+
+    #[test]
+    fn department_names_are_normalized() {
+        assert_eq!(normalize_department(None), Some(None));
+        assert_eq!(normalize_department(Some("  ")), Some(None));
+        assert_eq!(
+            normalize_department(Some(" IT ")),
+            Some(Some("IT".to_string()))
+        );
+        assert_eq!(normalize_department(Some(&"x".repeat(65))), None);
+    }
+}
@@ -0,0 +1,388 @@
+use crate::fairings::AdventskalenderDatabaseConnection;
+use chrono::{NaiveDate, NaiveDateTime};
+use rocket::futures::stream::{self, BoxStream, StreamExt};
+use rocket::http::{Accept, ContentType, Header, MediaType};
+use rocket::response::stream::TextStream;
+use rocket::response::{Responder, Response};
+use rocket::Request;
+use serde::Serialize;
+
+/// The number of rows which are fetched from the database at once while streaming an export.
+const EXPORT_BATCH_SIZE: i64 = 500;
+
+/// The formats in which winners and participants can be exported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma separated values with a header line.
+    Csv,
+    /// One JSON object per line (see https://jsonlines.org).
+    JsonLines,
+}
+
+impl ExportFormat {
+    /// Select the export format based on the `Accept` header of a request. If the client does not
+    /// explicitly ask for one of the export formats, `None` is returned.
+    pub fn from_accept(accept: Option<&Accept>) -> Option<ExportFormat> {
+        accept?.iter().find_map(|media_type| {
+            let media_type = media_type.media_type();
+            if media_type.top() == "text" && media_type.sub() == "csv" {
+                return Some(ExportFormat::Csv);
+            }
+            if media_type.top() == "application"
+                && ["x-ndjson", "jsonl", "x-jsonlines"]
+                    .iter()
+                    .any(|sub_type| media_type.sub() == *sub_type)
+            {
+                return Some(ExportFormat::JsonLines);
+            }
+            None
+        })
+    }
+
+    fn content_type(&self) -> ContentType {
+        match self {
+            ExportFormat::Csv => ContentType::CSV,
+            ExportFormat::JsonLines => {
+                ContentType(MediaType::const_new("application", "x-ndjson", &[]))
+            }
+        }
+    }
+
+    fn file_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+        }
+    }
+
+    /// The last line of an export which could not be finished. The response has already started
+    /// at that point, so the status cannot tell the client anymore that the export is incomplete.
+    fn error_line(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "ERROR: the export is incomplete since the database failed\n",
+            ExportFormat::JsonLines => {
+                "{\"error\":\"the export is incomplete since the database failed\"}\n"
+            }
+        }
+    }
+}
+
+/// A single participant (including the information about the win) as it is exported.
+#[derive(Serialize, Queryable, Clone, Debug)]
+pub struct ExportRow {
+    /// The internally used id for the participant.
+    pub id: i32,
+    /// The first name of the participant.
+    pub first_name: String,
+    /// The last name of the participant.
+    pub last_name: String,
+    /// The date on which the participant won (if the participant won at all).
+    pub won_on: Option<NaiveDate>,
+    /// The sub-package which was set for the winner.
+    pub present_identifier: Option<String>,
+    /// The name of the user who picked the participant as a winner.
+    pub picked_by: Option<String>,
+    /// The time at which the participant was picked as a winner.
+    pub picking_time: Option<NaiveDateTime>,
+}
+
+/// The header line of a CSV export.
+const CSV_HEADER: &str = "id,first_name,last_name,won_on,present_identifier,picked_by,picking_time";
+
+/// Quote a single CSV field if it contains characters which have a special meaning in CSV. Fields
+/// which a spreadsheet would evaluate as a formula are prefixed with `'` (CSV injection).
+fn escape_csv_field(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+    field
+}
+
+impl ExportRow {
+    /// Format the row as a single line (including the line break) in the requested format.
+    pub fn to_line(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Csv => format!(
+                "{},{},{},{},{},{},{}\n",
+                self.id,
+                escape_csv_field(&self.first_name),
+                escape_csv_field(&self.last_name),
+                self.won_on.map(|date| date.to_string()).unwrap_or_default(),
+                escape_csv_field(self.present_identifier.as_deref().unwrap_or_default()),
+                escape_csv_field(self.picked_by.as_deref().unwrap_or_default()),
+                self.picking_time
+                    .map(|time| time.format("%Y-%m-%dT%H:%M:%S").to_string())
+                    .unwrap_or_default(),
+            ),
+            ExportFormat::JsonLines => format!(
+                "{}\n",
+                rocket::serde::json::to_string(self).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// Fetch the next batch of rows which follow the supplied (date of the win, participant id) key
+/// without blocking the async runtime.
+async fn fetch_export_batch(
+    db_connection_pool: &AdventskalenderDatabaseConnection,
+    raffle_id: i32,
+    only_winners: bool,
+    last_key: Option<(Option<NaiveDate>, i32)>,
+) -> Result<Vec<ExportRow>, ()> {
+    use log::error;
+
+    let db_connection_pool = db_connection_pool.clone();
+    match rocket::tokio::task::spawn_blocking(move || {
+        load_export_batch(&db_connection_pool, raffle_id, only_winners, last_key)
+    })
+    .await
+    {
+        Ok(batch) => batch,
+        Err(error) => {
+            error!(
+                "Could not fetch the next batch of participants for an export. The error was: {}",
+                error
+            );
+            Err(())
+        }
+    }
+}
+
+/// Load the next batch of rows which follow the supplied (date of the win, participant id) key.
+/// Winners are ordered by the date of the win, all other participants by their id.
+fn load_export_batch(
+    db_connection_pool: &AdventskalenderDatabaseConnection,
+    raffle_id: i32,
+    only_winners: bool,
+    last_key: Option<(Option<NaiveDate>, i32)>,
+) -> Result<Vec<ExportRow>, ()> {
+    use crate::models::WinStatus;
+    use crate::schema::participants::dsl::{
+        first_name, id, last_name, participants, raffle_id as participant_raffle_id,
+    };
+    use crate::schema::presents::dsl::{id as present_id_column, identifier, presents};
+    use crate::schema::users::dsl::username;
+    use crate::schema::wins::dsl::{
+        participant_id, picked_at, picked_by, present_id, status, wins, won_on,
+    };
+    use diesel::{
+        BoolExpressionMethods, ExpressionMethods, JoinOnDsl, NullableExpressionMethods, QueryDsl,
+        RunQueryDsl,
+    };
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(());
+        }
+    };
+
+    // just the active win of a participant is exported (the history is available via the API)
+    let mut query = participants
+        .left_join(
+            wins.on(participant_id
+                .eq(id)
+                .and(status.eq(WinStatus::Active.as_str()))),
+        )
+        .left_join(
+            crate::schema::users::table.on(crate::schema::users::id.nullable().eq(picked_by)),
+        )
+        .left_join(presents.on(present_id_column.nullable().eq(present_id)))
+        .select((
+            id,
+            first_name,
+            last_name,
+            won_on.nullable(),
+            identifier.nullable(),
+            username.nullable(),
+            picked_at.nullable(),
+        ))
+        .filter(participant_raffle_id.eq(raffle_id))
+        .limit(EXPORT_BATCH_SIZE)
+        .into_boxed();
+
+    if only_winners {
+        query = query
+            .filter(won_on.nullable().is_not_null())
+            .order_by((won_on.nullable().asc(), id.asc()));
+        if let Some((Some(last_date), last_id)) = last_key {
+            query = query.filter(
+                won_on
+                    .nullable()
+                    .gt(last_date)
+                    .or(won_on.nullable().eq(last_date).and(id.gt(last_id))),
+            );
+        }
+    } else {
+        query = query.order_by(id.asc());
+        if let Some((_, last_id)) = last_key {
+            query = query.filter(id.gt(last_id));
+        }
+    }
+
+    query.load::<ExportRow>(db_connection).map_err(|error| {
+        error!(
+            "Could not fetch the next batch of participants for an export. The error was: {}",
+            error
+        );
+    })
+}
+
+/// Create a stream of export lines which fetches the rows batch-wise from the database instead
+/// of loading everything into memory at once. The first batch is fetched right away, so a failing
+/// database can still be reported before the export starts. If a later batch cannot be fetched,
+/// the stream ends with an error.
+pub async fn export_stream(
+    db_connection_pool: AdventskalenderDatabaseConnection,
+    raffle_id: i32,
+    only_winners: bool,
+    format: ExportFormat,
+) -> Result<BoxStream<'static, Result<String, ()>>, ()> {
+    let header = match format {
+        ExportFormat::Csv => vec![Ok(format!("{}\n", CSV_HEADER))],
+        ExportFormat::JsonLines => vec![],
+    };
+    let first_batch =
+        fetch_export_batch(&db_connection_pool, raffle_id, only_winners, None).await?;
+
+    // each step returns the lines of the fetched batch and fetches the one after it
+    let rows = stream::unfold(
+        (db_connection_pool, Some(Ok(first_batch))),
+        move |(db_connection_pool, batch)| async move {
+            let batch = match batch? {
+                Ok(batch) if batch.is_empty() => return None,
+                Ok(batch) => batch,
+                Err(()) => return Some((Err(()), (db_connection_pool, None))),
+            };
+            let next_batch = if batch.len() < EXPORT_BATCH_SIZE as usize {
+                None
+            } else {
+                let last_key = batch.last().map(|row| (row.won_on, row.id));
+                Some(
+                    fetch_export_batch(&db_connection_pool, raffle_id, only_winners, last_key)
+                        .await,
+                )
+            };
+            let lines = batch
+                .iter()
+                .map(|row| row.to_line(format))
+                .collect::<String>();
+            Some((Ok(lines), (db_connection_pool, next_batch)))
+        },
+    );
+
+    Ok(stream::iter(header).chain(rows).boxed())
+}
+
+pub struct ExportResponse {
+    /// The streamed lines of the export.
+    inner: TextStream<BoxStream<'static, String>>,
+    /// The content type matching the selected export format.
+    content_type: ContentType,
+    /// The header which tells a browser to store the export as a file.
+    content_disposition: Header<'static>,
+}
+
+impl<'r> Responder<'r, 'r> for ExportResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'r> {
+        Response::build_from(self.inner.respond_to(request)?)
+            .header(self.content_type)
+            .header(self.content_disposition)
+            .ok()
+    }
+}
+
+impl ExportResponse {
+    /// Start the export. `Err` is returned if the first rows could not be fetched from the
+    /// database.
+    pub async fn new(
+        db_connection_pool: AdventskalenderDatabaseConnection,
+        raffle_id: i32,
+        only_winners: bool,
+        format: ExportFormat,
+        file_name: &str,
+    ) -> Result<ExportResponse, ()> {
+        let lines = export_stream(db_connection_pool, raffle_id, only_winners, format).await?;
+        Ok(ExportResponse {
+            inner: TextStream::from(
+                lines
+                    .map(move |line| line.unwrap_or_else(|()| format.error_line().to_string()))
+                    .boxed(),
+            ),
+            content_type: format.content_type(),
+            content_disposition: Header::new(
+                "Content-Disposition",
+                format!(
+                    "attachment; filename=\"{}.{}\"",
+                    file_name,
+                    format.file_extension()
+                ),
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_lines_are_escaped() {
+        let row = ExportRow {
+            id: 1,
+            first_name: "Jane, Jr.".to_string(),
+            last_name: "O\"Neil".to_string(),
+            won_on: NaiveDate::from_ymd_opt(2024, 12, 3),
+            present_identifier: Some("B".to_string()),
+            picked_by: None,
+            picking_time: None,
+        };
+        assert_eq!(
+            row.to_line(ExportFormat::Csv),
+            "1,\"Jane, Jr.\",\"O\"\"Neil\",2024-12-03,B,,\n"
+        );
+    }
+
+    #[test]
+    fn csv_formulas_are_not_evaluated() {
+        assert_eq!(
+            escape_csv_field("=HYPERLINK(\"x\")"),
+            "\"'=HYPERLINK(\"\"x\"\")\""
+        );
+        assert_eq!(escape_csv_field("+49 123"), "'+49 123");
+        assert_eq!(escape_csv_field("-1"), "'-1");
+        assert_eq!(escape_csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(escape_csv_field("Jane-Doe"), "Jane-Doe");
+    }
+
+    #[test]
+    fn export_format_is_selected_from_accept_header() {
+        use std::str::FromStr;
+
+        let csv = Accept::from_str("text/csv, */*;q=0.1").unwrap();
+        let json_lines = Accept::from_str("application/x-ndjson").unwrap();
+        let json = Accept::from_str("application/json").unwrap();
+        assert_eq!(
+            ExportFormat::from_accept(Some(&csv)),
+            Some(ExportFormat::Csv)
+        );
+        assert_eq!(
+            ExportFormat::from_accept(Some(&json_lines)),
+            Some(ExportFormat::JsonLines)
+        );
+        assert_eq!(ExportFormat::from_accept(Some(&json)), None);
+        assert_eq!(ExportFormat::from_accept(None), None);
+    }
+}
@@ -1,27 +1,44 @@
+use crate::discovery::OpenIdConfiguration;
+use crate::keys::KeyStore;
+use crate::oidc::OidcSettings;
+use crate::rate_limiter::RateLimitConfig;
+use crate::two_factor::TotpSettings;
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
 use diesel::PgConnection;
-use jsonwebtoken::{DecodingKey, EncodingKey};
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::{Request, Response};
 use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
 
 #[derive(Clone)]
 pub struct BackendConfiguration {
     /// The host base URL of the API (e.g. https://www.example.com; without a path like /api).
     pub api_host: String,
-    /// The key which is used to encode a token signature.
-    pub encoding_key: Option<EncodingKey>,
-    /// The key which is used to decode a token signature.
-    pub decoding_key: Option<DecodingKey>,
-    /// The raw Ed25519 key bytes in PKCS8 format (for JWKS endpoint).
-    pub ed25519_key_bytes: Option<Vec<u8>>,
+    /// The keys for signing and validating the tokens. They are shared, so they can be rotated
+    /// while the server is running.
+    pub keys: Arc<RwLock<KeyStore>>,
+    /// The OpenID Connect discovery document, which is created once on startup.
+    pub openid_configuration: Arc<OpenIdConfiguration>,
     /// The UUID used for the health check on  healthcheck.io.
     pub healthcheck_project: String,
     /// A list of URLs which represent the audience for this token.
     pub token_audience: HashSet<String>,
+    /// The number of seconds an issued access token is valid.
+    pub token_lifetime_in_seconds: usize,
+    /// The number of seconds an issued refresh token can be exchanged for a new access token.
+    pub refresh_token_lifetime_in_seconds: usize,
+    /// The number of failed logins which are allowed per user and IP address in a time window.
+    pub login_rate_limit: RateLimitConfig,
+    /// The settings of the login through an external OpenID Connect provider (or `None` if it is
+    /// disabled).
+    pub external_login: Option<OidcSettings>,
+    /// The settings of the two-factor authentication with TOTP codes (or `None` if it is
+    /// disabled).
+    pub totp: Option<TotpSettings>,
 }
 
 /// TODO
+#[derive(Clone)]
 pub struct AdventskalenderDatabaseConnection(Pool<ConnectionManager<PgConnection>>);
 
 /// TODO
@@ -33,6 +50,55 @@ impl AdventskalenderDatabaseConnection {
     }
 }
 
+/// Create a pool of up to `pool_size` connections to the database server. If the server is not
+/// reachable (e.g. since it is still starting up), the connection is retried up to `max_retries`
+/// times with an increasing delay.
+pub fn connect_to_database(
+    database_connection_url: &str,
+    pool_size: u32,
+    max_retries: u32,
+) -> Result<Pool<ConnectionManager<PgConnection>>, String> {
+    use log::info;
+    use std::time::Duration;
+
+    let mut retry_count = 0;
+    let mut backoff_duration = Duration::from_millis(500);
+
+    loop {
+        let db_connection_pool_manager = ConnectionManager::new(database_connection_url);
+
+        // verify that we can actually get a connection from the new pool
+        let last_error = match Pool::builder()
+            .max_size(pool_size)
+            .connection_timeout(Duration::from_secs(5))
+            .build(db_connection_pool_manager)
+        {
+            Ok(pool) => match pool.get() {
+                Ok(_) => {
+                    info!("Successfully connected to the database server");
+                    return Ok(pool);
+                }
+                Err(e) => format!("Could not get a database connection: {}", e),
+            },
+            Err(e) => format!("Could not create the database connection pool: {}", e),
+        };
+
+        retry_count += 1;
+        if retry_count >= max_retries {
+            return Err(format!(
+                "Failed to connect to the database after {} attempts. Last error: {}",
+                max_retries, last_error
+            ));
+        }
+        info!(
+            "Database connection attempt {}/{} failed, retrying in {:?}...",
+            retry_count, max_retries, backoff_duration
+        );
+        std::thread::sleep(backoff_duration);
+        backoff_duration = std::cmp::min(backoff_duration * 2, Duration::from_secs(10));
+    }
+}
+
 /// TODO
 impl From<Pool<ConnectionManager<PgConnection>>> for AdventskalenderDatabaseConnection {
     /// TODO
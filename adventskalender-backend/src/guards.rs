@@ -1,10 +1,104 @@
 use rocket::request::{FromRequest, Outcome};
 use rocket::Request;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// The roles of the users. Each role includes all permissions of the roles before it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// The user can look at the raffles, participants and winners.
+    #[default]
+    Viewer,
+    /// The user can additionally pick, remove and replace winners and manage the presents.
+    Operator,
+    /// The user can additionally manage the raffles, the participants and the users.
+    Admin,
+}
+
+impl Role {
+    /// Get the value which is stored in the database for the role.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Get the role for the value stored in the database. Unknown values get the least
+    /// permissions.
+    pub fn from_name(name: &str) -> Role {
+        match name {
+            "admin" => Role::Admin,
+            "operator" => Role::Operator,
+            _ => Role::Viewer,
+        }
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
 
 /// The representation of an authenticated user. As soon as this is included in the parameters
 /// of a route, the call can be just made with an valid token in the header.
 pub struct AuthenticatedUser {
     pub username: String,
+    pub role: Role,
+    /// The id of the session (i.e. the login) the token of the user was issued for.
+    pub session_id: String,
+    /// Whether the password was reset by an administrator and has to be changed by the user.
+    pub must_change_password: bool,
+}
+
+/// The routes which can still be called by users who have to change their password.
+const PASSWORD_CHANGE_ROUTES: [&str; 2] = ["update_user_password", "get_current_user"];
+
+/// A marker for the role which is at least required to call a route (see [`RequireRole`]).
+pub trait RequiredRole {
+    /// The role the user needs at least.
+    const ROLE: Role;
+}
+
+/// Requires the [`Role::Viewer`] role (which every user has).
+pub struct Viewer;
+
+/// Requires the [`Role::Operator`] (or the [`Role::Admin`]) role.
+pub struct Operator;
+
+/// Requires the [`Role::Admin`] role.
+pub struct Admin;
+
+impl RequiredRole for Viewer {
+    const ROLE: Role = Role::Viewer;
+}
+
+impl RequiredRole for Operator {
+    const ROLE: Role = Role::Operator;
+}
+
+impl RequiredRole for Admin {
+    const ROLE: Role = Role::Admin;
+}
+
+/// An authenticated user who has at least the role `R` (e.g. `RequireRole<Operator>`). Routes with
+/// this guard are refused with `403 Forbidden` for users with a lesser role.
+pub struct RequireRole<R: RequiredRole> {
+    user: AuthenticatedUser,
+    role: PhantomData<R>,
+}
+
+impl<R: RequiredRole> Deref for RequireRole<R> {
+    type Target = AuthenticatedUser;
+
+    fn deref(&self) -> &AuthenticatedUser {
+        &self.user
+    }
 }
 
 #[derive(Debug)]
@@ -17,6 +111,14 @@ pub enum AuthorizationError {
     InvalidToken,
     /// It seems that we failed to validate the token (e.g. we do not know if the token is valid or not)
     CannotValidateToken,
+    /// The user is authenticated but the role of the user does not allow to call the route.
+    InsufficientRole,
+    /// The user of the token was disabled or deleted after the token was issued.
+    DisabledUser,
+    /// The session the token was issued for was revoked (e.g. by logging out) or expired.
+    RevokedSession,
+    /// The password of the user was reset and has to be changed before the route can be called.
+    PasswordChangeRequired,
 }
 
 #[rocket::async_trait]
@@ -26,9 +128,11 @@ impl<'r> FromRequest<'r> for AuthenticatedUser {
     async fn from_request(
         request: &'r Request<'_>,
     ) -> Outcome<AuthenticatedUser, AuthorizationError> {
-        use crate::fairings::BackendConfiguration;
-        use crate::Claims;
-        use jsonwebtoken::{decode, Algorithm, Validation};
+        use crate::fairings::{AdventskalenderDatabaseConnection, BackendConfiguration};
+        use crate::keys::validation_key;
+        use crate::sessions::is_session_active;
+        use crate::{lookup_user_by_name, Claims};
+        use jsonwebtoken::{decode, decode_header, Algorithm, Validation};
         use log::error;
         use rocket::http::Status;
 
@@ -77,11 +181,15 @@ impl<'r> FromRequest<'r> for AuthenticatedUser {
         let backend_config = request.rocket().state::<BackendConfiguration>().map_or(
             BackendConfiguration {
                 api_host: "".to_string(),
-                encoding_key: None,
-                decoding_key: None,
-                ed25519_key_bytes: None,
+                keys: Default::default(),
+                openid_configuration: Default::default(),
                 healthcheck_project: "".to_string(),
                 token_audience: [].into(),
+                token_lifetime_in_seconds: 0,
+                refresh_token_lifetime_in_seconds: 0,
+                login_rate_limit: Default::default(),
+                external_login: None,
+                totp: None,
             },
             |config| config.clone(),
         );
@@ -99,12 +207,23 @@ impl<'r> FromRequest<'r> for AuthenticatedUser {
         issuer_set.insert(backend_config.api_host.clone());
         validation_parameter.iss = Some(issuer_set);
 
+        // pick the key the token was signed with by the key id in its header. retired keys are
+        // still known until the tokens they signed expired
+        let decoding_key = match decode_header(&token_str)
+            .ok()
+            .and_then(|header| header.kid)
+            .and_then(|key_id| validation_key(&backend_config.keys, &key_id))
+        {
+            Some(decoding_key) => decoding_key,
+            None => {
+                error!("The supplied token was not signed with a known key");
+                return Outcome::Error((Status::Forbidden, AuthorizationError::InvalidToken));
+            }
+        };
+
         // verify the validity of the token supplied in the header or cookie
-        let decoded_token = match decode::<Claims>(
-            &token_str,
-            &backend_config.decoding_key.unwrap(),
-            &validation_parameter,
-        ) {
+        let decoded_token = match decode::<Claims>(&token_str, &decoding_key, &validation_parameter)
+        {
             Ok(token) => token,
             Err(error) => {
                 error!(
@@ -115,10 +234,153 @@ impl<'r> FromRequest<'r> for AuthenticatedUser {
             }
         };
 
+        // the token stays valid until it expires, so we have to check if the user was disabled
+        // or deleted in the meantime
+        let db_connection = &mut match request
+            .rocket()
+            .state::<AdventskalenderDatabaseConnection>()
+            .map(|db_connection_pool| db_connection_pool.get())
+        {
+            Some(Ok(connection)) => connection,
+            _ => {
+                error!("Could not get a connection from the database connection pool to check if the user is disabled");
+                return Outcome::Error((
+                    Status::InternalServerError,
+                    AuthorizationError::CannotValidateToken,
+                ));
+            }
+        };
+        let user = match lookup_user_by_name(db_connection, decoded_token.claims.sub.clone()) {
+            Ok(user) if !user.disabled => user,
+            _ => {
+                error!(
+                    "The user {} is disabled or does not exist anymore, the token is refused",
+                    decoded_token.claims.sub
+                );
+                return Outcome::Error((Status::Forbidden, AuthorizationError::DisabledUser));
+            }
+        };
+
+        // the session of the token may have been revoked (e.g. by logging out) in the meantime
+        match is_session_active(db_connection, &decoded_token.claims.sid, user.id) {
+            Ok(true) => {}
+            Ok(false) => {
+                error!(
+                    "The session of the token of {} was revoked or expired, the token is refused",
+                    decoded_token.claims.sub
+                );
+                return Outcome::Error((Status::Forbidden, AuthorizationError::RevokedSession));
+            }
+            Err(error) => {
+                error!(
+                    "Could not check if the session of the token is still active. The error was: {}",
+                    error
+                );
+                return Outcome::Error((
+                    Status::InternalServerError,
+                    AuthorizationError::CannotValidateToken,
+                ));
+            }
+        }
+
+        // a password which was reset by an administrator must not be used for anything but
+        // replacing it
+        let route_name = request
+            .route()
+            .and_then(|route| route.name.as_deref())
+            .unwrap_or_default();
+        if user.must_change_password && !PASSWORD_CHANGE_ROUTES.contains(&route_name) {
+            error!(
+                "The user {} has to change the password before calling {}",
+                decoded_token.claims.sub,
+                request.uri()
+            );
+            return Outcome::Error((
+                Status::Forbidden,
+                AuthorizationError::PasswordChangeRequired,
+            ));
+        }
+
         // if we reach this step, the validation was successful, and we can allow the user to
-        // call the route
+        // call the route. The role is taken from the database (and not from the token), so a
+        // changed role applies right away to the tokens which were issued before
         Outcome::Success(AuthenticatedUser {
             username: decoded_token.claims.sub,
+            role: Role::from_name(&user.role),
+            session_id: decoded_token.claims.sid,
+            must_change_password: user.must_change_password,
         })
     }
 }
+
+#[rocket::async_trait]
+impl<'r, R: RequiredRole> FromRequest<'r> for RequireRole<R> {
+    type Error = AuthorizationError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<RequireRole<R>, AuthorizationError> {
+        use log::error;
+        use rocket::http::Status;
+
+        let user = match request.guard::<AuthenticatedUser>().await {
+            Outcome::Success(user) => user,
+            Outcome::Error(error) => return Outcome::Error(error),
+            Outcome::Forward(status) => return Outcome::Forward(status),
+        };
+
+        // the roles are ordered by their permissions, so every role includes the lesser ones
+        if user.role < R::ROLE {
+            error!(
+                "The user {} with the role {} tried to call {} which requires the role {}",
+                user.username,
+                user.role,
+                request.uri(),
+                R::ROLE
+            );
+            return Outcome::Error((Status::Forbidden, AuthorizationError::InsufficientRole));
+        }
+
+        Outcome::Success(RequireRole {
+            user,
+            role: PhantomData,
+        })
+    }
+}
+
+/// The user agent (e.g. the browser) which sent the request, if it identified itself.
+pub struct UserAgent(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserAgent {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<UserAgent, Self::Error> {
+        Outcome::Success(UserAgent(
+            request
+                .headers()
+                .get_one("User-Agent")
+                .map(|user_agent| user_agent.to_string()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_include_the_lesser_roles() {
+        assert!(Role::Admin >= Operator::ROLE);
+        assert!(Role::Operator >= Operator::ROLE);
+        assert!(Role::Viewer < Operator::ROLE);
+        assert!(Role::Operator < Admin::ROLE);
+        assert!(Role::Viewer >= Viewer::ROLE);
+    }
+
+    #[test]
+    fn roles_are_read_from_the_database() {
+        for role in [Role::Viewer, Role::Operator, Role::Admin] {
+            assert_eq!(Role::from_name(role.as_str()), role);
+        }
+        assert_eq!(Role::from_name("superuser"), Role::Viewer);
+    }
+}
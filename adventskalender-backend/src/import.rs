@@ -0,0 +1,376 @@
+use crate::routes::{ParticipantName, MAX_PARTICIPANT_NAME_LENGTH};
+use diesel::PgConnection;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// The formats which are accepted for importing participants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// One participant per line with the first and the last name separated by `;` or `,`.
+    Csv,
+    /// A JSON array of objects with a `first_name` and a `last_name` field.
+    Json,
+}
+
+/// A single row of an import file before it was validated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportRow {
+    /// The row number in the supplied file (starting with 1, including a possible header).
+    pub row: usize,
+    /// The first name as it was supplied.
+    pub first_name: String,
+    /// The last name as it was supplied.
+    pub last_name: String,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ImportRowError {
+    /// The row number in the supplied file (starting with 1, including a possible header).
+    pub row: usize,
+    /// A human-readable description of what is wrong with the row.
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportReport {
+    /// A flag which indicates if the import was just validated without storing anything.
+    pub dry_run: bool,
+    /// The number of participant rows found in the supplied data.
+    pub total_rows: usize,
+    /// The number of rows which passed the validation (even if other rows prevented the import).
+    pub valid_rows: usize,
+    /// The number of participants which were (or would have been) added.
+    pub imported: usize,
+    /// All problems which were found in the supplied data.
+    pub errors: Vec<ImportRowError>,
+}
+
+/// Split a single CSV line into its fields. Fields can be quoted with `"` and quotes inside of
+/// quoted fields are escaped by doubling them.
+fn split_csv_line(line: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = vec![];
+    let mut current_field = String::new();
+    let mut in_quotes = false;
+    let mut characters = line.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '"' if in_quotes && characters.peek() == Some(&'"') => {
+                current_field.push('"');
+                characters.next();
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if current_field.trim().is_empty() => {
+                current_field.clear();
+                in_quotes = true;
+            }
+            _ if character == delimiter && !in_quotes => {
+                fields.push(std::mem::take(&mut current_field));
+            }
+            _ => current_field.push(character),
+        }
+    }
+
+    if in_quotes {
+        return Err("The row contains a quoted field which is not terminated".to_string());
+    }
+    fields.push(current_field);
+    Ok(fields)
+}
+
+/// The delimiters which can separate the first and the last name in CSV data.
+const CSV_DELIMITERS: [char; 2] = [';', ','];
+
+/// Detect the delimiter from the first non-empty line (the header, if the data has one). It is the
+/// delimiter which splits the line into exactly two fields, so a quoted name of the first line
+/// can still contain the other delimiter.
+fn detect_delimiter(input: &str) -> char {
+    let first_line = match input.lines().find(|line| !line.trim().is_empty()) {
+        Some(first_line) => first_line,
+        None => return ',',
+    };
+
+    CSV_DELIMITERS
+        .into_iter()
+        .find(|delimiter| {
+            matches!(split_csv_line(first_line, *delimiter), Ok(fields) if fields.len() == 2)
+        })
+        .unwrap_or(if first_line.contains(';') { ';' } else { ',' })
+}
+
+/// Check if the supplied fields look like the header of an import file.
+fn is_header(fields: &[String]) -> bool {
+    let normalized = fields
+        .iter()
+        .map(|field| field.trim().to_lowercase().replace([' ', '-'], "_"))
+        .collect::<Vec<String>>();
+    normalized.len() == 2
+        && ["first_name", "firstname"].contains(&normalized[0].as_str())
+        && ["last_name", "lastname"].contains(&normalized[1].as_str())
+}
+
+/// Parse CSV data with one participant per line. The delimiter (`;` or `,`) is detected from the
+/// first non-empty line, every other row has to use the same one and an optional
+/// `first_name;last_name` header is skipped.
+pub fn parse_csv(input: &str) -> (Vec<ImportRow>, Vec<ImportRowError>) {
+    let mut rows = vec![];
+    let mut errors = vec![];
+
+    let delimiter = detect_delimiter(input);
+
+    for (index, line) in input.lines().enumerate() {
+        let row = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields = match split_csv_line(line, delimiter) {
+            Ok(fields) => fields,
+            Err(message) => {
+                errors.push(ImportRowError { row, message });
+                continue;
+            }
+        };
+
+        if rows.is_empty() && errors.is_empty() && is_header(&fields) {
+            continue;
+        }
+
+        if fields.len() != 2 {
+            // a row which would be fine with the other delimiter was most likely added by hand,
+            // so point that out instead of just counting the fields
+            let uses_other_delimiter = CSV_DELIMITERS.into_iter().any(|other_delimiter| {
+                other_delimiter != delimiter
+                    && matches!(split_csv_line(line, other_delimiter), Ok(other_fields) if other_fields.len() == 2)
+            });
+            let message = if uses_other_delimiter {
+                format!(
+                    "The row does not separate the first and the last name by '{}' like the first row of the file",
+                    delimiter
+                )
+            } else {
+                format!(
+                    "Expected 2 fields (first and last name) separated by '{}' but got {}",
+                    delimiter,
+                    fields.len()
+                )
+            };
+            errors.push(ImportRowError { row, message });
+            continue;
+        }
+
+        rows.push(ImportRow {
+            row,
+            first_name: fields[0].clone(),
+            last_name: fields[1].clone(),
+        });
+    }
+
+    (rows, errors)
+}
+
+/// Parse a JSON array of participant names.
+pub fn parse_json(input: &str) -> Result<Vec<ImportRow>, String> {
+    match rocket::serde::json::from_str::<Vec<ParticipantName>>(input) {
+        Ok(names) => Ok(names
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| ImportRow {
+                row: index + 1,
+                first_name: name.first_name,
+                last_name: name.last_name,
+            })
+            .collect()),
+        Err(error) => Err(format!("The supplied JSON could not be parsed: {}", error)),
+    }
+}
+
+/// Validate the parsed rows against the limits of the database and the names of the
+/// participants which are already stored. Returns the names which can be inserted as well as all
+/// rows which cannot be imported.
+pub fn validate_rows(
+    rows: Vec<ImportRow>,
+    existing_names: &HashSet<(String, String)>,
+) -> (Vec<ParticipantName>, Vec<ImportRowError>) {
+    let mut valid_names = vec![];
+    let mut errors = vec![];
+    let mut names_in_import = HashSet::new();
+
+    for current_row in rows {
+        let supplied_name = ParticipantName {
+            first_name: current_row.first_name,
+            last_name: current_row.last_name,
+        };
+        let name = match supplied_name.normalized() {
+            Some(name) => name,
+            None => {
+                errors.push(ImportRowError {
+                    row: current_row.row,
+                    message: format!(
+                        "The first and the last name have to be between 1 and {} characters long",
+                        MAX_PARTICIPANT_NAME_LENGTH
+                    ),
+                });
+                continue;
+            }
+        };
+
+        let key = (name.first_name.clone(), name.last_name.clone());
+        if existing_names.contains(&key) {
+            errors.push(ImportRowError {
+                row: current_row.row,
+                message: format!(
+                    "A participant with the name '{} {}' already exists",
+                    name.first_name, name.last_name
+                ),
+            });
+            continue;
+        }
+        if !names_in_import.insert(key) {
+            errors.push(ImportRowError {
+                row: current_row.row,
+                message: format!(
+                    "The participant '{} {}' is contained more than once in the import",
+                    name.first_name, name.last_name
+                ),
+            });
+            continue;
+        }
+
+        valid_names.push(name);
+    }
+
+    (valid_names, errors)
+}
+
+/// Parse the supplied data in the given format into the rows of the participants and the rows
+/// which could not be parsed.
+pub fn parse_import(data: &str, format: ImportFormat) -> (Vec<ImportRow>, Vec<ImportRowError>) {
+    match format {
+        ImportFormat::Csv => parse_csv(data),
+        ImportFormat::Json => match parse_json(data) {
+            Ok(rows) => (rows, vec![]),
+            Err(message) => (vec![], vec![ImportRowError { row: 0, message }]),
+        },
+    }
+}
+
+/// Validate the supplied data and add the participants to the raffle in a single transaction. If
+/// a single row is invalid (or for a dry run), nothing gets inserted at all and the report just
+/// contains the number of participants which would have been added. The number of valid rows is
+/// reported in any case, so a dry run tells how many rows are fine besides the invalid ones.
+pub fn store_participants(
+    connection: &mut PgConnection,
+    raffle_id: i32,
+    data: &str,
+    format: ImportFormat,
+    dry_run: bool,
+) -> Result<ImportReport, diesel::result::Error> {
+    use crate::models::NewParticipant;
+    use crate::schema::participants::dsl::{
+        first_name, last_name, participants, raffle_id as participant_raffle_id,
+    };
+    use diesel::{insert_into, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    let (rows, mut errors) = parse_import(data, format);
+    let total_rows = rows.len() + errors.iter().filter(|error| error.row > 0).count();
+
+    let mut valid_rows = 0;
+    let importable_rows = connection.transaction::<_, diesel::result::Error, _>(|connection| {
+        let existing_names = participants
+            .filter(participant_raffle_id.eq(raffle_id))
+            .select((first_name, last_name))
+            .load::<(String, String)>(connection)?
+            .into_iter()
+            .collect::<HashSet<(String, String)>>();
+        let (valid_names, validation_errors) = validate_rows(rows, &existing_names);
+        errors.extend(validation_errors);
+        errors.sort_by_key(|error| error.row);
+        valid_rows = valid_names.len();
+
+        if dry_run || !errors.is_empty() {
+            return Ok(valid_names.len());
+        }
+
+        let participants_to_insert = valid_names
+            .into_iter()
+            .map(|name| NewParticipant {
+                first_name: name.first_name,
+                last_name: name.last_name,
+                raffle_id,
+            })
+            .collect::<Vec<NewParticipant>>();
+        insert_into(participants)
+            .values(&participants_to_insert)
+            .execute(connection)
+    })?;
+
+    Ok(ImportReport {
+        dry_run,
+        total_rows,
+        valid_rows,
+        imported: if errors.is_empty() {
+            importable_rows
+        } else {
+            0
+        },
+        errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_csv_detects_semicolon_and_skips_header() {
+        let (rows, errors) =
+            parse_csv("first_name;last_name\nJane;Doe\n\n\"Mc;Coy\";\"O\"\"Neil\"\n");
+        assert!(errors.is_empty());
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].row, 2);
+        assert_eq!(rows[1].first_name, "Mc;Coy");
+        assert_eq!(rows[1].last_name, "O\"Neil");
+    }
+
+    #[test]
+    fn parse_csv_reports_rows_with_wrong_number_of_fields() {
+        let (rows, errors) = parse_csv("Jane,Doe\nJohn\nA,B,C");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(
+            errors.iter().map(|error| error.row).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+    }
+
+    #[test]
+    fn parse_csv_detects_the_delimiter_from_the_first_row_and_checks_every_row() {
+        let (rows, errors) = parse_csv("\"Mc;Coy\",Doe\nJane,Doe\nJohn;Doe\n");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].first_name, "Mc;Coy");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row, 3);
+        assert!(errors[0].message.contains("like the first row"));
+    }
+
+    #[test]
+    fn validate_rows_rejects_long_duplicate_and_existing_names() {
+        let existing_names = HashSet::from([("Existing".to_string(), "User".to_string())]);
+        let rows = parse_json(&format!(
+            r#"[{{"first_name": "Jane", "last_name": "Doe"}},
+                {{"first_name": " Jane ", "last_name": "Doe"}},
+                {{"first_name": "Existing", "last_name": "User"}},
+                {{"first_name": "{}", "last_name": "Long"}},
+                {{"first_name": "", "last_name": "Empty"}}]"#,
+            "x".repeat(MAX_PARTICIPANT_NAME_LENGTH + 1)
+        ))
+        .unwrap();
+
+        let (valid_names, errors) = validate_rows(rows, &existing_names);
+        assert_eq!(valid_names.len(), 1);
+        assert_eq!(
+            errors.iter().map(|error| error.row).collect::<Vec<_>>(),
+            vec![2, 3, 4, 5]
+        );
+    }
+}
@@ -0,0 +1,390 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey};
+use ring::signature::Ed25519KeyPair;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// The file extension of the key files in the key directory.
+const KEY_FILE_EXTENSION: &str = "key";
+
+/// The format of the creation time at the start of a key id (e.g. `20261018T104900Z-3fa1`).
+const KEY_ID_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Read the Ed25519 key pair (PKCS8 format) from a file and ensure that it can be used.
+pub fn read_keypair(key_file_path: &Path) -> Result<Vec<u8>, String> {
+    let key_bytes =
+        fs::read(key_file_path).map_err(|e| format!("Failed to read the key file: {}", e))?;
+    Ed25519KeyPair::from_pkcs8(&key_bytes)
+        .map_err(|e| format!("The key file is invalid or corrupted: {}", e))?;
+    Ok(key_bytes)
+}
+
+/// Generate a new Ed25519 key pair and store it in the supplied file. The key is written to a
+/// temporary file first, so the file always contains a complete key.
+pub fn write_new_keypair(key_file_path: &Path) -> Result<Vec<u8>, String> {
+    use std::io::Write;
+
+    let key_pair_doc = match Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()) {
+        Ok(doc) => doc,
+        Err(e) => return Err(format!("Failed to generate Ed25519 key pair: {}", e)),
+    };
+
+    // create a parent directory if it doesn't exist
+    if let Some(parent) = key_file_path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create key directory: {}", e))?;
+        }
+    }
+
+    // write the key to a temporary file first which is renamed afterwards
+    let temporary_path = key_file_path.with_extension("tmp");
+    let mut file = fs::File::create(&temporary_path)
+        .map_err(|e| format!("Failed to create key file: {}", e))?;
+
+    // set file permissions to 0600 (read/write for the owner only)
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&temporary_path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to set file permissions: {}", e))?;
+    }
+
+    file.write_all(key_pair_doc.as_ref())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write key to file: {}", e))?;
+    fs::rename(&temporary_path, key_file_path)
+        .map_err(|e| format!("Failed to replace the key file: {}", e))?;
+
+    Ok(key_pair_doc.as_ref().to_vec())
+}
+
+/// Create the keys for signing and validating the tokens from an Ed25519 key pair. Encoding needs
+/// the full key pair, decoding needs only the public key.
+pub fn token_keys(ed25519_key_bytes: &[u8]) -> Result<(EncodingKey, DecodingKey), String> {
+    let encoding_key = EncodingKey::from_ed_der(ed25519_key_bytes);
+    let public_key_bytes = public_key(ed25519_key_bytes)?;
+    Ok((encoding_key, DecodingKey::from_ed_der(&public_key_bytes)))
+}
+
+/// Extract the public key (32 bytes) from an Ed25519 key pair.
+fn public_key(ed25519_key_bytes: &[u8]) -> Result<Vec<u8>, String> {
+    use ring::signature::KeyPair;
+
+    match Ed25519KeyPair::from_pkcs8(ed25519_key_bytes) {
+        Ok(keypair) => Ok(keypair.public_key().as_ref().to_vec()),
+        Err(e) => Err(format!(
+            "Failed to extract public key from Ed25519 keypair: {}",
+            e
+        )),
+    }
+}
+
+/// Generate the id of a key created at the supplied time. The ids start with the creation time, so
+/// sorting them sorts the keys from the oldest to the newest one.
+fn new_key_id(created_at: DateTime<Utc>) -> Result<String, String> {
+    use crate::sessions::random_identifier;
+
+    let suffix = random_identifier()
+        .map_err(|_| "Could not generate a random id for the new key".to_string())?;
+    Ok(format!(
+        "{}-{}",
+        created_at.format(KEY_ID_TIME_FORMAT),
+        &suffix[..4]
+    ))
+}
+
+/// Get the creation time of a key from its id (or `None` if the id was not generated by
+/// [`new_key_id`]).
+fn creation_time_of(key_id: &str) -> Option<DateTime<Utc>> {
+    let (time, _) = key_id.split_once('-')?;
+    NaiveDateTime::parse_from_str(time, KEY_ID_TIME_FORMAT)
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+/// A key for signing and validating tokens which is identified by the `kid` in the token header.
+#[derive(Clone)]
+pub struct SigningKey {
+    /// The id of the key (`kid`).
+    pub id: String,
+    /// The time the key was created (and became the active key).
+    pub created_at: DateTime<Utc>,
+    /// The key which is used to sign the tokens.
+    pub encoding_key: EncodingKey,
+    /// The key which is used to validate the signature of the tokens.
+    pub decoding_key: DecodingKey,
+    /// The raw public key (published in the JWKS).
+    pub public_key: Vec<u8>,
+}
+
+impl SigningKey {
+    fn from_file(path: &Path) -> Result<SigningKey, String> {
+        let id = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let created_at = creation_time_of(&id).ok_or(format!(
+            "The name of the key file {} is not a valid key id",
+            path.display()
+        ))?;
+        let key_bytes =
+            read_keypair(path).map_err(|error| format!("{} ({})", error, path.display()))?;
+        let (encoding_key, decoding_key) = token_keys(&key_bytes)?;
+        Ok(SigningKey {
+            id,
+            created_at,
+            encoding_key,
+            decoding_key,
+            public_key: public_key(&key_bytes)?,
+        })
+    }
+}
+
+/// The keys stored in the key directory. Each file `<kid>.key` contains one Ed25519 key pair. The
+/// newest key is the active one which signs new tokens; the older (retired) keys are just used to
+/// validate the tokens they signed until these tokens expired.
+#[derive(Default)]
+pub struct KeyStore {
+    /// The directory the keys are stored in.
+    directory: PathBuf,
+    /// The keys, sorted from the oldest to the newest (active) one.
+    keys: Vec<SigningKey>,
+}
+
+impl KeyStore {
+    /// Read all keys of the key directory (there are none if the directory does not exist yet).
+    pub fn load(directory: &Path) -> Result<KeyStore, String> {
+        let mut key_store = KeyStore {
+            directory: directory.to_path_buf(),
+            keys: vec![],
+        };
+        key_store.reload()?;
+        Ok(key_store)
+    }
+
+    /// Read all keys of the key directory and create a first key if there is no key yet.
+    pub fn load_or_create(directory: &Path) -> Result<KeyStore, String> {
+        use log::info;
+
+        let mut key_store = KeyStore::load(directory)?;
+        if key_store.keys.is_empty() {
+            let key = key_store.rotate()?;
+            info!(
+                "Generated the first Ed25519 key pair {} in {:?}",
+                key.id, directory
+            );
+        }
+        Ok(key_store)
+    }
+
+    /// Read the keys of the key directory again (e.g. to pick up a key which was created by
+    /// another instance or the `rotate-key` command).
+    pub fn reload(&mut self) -> Result<(), String> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                self.keys = vec![];
+                return Ok(());
+            }
+            Err(error) => {
+                return Err(format!(
+                    "Failed to read the key directory {}: {}",
+                    self.directory.display(),
+                    error
+                ))
+            }
+        };
+        let mut keys = vec![];
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("Failed to read the key directory: {}", e))?
+                .path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == KEY_FILE_EXTENSION)
+            {
+                keys.push(SigningKey::from_file(&path)?);
+            }
+        }
+        keys.sort_by(|first, second| first.id.cmp(&second.id));
+        self.keys = keys;
+        Ok(())
+    }
+
+    /// The key which signs new tokens (or `None` if there is no key at all).
+    pub fn active_key(&self) -> Option<SigningKey> {
+        self.keys.last().cloned()
+    }
+
+    /// The key with the supplied id (or `None` if there is no such key).
+    pub fn key(&self, key_id: &str) -> Option<SigningKey> {
+        self.keys.iter().find(|key| key.id == key_id).cloned()
+    }
+
+    /// All keys which can be used to validate tokens (including the active one).
+    pub fn keys(&self) -> &[SigningKey] {
+        &self.keys
+    }
+
+    /// Create a new key which becomes the active key. The previous keys are kept until the tokens
+    /// they signed expired (see [`KeyStore::prune`]).
+    pub fn rotate(&mut self) -> Result<SigningKey, String> {
+        // the ids are sorted by their creation time (in seconds), so a new key must be newer than
+        // the current one (even if it is rotated twice within a second or the clock was changed)
+        let created_at = self.keys.last().map_or(Utc::now(), |key| {
+            (key.created_at + Duration::seconds(1)).max(Utc::now())
+        });
+        let key_id = new_key_id(created_at)?;
+        write_new_keypair(
+            &self
+                .directory
+                .join(format!("{}.{}", key_id, KEY_FILE_EXTENSION)),
+        )?;
+        self.reload()?;
+        self.key(&key_id)
+            .ok_or(format!("The new key {} could not be read again", key_id))
+    }
+
+    /// Check if the active key is older than the supplied age, i.e. it should be rotated.
+    pub fn needs_rotation(&self, maximum_age: Duration) -> bool {
+        self.keys
+            .last()
+            .map_or(true, |key| key.created_at + maximum_age <= Utc::now())
+    }
+
+    /// Remove the retired keys which were replaced longer than the supplied retention ago (i.e.
+    /// all tokens they signed expired) and return their ids.
+    pub fn prune(&mut self, retention: Duration) -> Result<Vec<String>, String> {
+        let now = Utc::now();
+        let mut removed_keys = vec![];
+        for (key, successor) in self.keys.iter().zip(self.keys.iter().skip(1)) {
+            if successor.created_at + retention < now {
+                fs::remove_file(
+                    self.directory
+                        .join(format!("{}.{}", key.id, KEY_FILE_EXTENSION)),
+                )
+                .map_err(|e| format!("Failed to remove the retired key {}: {}", key.id, e))?;
+                removed_keys.push(key.id.clone());
+            }
+        }
+        self.keys.retain(|key| !removed_keys.contains(&key.id));
+        Ok(removed_keys)
+    }
+}
+
+/// The time a retired key is kept after it was replaced, i.e. until all tokens it signed expired
+/// (including the leeway of the token validation).
+pub fn key_retention(token_lifetime_in_seconds: usize) -> Duration {
+    Duration::seconds(token_lifetime_in_seconds as i64 + 60)
+}
+
+/// Get the key for validating a token which was signed with the supplied key. If the key is newer
+/// than all known keys, it may have been created by another instance (or the `rotate-key` command)
+/// in the meantime, so the key directory is read again.
+pub fn validation_key(keys: &RwLock<KeyStore>, key_id: &str) -> Option<DecodingKey> {
+    use log::{error, info};
+
+    let newest_key_created_at = {
+        let keys = keys.read().ok()?;
+        if let Some(key) = keys.key(key_id) {
+            return Some(key.decoding_key);
+        }
+        keys.keys.last().map(|key| key.created_at)
+    };
+    if creation_time_of(key_id)? <= newest_key_created_at? {
+        return None;
+    }
+
+    let mut keys = keys.write().ok()?;
+    if let Err(error) = keys.reload() {
+        error!("Could not read the keys again. The error was: {}", error);
+        return None;
+    }
+    let key = keys.key(key_id)?;
+    info!("Picked up the new signing key {}", key.id);
+    Some(key.decoding_key)
+}
+
+/// Read the keys of the key directory again, create a new key if the active one is older than the
+/// rotation interval and remove the retired keys which are not needed anymore.
+pub fn maintain_keys(keys: &RwLock<KeyStore>, rotation: Option<Duration>, retention: Duration) {
+    use log::{error, info};
+
+    let mut keys = match keys.write() {
+        Ok(keys) => keys,
+        Err(_) => {
+            error!("The keys for signing the tokens are not available anymore");
+            return;
+        }
+    };
+    if let Err(error) = keys.reload() {
+        error!("Could not read the keys again. The error was: {}", error);
+        return;
+    }
+    if rotation.is_some_and(|rotation| keys.needs_rotation(rotation)) {
+        match keys.rotate() {
+            Ok(key) => info!("Rotated the signing key, the new active key is {}", key.id),
+            Err(error) => error!("Could not rotate the signing key. The error was: {}", error),
+        }
+    }
+    match keys.prune(retention) {
+        Ok(removed_keys) => {
+            for key_id in removed_keys {
+                info!("Removed the retired key {}", key_id);
+            }
+        }
+        Err(error) => error!(
+            "Could not remove the retired keys. The error was: {}",
+            error
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_ids_contain_the_creation_time() {
+        let created_at = DateTime::parse_from_rfc3339("2026-10-18T10:49:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let key_id = new_key_id(created_at).unwrap();
+        assert!(key_id.starts_with("20261018T104900Z-"));
+        assert_eq!(creation_time_of(&key_id), Some(created_at));
+        assert_eq!(creation_time_of("adventskalender-key-1"), None);
+    }
+
+    #[test]
+    fn keys_are_rotated_and_pruned() {
+        let directory =
+            std::env::temp_dir().join(format!("adventskalender_test_keys_{}", std::process::id()));
+
+        let mut key_store = KeyStore::load_or_create(&directory).unwrap();
+        let first_key = key_store.active_key().unwrap();
+        assert!(!key_store.needs_rotation(Duration::days(1)));
+        assert!(key_store.needs_rotation(Duration::zero()));
+
+        // the retired key is kept until the retention passed
+        let second_key = key_store.rotate().unwrap();
+        assert_eq!(key_store.active_key().unwrap().id, second_key.id);
+        assert!(second_key.id > first_key.id);
+        assert_eq!(key_store.prune(Duration::hours(1)).unwrap().len(), 0);
+        assert!(KeyStore::load(&directory)
+            .unwrap()
+            .key(&first_key.id)
+            .is_some());
+
+        assert_eq!(
+            key_store.prune(Duration::seconds(-1)).unwrap(),
+            vec![first_key.id]
+        );
+        let reloaded_keys = KeyStore::load(&directory).unwrap();
+        assert_eq!(reloaded_keys.keys().len(), 1);
+        assert_eq!(reloaded_keys.active_key().unwrap().id, second_key.id);
+
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
@@ -5,8 +5,7 @@ use crate::fairings::AdventskalenderDatabaseConnection;
 use crate::models::User;
 use chrono::{DateTime, Utc};
 use diesel::PgConnection;
-use jsonwebtoken::EncodingKey;
-use lazy_static::lazy_static;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations};
 use log::debug;
 use rocket::State;
 use serde::{Deserialize, Serialize};
@@ -16,17 +15,60 @@ use std::fmt::{Display, Formatter};
 use std::sync::{LazyLock, Mutex};
 use std::time::{Duration, SystemTime};
 
+pub mod calendar;
+pub mod cli;
+pub mod config;
+pub mod discovery;
+pub mod draw;
+pub mod eligibility;
+pub mod export;
 pub mod fairings;
 pub mod guards;
+pub mod import;
+pub mod keys;
 pub mod models;
+pub mod oidc;
+pub mod picking;
+pub mod presents;
 pub mod rate_limiter;
+pub mod refresh_tokens;
 pub mod rocket_cors;
 pub mod routes;
+pub mod scheduler;
 mod schema;
+pub mod sessions;
+pub mod two_factor;
+pub mod users;
 
-lazy_static! {
-    /// The time in seconds a token is valid.
-    static ref TOKEN_LIFETIME_IN_SECONDS: usize = 60 * 60;
+/// The migrations of the database which are embedded into the binary.
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/");
+
+/// Run all migrations which were not applied to the database so far. On success, the number of
+/// migrations which were applied is returned.
+pub fn run_migrations(connection: &mut PgConnection) -> Result<usize, String> {
+    use diesel_migrations::MigrationHarness;
+    use log::{error, info};
+
+    match connection.run_pending_migrations(MIGRATIONS) {
+        Ok(ran_migrations) => {
+            if !ran_migrations.is_empty() {
+                info!(
+                    "Successfully ran {} database migrations",
+                    ran_migrations.len()
+                );
+            } else {
+                info!("No migrations had to be run since the database is up to date");
+            }
+            Ok(ran_migrations.len())
+        }
+        Err(error) => {
+            error!(
+                "Failed to run the database migrations. The error was: {}",
+                error
+            );
+            Err(error.to_string())
+        }
+    }
 }
 
 pub static BACKOFF_HANDLER: LazyLock<Mutex<BackoffHandler>> =
@@ -40,6 +82,13 @@ struct Claims {
     sub: String,
     iss: String,
     aud: HashSet<String>,
+    /// The role of the user (tokens issued before roles existed are treated as viewers).
+    #[serde(default)]
+    role: crate::guards::Role,
+    /// The unique id of the token.
+    jti: String,
+    /// The id of the session (i.e. the login) the token was issued for.
+    sid: String,
 }
 
 #[derive(Clone)]
@@ -99,6 +148,62 @@ pub enum Action {
     PackageChanged,
     /// The user changed their own password.
     PasswordChanged,
+    /// The user added a new participant to the raffle
+    ParticipantCreated,
+    /// The user changed the name of a participant
+    ParticipantRenamed,
+    /// The user removed a participant from the raffle
+    ParticipantDeleted,
+    /// The user added a list of participants with a bulk import
+    ParticipantsImported,
+    /// The user changed the tickets, the department or the blocklist entry of a participant
+    ParticipantEligibilityChanged,
+    /// The user changed the presents a participant would like to get
+    ParticipantPreferencesChanged,
+    /// The user marked a participant as absent for a date range
+    ParticipantAbsenceAdded,
+    /// The user removed an absence of a participant
+    ParticipantAbsenceRemoved,
+    /// The user created a new raffle
+    RaffleCreated,
+    /// The user changed the settings of a raffle (including archiving it)
+    RaffleUpdated,
+    /// The user published a commitment to the seed of an upcoming draw
+    DrawCommitted,
+    /// The user released a winner who is replaced by a substitute (the win is kept as history)
+    WinnerReplaced,
+    /// A substitute was picked for a replaced winner
+    SubstitutePicked,
+    /// The user added a present to the inventory of a raffle
+    PresentCreated,
+    /// The user changed a present of the inventory (e.g. its quantity)
+    PresentChanged,
+    /// The user removed a present from the inventory
+    PresentDeleted,
+    /// The server performed the scheduled draw of a raffle
+    ScheduledDraw,
+    /// An administrator created a new user
+    UserCreated,
+    /// An administrator changed the role of a user or disabled or enabled a user
+    UserUpdated,
+    /// An administrator deleted a user
+    UserDeleted,
+    /// An administrator set a new password for another user
+    PasswordReset,
+    /// A refresh token was used a second time, so all tokens of its login were revoked
+    RefreshTokenReused,
+    /// The user revoked one or all of the own sessions (e.g. to log out everywhere)
+    SessionsRevoked,
+    /// An administrator created a new key for signing the tokens
+    KeyRotated,
+    /// A new user was created for a login through the OpenID Connect provider
+    UserProvisioned,
+    /// A wrong or already used code was supplied as second factor
+    FailedSecondFactor,
+    /// The user confirmed a new second factor (TOTP) for the login
+    SecondFactorEnrolled,
+    /// The second factor of a user was removed (by the user or reset by an administrator)
+    SecondFactorRemoved,
     /// The server indicated that the server started
     ServerStarted,
     /// The server indicated that it shuts down
@@ -115,6 +220,34 @@ impl Display for Action {
             Action::PackageChanged => write!(f, "package_changed"),
             Action::RemovedWinner => write!(f, "removed_winner"),
             Action::PasswordChanged => write!(f, "password_changed"),
+            Action::ParticipantCreated => write!(f, "participant_created"),
+            Action::ParticipantRenamed => write!(f, "participant_renamed"),
+            Action::ParticipantDeleted => write!(f, "participant_deleted"),
+            Action::ParticipantsImported => write!(f, "participants_imported"),
+            Action::ParticipantEligibilityChanged => write!(f, "participant_eligibility_changed"),
+            Action::ParticipantPreferencesChanged => write!(f, "participant_preferences_changed"),
+            Action::ParticipantAbsenceAdded => write!(f, "participant_absence_added"),
+            Action::ParticipantAbsenceRemoved => write!(f, "participant_absence_removed"),
+            Action::RaffleCreated => write!(f, "raffle_created"),
+            Action::RaffleUpdated => write!(f, "raffle_updated"),
+            Action::DrawCommitted => write!(f, "draw_committed"),
+            Action::WinnerReplaced => write!(f, "winner_replaced"),
+            Action::SubstitutePicked => write!(f, "substitute_picked"),
+            Action::PresentCreated => write!(f, "present_created"),
+            Action::PresentChanged => write!(f, "present_changed"),
+            Action::PresentDeleted => write!(f, "present_deleted"),
+            Action::ScheduledDraw => write!(f, "scheduled_draw"),
+            Action::UserCreated => write!(f, "user_created"),
+            Action::UserUpdated => write!(f, "user_updated"),
+            Action::UserDeleted => write!(f, "user_deleted"),
+            Action::PasswordReset => write!(f, "password_reset"),
+            Action::RefreshTokenReused => write!(f, "refresh_token_reused"),
+            Action::SessionsRevoked => write!(f, "sessions_revoked"),
+            Action::KeyRotated => write!(f, "key_rotated"),
+            Action::UserProvisioned => write!(f, "user_provisioned"),
+            Action::FailedSecondFactor => write!(f, "failed_second_factor"),
+            Action::SecondFactorEnrolled => write!(f, "second_factor_enrolled"),
+            Action::SecondFactorRemoved => write!(f, "second_factor_removed"),
             Action::ServerStarted => write!(f, "server_started"),
             Action::ServerTerminated => write!(f, "server_terminated"),
         }
@@ -241,9 +374,12 @@ pub fn log_action(
 
 pub fn get_token_for_user(
     subject: &str,
+    role: crate::guards::Role,
+    session_id: &str,
     audience: HashSet<String>,
     issuer: String,
-    encoding_key: &EncodingKey,
+    lifetime_in_seconds: usize,
+    signing_key: &crate::keys::SigningKey,
 ) -> Option<String> {
     use jsonwebtoken::{encode, Algorithm, Header};
     use log::error;
@@ -262,7 +398,7 @@ pub fn get_token_for_user(
     };
 
     // calculate the time when the token expires
-    let token_expires_at = token_issued_at + 1 + *TOKEN_LIFETIME_IN_SECONDS;
+    let token_expires_at = token_issued_at + 1 + lifetime_in_seconds;
 
     // define the content of the actual token
     let token_claims = Claims {
@@ -272,12 +408,23 @@ pub fn get_token_for_user(
         sub: subject.to_owned(),
         iss: issuer,
         aud: audience,
+        role,
+        jti: match crate::sessions::random_identifier() {
+            Ok(token_id) => token_id,
+            Err(_) => {
+                error!("Could not generate a random id for the token");
+                return None;
+            }
+        },
+        sid: session_id.to_owned(),
     };
 
-    // generate a new JWT for the supplied header and token claims. if we were successful, return
-    // the token
-    let header = Header::new(Algorithm::EdDSA);
-    if let Ok(token) = encode(&header, &token_claims, encoding_key) {
+    // generate a new JWT for the supplied header and token claims. the id of the key is part of
+    // the header, so the matching key can be used to validate the token. if we were successful,
+    // return the token
+    let mut header = Header::new(Algorithm::EdDSA);
+    header.kid = Some(signing_key.id.clone());
+    if let Ok(token) = encode(&header, &token_claims, &signing_key.encoding_key) {
         return Some(token);
     }
 
@@ -1,44 +1,30 @@
+use adventskalender_backend::config::Configuration;
 use adventskalender_backend::rocket_cors::AllowedOrigins;
 use adventskalender_backend::routes::{
+    add_participant_absence, commit_to_draw, commitments_options, confirm_second_factor,
+    create_present, create_user, delete_present, delete_user, draw_options, finish_external_login,
     get_backend_version_options, get_login_token_options,
     get_number_of_participants_who_already_won_options, get_openid_configuration,
-    participants_won_options,
+    get_public_commitment, list_participant_absences, list_presents, list_replacements,
+    list_sessions, list_users, list_wins, login_with_second_factor, participant_absence_options,
+    participant_absences_options, participant_eligibility_options, participant_preferences_options,
+    participants_options, participants_won_options, present_options, presents_options,
+    public_commitment_options, raffle_options, raffles_options, refresh_login_token,
+    refresh_login_token_options, remove_own_second_factor, remove_participant_absence,
+    replace_winner, replace_winner_options, replacements_options, reset_user_password,
+    reset_user_second_factor, reveal_commitment, reveal_commitment_options, revoke_all_sessions,
+    revoke_session, rotate_key_options, rotate_signing_key, second_factor_login_enrollment_options,
+    second_factor_login_options, second_factor_options, session_options, sessions_options,
+    start_external_login, start_second_factor_enrollment, start_second_factor_enrollment_for_login,
+    update_participant_eligibility, update_participant_preferences, update_present, update_user,
+    user_options, user_password_options, user_second_factor_options, users_options,
+    verify_draw_options, wins_options,
 };
-use adventskalender_backend::{log_action, Action};
+use adventskalender_backend::scheduler::schedule_draws;
+use adventskalender_backend::{log_action, run_migrations, Action};
 use chrono::DateTime;
-use diesel::PgConnection;
-use diesel_migrations::{embed_migrations, EmbeddedMigrations};
-use jsonwebtoken::{DecodingKey, EncodingKey};
 use log::LevelFilter;
-use ring::signature::Ed25519KeyPair;
 use rocket::config::{Shutdown, Sig};
-use std::collections::HashSet;
-use std::time::Duration;
-
-pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/");
-
-pub fn run_migrations(connection: &mut PgConnection) {
-    use diesel_migrations::MigrationHarness;
-    use log::{error, info};
-    match connection.run_pending_migrations(MIGRATIONS) {
-        Ok(ran_migrations) => {
-            if !ran_migrations.is_empty() {
-                info!(
-                    "Successfully ran {} database migrations",
-                    ran_migrations.len()
-                );
-            } else {
-                info!("No migrations had to be run since the database is up to date");
-            }
-        }
-        Err(error) => {
-            error!(
-                "Failed to run the database migrations. The error was: {}",
-                error
-            )
-        }
-    }
-}
 
 fn unset_environment_variable(name: &str) {
     use std::env::remove_var;
@@ -83,119 +69,89 @@ fn setup_logging(logging_level: LevelFilter) {
         .unwrap();
 }
 
-/// Load Ed25519 key pair from a file, or generate and save a new one if it doesn't exist
-fn load_or_generate_keypair(key_file_path: &std::path::Path) -> Result<Vec<u8>, String> {
-    use log::{info, warn};
-    use std::fs;
-    use std::io::Write;
-
-    // Try to load the existing key
-    if key_file_path.exists() {
-        info!("Loading existing Ed25519 key pair from {:?}", key_file_path);
-        match fs::read(key_file_path) {
-            Ok(key_bytes) => {
-                // Validate the key can be parsed
-                match Ed25519KeyPair::from_pkcs8(&key_bytes) {
-                    Ok(_) => {
-                        info!("Successfully loaded existing Ed25519 key pair");
-                        return Ok(key_bytes);
-                    }
-                    Err(e) => {
-                        warn!(
-                            "Existing key file is invalid or corrupted: {}. Generating new key.",
-                            e
-                        );
-                    }
-                }
-            }
-            Err(e) => {
-                warn!(
-                    "Failed to read existing key file: {}. Generating new key.",
-                    e
-                );
+#[rocket::main]
+async fn main() {
+    use adventskalender_backend::cli::{self, Cli, Command};
+    use adventskalender_backend::config::ConfigurationErrors;
+    use clap::Parser;
+    use log::error;
+
+    // parse the command line before anything else, so that `--help` works without a configuration
+    let command_line = Cli::parse();
+
+    // load and validate the configuration before doing anything else. all problems are reported
+    // at once, so they can be fixed in one go
+    let configuration = match Configuration::load(command_line.config.as_deref()) {
+        Ok(configuration) => configuration,
+        Err(ConfigurationErrors(problems)) => {
+            setup_logging(LevelFilter::Info);
+            for problem in problems {
+                error!("{}", problem);
             }
+            std::process::exit(1);
         }
-    }
-
-    // generate a new key
-    info!(
-        "Generating new Ed25519 key pair and saving to {:?}",
-        key_file_path
-    );
-    let key_pair_doc = match Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()) {
-        Ok(doc) => doc,
-        Err(e) => return Err(format!("Failed to generate Ed25519 key pair: {}", e)),
     };
 
-    // create a parent directory if it doesn't exist
-    if let Some(parent) = key_file_path.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create key directory: {}", e))?;
-        }
-    }
-
-    // write key to file
-    let mut file =
-        fs::File::create(key_file_path).map_err(|e| format!("Failed to create key file: {}", e))?;
-    file.write_all(key_pair_doc.as_ref())
-        .map_err(|e| format!("Failed to write key to file: {}", e))?;
-
-    // set file permissions to 0600 (read/write for the owner only)
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = file
-            .metadata()
-            .map_err(|e| format!("Failed to get file metadata: {}", e))?
-            .permissions();
-        perms.set_mode(0o600);
-        fs::set_permissions(key_file_path, perms)
-            .map_err(|e| format!("Failed to set file permissions: {}", e))?;
-        info!("Set key file permissions to 0600");
+    // setup the logging of the application based on the configuration
+    setup_logging(configuration.logging_level);
+
+    let result = match command_line.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(configuration).await,
+        Command::Migrate => cli::migrate(&configuration),
+        Command::CreateUser {
+            username,
+            role,
+            password_stdin,
+        } => cli::create_user(&configuration, &username, role, password_stdin),
+        Command::ResetPassword {
+            username,
+            password_stdin,
+        } => cli::reset_password(&configuration, &username, password_stdin),
+        Command::ImportParticipants {
+            raffle,
+            dry_run,
+            file,
+        } => cli::import_participants(&configuration, raffle, &file, dry_run),
+        Command::ExportWinners {
+            raffle,
+            format,
+            output,
+        } => cli::export_winners(&configuration, raffle, format, output).await,
+        Command::RotateKey => cli::rotate_key(&configuration),
+        Command::CheckConfig => cli::check_config(&configuration),
+    };
+    if let Err(message) = result {
+        error!("{}", message);
+        std::process::exit(1);
     }
-
-    info!("Successfully generated and saved new Ed25519 key pair");
-    Ok(key_pair_doc.as_ref().to_vec())
 }
 
-#[rocket::main]
-async fn main() {
+/// Start the server with the supplied configuration and run it until it is shut down.
+async fn serve(configuration: Configuration) -> Result<(), String> {
+    use adventskalender_backend::config::ENVIRONMENT_PREFIX;
+    use adventskalender_backend::discovery::{
+        OpenIdConfiguration, API_BASE_PATH, WELL_KNOWN_BASE_PATH,
+    };
+    use adventskalender_backend::fairings::connect_to_database;
     use adventskalender_backend::fairings::{
         AdventskalenderDatabaseConnection, BackendConfiguration, SecurityHeaders,
     };
+    use adventskalender_backend::keys::{key_retention, maintain_keys, KeyStore};
     use adventskalender_backend::routes::{
-        check_backend_health, count_won_participants_on_day, get_all_won_participants,
-        get_audit_event_count, get_backend_version, get_current_user, get_jwks, get_login_token,
-        get_number_of_participants_who_already_won, get_won_participants_on_day_route, logout,
+        check_backend_health, count_won_participants_on_day, create_participant, create_raffle,
+        delete_participant, export_participants, get_all_won_participants, get_audit_event_count,
+        get_backend_version, get_current_user, get_draw, get_jwks, get_login_token,
+        get_number_of_participants_who_already_won, get_raffle, get_won_participants_on_day_route,
+        import_participants, list_participants, list_raffles, logout,
         pick_multiple_random_participant_from_raffle_list, remove_participant_from_winner_list,
-        update_participant_values, update_user_password,
-    };
-    use log::{debug, error, info};
-    use rocket::figment::{
-        util::map,
-        value::{Map, Value},
+        rename_participant, update_participant_values, update_raffle, update_user_password,
+        verify_recorded_draw,
     };
+    use log::{debug, info};
     use rocket::http::Method;
     use rocket::routes;
     use rocket::Config as RocketConfig;
-    use std::env;
-
-    // select the logging level from a set environment variable
-    let logging_level = match env::var("ADVENTSKALENDER_LOGGING_LEVEL") {
-        Ok(value) => match value.to_lowercase().as_str() {
-            "trace" => LevelFilter::Trace,
-            "debug" => LevelFilter::Debug,
-            "info" => LevelFilter::Info,
-            "warn" => LevelFilter::Warn,
-            "error" => LevelFilter::Error,
-            _ => LevelFilter::Info,
-        },
-        Err(_) => LevelFilter::Info,
-    };
-
-    // setup the logging of the application based on the environment variable
-    setup_logging(logging_level);
+    use std::sync::{Arc, RwLock};
 
     // just inform the user that we are starting up
     info!(
@@ -204,169 +160,67 @@ async fn main() {
         env!("VERGEN_RUSTC_SEMVER")
     );
 
-    // get the API base host
-    let api_host = env::var("ADVENTSKALENDER_API_HOST").unwrap_or("".to_string());
-    if api_host.is_empty() {
-        error!("Could not get the token signature PSK. Ensure ADVENTSKALENDER_API_HOST is set properly");
-        return;
-    }
-
-    // get the configuration for the database server and terminate if something is missing
-    let database_connection_url =
-        env::var("ADVENTSKALENDER_DB_CONNECTION").unwrap_or("".to_string());
-    if database_connection_url.is_empty() {
-        error!("Could not get the configuration for the database server. Ensure ADVENTSKALENDER_DB_CONNECTION is set properly");
-        return;
-    }
-
-    // get the project UUID for the health check
-    let healthcheck_io_project =
-        env::var("ADVENTSKALENDER_HEALTHCHECK_IO_PROJECT").unwrap_or("".to_string());
-    if healthcheck_io_project.is_empty() {
-        error!("Could not get the token signature PSK. Ensure ADVENTSKALENDER_HEALTHCHECK_IO_PROJECT is set properly");
-        return;
-    }
-
-    // get the audience for the token
-    let token_audience_str = env::var("ADVENTSKALENDER_TOKEN_AUDIENCE").unwrap_or("".to_string());
-    if token_audience_str.is_empty() {
-        error!("Could not get the token signature PSK. Ensure ADVENTSKALENDER_TOKEN_AUDIENCE is set properly");
-        return;
-    }
-    let token_audience_hash_set = token_audience_str
-        .split(',')
-        .map(|s| s.to_string())
-        .collect::<HashSet<String>>();
-
-    // get the allowed CORS origins (comma-separated list)
-    let allowed_cors_origins =
-        env::var("ADVENTSKALENDER_CORS_ORIGINS").unwrap_or("http://localhost:5173".to_string());
-    info!("Allowed CORS origins: {}", allowed_cors_origins);
-
-    // get the key file path for Ed25519 key persistence
-    let key_file_path_str = env::var("ADVENTSKALENDER_KEY_FILE_PATH")
-        .unwrap_or_else(|_| "/data/adventskalender_ed25519.key".to_string());
-    let key_file_path = std::path::Path::new(&key_file_path_str);
+    info!(
+        "Allowed CORS origins: {}",
+        configuration.cors_origins.join(",")
+    );
 
-    // load or generate Ed25519 key pair for signing tokens (persisted across restarts)
-    let ed25519_key_bytes = match load_or_generate_keypair(key_file_path) {
-        Ok(bytes) => bytes,
-        Err(error) => {
-            error!("Failed to load or generate Ed25519 key pair: {}", error);
-            return;
+    // load the Ed25519 key pairs for signing the tokens (or generate a first one) and keep them up
+    // to date while the server is running
+    let key_store = KeyStore::load_or_create(&configuration.key_directory)
+        .map_err(|error| format!("Failed to load the keys for signing the tokens: {}", error))?;
+    let keys = Arc::new(RwLock::new(key_store));
+    let key_rotation = configuration
+        .key_rotation_in_days
+        .map(|days| chrono::Duration::days(days as i64));
+    let retention = key_retention(configuration.token_lifetime_in_seconds);
+    maintain_keys(&keys, key_rotation, retention);
+    let maintained_keys = keys.clone();
+    rocket::tokio::spawn(async move {
+        loop {
+            rocket::tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            maintain_keys(&maintained_keys, key_rotation, retention);
         }
-    };
-
-    // create encoding/decoding keys from the key bytes
-    // For Ed25519: encoding needs the full keypair, decoding needs only the public key
-    let encoding_key = EncodingKey::from_ed_der(&ed25519_key_bytes);
+    });
 
-    // Extract public key from the keypair for decoding
-    use ring::signature::KeyPair; // Import trait for public_key() method
-    let public_key_bytes = match Ed25519KeyPair::from_pkcs8(&ed25519_key_bytes) {
-        Ok(keypair) => keypair.public_key().as_ref().to_vec(),
-        Err(e) => {
-            error!("Failed to extract public key from Ed25519 keypair: {}", e);
-            return;
-        }
-    };
-    let decoding_key = DecodingKey::from_ed_der(&public_key_bytes);
+    // the discovery document only depends on the configuration, so it is created (and validated)
+    // just once
+    let openid_configuration = OpenIdConfiguration::for_api_host(&configuration.api_host)?;
 
     let backend_config = BackendConfiguration {
-        api_host,
-        encoding_key: Some(encoding_key),
-        decoding_key: Some(decoding_key),
-        ed25519_key_bytes: Some(ed25519_key_bytes.clone()),
-        token_audience: token_audience_hash_set,
-        healthcheck_project: healthcheck_io_project.to_string(),
+        api_host: configuration.api_host.clone(),
+        keys,
+        openid_configuration: Arc::new(openid_configuration),
+        token_audience: configuration.token_audience.clone(),
+        healthcheck_project: configuration.healthcheck_project.clone(),
+        token_lifetime_in_seconds: configuration.token_lifetime_in_seconds,
+        refresh_token_lifetime_in_seconds: configuration.refresh_token_lifetime_in_seconds,
+        login_rate_limit: configuration.login_rate_limit.clone(),
+        external_login: configuration.external_login.clone(),
+        totp: configuration.totp.clone(),
     };
 
     // create a db connection pool manager and the corresponding pool with retry logic
     info!("Connecting to database with retry logic...");
-    let db_connection_pool = {
-        let max_retries = 10;
-        let mut retry_count = 0;
-        let mut backoff_duration = Duration::from_millis(500);
-
-        loop {
-            let db_connection_pool_manager =
-                diesel::r2d2::ConnectionManager::new(database_connection_url.clone());
-
-            match r2d2::Pool::builder()
-                .max_size(15)
-                .connection_timeout(Duration::from_secs(5))
-                .build(db_connection_pool_manager)
-            {
-                Ok(pool) => {
-                    // Verify we can actually get a connection
-                    match pool.get() {
-                        Ok(_) => {
-                            info!("Successfully connected to the database server");
-                            break pool;
-                        }
-                        Err(e) => {
-                            retry_count += 1;
-                            if retry_count >= max_retries {
-                                error!(
-                                    "Failed to get database connection after {} attempts. Last error: {}",
-                                    max_retries, e
-                                );
-                                std::process::exit(-1);
-                            }
-                            info!(
-                                "Database connection attempt {}/{} failed, retrying in {:?}...",
-                                retry_count, max_retries, backoff_duration
-                            );
-                            std::thread::sleep(backoff_duration);
-                            backoff_duration =
-                                std::cmp::min(backoff_duration * 2, Duration::from_secs(10));
-                        }
-                    }
-                }
-                Err(e) => {
-                    retry_count += 1;
-                    if retry_count >= max_retries {
-                        error!(
-                            "Failed to create database connection pool after {} attempts. Last error: {}",
-                            max_retries, e
-                        );
-                        std::process::exit(-1);
-                    }
-                    info!(
-                        "Database pool creation attempt {}/{} failed, retrying in {:?}...",
-                        retry_count, max_retries, backoff_duration
-                    );
-                    std::thread::sleep(backoff_duration);
-                    backoff_duration = std::cmp::min(backoff_duration * 2, Duration::from_secs(10));
-                }
-            }
-        }
-    };
+    let db_connection_pool = connect_to_database(
+        &configuration.database_connection_url,
+        configuration.database_pool_size,
+        10,
+    )?;
 
     // ensure the database is setup correctly
-    let mut db_connection = db_connection_pool.get().unwrap_or_else(|e| {
-        error!(
+    let mut db_connection = db_connection_pool.get().map_err(|e| {
+        format!(
             "Could not get a database connection from the connection pool. The error was: {}",
             e
-        );
-        std::process::exit(-1);
-    });
-    run_migrations(&mut db_connection);
+        )
+    })?;
+    let _ = run_migrations(&mut db_connection);
     info!("Database preparations finished");
 
-    // configure the database pool based on the supplied connection URL
-    let adventskalender_database_config: Map<_, Value> = map! {
-        "url" => database_connection_url.into(),
-        "pool_size" => 25.into()
-    };
-
     // rocket configuration figment
     let rocket_configuration_figment = RocketConfig::figment()
-        .merge((
-            "databases",
-            map!["adventskalender" => adventskalender_database_config],
-        ))
-        .merge(("port", 5479))
+        .merge(("port", configuration.port))
         .merge(("address", std::net::Ipv4Addr::new(0, 0, 0, 0)))
         .merge((
             "shutdown",
@@ -385,12 +239,11 @@ async fn main() {
         ));
 
     // prepare the fairing for the CORS headers
-    let origins_list: Vec<String> = allowed_cors_origins
-        .split(',')
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
+    let origins_refs: Vec<&str> = configuration
+        .cors_origins
+        .iter()
+        .map(|s| s.as_str())
         .collect();
-    let origins_refs: Vec<&str> = origins_list.iter().map(|s| s.as_str()).collect();
     let allowed_origins = AllowedOrigins::some_exact(&origins_refs);
     let cors_header = adventskalender_backend::rocket_cors::CorsOptions {
         allowed_origins,
@@ -408,13 +261,11 @@ async fn main() {
     // after everything is set up, we should unset all environment variables to prevent leaking
     // sensitive information through process memory inspection or core dumps
     debug!("Clearing sensitive environment variables from memory");
-    unset_environment_variable("ADVENTSKALENDER_LOGGING_LEVEL");
-    unset_environment_variable("ADVENTSKALENDER_DB_CONNECTION");
-    unset_environment_variable("ADVENTSKALENDER_API_HOST");
-    unset_environment_variable("ADVENTSKALENDER_HEALTHCHECK_IO_PROJECT");
-    unset_environment_variable("ADVENTSKALENDER_TOKEN_AUDIENCE");
-    unset_environment_variable("ADVENTSKALENDER_CORS_ORIGINS");
-    unset_environment_variable("ADVENTSKALENDER_KEY_FILE_PATH");
+    for (name, _) in std::env::vars_os() {
+        if name.to_string_lossy().starts_with(ENVIRONMENT_PREFIX) {
+            unset_environment_variable(&name.to_string_lossy());
+        }
+    }
     debug!("Environment variable cleanup completed");
 
     // log the startup of the backend service
@@ -435,11 +286,11 @@ async fn main() {
     );
 
     // spawn background task for rate limiter cleanup
-    rocket::tokio::spawn(async {
-        use adventskalender_backend::rate_limiter::{cleanup_old_entries, RateLimitConfig};
+    let config = configuration.login_rate_limit.clone();
+    rocket::tokio::spawn(async move {
+        use adventskalender_backend::rate_limiter::cleanup_old_entries;
         use log::debug;
 
-        let config = RateLimitConfig::default();
         loop {
             rocket::tokio::time::sleep(std::time::Duration::from_secs(300)).await; // 5 minutes
             debug!("Running rate limiter cleanup task");
@@ -447,16 +298,36 @@ async fn main() {
         }
     });
 
+    // spawn background task for the scheduled draws (if they are configured at all)
+    let adventskalender_database_connection =
+        AdventskalenderDatabaseConnection::from(db_connection_pool);
+    match configuration.draw_schedule {
+        Some(schedule) => {
+            info!(
+                "The scheduled draws are performed every day at {} ({})",
+                schedule.time, schedule.time_zone
+            );
+            rocket::tokio::spawn(schedule_draws(
+                adventskalender_database_connection.clone(),
+                schedule,
+            ));
+        }
+        None => info!("No time for the scheduled draws is configured, they are disabled"),
+    }
+
     // mount all supported routes and launch the rocket :)
     info!("Database preparations done and starting up the API endpoints now...");
     let _ = rocket::custom(rocket_configuration_figment)
         .attach(cors_header)
         .attach(SecurityHeaders)
         .manage(backend_config)
-        .manage(AdventskalenderDatabaseConnection::from(db_connection_pool))
-        .mount("/.well-known", routes![get_openid_configuration, get_jwks])
+        .manage(adventskalender_database_connection)
+        .mount(
+            WELL_KNOWN_BASE_PATH,
+            routes![get_openid_configuration, get_jwks],
+        )
         .mount(
-            "/v1",
+            API_BASE_PATH,
             routes![
                 get_login_token,
                 get_login_token_options,
@@ -476,6 +347,79 @@ async fn main() {
                 update_user_password,
                 get_audit_event_count,
                 get_won_participants_on_day_route,
+                participants_options,
+                list_participants,
+                create_participant,
+                rename_participant,
+                delete_participant,
+                import_participants,
+                export_participants,
+                raffles_options,
+                raffle_options,
+                list_raffles,
+                create_raffle,
+                get_raffle,
+                update_raffle,
+                draw_options,
+                get_draw,
+                verify_draw_options,
+                verify_recorded_draw,
+                commitments_options,
+                commit_to_draw,
+                reveal_commitment_options,
+                reveal_commitment,
+                public_commitment_options,
+                get_public_commitment,
+                participant_eligibility_options,
+                update_participant_eligibility,
+                participant_absences_options,
+                list_participant_absences,
+                add_participant_absence,
+                participant_absence_options,
+                remove_participant_absence,
+                replace_winner_options,
+                replace_winner,
+                replacements_options,
+                list_replacements,
+                wins_options,
+                list_wins,
+                presents_options,
+                list_presents,
+                create_present,
+                present_options,
+                update_present,
+                delete_present,
+                participant_preferences_options,
+                update_participant_preferences,
+                users_options,
+                user_options,
+                user_password_options,
+                list_users,
+                create_user,
+                update_user,
+                delete_user,
+                reset_user_password,
+                user_second_factor_options,
+                reset_user_second_factor,
+                refresh_login_token_options,
+                refresh_login_token,
+                sessions_options,
+                session_options,
+                list_sessions,
+                revoke_session,
+                revoke_all_sessions,
+                rotate_key_options,
+                rotate_signing_key,
+                start_external_login,
+                finish_external_login,
+                second_factor_options,
+                second_factor_login_options,
+                second_factor_login_enrollment_options,
+                login_with_second_factor,
+                start_second_factor_enrollment_for_login,
+                start_second_factor_enrollment,
+                confirm_second_factor,
+                remove_own_second_factor,
             ],
         )
         .launch()
@@ -483,4 +427,5 @@ async fn main() {
 
     // log the shutdown of the backend service
     log_action(&mut db_connection, None, Action::ServerTerminated, None);
+    Ok(())
 }
@@ -1,4 +1,8 @@
-use crate::schema::{participants, performed_actions};
+use crate::schema::{
+    draw_commitments, draws, participant_absences, participants, performed_actions, presents,
+    raffles, recovery_codes, refresh_tokens, second_factors, sessions, users, winner_replacements,
+    wins,
+};
 use chrono::{NaiveDate, NaiveDateTime};
 
 #[derive(Queryable, Clone)]
@@ -6,19 +10,266 @@ pub struct Participant {
     pub id: i32,
     pub first_name: String,
     pub last_name: String,
-    pub won_on: Option<NaiveDate>,
-    pub picked_by: Option<i32>,
-    pub picking_time: Option<NaiveDateTime>,
-    pub present_identifier: Option<String>,
+    pub raffle_id: i32,
+    pub tickets: i32,
+    pub department: Option<String>,
+    pub blocked: bool,
+    pub present_preferences: Vec<String>,
 }
 
 #[derive(AsChangeset)]
 #[diesel(table_name = participants, treat_none_as_null = true)]
-pub struct ParticipantPicking {
-    pub won_on: Option<NaiveDate>,
+pub struct ParticipantEligibility {
+    pub tickets: i32,
+    pub department: Option<String>,
+    pub blocked: bool,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = participants)]
+pub struct ParticipantPresentPreferences {
+    pub present_preferences: Vec<String>,
+}
+
+#[derive(Queryable, Clone)]
+pub struct ParticipantAbsence {
+    pub id: i32,
+    pub participant_id: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = participant_absences)]
+pub struct NewParticipantAbsence {
+    pub participant_id: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = participants)]
+pub struct NewParticipant {
+    pub first_name: String,
+    pub last_name: String,
+    pub raffle_id: i32,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = participants)]
+pub struct ParticipantRenaming {
+    pub first_name: String,
+    pub last_name: String,
+}
+
+#[derive(Queryable, Clone)]
+pub struct Raffle {
+    pub id: i32,
+    pub name: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub archived: bool,
+    pub exclude_weekends: bool,
+    pub excluded_dates: Vec<NaiveDate>,
+    pub winners_per_day: i32,
+    pub use_tickets: bool,
+    pub max_winners_per_department: Option<i32>,
+    pub present_assignment: String,
+    pub scheduled_draws: bool,
+    pub scheduled_draws_enabled_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = raffles, treat_none_as_null = true)]
+pub struct NewRaffle {
+    pub name: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub archived: bool,
+    pub exclude_weekends: bool,
+    pub excluded_dates: Vec<NaiveDate>,
+    pub winners_per_day: i32,
+    pub use_tickets: bool,
+    pub max_winners_per_department: Option<i32>,
+    pub present_assignment: String,
+    pub scheduled_draws: bool,
+    pub scheduled_draws_enabled_at: Option<NaiveDateTime>,
+}
+
+/// The states of a win. Just a single win of a participant can be active, all others are kept as
+/// history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WinStatus {
+    /// The participant is a winner of the day.
+    Active,
+    /// The participant was removed from the list of winners.
+    Revoked,
+    /// The participant was replaced by a substitute.
+    Replaced,
+    /// The present of the winner was changed, so a new (active) win replaced this one.
+    Superseded,
+}
+
+impl WinStatus {
+    /// Get the value which is stored in the database for the status.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WinStatus::Active => "active",
+            WinStatus::Revoked => "revoked",
+            WinStatus::Replaced => "replaced",
+            WinStatus::Superseded => "superseded",
+        }
+    }
+}
+
+#[derive(Queryable, Clone)]
+pub struct Win {
+    pub id: i32,
+    pub participant_id: i32,
+    pub won_on: NaiveDate,
     pub picked_by: Option<i32>,
-    pub picking_time: Option<NaiveDateTime>,
-    pub present_identifier: Option<String>,
+    pub picked_at: Option<NaiveDateTime>,
+    pub status: String,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub draw_id: Option<i32>,
+    pub present_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = wins)]
+pub struct NewWin {
+    pub participant_id: i32,
+    pub won_on: NaiveDate,
+    pub picked_by: Option<i32>,
+    pub picked_at: Option<NaiveDateTime>,
+    pub draw_id: Option<i32>,
+    pub present_id: Option<i32>,
+}
+
+#[derive(Queryable, Clone)]
+pub struct Present {
+    pub id: i32,
+    pub raffle_id: i32,
+    pub available_on: NaiveDate,
+    pub identifier: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub sponsor: Option<String>,
+    pub quantity: i32,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = presents, treat_none_as_null = true)]
+pub struct NewPresent {
+    pub raffle_id: i32,
+    pub available_on: NaiveDate,
+    pub identifier: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub sponsor: Option<String>,
+    pub quantity: i32,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = wins)]
+pub struct WinRevocation {
+    pub status: String,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Queryable, Clone)]
+pub struct Draw {
+    pub id: i32,
+    pub raffle_id: i32,
+    pub drawn_for: NaiveDate,
+    pub drawn_at: NaiveDateTime,
+    pub drawn_by: Option<i32>,
+    pub algorithm_version: i32,
+    pub seed: Vec<u8>,
+    pub candidate_ids: Vec<i32>,
+    pub candidates_hash: String,
+    pub winner_ids: Vec<i32>,
+    pub candidate_weights: Option<Vec<i32>>,
+    pub candidate_groups: Option<Vec<Option<i32>>>,
+    pub group_capacities: Option<Vec<i32>>,
+    pub rules: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = draws)]
+pub struct NewDraw {
+    pub raffle_id: i32,
+    pub drawn_for: NaiveDate,
+    pub drawn_by: Option<i32>,
+    pub algorithm_version: i32,
+    pub seed: Vec<u8>,
+    pub candidate_ids: Vec<i32>,
+    pub candidates_hash: String,
+    pub winner_ids: Vec<i32>,
+    pub candidate_weights: Option<Vec<i32>>,
+    pub candidate_groups: Option<Vec<Option<i32>>>,
+    pub group_capacities: Option<Vec<i32>>,
+    pub rules: Option<String>,
+}
+
+#[derive(Queryable, Clone)]
+pub struct DrawCommitment {
+    pub id: i32,
+    pub raffle_id: i32,
+    pub drawn_for: NaiveDate,
+    pub winner_count: i32,
+    pub seed: Vec<u8>,
+    pub seed_hash: String,
+    pub candidates_hash: String,
+    pub committed_at: NaiveDateTime,
+    pub committed_by: Option<i32>,
+    pub revealed_at: Option<NaiveDateTime>,
+    pub draw_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = draw_commitments)]
+pub struct NewDrawCommitment {
+    pub raffle_id: i32,
+    pub drawn_for: NaiveDate,
+    pub winner_count: i32,
+    pub seed: Vec<u8>,
+    pub seed_hash: String,
+    pub candidates_hash: String,
+    pub committed_by: Option<i32>,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = draw_commitments)]
+pub struct DrawCommitmentReveal {
+    pub revealed_at: Option<NaiveDateTime>,
+    pub draw_id: Option<i32>,
+}
+
+#[derive(Queryable, Clone)]
+pub struct WinnerReplacement {
+    pub id: i32,
+    pub raffle_id: i32,
+    pub reason: String,
+    pub replaced_at: NaiveDateTime,
+    pub replaced_by: Option<i32>,
+    pub win_id: i32,
+    pub substitute_win_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = winner_replacements)]
+pub struct NewWinnerReplacement {
+    pub raffle_id: i32,
+    pub reason: String,
+    pub replaced_by: Option<i32>,
+    pub win_id: i32,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = winner_replacements)]
+pub struct WinnerSubstitution {
+    pub substitute_win_id: Option<i32>,
 }
 
 #[derive(Queryable, Clone)]
@@ -26,6 +277,92 @@ pub struct User {
     pub id: i32,
     pub username: String,
     pub password_hash: String,
+    pub role: String,
+    pub disabled: bool,
+    pub external_subject: Option<String>,
+    pub must_change_password: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = users)]
+pub struct NewUser {
+    pub username: String,
+    pub password_hash: String,
+    pub role: String,
+    pub external_subject: Option<String>,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = users)]
+pub struct UserChangeset {
+    pub role: Option<String>,
+    pub disabled: Option<bool>,
+}
+
+#[derive(Queryable, Clone)]
+pub struct RefreshToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub family: String,
+    pub token_hash: String,
+    pub issued_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = refresh_tokens)]
+pub struct NewRefreshToken {
+    pub user_id: i32,
+    pub family: String,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Clone)]
+pub struct Session {
+    pub id: String,
+    pub user_id: i32,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = sessions)]
+pub struct NewSession {
+    pub id: String,
+    pub user_id: i32,
+    pub expires_at: NaiveDateTime,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[derive(Queryable, Clone)]
+pub struct SecondFactor {
+    pub user_id: i32,
+    pub secret: String,
+    pub created_at: NaiveDateTime,
+    pub confirmed_at: Option<NaiveDateTime>,
+    pub last_used_step: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = second_factors)]
+pub struct NewSecondFactor {
+    pub user_id: i32,
+    pub secret: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = recovery_codes)]
+pub struct NewRecoveryCode {
+    pub user_id: i32,
+    pub code_hash: String,
 }
 
 #[derive(Insertable)]
@@ -0,0 +1,303 @@
+use crate::guards::Role;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::Algorithm;
+use serde::Deserialize;
+use std::sync::LazyLock;
+use url::Url;
+
+/// The time a user has to finish the login at the external provider.
+const PENDING_LOGIN_LIFETIME_IN_MINUTES: i64 = 10;
+
+/// The time after which a request to the external provider is aborted.
+const PROVIDER_TIMEOUT_IN_SECONDS: u64 = 10;
+
+/// The scopes which are requested from the external provider.
+const REQUESTED_SCOPES: &str = "openid email profile";
+
+/// The algorithms the ID tokens of the external provider may be signed with. Symmetric algorithms
+/// are not accepted, since the keys are taken from the public JWKS of the provider.
+const ACCEPTED_ALGORITHMS: [Algorithm; 9] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// The settings of the (optional) login through an external OpenID Connect provider.
+#[derive(Clone, Debug)]
+pub struct OidcSettings {
+    /// The issuer of the external provider (its discovery document is read from
+    /// `<issuer>/.well-known/openid-configuration`).
+    pub issuer: String,
+    /// The id of this application at the external provider.
+    pub client_id: String,
+    /// The secret of this application at the external provider (or `None` for a public client,
+    /// which is just protected by PKCE).
+    pub client_secret: Option<String>,
+    /// The role of the users which are created for unknown external users (or `None` if just
+    /// existing users can log in).
+    pub default_role: Option<Role>,
+    /// The URL (of the frontend) the user is sent to after the login.
+    pub login_redirect: String,
+}
+
+/// The parts of the discovery document of the external provider which are needed for the login.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// The user who logged in at the external provider (taken from the validated ID token).
+#[derive(Clone, Debug, Deserialize)]
+pub struct ExternalIdentity {
+    /// The unique id of the user at the external provider.
+    #[serde(rename = "sub")]
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub preferred_username: Option<String>,
+}
+
+/// The claims of an ID token which are checked in addition to the ones validated by the
+/// `jsonwebtoken` crate.
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    nonce: Option<String>,
+    #[serde(flatten)]
+    identity: ExternalIdentity,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+/// A login which was started but not finished yet. It is identified by the `state` which is sent
+/// to the external provider and returned to the callback.
+struct PendingLogin {
+    provider: ProviderMetadata,
+    code_verifier: String,
+    nonce: String,
+    started_at: DateTime<Utc>,
+}
+
+static PENDING_LOGINS: LazyLock<DashMap<String, PendingLogin>> = LazyLock::new(DashMap::new);
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(std::time::Duration::from_secs(PROVIDER_TIMEOUT_IN_SECONDS))
+        .build()
+}
+
+/// Read the discovery document of the external provider and ensure that it belongs to the
+/// configured issuer.
+pub fn discover_provider(issuer: &str) -> Result<ProviderMetadata, String> {
+    let discovery_url = format!(
+        "{}/.well-known/openid-configuration",
+        issuer.trim_end_matches('/')
+    );
+    let metadata = agent()
+        .get(&discovery_url)
+        .call()
+        .map_err(|error| format!("Could not read {}: {}", discovery_url, error))?
+        .into_json::<ProviderMetadata>()
+        .map_err(|error| format!("The discovery document is invalid: {}", error))?;
+    if metadata.issuer.trim_end_matches('/') != issuer.trim_end_matches('/') {
+        return Err(format!(
+            "The discovery document belongs to the issuer {} instead of {}",
+            metadata.issuer, issuer
+        ));
+    }
+    Ok(metadata)
+}
+
+/// Get the PKCE code challenge (method `S256`) for a code verifier.
+pub fn code_challenge(code_verifier: &str) -> String {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use ring::digest::{digest, SHA256};
+
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, code_verifier.as_bytes()))
+}
+
+/// Start a login at the external provider. The returned state identifies the login and has to be
+/// supplied again when it is finished, the user has to be sent to the returned URL.
+pub fn start_login(
+    settings: &OidcSettings,
+    provider: ProviderMetadata,
+    redirect_uri: &str,
+) -> Result<(String, String), String> {
+    use crate::sessions::random_identifier;
+
+    // the logins which were never finished are not needed anymore
+    let now = Utc::now();
+    PENDING_LOGINS.retain(|_, login| {
+        login.started_at + Duration::minutes(PENDING_LOGIN_LIFETIME_IN_MINUTES) > now
+    });
+
+    let random_identifier =
+        || random_identifier().map_err(|_| "Could not generate a random state".to_string());
+    let state = random_identifier()?;
+    let nonce = random_identifier()?;
+    let code_verifier = format!("{}{}", random_identifier()?, random_identifier()?);
+
+    let mut authorization_url = Url::parse(&provider.authorization_endpoint)
+        .map_err(|error| format!("The authorization endpoint is invalid: {}", error))?;
+    authorization_url
+        .query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &settings.client_id)
+        .append_pair("redirect_uri", redirect_uri)
+        .append_pair("scope", REQUESTED_SCOPES)
+        .append_pair("state", &state)
+        .append_pair("nonce", &nonce)
+        .append_pair("code_challenge", &code_challenge(&code_verifier))
+        .append_pair("code_challenge_method", "S256");
+
+    PENDING_LOGINS.insert(
+        state.clone(),
+        PendingLogin {
+            provider,
+            code_verifier,
+            nonce,
+            started_at: now,
+        },
+    );
+    Ok((state, authorization_url.to_string()))
+}
+
+/// Finish the login with the supplied state. The authorization code is exchanged for an ID token
+/// at the external provider, which is validated and identifies the user. Each login can just be
+/// finished once.
+pub fn finish_login(
+    settings: &OidcSettings,
+    state: &str,
+    code: &str,
+    redirect_uri: &str,
+) -> Result<ExternalIdentity, String> {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use url::form_urlencoded::byte_serialize;
+
+    let (_, login) = PENDING_LOGINS
+        .remove(state)
+        .ok_or("The login is unknown or was already finished".to_string())?;
+    if login.started_at + Duration::minutes(PENDING_LOGIN_LIFETIME_IN_MINUTES) < Utc::now() {
+        return Err("The login was not finished in time".to_string());
+    }
+
+    // exchange the code for the tokens, confidential clients authenticate with their secret
+    let mut request = agent().post(&login.provider.token_endpoint);
+    if let Some(client_secret) = &settings.client_secret {
+        let credentials = format!(
+            "{}:{}",
+            byte_serialize(settings.client_id.as_bytes()).collect::<String>(),
+            byte_serialize(client_secret.as_bytes()).collect::<String>()
+        );
+        request = request.set(
+            "Authorization",
+            &format!("Basic {}", STANDARD.encode(credentials)),
+        );
+    }
+    let token_response = request
+        .send_form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("client_id", &settings.client_id),
+            ("code_verifier", &login.code_verifier),
+        ])
+        .map_err(|error| format!("Could not exchange the authorization code: {}", error))?
+        .into_json::<TokenResponse>()
+        .map_err(|error| format!("The token response is invalid: {}", error))?;
+    let id_token = token_response
+        .id_token
+        .ok_or("The token response does not contain an ID token".to_string())?;
+
+    let keys = agent()
+        .get(&login.provider.jwks_uri)
+        .call()
+        .map_err(|error| format!("Could not read the keys of the provider: {}", error))?
+        .into_json::<JwkSet>()
+        .map_err(|error| format!("The keys of the provider are invalid: {}", error))?;
+    validate_id_token(&id_token, settings, &login.provider, &keys, &login.nonce)
+}
+
+/// Validate the signature and the claims of an ID token of the external provider and get the user
+/// it was issued for.
+fn validate_id_token(
+    id_token: &str,
+    settings: &OidcSettings,
+    provider: &ProviderMetadata,
+    keys: &JwkSet,
+    nonce: &str,
+) -> Result<ExternalIdentity, String> {
+    use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+
+    let header =
+        decode_header(id_token).map_err(|error| format!("The ID token is invalid: {}", error))?;
+    if !ACCEPTED_ALGORITHMS.contains(&header.alg) {
+        return Err(format!(
+            "The ID token is signed with the unsupported algorithm {:?}",
+            header.alg
+        ));
+    }
+    let key = match &header.kid {
+        Some(key_id) => keys.find(key_id),
+        None if keys.keys.len() == 1 => keys.keys.first(),
+        None => None,
+    }
+    .ok_or("The ID token was not signed with a known key of the provider".to_string())?;
+    let decoding_key = DecodingKey::from_jwk(key)
+        .map_err(|error| format!("The key of the provider is invalid: {}", error))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[&settings.client_id]);
+    validation.set_issuer(&[&provider.issuer]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    let claims = decode::<IdTokenClaims>(id_token, &decoding_key, &validation)
+        .map_err(|error| format!("The ID token is invalid: {}", error))?
+        .claims;
+
+    // the nonce binds the ID token to the login it was requested for
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err("The nonce of the ID token does not match the login".to_string());
+    }
+    Ok(claims.identity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_challenges_follow_rfc_7636() {
+        // the example of appendix B of RFC 7636
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn unknown_logins_cannot_be_finished() {
+        let settings = OidcSettings {
+            issuer: "http://localhost:1".to_string(),
+            client_id: "adventskalender".to_string(),
+            client_secret: None,
+            default_role: None,
+            login_redirect: "http://localhost:5173".to_string(),
+        };
+        assert!(finish_login(&settings, "unknown", "code", "http://localhost/callback").is_err());
+    }
+}
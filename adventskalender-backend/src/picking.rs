@@ -0,0 +1,1006 @@
+use crate::calendar::{PickRefusal, RaffleCalendar};
+use crate::eligibility::DrawInput;
+use crate::models::{
+    Draw, DrawCommitment, Participant, Present, Raffle, Win, WinStatus, WinnerReplacement,
+};
+use chrono::NaiveDate;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::PgConnection;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// The first key of the advisory lock which is held while winners of a raffle are picked. The
+/// second key is the id of the raffle.
+const PICKING_LOCK_NAMESPACE: i32 = 0x5049_434b;
+
+/// The number of times a pick is retried if the database could not serialize it with a concurrent
+/// transaction (e.g. a winner which was removed at the same time).
+const MAX_PICK_ATTEMPTS: usize = 5;
+
+/// How long a pick waits for the picking lock of the raffle before it gives up, so a pick which
+/// hangs does not block all other picks of the raffle as well.
+const PICKING_LOCK_TIMEOUT: &str = "10s";
+
+/// The reasons why picking winners failed.
+#[derive(Debug)]
+pub enum PickError {
+    /// The calendar of the raffle does not allow to pick the winners for the requested day.
+    Refused(PickRefusal),
+    /// There are not enough participants left who did not win so far.
+    NotEnoughParticipants,
+    /// The user who picked the winners could not be looked up.
+    UnknownUser,
+    /// No seed for the draw could be generated.
+    NoRandomness,
+    /// The commitment which should be revealed does not exist.
+    UnknownCommitment,
+    /// The commitment was already revealed or does not belong to the requested draw.
+    CommitmentMismatch,
+    /// The participants who can win changed since the commitment was published.
+    CandidatesChanged,
+    /// The day has a commitment which was not revealed yet, so its winners can just be drawn by
+    /// revealing it.
+    OpenCommitment,
+    /// The participant is not a winner of the raffle.
+    UnknownWinner,
+    /// Another pick of the raffle did not finish in time.
+    Busy,
+    /// The database failed to perform the pick.
+    Database(DieselError),
+}
+
+impl Display for PickError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PickError::Refused(refusal) => write!(f, "{}", refusal),
+            PickError::NotEnoughParticipants => {
+                write!(f, "there are not enough participants left")
+            }
+            PickError::UnknownUser => write!(f, "the user could not be looked up"),
+            PickError::NoRandomness => write!(f, "no seed for the draw could be generated"),
+            PickError::UnknownCommitment => write!(f, "the commitment does not exist"),
+            PickError::CommitmentMismatch => {
+                write!(f, "the commitment does not match the requested draw")
+            }
+            PickError::CandidatesChanged => {
+                write!(
+                    f,
+                    "the participants changed since the commitment was published"
+                )
+            }
+            PickError::OpenCommitment => {
+                write!(f, "the day has a commitment which was not revealed yet")
+            }
+            PickError::UnknownWinner => write!(f, "the participant is not a winner of the raffle"),
+            PickError::Busy => write!(f, "another pick of the raffle did not finish in time"),
+            PickError::Database(error) => write!(f, "the database failed with: {}", error),
+        }
+    }
+}
+
+impl From<PickRefusal> for PickError {
+    fn from(refusal: PickRefusal) -> Self {
+        PickError::Refused(refusal)
+    }
+}
+
+impl From<DieselError> for PickError {
+    fn from(error: DieselError) -> Self {
+        PickError::Database(error)
+    }
+}
+
+/// A participant together with its (active) win.
+pub struct Winner {
+    /// The participant who won.
+    pub participant: Participant,
+    /// The recorded win of the participant.
+    pub win: Win,
+    /// The present which was assigned to the winner.
+    pub present: Option<Present>,
+}
+
+/// The winners of a draw together with the recorded draw itself.
+pub struct DrawnWinners {
+    /// The recorded draw which can be used to verify the winners.
+    pub draw: Draw,
+    /// The winners in the order in which they were drawn.
+    pub winners: Vec<Winner>,
+}
+
+/// The ids of all participants who are currently winners (of any raffle), to be used as a
+/// subselect when looking for the participants who did not win so far.
+pub(crate) fn active_winner_ids(
+) -> crate::schema::wins::BoxedQuery<'static, diesel::pg::Pg, diesel::sql_types::Integer> {
+    use crate::schema::wins::dsl::{participant_id, status, wins};
+    use diesel::{ExpressionMethods, QueryDsl};
+
+    wins.filter(status.eq(WinStatus::Active.as_str()))
+        .select(participant_id)
+        .into_boxed()
+}
+
+/// Count the participants of a raffle who are (active) winners of the supplied day.
+pub(crate) fn count_winners_on(
+    connection: &mut PgConnection,
+    raffle_id: i32,
+    date: NaiveDate,
+) -> Result<usize, DieselError> {
+    use crate::schema::{participants, wins};
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    let count = wins::table
+        .inner_join(participants::table)
+        .filter(participants::raffle_id.eq(raffle_id))
+        .filter(wins::won_on.eq(date))
+        .filter(wins::status.eq(WinStatus::Active.as_str()))
+        .count()
+        .get_result::<i64>(connection)?;
+    Ok(count as usize)
+}
+
+/// Check if the raffle has a commitment for the supplied day which was not revealed yet. The
+/// winners of such a day can just be drawn by revealing the commitment, otherwise the committed
+/// draw could be re-rolled.
+fn has_open_commitment(
+    connection: &mut PgConnection,
+    raffle_id: i32,
+    date: NaiveDate,
+) -> Result<bool, DieselError> {
+    use crate::schema::draw_commitments;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    let count = draw_commitments::table
+        .filter(draw_commitments::raffle_id.eq(raffle_id))
+        .filter(draw_commitments::drawn_for.eq(date))
+        .filter(draw_commitments::revealed_at.is_null())
+        .count()
+        .get_result::<i64>(connection)?;
+    Ok(count > 0)
+}
+
+/// Load the participants of the raffle who did not win so far and apply the eligibility rules of
+/// the raffle (tickets, blocklist, absences, replaced winners and the limit of winners per
+/// department) for the supplied day. If `lock_candidates` is set, the rows of the candidates are
+/// locked until the end of the transaction.
+fn load_draw_input(
+    connection: &mut PgConnection,
+    raffle: &Raffle,
+    date: NaiveDate,
+    lock_candidates: bool,
+) -> Result<DrawInput, DieselError> {
+    use crate::eligibility::{build_draw_input, EligibilityEntry};
+    use crate::schema::participant_absences::dsl::{
+        end_date, participant_absences, participant_id, start_date,
+    };
+    use crate::schema::participants::dsl::{
+        blocked, department, id, participants, raffle_id, tickets,
+    };
+    use crate::schema::wins;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use std::collections::{HashMap, HashSet};
+
+    // the candidates are ordered by their id, so the list of candidates can be reproduced
+    let query = participants
+        .filter(raffle_id.eq(raffle.id))
+        .filter(id.ne_all(active_winner_ids()))
+        .select((id, tickets, department, blocked))
+        .order_by(id.asc());
+    let rows = if lock_candidates {
+        query
+            .for_update()
+            .load::<(i32, i32, Option<String>, bool)>(connection)?
+    } else {
+        query.load::<(i32, i32, Option<String>, bool)>(connection)?
+    };
+    let entries = rows
+        .into_iter()
+        .map(
+            |(entry_id, entry_tickets, entry_department, entry_blocked)| EligibilityEntry {
+                id: entry_id,
+                tickets: entry_tickets,
+                department: entry_department,
+                blocked: entry_blocked,
+            },
+        )
+        .collect::<Vec<EligibilityEntry>>();
+
+    let absent_ids = participant_absences
+        .inner_join(participants)
+        .filter(raffle_id.eq(raffle.id))
+        .filter(start_date.le(date))
+        .filter(end_date.ge(date))
+        .select(participant_id)
+        .load::<i32>(connection)?
+        .into_iter()
+        .collect::<HashSet<i32>>();
+
+    let replaced_ids = wins::table
+        .inner_join(participants)
+        .filter(raffle_id.eq(raffle.id))
+        .filter(wins::status.eq(WinStatus::Replaced.as_str()))
+        .select(wins::participant_id)
+        .load::<i32>(connection)?
+        .into_iter()
+        .collect::<HashSet<i32>>();
+
+    let mut department_winners = HashMap::new();
+    if raffle.max_winners_per_department.is_some() {
+        for winner_department in wins::table
+            .inner_join(participants)
+            .filter(raffle_id.eq(raffle.id))
+            .filter(wins::won_on.eq(date))
+            .filter(wins::status.eq(WinStatus::Active.as_str()))
+            .filter(department.is_not_null())
+            .select(department)
+            .load::<Option<String>>(connection)?
+            .into_iter()
+            .flatten()
+        {
+            *department_winners.entry(winner_department).or_insert(0) += 1;
+        }
+    }
+
+    Ok(build_draw_input(
+        &entries,
+        &absent_ids,
+        &replaced_ids,
+        &department_winners,
+        raffle.use_tickets,
+        raffle
+            .max_winners_per_department
+            .and_then(|max_winners| usize::try_from(max_winners).ok()),
+    ))
+}
+
+/// The advisory lock which serializes all picks of a raffle. It is a session lock (and not bound
+/// to a transaction), since the picks run in serializable transactions which would work on the
+/// snapshot taken before the lock was granted. The lock is released when the guard is dropped,
+/// even if the pick failed or panicked.
+struct PickingLock<'a> {
+    connection: &'a mut PgConnection,
+    raffle_id: i32,
+}
+
+impl<'a> PickingLock<'a> {
+    /// Wait for the picking lock of the raffle, but at most for [`PICKING_LOCK_TIMEOUT`].
+    fn acquire(connection: &'a mut PgConnection, raffle_id: i32) -> Result<Self, PickError> {
+        use diesel::sql_types::{Bool, Integer, Text};
+        use diesel::{sql_query, QueryableByName, RunQueryDsl};
+
+        #[derive(QueryableByName)]
+        struct AcquiredLock {
+            #[diesel(sql_type = Bool)]
+            acquired: bool,
+        }
+
+        // the timeout just applies to the statement which takes the lock, and the function just
+        // reports a lock which is not available in time (SQLSTATE 55P03) as not acquired, so all
+        // other errors of the database are not mistaken for a busy raffle
+        let lock = sql_query("SELECT advisory_lock_with_timeout($1, $2, $3) AS acquired")
+            .bind::<Integer, _>(PICKING_LOCK_NAMESPACE)
+            .bind::<Integer, _>(raffle_id)
+            .bind::<Text, _>(PICKING_LOCK_TIMEOUT)
+            .get_result::<AcquiredLock>(connection)?;
+        if !lock.acquired {
+            return Err(PickError::Busy);
+        }
+
+        Ok(PickingLock {
+            connection,
+            raffle_id,
+        })
+    }
+}
+
+impl Drop for PickingLock<'_> {
+    fn drop(&mut self) {
+        use diesel::sql_types::Integer;
+        use diesel::{sql_query, RunQueryDsl};
+        use log::error;
+
+        // if the lock cannot be released, the connection is most likely broken and discarded by
+        // the connection pool, which ends the session and releases the lock as well
+        if let Err(error) = sql_query("SELECT pg_advisory_unlock($1, $2)")
+            .bind::<Integer, _>(PICKING_LOCK_NAMESPACE)
+            .bind::<Integer, _>(self.raffle_id)
+            .execute(self.connection)
+        {
+            error!(
+                "Could not release the picking lock of the raffle {}. The error was: {}",
+                self.raffle_id, error
+            );
+        }
+    }
+}
+
+/// Run `operation` while holding the advisory lock which serializes all picks of a raffle. If the
+/// database could not serialize the operation with a concurrent transaction, it is retried.
+///
+/// This blocks until the lock is granted (or [`PICKING_LOCK_TIMEOUT`] passed), so it must not be
+/// called on an async worker thread.
+pub(crate) fn with_picking_lock<T, F>(
+    db_connection: &mut PgConnection,
+    raffle_id: i32,
+    mut operation: F,
+) -> Result<T, PickError>
+where
+    F: FnMut(&mut PgConnection) -> Result<T, PickError>,
+{
+    use log::debug;
+
+    // the lock is taken before the transaction starts, since a serializable transaction works on
+    // the snapshot of its first statement and would not see the winners picked while waiting
+    let lock = PickingLock::acquire(db_connection, raffle_id)?;
+
+    let mut attempt = 1;
+    loop {
+        match operation(lock.connection) {
+            Err(PickError::Database(DieselError::DatabaseError(
+                DatabaseErrorKind::SerializationFailure,
+                _,
+            ))) if attempt < MAX_PICK_ATTEMPTS => {
+                debug!(
+                    "Picking winners for the raffle {} could not be serialized (attempt {} of {}), retrying",
+                    raffle_id, attempt, MAX_PICK_ATTEMPTS
+                );
+                attempt += 1;
+            }
+            result => break result,
+        }
+    }
+}
+
+/// Draw `count` winners from the candidates with the supplied seed and record the draw, so it can
+/// be verified later on.
+fn draw_and_record(
+    connection: &mut PgConnection,
+    raffle_id: i32,
+    date: NaiveDate,
+    count: usize,
+    drawn_by: Option<i32>,
+    seed: &[u8; crate::draw::SEED_LENGTH],
+    input: DrawInput,
+) -> Result<(Draw, Vec<i32>), PickError> {
+    use crate::draw::{draw_weighted_winners, weighted_candidates_hash, DRAW_ALGORITHM_VERSION};
+    use crate::models::NewDraw;
+    use crate::schema::draws::dsl::draws;
+    use diesel::{insert_into, RunQueryDsl};
+
+    let winner_ids = draw_weighted_winners(seed, &input.candidates, &input.group_capacities, count);
+    if winner_ids.len() < count {
+        // the limits of the departments do not allow to draw enough winners
+        return Err(PickError::NotEnoughParticipants);
+    }
+
+    let draw = insert_into(draws)
+        .values(&NewDraw {
+            raffle_id,
+            drawn_for: date,
+            drawn_by,
+            algorithm_version: DRAW_ALGORITHM_VERSION,
+            seed: seed.to_vec(),
+            candidates_hash: weighted_candidates_hash(&input.candidates, &input.group_capacities),
+            candidate_ids: input
+                .candidates
+                .iter()
+                .map(|candidate| candidate.id)
+                .collect(),
+            winner_ids: winner_ids.clone(),
+            candidate_weights: Some(
+                input
+                    .candidates
+                    .iter()
+                    .map(|candidate| candidate.weight as i32)
+                    .collect(),
+            ),
+            candidate_groups: Some(
+                input
+                    .candidates
+                    .iter()
+                    .map(|candidate| candidate.group.map(|group| group as i32))
+                    .collect(),
+            ),
+            group_capacities: Some(
+                input
+                    .group_capacities
+                    .iter()
+                    .map(|capacity| *capacity as i32)
+                    .collect(),
+            ),
+            rules: rocket::serde::json::to_string(&input.rules).ok(),
+        })
+        .get_result::<Draw>(connection)?;
+    Ok((draw, winner_ids))
+}
+
+/// Record a win (with the present at the same position) for each of the drawn participants and
+/// return them in the order in which they were drawn.
+fn mark_as_won(
+    connection: &mut PgConnection,
+    draw: &Draw,
+    winner_ids: &[i32],
+    picked_by: Option<i32>,
+    winner_presents: Vec<Option<Present>>,
+) -> Result<Vec<Winner>, PickError> {
+    use crate::models::NewWin;
+    use crate::schema::participants::dsl::{id, participants};
+    use crate::schema::wins::dsl::wins;
+    use chrono::Utc;
+    use diesel::{insert_into, ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    let picked_at = Utc::now().naive_utc();
+    let new_wins = winner_ids
+        .iter()
+        .zip(&winner_presents)
+        .map(|(winner_id, present)| NewWin {
+            participant_id: *winner_id,
+            won_on: draw.drawn_for,
+            picked_by,
+            picked_at: Some(picked_at),
+            draw_id: Some(draw.id),
+            present_id: present.as_ref().map(|present| present.id),
+        })
+        .collect::<Vec<_>>();
+    // the unique index on the active wins refuses participants who won in the meantime
+    let recorded_wins = insert_into(wins)
+        .values(&new_wins)
+        .get_results::<Win>(connection)?;
+    let mut winners = participants
+        .filter(id.eq_any(winner_ids))
+        .load::<Participant>(connection)?;
+    if winners.len() != winner_ids.len() {
+        return Err(PickError::NotEnoughParticipants);
+    }
+    winners.sort_by_key(|winner| {
+        winner_ids
+            .iter()
+            .position(|winner_id| *winner_id == winner.id)
+    });
+    Ok(winners
+        .into_iter()
+        .zip(recorded_wins)
+        .zip(winner_presents)
+        .map(|((participant, win), present)| Winner {
+            participant,
+            win,
+            present,
+        })
+        .collect())
+}
+
+/// Randomly pick `count` winners of a raffle for the supplied day and mark them as won.
+///
+/// Selecting and marking the winners happens in a single serializable transaction. Additionally,
+/// an advisory lock per raffle is held while doing so. All days of a raffle share the same
+/// participants, so two parallel picks (even for different days) are processed one after another
+/// and can never select the same participant or exceed the quota of a day.
+///
+/// Only participants who are eligible on that day are drawn (see [`load_draw_input`]). The winners
+/// are drawn with a freshly generated seed which is recorded (together with the ordered and
+/// weighted candidates and the applied rules) in the `draws` table, so the draw can be replayed
+/// later on. If a `commitment_id` is supplied, the seed of that (previously published) commitment
+/// is used instead and the commitment is marked as revealed.
+///
+/// If the raffle assigns the presents automatically, the winners get the remaining presents of the
+/// day (see [`crate::presents::plan_assignments`]) within the same transaction and each assignment
+/// is logged as selected package.
+pub fn pick_winners(
+    db_connection: &mut PgConnection,
+    raffle: &Raffle,
+    date: NaiveDate,
+    count: usize,
+    user_who_picked: &str,
+    commitment_id: Option<i32>,
+) -> Result<DrawnWinners, PickError> {
+    // refuse invalid days before waiting for the lock of the raffle
+    let calendar = RaffleCalendar::from(raffle);
+    calendar.check_day(date)?;
+
+    with_picking_lock(db_connection, raffle.id, |connection| {
+        pick_winners_in_transaction(
+            connection,
+            raffle,
+            &calendar,
+            date,
+            count,
+            Some(user_who_picked),
+            commitment_id,
+        )
+    })
+}
+
+/// Pick all winners of the supplied day for a scheduled draw (without a user who picked them).
+///
+/// `None` is returned if the day already has winners, so the scheduled draw can be repeated (e.g.
+/// after a restart of the server) without picking any additional winners. The check happens while
+/// the picking lock of the raffle is held, so it cannot race with a manual pick.
+pub fn pick_scheduled_winners(
+    db_connection: &mut PgConnection,
+    raffle: &Raffle,
+    date: NaiveDate,
+) -> Result<Option<DrawnWinners>, PickError> {
+    let calendar = RaffleCalendar::from(raffle);
+    calendar.check_day(date)?;
+
+    with_picking_lock(db_connection, raffle.id, |connection| {
+        if count_winners_on(connection, raffle.id, date)? > 0 {
+            return Ok(None);
+        }
+        pick_winners_in_transaction(
+            connection,
+            raffle,
+            &calendar,
+            date,
+            calendar.winners_per_day,
+            None,
+            None,
+        )
+        .map(Some)
+    })
+}
+
+fn pick_winners_in_transaction(
+    db_connection: &mut PgConnection,
+    raffle: &Raffle,
+    calendar: &RaffleCalendar,
+    date: NaiveDate,
+    count: usize,
+    user_who_picked: Option<&str>,
+    commitment_id: Option<i32>,
+) -> Result<DrawnWinners, PickError> {
+    use crate::draw::{generate_seed, weighted_candidates_hash, SEED_LENGTH};
+    use crate::models::DrawCommitmentReveal;
+    use crate::presents::{plan_presents_for_winners, AssignmentStrategy};
+    use crate::schema::draw_commitments::dsl::draw_commitments;
+    use crate::{log_action, lookup_user_by_name, Action};
+    use chrono::Utc;
+    use diesel::{update, OptionalExtension, QueryDsl, RunQueryDsl};
+
+    let raffle_id = raffle.id;
+    db_connection
+        .build_transaction()
+        .serializable()
+        .read_write()
+        .run::<_, PickError, _>(|connection| {
+            // look up the user object who initiated the call (scheduled draws are performed by the
+            // system)
+            let user = match user_who_picked {
+                Some(username) => Some(
+                    lookup_user_by_name(connection, username.to_string())
+                        .map_err(|_| PickError::UnknownUser)?,
+                ),
+                None => None,
+            };
+            let user_id = user.as_ref().map(|user| user.id);
+
+            // ensure that the quota of the day is not exceeded by the new winners
+            let already_won = count_winners_on(connection, raffle_id, date)?;
+            calendar.check_pick(date, count, already_won)?;
+
+            // a day with a published commitment can just be drawn by revealing it
+            if commitment_id.is_none() && has_open_commitment(connection, raffle_id, date)? {
+                return Err(PickError::OpenCommitment);
+            }
+
+            // draw the winners from all eligible participants who did not win so far
+            let input = load_draw_input(connection, raffle, date, true)?;
+            if input.candidates.len() < count {
+                return Err(PickError::NotEnoughParticipants);
+            }
+            let candidates_hash =
+                weighted_candidates_hash(&input.candidates, &input.group_capacities);
+
+            // use the published seed of the commitment (if the draw was committed to before) or a
+            // freshly generated one
+            let seed = match commitment_id {
+                Some(commitment_id) => {
+                    let commitment = draw_commitments
+                        .find(commitment_id)
+                        .for_update()
+                        .first::<DrawCommitment>(connection)
+                        .optional()?
+                        .ok_or(PickError::UnknownCommitment)?;
+                    if commitment.raffle_id != raffle_id
+                        || commitment.drawn_for != date
+                        || commitment.winner_count as usize != count
+                        || commitment.revealed_at.is_some()
+                    {
+                        return Err(PickError::CommitmentMismatch);
+                    }
+                    if commitment.candidates_hash != candidates_hash {
+                        return Err(PickError::CandidatesChanged);
+                    }
+                    <[u8; SEED_LENGTH]>::try_from(commitment.seed.as_slice())
+                        .map_err(|_| PickError::CommitmentMismatch)?
+                }
+                None => generate_seed().map_err(|_| PickError::NoRandomness)?,
+            };
+
+            // record the draw, so it can be verified later on
+            let (draw, winner_ids) =
+                draw_and_record(connection, raffle_id, date, count, user_id, &seed, input)?;
+            if let Some(commitment_id) = commitment_id {
+                update(draw_commitments.find(commitment_id))
+                    .set(&DrawCommitmentReveal {
+                        revealed_at: Some(Utc::now().naive_utc()),
+                        draw_id: Some(draw.id),
+                    })
+                    .execute(connection)?;
+            }
+
+            // select the presents of the winners (if they are assigned automatically) and mark the
+            // winners as won before the transaction (and with it the row locks) ends
+            let winner_presents =
+                plan_presents_for_winners(connection, raffle, date, &seed, &winner_ids)?;
+            let winners = mark_as_won(connection, &draw, &winner_ids, user_id, winner_presents)?;
+            for winner in &winners {
+                if let Some(present) = &winner.present {
+                    log_action(
+                        connection,
+                        user.as_ref().map(|user| user.username.clone()),
+                        Action::PackageSelected,
+                        Some(format!(
+                            "The participant with the id {} was assigned to package {} (id {}) by the {} assignment of the draw {}",
+                            winner.participant.id,
+                            present.identifier,
+                            present.id,
+                            AssignmentStrategy::of(raffle),
+                            draw.id
+                        )),
+                    );
+                }
+            }
+            Ok(DrawnWinners { draw, winners })
+        })
+}
+
+/// The reasons why a winner is replaced by a substitute.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplacementReason {
+    /// The winner declined the present.
+    Declined,
+    /// The winner is absent and cannot receive the present.
+    Absent,
+    /// The winner is a duplicate of another participant.
+    Duplicate,
+}
+
+impl Display for ReplacementReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            ReplacementReason::Declined => write!(f, "declined"),
+            ReplacementReason::Absent => write!(f, "absent"),
+            ReplacementReason::Duplicate => write!(f, "duplicate"),
+        }
+    }
+}
+
+/// A replaced winner together with the substitute who was drawn instead.
+pub struct ReplacedWinner {
+    /// The record of the replacement.
+    pub replacement: WinnerReplacement,
+    /// The original (now replaced) win.
+    pub replaced_win: Win,
+    /// The present of the replaced winner which was taken over by the substitute.
+    pub present: Option<Present>,
+    /// The recorded draw which selected the substitute.
+    pub draw: Draw,
+    /// The substitute who won instead.
+    pub substitute: Winner,
+}
+
+/// Replace a winner of a raffle by a randomly drawn substitute for the same day.
+///
+/// The original win is kept with the status `replaced` (and linked to the substitute in the
+/// `winner_replacements` table), the winner is released (and cannot win again) and the substitute
+/// is drawn and takes over the present of the original winner. All of this happens in a single
+/// transaction under the picking lock of the raffle (see [`pick_winners`]), together with the two
+/// (linked) entries of the audit log.
+pub fn replace_winner(
+    db_connection: &mut PgConnection,
+    raffle: &Raffle,
+    winner_id: i32,
+    reason: ReplacementReason,
+    user_who_replaced: &str,
+) -> Result<ReplacedWinner, PickError> {
+    let calendar = RaffleCalendar::from(raffle);
+    with_picking_lock(db_connection, raffle.id, |connection| {
+        replace_winner_in_transaction(
+            connection,
+            raffle,
+            &calendar,
+            winner_id,
+            reason,
+            user_who_replaced,
+        )
+    })
+}
+
+fn replace_winner_in_transaction(
+    db_connection: &mut PgConnection,
+    raffle: &Raffle,
+    calendar: &RaffleCalendar,
+    winner_id: i32,
+    reason: ReplacementReason,
+    user_who_replaced: &str,
+) -> Result<ReplacedWinner, PickError> {
+    use crate::draw::generate_seed;
+    use crate::models::{NewWinnerReplacement, WinRevocation, WinnerSubstitution};
+    use crate::schema::participants::dsl::{id, participants, raffle_id as participant_raffle_id};
+    use crate::schema::presents::dsl::presents;
+    use crate::schema::winner_replacements::dsl::winner_replacements;
+    use crate::schema::wins::dsl::{participant_id, status, wins};
+    use crate::{log_action, lookup_user_by_name, Action};
+    use chrono::Utc;
+    use diesel::{
+        insert_into, update, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+    };
+
+    db_connection
+        .build_transaction()
+        .serializable()
+        .read_write()
+        .run::<_, PickError, _>(|connection| {
+            // look up the user object who initiated the call
+            let user = lookup_user_by_name(connection, user_who_replaced.to_string())
+                .map_err(|_| PickError::UnknownUser)?;
+
+            let winner = participants
+                .filter(id.eq(winner_id))
+                .filter(participant_raffle_id.eq(raffle.id))
+                .for_update()
+                .first::<Participant>(connection)
+                .optional()?
+                .ok_or(PickError::UnknownWinner)?;
+            let win = wins
+                .filter(participant_id.eq(winner.id))
+                .filter(status.eq(WinStatus::Active.as_str()))
+                .for_update()
+                .first::<Win>(connection)
+                .optional()?
+                .ok_or(PickError::UnknownWinner)?;
+            let date = win.won_on;
+            let present = match win.present_id {
+                Some(present_id) => presents
+                    .find(present_id)
+                    .first::<Present>(connection)
+                    .optional()?,
+                None => None,
+            };
+
+            // keep the original win as history and release the winner
+            let replaced_win = update(wins.find(win.id))
+                .set(&WinRevocation {
+                    status: WinStatus::Replaced.as_str().to_string(),
+                    revoked_at: Some(Utc::now().naive_utc()),
+                })
+                .get_result::<Win>(connection)?;
+            let replacement = insert_into(winner_replacements)
+                .values(&NewWinnerReplacement {
+                    raffle_id: raffle.id,
+                    reason: reason.to_string(),
+                    replaced_by: Some(user.id),
+                    win_id: win.id,
+                })
+                .get_result::<WinnerReplacement>(connection)?;
+
+            // draw the substitute from all eligible participants (the released winner is excluded
+            // since its win is marked as replaced)
+            let already_won = count_winners_on(connection, raffle.id, date)?;
+            calendar.check_pick(date, 1, already_won)?;
+            if has_open_commitment(connection, raffle.id, date)? {
+                return Err(PickError::OpenCommitment);
+            }
+            let input = load_draw_input(connection, raffle, date, true)?;
+            if input.candidates.is_empty() {
+                return Err(PickError::NotEnoughParticipants);
+            }
+            let seed = generate_seed().map_err(|_| PickError::NoRandomness)?;
+            let (draw, substitute_ids) = draw_and_record(
+                connection,
+                raffle.id,
+                date,
+                1,
+                Some(user.id),
+                &seed,
+                input,
+            )?;
+            let substitute = mark_as_won(
+                connection,
+                &draw,
+                &substitute_ids,
+                Some(user.id),
+                vec![present.clone()],
+            )?
+            .remove(0);
+            let replacement = update(winner_replacements.find(replacement.id))
+                .set(&WinnerSubstitution {
+                    substitute_win_id: Some(substitute.win.id),
+                })
+                .get_result::<WinnerReplacement>(connection)?;
+
+            // both halves are logged within the transaction and reference the same replacement
+            log_action(
+                connection,
+                Some(user.username.clone()),
+                Action::WinnerReplaced,
+                Some(format!(
+                    "The winner with the id {} of the {} in the raffle {} was released (reason: {}, replacement {}, substitute {})",
+                    winner.id, date, raffle.id, reason, replacement.id, substitute.participant.id
+                )),
+            );
+            log_action(
+                connection,
+                Some(user.username.clone()),
+                Action::SubstitutePicked,
+                Some(format!(
+                    "The participant with the id {} was marked as won in the raffle {} by the draw {} as substitute for the winner with the id {} (replacement {})",
+                    substitute.participant.id, raffle.id, draw.id, winner.id, replacement.id
+                )),
+            );
+
+            Ok(ReplacedWinner {
+                replacement,
+                replaced_win,
+                present,
+                draw,
+                substitute,
+            })
+        })
+}
+
+/// The expected outcome of picking winners, determined without changing anything.
+#[derive(Serialize, Clone, Debug)]
+pub struct PickPreview {
+    /// The day for which the winners would be picked.
+    pub date: NaiveDate,
+    /// The number of winners which would be picked.
+    pub requested: usize,
+    /// The number of winners which were already picked for the day.
+    pub already_won: usize,
+    /// The number of winners which can still be picked for the day.
+    pub remaining_quota: usize,
+    /// The number of participants who did not win so far.
+    pub remaining_participants: usize,
+    /// The number of participants who did not win so far and are eligible on the day.
+    pub eligible_participants: usize,
+    /// The maximum number of winners which can be drawn from the eligible participants without
+    /// exceeding the limits of their departments.
+    pub drawable_winners: usize,
+    /// The number of participants who are excluded since they are on the blocklist.
+    pub blocked_participants: usize,
+    /// The number of participants who are excluded since they are absent on the day.
+    pub absent_participants: usize,
+    /// The number of participants who are excluded since their department has enough winners.
+    pub department_limited_participants: usize,
+    /// The number of former winners who are excluded since they were replaced.
+    pub replaced_participants: usize,
+    /// A flag which indicates that the pick would succeed (unless something changes until then).
+    pub can_pick: bool,
+    /// The reasons why the pick would be refused.
+    pub problems: Vec<String>,
+}
+
+/// Determine how picking `count` winners of a raffle for the supplied day would turn out. All
+/// checks of [`pick_winners`] are performed, but nothing is written to the database.
+pub fn preview_pick(
+    db_connection: &mut PgConnection,
+    raffle: &Raffle,
+    date: NaiveDate,
+    count: usize,
+) -> Result<PickPreview, DieselError> {
+    let calendar = RaffleCalendar::from(raffle);
+    db_connection
+        .build_transaction()
+        .read_only()
+        .run::<_, DieselError, _>(|connection| {
+            let already_won = count_winners_on(connection, raffle.id, date)?;
+            let input = load_draw_input(connection, raffle, date, false)?;
+            let drawable_winners = input.max_winners();
+
+            let mut problems = vec![];
+            if raffle.archived {
+                problems.push("the raffle is archived".to_string());
+            }
+            if let Err(refusal) = calendar.check_pick(date, count, already_won) {
+                problems.push(refusal.to_string());
+            }
+            if drawable_winners < count {
+                problems.push(format!(
+                    "just {} of the {} eligible participants can win, but {} winners were requested",
+                    drawable_winners,
+                    input.candidates.len(),
+                    count
+                ));
+            }
+
+            Ok(PickPreview {
+                date,
+                requested: count,
+                already_won,
+                remaining_quota: calendar.winners_per_day.saturating_sub(already_won),
+                remaining_participants: input.candidates.len()
+                    + input.rules.blocked_ids.len()
+                    + input.rules.absent_ids.len()
+                    + input.rules.department_limit_ids.len()
+                    + input.rules.replaced_ids.len(),
+                eligible_participants: input.candidates.len(),
+                drawable_winners,
+                blocked_participants: input.rules.blocked_ids.len(),
+                absent_participants: input.rules.absent_ids.len(),
+                department_limited_participants: input.rules.department_limit_ids.len(),
+                replaced_participants: input.rules.replaced_ids.len(),
+                can_pick: problems.is_empty(),
+                problems,
+            })
+        })
+}
+
+/// Commit to the seed of an upcoming draw of `count` winners for the supplied day. The hash of the
+/// seed (and of the current candidates) can be published before the draw is performed with
+/// [`pick_winners`], so everybody can check afterwards that the winners were not re-rolled.
+///
+/// A day can just have one open commitment, and its winners cannot be picked without revealing it.
+/// The commitment is created under the picking lock of the raffle, so it cannot race with a pick.
+pub fn commit_draw(
+    db_connection: &mut PgConnection,
+    raffle: &Raffle,
+    date: NaiveDate,
+    count: usize,
+    user_who_committed: &str,
+) -> Result<DrawCommitment, PickError> {
+    use crate::draw::{generate_seed, seed_hash, weighted_candidates_hash};
+    use crate::lookup_user_by_name;
+    use crate::models::NewDrawCommitment;
+    use crate::schema::draw_commitments::dsl::draw_commitments;
+    use diesel::{insert_into, RunQueryDsl};
+
+    let calendar = RaffleCalendar::from(raffle);
+    let raffle_id = raffle.id;
+    with_picking_lock(db_connection, raffle_id, |connection| {
+        connection
+            .build_transaction()
+            .read_write()
+            .run::<_, PickError, _>(|connection| {
+                // look up the user object who initiated the call
+                let user = lookup_user_by_name(connection, user_who_committed.to_string())
+                    .map_err(|_| PickError::UnknownUser)?;
+
+                // the committed draw has to be possible with the current winners and candidates
+                let already_won = count_winners_on(connection, raffle_id, date)?;
+                calendar.check_pick(date, count, already_won)?;
+                if has_open_commitment(connection, raffle_id, date)? {
+                    return Err(PickError::OpenCommitment);
+                }
+                let input = load_draw_input(connection, raffle, date, false)?;
+                if input.candidates.len() < count {
+                    return Err(PickError::NotEnoughParticipants);
+                }
+
+                let seed = generate_seed().map_err(|_| PickError::NoRandomness)?;
+                insert_into(draw_commitments)
+                    .values(&NewDrawCommitment {
+                        raffle_id,
+                        drawn_for: date,
+                        winner_count: count as i32,
+                        seed: seed.to_vec(),
+                        seed_hash: seed_hash(&seed),
+                        candidates_hash: weighted_candidates_hash(
+                            &input.candidates,
+                            &input.group_capacities,
+                        ),
+                        committed_by: Some(user.id),
+                    })
+                    .get_result::<DrawCommitment>(connection)
+                    .map_err(|error| match error {
+                        // the unique index refuses a second open commitment for the day
+                        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                            PickError::OpenCommitment
+                        }
+                        error => PickError::Database(error),
+                    })
+            })
+    })
+}
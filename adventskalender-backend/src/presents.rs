@@ -0,0 +1,714 @@
+use crate::calendar::{PickRefusal, RaffleCalendar};
+use crate::models::{NewPresent, Present, Raffle, Win, WinStatus};
+use chrono::NaiveDate;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::PgConnection;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// The maximum length of the short identifier of a present (e.g. `A`) which is shown in the
+/// calendar.
+pub const MAX_PRESENT_IDENTIFIER_LENGTH: usize = 8;
+
+/// The maximum length of the name of a present.
+pub const MAX_PRESENT_NAME_LENGTH: usize = 128;
+
+/// The maximum length of the name of the sponsor of a present.
+pub const MAX_PRESENT_SPONSOR_LENGTH: usize = 128;
+
+/// The maximum length of the description of a present.
+pub const MAX_PRESENT_DESCRIPTION_LENGTH: usize = 2048;
+
+/// The reasons why the inventory could not be changed or a present could not be assigned.
+#[derive(Debug)]
+pub enum PresentError {
+    /// The present does not exist (in the raffle).
+    UnknownPresent,
+    /// The participant does not exist (in the raffle).
+    UnknownParticipant,
+    /// The participant is not a winner, so no present can be assigned.
+    NotAWinner,
+    /// The present is not available on the day the winner won or the day is not part of the
+    /// raffle.
+    WrongDay,
+    /// All pieces of the present are already assigned to other winners.
+    OutOfStock,
+    /// The present is (or was) assigned to winners, so it cannot be moved or removed and its
+    /// quantity cannot be reduced below the number of assigned pieces.
+    InUse,
+    /// Another present of the same day already uses the identifier.
+    DuplicateIdentifier,
+    /// The database failed to perform the change.
+    Database(DieselError),
+}
+
+impl Display for PresentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PresentError::UnknownPresent => write!(f, "the present does not exist"),
+            PresentError::UnknownParticipant => write!(f, "the participant does not exist"),
+            PresentError::NotAWinner => write!(f, "the participant is not a winner"),
+            PresentError::WrongDay => {
+                write!(f, "the present is not available on the day of the win")
+            }
+            PresentError::OutOfStock => write!(f, "all pieces of the present are assigned"),
+            PresentError::InUse => write!(f, "the present is assigned to winners"),
+            PresentError::DuplicateIdentifier => {
+                write!(f, "the identifier is already used on the same day")
+            }
+            PresentError::Database(error) => write!(f, "the database failed with: {}", error),
+        }
+    }
+}
+
+impl From<DieselError> for PresentError {
+    fn from(error: DieselError) -> Self {
+        match error {
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                PresentError::DuplicateIdentifier
+            }
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                PresentError::InUse
+            }
+            error => PresentError::Database(error),
+        }
+    }
+}
+
+impl From<PickRefusal> for PresentError {
+    fn from(_: PickRefusal) -> Self {
+        PresentError::WrongDay
+    }
+}
+
+/// The values of a present which can be set by the users.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PresentValues {
+    /// The day on which the present is handed out.
+    pub available_on: NaiveDate,
+    /// The short identifier of the present (e.g. `A`) which is shown in the calendar.
+    pub identifier: String,
+    /// The name of the present.
+    pub name: String,
+    /// An optional description of the present.
+    pub description: Option<String>,
+    /// The (optional) sponsor of the present.
+    pub sponsor: Option<String>,
+    /// The number of pieces which can be handed out.
+    pub quantity: i32,
+}
+
+/// Trim the supplied optional text and treat empty texts as no text. `None` is returned for texts
+/// which are too long.
+fn normalize_optional_text(text: Option<&str>, max_length: usize) -> Option<Option<String>> {
+    match text.map(str::trim) {
+        None | Some("") => Some(None),
+        Some(text) if text.chars().count() > max_length => None,
+        Some(text) => Some(Some(text.to_string())),
+    }
+}
+
+impl PresentValues {
+    /// Get the normalized values (with surrounding whitespace removed) or `None` if the values
+    /// cannot be stored.
+    pub fn validated(&self) -> Option<PresentValues> {
+        let identifier = self.identifier.trim();
+        let name = self.name.trim();
+        if identifier.is_empty()
+            || name.is_empty()
+            || identifier.chars().count() > MAX_PRESENT_IDENTIFIER_LENGTH
+            || name.chars().count() > MAX_PRESENT_NAME_LENGTH
+            || self.quantity < 0
+        {
+            return None;
+        }
+        Some(PresentValues {
+            available_on: self.available_on,
+            identifier: identifier.to_string(),
+            name: name.to_string(),
+            description: normalize_optional_text(
+                self.description.as_deref(),
+                MAX_PRESENT_DESCRIPTION_LENGTH,
+            )?,
+            sponsor: normalize_optional_text(self.sponsor.as_deref(), MAX_PRESENT_SPONSOR_LENGTH)?,
+            quantity: self.quantity,
+        })
+    }
+
+    fn to_new_present(&self, raffle_id: i32) -> NewPresent {
+        NewPresent {
+            raffle_id,
+            available_on: self.available_on,
+            identifier: self.identifier.clone(),
+            name: self.name.clone(),
+            description: self.description.clone(),
+            sponsor: self.sponsor.clone(),
+            quantity: self.quantity,
+        }
+    }
+}
+
+/// Count the (active) winners to which the present is assigned.
+pub fn count_assigned(connection: &mut PgConnection, present_id: i32) -> Result<i64, DieselError> {
+    use crate::schema::wins;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    wins::table
+        .filter(wins::present_id.eq(present_id))
+        .filter(wins::status.eq(WinStatus::Active.as_str()))
+        .count()
+        .get_result::<i64>(connection)
+}
+
+/// Load the presents of a raffle (optionally just the ones of a single day) together with the
+/// number of pieces which are assigned to winners.
+pub fn load_presents(
+    connection: &mut PgConnection,
+    raffle_id: i32,
+    date: Option<NaiveDate>,
+) -> Result<Vec<(Present, i64)>, DieselError> {
+    use crate::schema::{presents, wins};
+    use diesel::dsl::count_star;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use std::collections::HashMap;
+
+    let mut query = presents::table
+        .filter(presents::raffle_id.eq(raffle_id))
+        .order_by((
+            presents::available_on.asc(),
+            presents::identifier.asc(),
+            presents::id.asc(),
+        ))
+        .into_boxed();
+    if let Some(date) = date {
+        query = query.filter(presents::available_on.eq(date));
+    }
+    let found_presents = query.load::<Present>(connection)?;
+
+    let assigned = wins::table
+        .filter(wins::status.eq(WinStatus::Active.as_str()))
+        .filter(
+            wins::present_id.eq_any(
+                found_presents
+                    .iter()
+                    .map(|present| present.id)
+                    .collect::<Vec<_>>(),
+            ),
+        )
+        .group_by(wins::present_id)
+        .select((wins::present_id, count_star()))
+        .load::<(Option<i32>, i64)>(connection)?
+        .into_iter()
+        .filter_map(|(present_id, count)| present_id.map(|present_id| (present_id, count)))
+        .collect::<HashMap<_, _>>();
+
+    Ok(found_presents
+        .into_iter()
+        .map(|present| {
+            let count = assigned.get(&present.id).copied().unwrap_or(0);
+            (present, count)
+        })
+        .collect())
+}
+
+/// Add a present to the inventory of a raffle. The day of the present has to be a valid day of
+/// the raffle.
+pub fn create_present(
+    connection: &mut PgConnection,
+    raffle: &Raffle,
+    values: &PresentValues,
+) -> Result<Present, PresentError> {
+    use crate::schema::presents::dsl::presents;
+    use diesel::{insert_into, RunQueryDsl};
+
+    RaffleCalendar::from(raffle).check_day(values.available_on)?;
+    Ok(insert_into(presents)
+        .values(&values.to_new_present(raffle.id))
+        .get_result::<Present>(connection)?)
+}
+
+/// Change a present of the inventory and return it before and after the change.
+///
+/// The present is locked while the change is checked, so its quantity can never be reduced below
+/// the number of pieces which are assigned concurrently. Presents which are assigned to winners
+/// cannot be moved to another day.
+pub fn change_present(
+    connection: &mut PgConnection,
+    raffle: &Raffle,
+    present_id: i32,
+    values: &PresentValues,
+) -> Result<(Present, Present), PresentError> {
+    use crate::schema::presents::dsl::{id, presents, raffle_id};
+    use diesel::{update, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+
+    RaffleCalendar::from(raffle).check_day(values.available_on)?;
+    connection
+        .build_transaction()
+        .read_write()
+        .run::<_, PresentError, _>(|connection| {
+            let old_present = presents
+                .filter(id.eq(present_id))
+                .filter(raffle_id.eq(raffle.id))
+                .for_update()
+                .first::<Present>(connection)
+                .optional()?
+                .ok_or(PresentError::UnknownPresent)?;
+            let assigned = count_assigned(connection, present_id)?;
+            if i64::from(values.quantity) < assigned
+                || (assigned > 0 && old_present.available_on != values.available_on)
+            {
+                return Err(PresentError::InUse);
+            }
+            let changed_present = update(presents.filter(id.eq(present_id)))
+                .set(&values.to_new_present(raffle.id))
+                .get_result::<Present>(connection)?;
+            Ok((old_present, changed_present))
+        })
+}
+
+/// Remove a present from the inventory. Presents which are (or were) assigned to a winner are kept
+/// for the history of the wins and cannot be removed.
+pub fn delete_present(
+    connection: &mut PgConnection,
+    raffle_id: i32,
+    present_id: i32,
+) -> Result<Present, PresentError> {
+    use crate::schema::presents::dsl::{id, presents, raffle_id as present_raffle_id};
+    use diesel::{delete, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+
+    delete(
+        presents
+            .filter(id.eq(present_id))
+            .filter(present_raffle_id.eq(raffle_id)),
+    )
+    .get_result::<Present>(connection)
+    .optional()?
+    .ok_or(PresentError::UnknownPresent)
+}
+
+/// The ways in which the presents of a day are assigned to the winners when they are picked.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AssignmentStrategy {
+    /// The presents are assigned by hand after the winners were picked.
+    #[default]
+    Manual,
+    /// The remaining pieces are assigned randomly (with the seed of the draw).
+    Random,
+    /// The remaining pieces are assigned in the order of the identifiers of the presents.
+    Ordered,
+    /// Every winner gets the first present of his or her preferences which is still available.
+    /// Winners without an available preference get the next present in the order of the
+    /// identifiers.
+    Preference,
+}
+
+impl AssignmentStrategy {
+    /// Get the value which is stored in the database for the strategy.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssignmentStrategy::Manual => "manual",
+            AssignmentStrategy::Random => "random",
+            AssignmentStrategy::Ordered => "ordered",
+            AssignmentStrategy::Preference => "preference",
+        }
+    }
+
+    /// Get the strategy which is configured for the raffle. Unknown values are treated as manual
+    /// assignments.
+    pub fn of(raffle: &Raffle) -> Self {
+        match raffle.present_assignment.as_str() {
+            "random" => AssignmentStrategy::Random,
+            "ordered" => AssignmentStrategy::Ordered,
+            "preference" => AssignmentStrategy::Preference,
+            _ => AssignmentStrategy::Manual,
+        }
+    }
+}
+
+impl Display for AssignmentStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A present which has pieces left that can be assigned to new winners.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AvailablePresent {
+    /// The id of the present.
+    pub id: i32,
+    /// The short identifier of the present (used to match the preferences of the winners).
+    pub identifier: String,
+    /// The number of pieces which are not assigned so far.
+    pub remaining: usize,
+}
+
+/// Select a present for each of the winners (in the order in which they were drawn).
+///
+/// `available` has to be ordered by the identifiers of the presents and `preferences` contains
+/// the preferred identifiers of each winner. Winners for whom no piece is left (or all winners if
+/// the presents are assigned by hand) do not get a present.
+pub fn plan_assignments(
+    strategy: AssignmentStrategy,
+    seed: &[u8; crate::draw::SEED_LENGTH],
+    available: &[AvailablePresent],
+    preferences: &[Vec<String>],
+) -> Vec<Option<i32>> {
+    use crate::draw::draw_presents;
+
+    let pieces = available
+        .iter()
+        .flat_map(|present| std::iter::repeat(present.id).take(present.remaining))
+        .collect::<Vec<i32>>();
+    let mut planned = match strategy {
+        AssignmentStrategy::Manual => vec![],
+        AssignmentStrategy::Random => draw_presents(seed, &pieces, preferences.len())
+            .into_iter()
+            .map(Some)
+            .collect(),
+        AssignmentStrategy::Ordered => pieces
+            .into_iter()
+            .take(preferences.len())
+            .map(Some)
+            .collect(),
+        AssignmentStrategy::Preference => {
+            let mut remaining = available.to_vec();
+            preferences
+                .iter()
+                .map(|preferred| {
+                    let index = preferred
+                        .iter()
+                        .find_map(|identifier| {
+                            remaining.iter().position(|present| {
+                                present.remaining > 0 && present.identifier == *identifier
+                            })
+                        })
+                        .or_else(|| remaining.iter().position(|present| present.remaining > 0))?;
+                    remaining[index].remaining -= 1;
+                    Some(remaining[index].id)
+                })
+                .collect()
+        }
+    };
+    planned.resize(preferences.len(), None);
+    planned
+}
+
+/// Lock the presents of the day and select a present for each of the supplied winners (in the
+/// order in which they were drawn) with the strategy of the raffle. The presents stay locked until
+/// the end of the transaction, so concurrent (manual) assignments cannot exceed their stock.
+pub fn plan_presents_for_winners(
+    connection: &mut PgConnection,
+    raffle: &Raffle,
+    date: NaiveDate,
+    seed: &[u8; crate::draw::SEED_LENGTH],
+    winner_ids: &[i32],
+) -> Result<Vec<Option<Present>>, DieselError> {
+    use crate::schema::{participants, presents};
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use std::collections::HashMap;
+
+    let strategy = AssignmentStrategy::of(raffle);
+    if strategy == AssignmentStrategy::Manual {
+        return Ok(vec![None; winner_ids.len()]);
+    }
+
+    let day_presents = presents::table
+        .filter(presents::raffle_id.eq(raffle.id))
+        .filter(presents::available_on.eq(date))
+        .order_by((presents::identifier.asc(), presents::id.asc()))
+        .for_update()
+        .load::<Present>(connection)?;
+    let mut available = Vec::with_capacity(day_presents.len());
+    for present in &day_presents {
+        let assigned = count_assigned(connection, present.id)?;
+        available.push(AvailablePresent {
+            id: present.id,
+            identifier: present.identifier.clone(),
+            remaining: usize::try_from(i64::from(present.quantity) - assigned).unwrap_or(0),
+        });
+    }
+
+    let mut preferences_by_id = participants::table
+        .filter(participants::id.eq_any(winner_ids))
+        .select((participants::id, participants::present_preferences))
+        .load::<(i32, Vec<String>)>(connection)?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    let preferences = winner_ids
+        .iter()
+        .map(|winner_id| preferences_by_id.remove(winner_id).unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    Ok(plan_assignments(strategy, seed, &available, &preferences)
+        .into_iter()
+        .map(|present_id| {
+            present_id.and_then(|present_id| {
+                day_presents
+                    .iter()
+                    .find(|present| present.id == present_id)
+                    .cloned()
+            })
+        })
+        .collect())
+}
+
+/// Normalize the preferred identifiers of the presents of a participant. Empty and duplicate
+/// identifiers are removed. `None` is returned if an identifier is too long.
+pub fn normalize_preferences(preferences: &[String]) -> Option<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::with_capacity(preferences.len());
+    for identifier in preferences.iter().map(|identifier| identifier.trim()) {
+        if identifier.chars().count() > MAX_PRESENT_IDENTIFIER_LENGTH {
+            return None;
+        }
+        if !identifier.is_empty() && !normalized.iter().any(|known| known == identifier) {
+            normalized.push(identifier.to_string());
+        }
+    }
+    Some(normalized)
+}
+
+/// The result of assigning a present to a winner.
+pub struct PresentAssignment {
+    /// The (new) active win of the participant.
+    pub win: Win,
+    /// The present which was assigned before.
+    pub previous: Option<Present>,
+    /// The present which is assigned now.
+    pub present: Option<Present>,
+}
+
+/// Assign a present of the day to a winner (or remove the assignment if `present_id` is `None`).
+///
+/// The active win of the participant is superseded by a copy which references the new present,
+/// so the previous assignment stays visible in the history of the wins. The present is locked while
+/// its stock is checked, so two winners can never get the last piece of a present at the same time.
+pub fn assign_present(
+    connection: &mut PgConnection,
+    raffle_id: i32,
+    participant_id: i32,
+    present_id: Option<i32>,
+) -> Result<PresentAssignment, PresentError> {
+    use crate::models::{NewWin, WinRevocation};
+    use crate::schema::{participants, presents, wins};
+    use chrono::Utc;
+    use diesel::{
+        insert_into, update, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+    };
+
+    connection
+        .build_transaction()
+        .read_write()
+        .run::<_, PresentError, _>(|connection| {
+            let participant_exists = participants::table
+                .filter(participants::id.eq(participant_id))
+                .filter(participants::raffle_id.eq(raffle_id))
+                .count()
+                .get_result::<i64>(connection)?
+                > 0;
+            if !participant_exists {
+                return Err(PresentError::UnknownParticipant);
+            }
+            let win = wins::table
+                .filter(wins::participant_id.eq(participant_id))
+                .filter(wins::status.eq(WinStatus::Active.as_str()))
+                .for_update()
+                .first::<Win>(connection)
+                .optional()?
+                .ok_or(PresentError::NotAWinner)?;
+            let previous = match win.present_id {
+                Some(previous_id) => presents::table
+                    .find(previous_id)
+                    .first::<Present>(connection)
+                    .optional()?,
+                None => None,
+            };
+
+            let present = match present_id {
+                Some(present_id) => {
+                    let present = presents::table
+                        .filter(presents::id.eq(present_id))
+                        .filter(presents::raffle_id.eq(raffle_id))
+                        .for_update()
+                        .first::<Present>(connection)
+                        .optional()?
+                        .ok_or(PresentError::UnknownPresent)?;
+                    if present.available_on != win.won_on {
+                        return Err(PresentError::WrongDay);
+                    }
+                    if win.present_id != Some(present.id)
+                        && count_assigned(connection, present.id)? >= i64::from(present.quantity)
+                    {
+                        return Err(PresentError::OutOfStock);
+                    }
+                    Some(present)
+                }
+                None => None,
+            };
+            if win.present_id == present_id {
+                return Ok(PresentAssignment {
+                    win,
+                    previous,
+                    present,
+                });
+            }
+
+            // the previous win is kept as history (superseded) and replaced by a copy with the new
+            // present, so it can still be seen who held which present at which time
+            update(wins::table.find(win.id))
+                .set(&WinRevocation {
+                    status: WinStatus::Superseded.as_str().to_string(),
+                    revoked_at: Some(Utc::now().naive_utc()),
+                })
+                .execute(connection)?;
+            let new_win = insert_into(wins::table)
+                .values(&NewWin {
+                    participant_id: win.participant_id,
+                    won_on: win.won_on,
+                    picked_by: win.picked_by,
+                    picked_at: win.picked_at,
+                    draw_id: win.draw_id,
+                    present_id,
+                })
+                .get_result::<Win>(connection)?;
+            Ok(PresentAssignment {
+                win: new_win,
+                previous,
+                present,
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(identifier: &str, name: &str, quantity: i32) -> PresentValues {
+        PresentValues {
+            available_on: NaiveDate::from_ymd_opt(2026, 12, 3).unwrap(),
+            identifier: identifier.to_string(),
+            name: name.to_string(),
+            description: Some("  ".to_string()),
+            sponsor: Some(" ACME ".to_string()),
+            quantity,
+        }
+    }
+
+    #[test]
+    fn present_values_are_normalized() {
+        let validated = values(" A ", " Chocolate ", 2).validated().unwrap();
+        assert_eq!(validated.identifier, "A");
+        assert_eq!(validated.name, "Chocolate");
+        assert_eq!(validated.description, None);
+        assert_eq!(validated.sponsor, Some("ACME".to_string()));
+        assert_eq!(validated.quantity, 2);
+    }
+
+    #[test]
+    fn invalid_present_values_are_refused() {
+        assert!(values("", "Chocolate", 1).validated().is_none());
+        assert!(values("A", " ", 1).validated().is_none());
+        assert!(values("ABCDEFGHI", "Chocolate", 1).validated().is_none());
+        assert!(values("A", "Chocolate", -1).validated().is_none());
+        assert!(values("A", "Chocolate", 0).validated().is_some());
+
+        let mut too_long_sponsor = values("A", "Chocolate", 1);
+        too_long_sponsor.sponsor = Some("x".repeat(MAX_PRESENT_SPONSOR_LENGTH + 1));
+        assert!(too_long_sponsor.validated().is_none());
+    }
+
+    fn inventory() -> Vec<AvailablePresent> {
+        vec![
+            AvailablePresent {
+                id: 10,
+                identifier: "A".to_string(),
+                remaining: 1,
+            },
+            AvailablePresent {
+                id: 11,
+                identifier: "B".to_string(),
+                remaining: 0,
+            },
+            AvailablePresent {
+                id: 12,
+                identifier: "C".to_string(),
+                remaining: 2,
+            },
+        ]
+    }
+
+    fn no_preferences(count: usize) -> Vec<Vec<String>> {
+        vec![vec![]; count]
+    }
+
+    #[test]
+    fn ordered_and_manual_assignments() {
+        let seed = [7u8; crate::draw::SEED_LENGTH];
+        assert_eq!(
+            plan_assignments(
+                AssignmentStrategy::Ordered,
+                &seed,
+                &inventory(),
+                &no_preferences(4)
+            ),
+            vec![Some(10), Some(12), Some(12), None]
+        );
+        assert_eq!(
+            plan_assignments(
+                AssignmentStrategy::Manual,
+                &seed,
+                &inventory(),
+                &no_preferences(2)
+            ),
+            vec![None, None]
+        );
+    }
+
+    #[test]
+    fn random_assignments_respect_the_stock_and_can_be_replayed() {
+        let seed = [7u8; crate::draw::SEED_LENGTH];
+        let planned = plan_assignments(
+            AssignmentStrategy::Random,
+            &seed,
+            &inventory(),
+            &no_preferences(3),
+        );
+        assert_eq!(
+            planned,
+            plan_assignments(
+                AssignmentStrategy::Random,
+                &seed,
+                &inventory(),
+                &no_preferences(3)
+            )
+        );
+        let mut sorted = planned.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec![Some(10), Some(12), Some(12)]);
+    }
+
+    #[test]
+    fn preferences_are_respected_while_pieces_are_left() {
+        let seed = [7u8; crate::draw::SEED_LENGTH];
+        let preferences = vec![
+            vec!["C".to_string()],
+            vec!["B".to_string(), "C".to_string()],
+            vec!["C".to_string(), "A".to_string()],
+            vec!["A".to_string()],
+        ];
+        assert_eq!(
+            plan_assignments(
+                AssignmentStrategy::Preference,
+                &seed,
+                &inventory(),
+                &preferences
+            ),
+            vec![Some(12), Some(12), Some(10), None]
+        );
+        assert_eq!(
+            normalize_preferences(&[" B ".to_string(), "".to_string(), "B".to_string()]),
+            Some(vec!["B".to_string()])
+        );
+        assert_eq!(normalize_preferences(&["ABCDEFGHI".to_string()]), None);
+    }
+}
@@ -11,6 +11,7 @@ pub(crate) struct RateLimitEntry {
 pub(crate) static RATE_LIMITER: LazyLock<DashMap<String, RateLimitEntry>> =
     LazyLock::new(DashMap::new);
 
+#[derive(Clone)]
 pub struct RateLimitConfig {
     pub max_attempts: u32,
     pub window_minutes: i64,
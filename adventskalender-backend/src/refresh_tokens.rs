@@ -0,0 +1,203 @@
+use crate::models::User;
+use crate::sessions::SessionError;
+use diesel::{PgConnection, QueryResult};
+use ring::error::Unspecified;
+
+/// The number of random bytes of a refresh token.
+const REFRESH_TOKEN_BYTES: usize = 32;
+
+/// The number of seconds in which a refresh token which was just exchanged is refused without
+/// revoking its session, e.g. if two browser tabs refreshed their tokens at the same time.
+const REUSE_GRACE_PERIOD_IN_SECONDS: i64 = 10;
+
+/// The result of exchanging a refresh token for a new one.
+pub enum RefreshOutcome {
+    /// The token was valid and is replaced by the contained token of the same session.
+    Rotated {
+        user: User,
+        session_id: String,
+        refresh_token: String,
+    },
+    /// The token was exchanged a moment ago and its successor was not used yet, e.g. by a parallel
+    /// request. The token is not exchanged again, but the session is kept.
+    Superseded,
+    /// The token was already exchanged before, so it was most likely stolen. The whole session of
+    /// the token is revoked.
+    Reused { user: User },
+    /// The token is unknown, expired, revoked or belongs to a disabled user.
+    Rejected,
+}
+
+fn random_bytes(length: usize) -> Result<Vec<u8>, Unspecified> {
+    use ring::rand::{SecureRandom, SystemRandom};
+
+    let mut bytes = vec![0u8; length];
+    SystemRandom::new().fill(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Generate a new random refresh token which can be stored in a cookie.
+pub fn generate_refresh_token() -> Result<String, Unspecified> {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    Ok(URL_SAFE_NO_PAD.encode(random_bytes(REFRESH_TOKEN_BYTES)?))
+}
+
+/// Calculate the hash of a refresh token which is stored instead of the token itself.
+pub fn hash_refresh_token(refresh_token: &str) -> String {
+    use crate::draw::to_hex;
+    use ring::digest::{digest, SHA256};
+
+    to_hex(digest(&SHA256, refresh_token.as_bytes()).as_ref())
+}
+
+/// Store a new refresh token for the session of the user and return it. The session is the family
+/// of the token, i.e. all tokens which replace each other belong to the same session. Expired
+/// tokens of the user are removed on the way.
+pub fn issue_refresh_token(
+    connection: &mut PgConnection,
+    user_id: i32,
+    session_id: &str,
+    lifetime_in_seconds: usize,
+) -> Result<String, SessionError> {
+    use crate::models::NewRefreshToken;
+    use crate::schema::refresh_tokens::dsl;
+    use chrono::{Duration, Utc};
+    use diesel::{delete, insert_into, ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    let now = Utc::now().naive_utc();
+    delete(
+        dsl::refresh_tokens
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::expires_at.lt(now)),
+    )
+    .execute(connection)?;
+
+    let refresh_token = generate_refresh_token()?;
+    insert_into(dsl::refresh_tokens)
+        .values(&NewRefreshToken {
+            user_id,
+            family: session_id.to_string(),
+            token_hash: hash_refresh_token(&refresh_token),
+            expires_at: now + Duration::seconds(lifetime_in_seconds as i64),
+        })
+        .execute(connection)?;
+    Ok(refresh_token)
+}
+
+/// Exchange a refresh token for a new one of the same session and extend the session. Each token
+/// can be exchanged only once; presenting it a second time revokes its session, unless it is the
+/// last exchanged token of the session and was exchanged within the grace period.
+pub fn rotate_refresh_token(
+    connection: &mut PgConnection,
+    refresh_token: &str,
+    lifetime_in_seconds: usize,
+) -> Result<RefreshOutcome, SessionError> {
+    use crate::models::RefreshToken;
+    use crate::schema::refresh_tokens::dsl;
+    use crate::schema::users::dsl::users;
+    use crate::sessions::{extend_session, revoke_sessions};
+    use chrono::{Duration, Utc};
+    use diesel::{update, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+
+    let token_hash = hash_refresh_token(refresh_token);
+    connection.transaction(|connection| {
+        // lock the token, so it cannot be exchanged twice by parallel requests
+        let stored_token = match dsl::refresh_tokens
+            .filter(dsl::token_hash.eq(&token_hash))
+            .for_update()
+            .first::<RefreshToken>(connection)
+            .optional()?
+        {
+            Some(stored_token) => stored_token,
+            None => return Ok(RefreshOutcome::Rejected),
+        };
+        let now = Utc::now().naive_utc();
+        if stored_token.revoked_at.is_some() || stored_token.expires_at < now {
+            return Ok(RefreshOutcome::Rejected);
+        }
+
+        let user = users.find(stored_token.user_id).first::<User>(connection)?;
+        if let Some(used_at) = stored_token.used_at.filter(|_| !user.disabled) {
+            // a parallel request may have exchanged the token a moment ago, which is no theft as
+            // long as no token of the session was exchanged after it
+            let is_latest_exchange = dsl::refresh_tokens
+                .filter(dsl::family.eq(&stored_token.family))
+                .filter(dsl::used_at.gt(used_at))
+                .count()
+                .get_result::<i64>(connection)?
+                == 0;
+            if is_latest_exchange
+                && now - used_at <= Duration::seconds(REUSE_GRACE_PERIOD_IN_SECONDS)
+            {
+                return Ok(RefreshOutcome::Superseded);
+            }
+        }
+        if stored_token.used_at.is_some() || user.disabled {
+            revoke_sessions(connection, user.id, Some(&stored_token.family), None)?;
+            return Ok(if user.disabled {
+                RefreshOutcome::Rejected
+            } else {
+                RefreshOutcome::Reused { user }
+            });
+        }
+
+        update(dsl::refresh_tokens.find(stored_token.id))
+            .set(dsl::used_at.eq(now))
+            .execute(connection)?;
+        extend_session(connection, &stored_token.family, lifetime_in_seconds)?;
+        let refresh_token = issue_refresh_token(
+            connection,
+            user.id,
+            &stored_token.family,
+            lifetime_in_seconds,
+        )?;
+        Ok(RefreshOutcome::Rotated {
+            user,
+            session_id: stored_token.family,
+            refresh_token,
+        })
+    })
+}
+
+/// Revoke the session the supplied refresh token belongs to (e.g. on logout) and return the number
+/// of revoked sessions.
+pub fn revoke_session_of_refresh_token(
+    connection: &mut PgConnection,
+    refresh_token: &str,
+) -> QueryResult<usize> {
+    use crate::schema::refresh_tokens::dsl;
+    use crate::sessions::revoke_sessions;
+    use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+
+    match dsl::refresh_tokens
+        .filter(dsl::token_hash.eq(hash_refresh_token(refresh_token)))
+        .select((dsl::user_id, dsl::family))
+        .first::<(i32, String)>(connection)
+        .optional()?
+    {
+        Some((user_id, session_id)) => {
+            revoke_sessions(connection, user_id, Some(&session_id), None)
+        }
+        None => Ok(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_tokens_are_random_and_hashed() {
+        let first_token = generate_refresh_token().unwrap();
+        let second_token = generate_refresh_token().unwrap();
+        assert_ne!(first_token, second_token);
+        assert_eq!(first_token.len(), 43);
+
+        let hash = hash_refresh_token(&first_token);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_refresh_token(&first_token));
+        assert_ne!(hash, hash_refresh_token(&second_token));
+    }
+}
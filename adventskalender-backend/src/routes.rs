@@ -1,18 +1,25 @@
+use crate::calendar::{PickRefusal, RaffleCalendar};
+use crate::discovery::OpenIdConfiguration;
+use crate::draw::DrawVerification;
+use crate::eligibility::DrawRules;
+use crate::export::{ExportFormat, ExportResponse};
 use crate::fairings::{AdventskalenderDatabaseConnection, BackendConfiguration};
-use crate::guards::AuthenticatedUser;
-use crate::models::User;
+use crate::guards::{Admin, AuthenticatedUser, Operator, RequireRole, Role, UserAgent};
+use crate::import::ImportReport;
+use crate::models::{Raffle, User};
 use crate::rocket_cors::{AllowedHeaders, AllowedOrigins, CorsOptions};
+use crate::sessions::SessionSummary;
+use crate::two_factor::{SecondFactorCheck, TotpEnrollment};
+use crate::users::UserSummary;
 use crate::{Action, BACKOFF_HANDLER};
-use chrono::{DateTime, NaiveDate};
-use rand::prelude::IndexedRandom;
-use rocket::http::{Cookie, CookieJar, Method, SameSite, Status};
-use rocket::response::status::NoContent;
-use rocket::response::Responder;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use rocket::http::{Accept, ContentType, Cookie, CookieJar, Method, SameSite, Status};
+use rocket::response::status::{Created, Custom, NoContent};
+use rocket::response::{Redirect, Responder};
 use rocket::serde::json::{json, Json};
-use rocket::{delete, get, options, post, put, State};
+use rocket::{delete, get, options, post, put, Data, Request, State};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use url::Url;
 
 #[derive(Serialize)]
 pub struct ParticipantCount {
@@ -24,18 +31,6 @@ pub struct ParticipantCount {
     pub number_of_participants_still_in_raffle: u16,
 }
 
-/// See https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderMetadata for more
-#[derive(Serialize)]
-pub struct OpenIdConfiguration {
-    pub issuer: String,
-    pub authorization_endpoint: String,
-    pub token_endpoint: String, // TODO: only if using implicit flow
-    pub jwks_uri: String,
-    pub response_types_supported: Vec<String>,
-    pub subject_types_supported: Vec<String>,
-    pub id_token_signing_alg_values_supported: Vec<String>,
-}
-
 pub fn cors_options() -> CorsOptions {
     // Use AllowedOrigins::all() for OPTIONS handlers since the actual CORS validation
     // is performed by the global CORS fairing attached in main.rs which reads from
@@ -68,78 +63,550 @@ pub struct JwksResponse {
     pub keys: Vec<JsonWebKey>,
 }
 
-#[get("/.well-known/jwks.json")]
+#[get("/jwks.json")]
 pub async fn get_jwks(config: &State<BackendConfiguration>) -> Result<Json<JwksResponse>, Status> {
     use base64::engine::general_purpose::URL_SAFE_NO_PAD;
     use base64::Engine;
     use log::error;
-    use ring::signature::{Ed25519KeyPair, KeyPair};
 
-    // get the Ed25519 key bytes from config
-    let key_bytes = match &config.ed25519_key_bytes {
-        Some(bytes) => bytes,
-        None => {
-            error!("Ed25519 key bytes not available in configuration");
+    // publish all keys which may have signed tokens that are still valid (the active key and the
+    // retired keys which are kept until their tokens expired)
+    let keys = match config.keys.read() {
+        Ok(keys) => keys,
+        Err(_) => {
+            error!("The keys for validating the tokens are not available");
             return Err(Status::InternalServerError);
         }
     };
 
-    // parse the PKCS8 key to extract the public key
-    let key_pair = match Ed25519KeyPair::from_pkcs8(key_bytes) {
-        Ok(kp) => kp,
-        Err(e) => {
-            error!("Failed to parse Ed25519 key pair: {}", e);
+    Ok(Json(JwksResponse {
+        keys: keys
+            .keys()
+            .iter()
+            .map(|key| JsonWebKey {
+                kty: "OKP".to_string(),
+                use_: "sig".to_string(),
+                kid: key.id.clone(),
+                alg: "EdDSA".to_string(),
+                crv: "Ed25519".to_string(),
+                x: URL_SAFE_NO_PAD.encode(&key.public_key),
+            })
+            .collect(),
+    }))
+}
+
+#[get("/openid-configuration")]
+pub async fn get_openid_configuration(
+    config: &State<BackendConfiguration>,
+) -> Json<OpenIdConfiguration> {
+    // the document was already created (and validated) on startup
+    Json(config.openid_configuration.as_ref().clone())
+}
+
+/// Look up the raffle a request is scoped to. If the raffle does not exist (or cannot be looked up),
+/// the status which should be returned to the caller is returned as an error.
+pub(crate) fn lookup_raffle(
+    db_connection_pool: &AdventskalenderDatabaseConnection,
+    raffle_id: i32,
+) -> Result<Raffle, Status> {
+    use crate::schema::raffles::dsl::raffles;
+    use diesel::{OptionalExtension, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
             return Err(Status::InternalServerError);
         }
     };
 
-    // get the public key bytes (32 bytes for Ed25519)
-    let public_key_bytes = key_pair.public_key().as_ref();
+    match raffles
+        .find(raffle_id)
+        .first::<Raffle>(db_connection)
+        .optional()
+    {
+        Ok(Some(raffle)) => Ok(raffle),
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            error!(
+                "Could not look up the raffle with the id {}. The error was: {}",
+                raffle_id, error
+            );
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+/// Look up the raffle a request is scoped to and ensure that it can still be changed. Archived
+/// raffles are kept for the history but neither their participants nor their winners can be
+/// changed anymore.
+pub(crate) fn lookup_writable_raffle(
+    db_connection_pool: &AdventskalenderDatabaseConnection,
+    raffle_id: i32,
+) -> Result<Raffle, Status> {
+    use log::error;
+
+    let raffle = lookup_raffle(db_connection_pool, raffle_id)?;
+    if raffle.archived {
+        error!(
+            "Tried to change the raffle with the id {} but the raffle is already archived",
+            raffle_id
+        );
+        return Err(Status::Conflict);
+    }
+    Ok(raffle)
+}
+
+/// The maximum number of characters the database allows for the name of a raffle.
+const MAX_RAFFLE_NAME_LENGTH: usize = 64;
 
-    // base64url encode the public key
-    let public_key_base64 = URL_SAFE_NO_PAD.encode(public_key_bytes);
+/// The number of winners per day of a raffle which does not configure it explicitly.
+const DEFAULT_WINNERS_PER_DAY: i32 = 5;
 
-    Ok(Json(JwksResponse {
-        keys: vec![JsonWebKey {
-            kty: "OKP".to_string(),
-            use_: "sig".to_string(),
-            kid: "adventskalender-key-1".to_string(),
-            alg: "EdDSA".to_string(),
-            crv: "Ed25519".to_string(),
-            x: public_key_base64,
-        }],
-    }))
+#[derive(Serialize, Clone)]
+pub struct RaffleDetails {
+    /// The internally used id for the raffle.
+    pub id: i32,
+    /// The human-readable name of the raffle (e.g. `Advent calendar 2025`).
+    pub name: String,
+    /// The first day on which winners can be picked.
+    pub start_date: NaiveDate,
+    /// The last day on which winners can be picked.
+    pub end_date: NaiveDate,
+    /// A flag which indicates that the raffle is just kept for the history and cannot be changed.
+    pub archived: bool,
+    /// A flag which indicates that no winners are picked on saturdays and sundays.
+    pub exclude_weekends: bool,
+    /// Single days (e.g. holidays) within the date range on which no winners are picked.
+    pub excluded_dates: Vec<NaiveDate>,
+    /// The number of winners which are picked on each valid day.
+    pub winners_per_day: i32,
+    /// A flag which indicates that the chance of a participant depends on its number of tickets.
+    pub use_tickets: bool,
+    /// The maximum number of winners per department and day (if it is limited at all).
+    pub max_winners_per_department: Option<i32>,
+    /// The way in which the presents are assigned to the winners when they are picked.
+    pub present_assignment: crate::presents::AssignmentStrategy,
+    /// A flag which indicates that the winners of each day are picked automatically at the
+    /// configured time.
+    pub scheduled_draws: bool,
+    /// All days of the raffle on which winners can be picked.
+    pub valid_days: Vec<NaiveDate>,
 }
 
-#[get("/openid-configuration")]
-pub async fn get_openid_configuration(
-    config: &State<BackendConfiguration>,
-) -> Result<Json<OpenIdConfiguration>, Status> {
-    Ok(Json(OpenIdConfiguration {
-        issuer: config.api_host.clone(),
-        authorization_endpoint: Url::parse(config.api_host.as_str())
-            .unwrap()
-            .join("/foo")
-            .unwrap()
-            .to_string(),
-        token_endpoint: Url::parse(config.api_host.as_str())
-            .unwrap()
-            .join("/foo")
-            .unwrap()
-            .to_string(), // TODO: only if using implicit flow
-        jwks_uri: Url::parse(config.api_host.as_str())
-            .unwrap()
-            .join("/.well-known/jwks.json")
-            .unwrap()
-            .to_string(),
-        response_types_supported: vec![],
-        subject_types_supported: vec![],
-        id_token_signing_alg_values_supported: vec!["RS256".to_string(), "EdDSA".to_string()],
-    }))
+impl From<&Raffle> for RaffleDetails {
+    fn from(raffle: &Raffle) -> Self {
+        RaffleDetails {
+            id: raffle.id,
+            name: raffle.name.clone(),
+            start_date: raffle.start_date,
+            end_date: raffle.end_date,
+            archived: raffle.archived,
+            exclude_weekends: raffle.exclude_weekends,
+            excluded_dates: raffle.excluded_dates.clone(),
+            winners_per_day: raffle.winners_per_day,
+            use_tickets: raffle.use_tickets,
+            max_winners_per_department: raffle.max_winners_per_department,
+            present_assignment: crate::presents::AssignmentStrategy::of(raffle),
+            scheduled_draws: raffle.scheduled_draws,
+            valid_days: RaffleCalendar::from(raffle).valid_days(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RaffleSettings {
+    /// The human-readable name of the raffle.
+    pub name: String,
+    /// The first day on which winners can be picked.
+    pub start_date: NaiveDate,
+    /// The last day on which winners can be picked.
+    pub end_date: NaiveDate,
+    /// A flag which indicates that the raffle is just kept for the history and cannot be changed.
+    #[serde(default)]
+    pub archived: bool,
+    /// A flag which indicates that no winners are picked on saturdays and sundays.
+    #[serde(default)]
+    pub exclude_weekends: bool,
+    /// Single days (e.g. holidays) within the date range on which no winners are picked.
+    #[serde(default)]
+    pub excluded_dates: Vec<NaiveDate>,
+    /// The number of winners which are picked on each valid day.
+    #[serde(default = "default_winners_per_day")]
+    pub winners_per_day: i32,
+    /// A flag which indicates that the chance of a participant depends on its number of tickets.
+    #[serde(default)]
+    pub use_tickets: bool,
+    /// The maximum number of winners per department and day (if it should be limited at all).
+    #[serde(default)]
+    pub max_winners_per_department: Option<i32>,
+    /// The way in which the presents are assigned to the winners when they are picked.
+    #[serde(default)]
+    pub present_assignment: crate::presents::AssignmentStrategy,
+    /// A flag which indicates that the winners of each day are picked automatically at the
+    /// configured time.
+    #[serde(default)]
+    pub scheduled_draws: bool,
+}
+
+/// The number of winners per day if a raffle does not configure it explicitly.
+fn default_winners_per_day() -> i32 {
+    DEFAULT_WINNERS_PER_DAY
+}
+
+impl RaffleSettings {
+    /// Get the settings in the form in which they can be stored in the database or `None` if the
+    /// name does not fit into the database column, the date range is empty or the number of winners
+    /// (per day or per department) is not positive.
+    fn validated(&self) -> Option<crate::models::NewRaffle> {
+        let name = self.name.trim();
+        if name.is_empty()
+            || name.chars().count() > MAX_RAFFLE_NAME_LENGTH
+            || self.start_date > self.end_date
+            || self.winners_per_day < 1
+            || self
+                .max_winners_per_department
+                .is_some_and(|max_winners| max_winners < 1)
+        {
+            return None;
+        }
+        let mut excluded_dates = self.excluded_dates.clone();
+        excluded_dates.sort();
+        excluded_dates.dedup();
+        Some(crate::models::NewRaffle {
+            name: name.to_string(),
+            start_date: self.start_date,
+            end_date: self.end_date,
+            archived: self.archived,
+            exclude_weekends: self.exclude_weekends,
+            excluded_dates,
+            winners_per_day: self.winners_per_day,
+            use_tickets: self.use_tickets,
+            max_winners_per_department: self.max_winners_per_department,
+            present_assignment: self.present_assignment.as_str().to_string(),
+            scheduled_draws: self.scheduled_draws,
+            scheduled_draws_enabled_at: self.scheduled_draws.then(|| Utc::now().naive_utc()),
+        })
+    }
+}
+
+/// Check if the supplied settings change anything besides the flag which archives the raffle (and
+/// the point in time at which the scheduled draws were enabled, which just follows them).
+fn raffle_settings_differ(raffle: &Raffle, settings: &crate::models::NewRaffle) -> bool {
+    raffle.name != settings.name
+        || raffle.start_date != settings.start_date
+        || raffle.end_date != settings.end_date
+        || raffle.exclude_weekends != settings.exclude_weekends
+        || raffle.excluded_dates != settings.excluded_dates
+        || raffle.winners_per_day != settings.winners_per_day
+        || raffle.use_tickets != settings.use_tickets
+        || raffle.max_winners_per_department != settings.max_winners_per_department
+        || raffle.present_assignment != settings.present_assignment
+        || raffle.scheduled_draws != settings.scheduled_draws
+}
+
+/// Describe the settings of a raffle (including its quotas and rules) for the audit log.
+fn describe_raffle_settings(raffle: &Raffle) -> String {
+    format!(
+        "'{}' ({} to {}, {} winners per day, weekends excluded: {}, excluded dates: {:?}, tickets: {}, max. winners per department: {:?}, presents: {}, scheduled draws: {}, archived: {})",
+        raffle.name,
+        raffle.start_date,
+        raffle.end_date,
+        raffle.winners_per_day,
+        raffle.exclude_weekends,
+        raffle.excluded_dates,
+        raffle.use_tickets,
+        raffle.max_winners_per_department,
+        raffle.present_assignment,
+        raffle.scheduled_draws,
+        raffle.archived
+    )
+}
+
+/// Find a day of the raffle whose active winners do not fit into the supplied calendar anymore,
+/// i.e. the day is not valid anymore or has more winners than the quota allows.
+fn find_day_with_misfitting_winners(
+    connection: &mut diesel::PgConnection,
+    raffle_id: i32,
+    calendar: &RaffleCalendar,
+) -> diesel::QueryResult<Option<NaiveDate>> {
+    use crate::models::WinStatus;
+    use crate::schema::participants::dsl::{participants, raffle_id as participant_raffle_id};
+    use crate::schema::wins::dsl::{status, wins, won_on};
+    use diesel::dsl::count_star;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    let winners_per_day = wins
+        .inner_join(participants)
+        .filter(participant_raffle_id.eq(raffle_id))
+        .filter(status.eq(WinStatus::Active.as_str()))
+        .group_by(won_on)
+        .select((won_on, count_star()))
+        .order_by(won_on.asc())
+        .load::<(NaiveDate, i64)>(connection)?;
+    Ok(winners_per_day
+        .into_iter()
+        .find(|(day, winners)| {
+            calendar.check_day(*day).is_err()
+                || usize::try_from(*winners).unwrap_or_default() > calendar.winners_per_day
+        })
+        .map(|(day, _)| day))
+}
+
+#[options("/raffles")]
+pub async fn raffles_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get, Method::Post]
+        .into_iter()
+        .map(From::from)
+        .collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[options("/raffles/<_raffle_id>")]
+pub async fn raffle_options<'r, 'o: 'r>(_raffle_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get, Method::Put]
+        .into_iter()
+        .map(From::from)
+        .collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[get("/raffles")]
+pub async fn list_raffles(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    _authenticated_user: AuthenticatedUser,
+) -> Result<Json<Vec<RaffleDetails>>, Status> {
+    use crate::schema::raffles::dsl::{raffles, start_date};
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // the most recent raffles are the most interesting ones, so they are returned first
+    match db_connection
+        .build_transaction()
+        .read_only()
+        .run::<_, diesel::result::Error, _>(|connection| {
+            raffles
+                .order_by(start_date.desc())
+                .load::<Raffle>(connection)
+        }) {
+        Ok(found_raffles) => Ok(Json(found_raffles.iter().map(From::from).collect())),
+        Err(error) => {
+            error!(
+                "Could not fetch the list of raffles. The error was: {}",
+                error
+            );
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+#[post("/raffles", data = "<new_raffle>")]
+pub async fn create_raffle(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    new_raffle: Json<RaffleSettings>,
+) -> Result<Created<Json<RaffleDetails>>, Status> {
+    use crate::log_action_rocket;
+    use crate::schema::raffles::dsl::raffles;
+    use diesel::{insert_into, RunQueryDsl};
+    use log::error;
+
+    // ensure that the supplied settings can be stored in the database
+    let raffle_to_insert = match new_raffle.validated() {
+        Some(raffle) => raffle,
+        None => return Err(Status::UnprocessableEntity),
+    };
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // try to insert the new raffle
+    let created_raffle = match db_connection
+        .build_transaction()
+        .read_write()
+        .run::<_, diesel::result::Error, _>(|connection| {
+            insert_into(raffles)
+                .values(&raffle_to_insert)
+                .get_result::<Raffle>(connection)
+        }) {
+        Ok(raffle) => raffle,
+        Err(error) if is_unique_violation(&error) => {
+            error!(
+                "The user {} tried to add the raffle '{}' but a raffle with the same name already exists",
+                authenticated_user.username, raffle_to_insert.name
+            );
+            return Err(Status::Conflict);
+        }
+        Err(error) => {
+            error!(
+                "Could not add a new raffle to the database. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::RaffleCreated,
+        Some(format!(
+            "The raffle '{}' ({} to {}, {} winners per day) was created with the id {}",
+            created_raffle.name,
+            created_raffle.start_date,
+            created_raffle.end_date,
+            created_raffle.winners_per_day,
+            created_raffle.id
+        )),
+    )
+    .await;
+
+    Ok(Created::new(format!("/v1/raffles/{}", created_raffle.id))
+        .body(Json(RaffleDetails::from(&created_raffle))))
+}
+
+#[get("/raffles/<raffle_id>")]
+pub async fn get_raffle(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    _authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
+) -> Result<Json<RaffleDetails>, Status> {
+    let raffle = lookup_raffle(db_connection_pool, raffle_id)?;
+    Ok(Json(RaffleDetails::from(&raffle)))
+}
+
+#[put("/raffles/<raffle_id>", data = "<raffle_settings>")]
+pub async fn update_raffle(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    raffle_id: i32,
+    raffle_settings: Json<RaffleSettings>,
+) -> Result<Json<RaffleDetails>, Status> {
+    use crate::log_action_rocket;
+    use crate::picking::{with_picking_lock, PickError};
+    use crate::schema::raffles::dsl::raffles;
+    use diesel::{update, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // ensure that the supplied settings can be stored in the database
+    let mut changed_raffle = match raffle_settings.validated() {
+        Some(raffle) => raffle,
+        None => return Err(Status::UnprocessableEntity),
+    };
+
+    // archived raffles can just be restored but not changed otherwise
+    let old_raffle = lookup_raffle(db_connection_pool, raffle_id)?;
+    if old_raffle.archived
+        && changed_raffle.archived
+        && raffle_settings_differ(&old_raffle, &changed_raffle)
+    {
+        return Err(Status::Conflict);
+    }
+
+    // the scheduled draws just catch up the days which are due after they were enabled, so
+    // keeping them enabled must not move the point in time at which this happened
+    if old_raffle.scheduled_draws && changed_raffle.scheduled_draws {
+        changed_raffle.scheduled_draws_enabled_at = old_raffle.scheduled_draws_enabled_at;
+    }
+
+    // store the new settings of the raffle if the winners which were already picked still fit into
+    // them. The picks are locked meanwhile, so no winners can be picked for the old settings
+    let changed_name = changed_raffle.name.clone();
+    let updated_raffle = match run_picking(db_connection_pool, move |db_connection| {
+        let calendar = RaffleCalendar::from(&changed_raffle);
+        with_picking_lock(db_connection, raffle_id, |connection| {
+            connection
+                .build_transaction()
+                .read_write()
+                .run::<_, PickError, _>(|connection| {
+                    if let Some(day) =
+                        find_day_with_misfitting_winners(connection, raffle_id, &calendar)?
+                    {
+                        return Ok(Err(day));
+                    }
+                    Ok(Ok(update(raffles.find(raffle_id))
+                        .set(&changed_raffle)
+                        .get_result::<Raffle>(connection)?))
+                })
+        })
+    })
+    .await?
+    {
+        Ok(Ok(raffle)) => raffle,
+        Ok(Err(day)) => {
+            error!(
+                "The user {} tried to change the raffle with the id {} but the winners of the {} would not fit into the new settings",
+                authenticated_user.username, raffle_id, day
+            );
+            return Err(Status::Conflict);
+        }
+        Err(PickError::Database(error)) if is_unique_violation(&error) => {
+            error!(
+                "The user {} tried to rename the raffle with the id {} to '{}' but a raffle with the same name already exists",
+                authenticated_user.username, raffle_id, changed_name
+            );
+            return Err(Status::Conflict);
+        }
+        Err(error) => {
+            error!(
+                "Could not update the raffle with the id {}. The error was: {}",
+                raffle_id, error
+            );
+            return Err(pick_error_status(&error));
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::RaffleUpdated,
+        Some(format!(
+            "The raffle with the id {} was changed from {} to {}",
+            raffle_id,
+            describe_raffle_settings(&old_raffle),
+            describe_raffle_settings(&updated_raffle)
+        )),
+    )
+    .await;
+
+    Ok(Json(RaffleDetails::from(&updated_raffle)))
 }
 
-#[options("/participants/count")]
+#[options("/raffles/<_raffle_id>/participants/count")]
 pub async fn get_number_of_participants_who_already_won_options<'r, 'o: 'r>(
+    _raffle_id: i32,
 ) -> impl Responder<'r, 'o> {
     let mut options = cors_options();
     options.allowed_methods = vec![Method::Get].into_iter().map(From::from).collect();
@@ -148,22 +615,27 @@ pub async fn get_number_of_participants_who_already_won_options<'r, 'o: 'r>(
     cors.respond_owned(|guard| guard.responder(()))
 }
 
-#[get("/participants/count")]
+#[get("/raffles/<raffle_id>/participants/count")]
 pub async fn get_number_of_participants_who_already_won(
     db_connection_pool: &State<AdventskalenderDatabaseConnection>,
     authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
 ) -> Result<Json<ParticipantCount>, Status> {
-    use crate::schema::participants::dsl::{id, participants, won_on};
+    use crate::picking::active_winner_ids;
+    use crate::schema::participants::dsl::{id, participants, raffle_id as participant_raffle_id};
     use diesel::dsl::count;
     use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
     use log::{debug, error};
 
     // log that a user queried the statistics for the participants
     debug!(
-        "The user {} requested the statistics for the participants of the raffle",
-        authenticated_user.username
+        "The user {} requested the statistics for the participants of the raffle {}",
+        authenticated_user.username, raffle_id
     );
 
+    // ensure that the requested raffle exists at all
+    lookup_raffle(db_connection_pool, raffle_id)?;
+
     // get a connection to the database for dealing with the request
     let db_connection = &mut match db_connection_pool.get() {
         Ok(connection) => connection,
@@ -181,10 +653,15 @@ pub async fn get_number_of_participants_who_already_won(
         .build_transaction()
         .read_only()
         .run::<ParticipantCount, diesel::result::Error, _>(|connection| {
-            match participants.select(count(id)).first::<i64>(connection) {
+            match participants
+                .filter(participant_raffle_id.eq(raffle_id))
+                .select(count(id))
+                .first::<i64>(connection)
+            {
                 Ok(all_participants) => {
                     match participants
-                        .filter(won_on.is_not_null())
+                        .filter(participant_raffle_id.eq(raffle_id))
+                        .filter(id.eq_any(active_winner_ids()))
                         .select(count(id))
                         .first::<i64>(connection)
                     {
@@ -220,15 +697,22 @@ pub struct Participant {
     pub first_name: String,
     /// The last name of the participant.
     pub last_name: String,
-    /// The sub-package which was set for the winner.
+    /// The short identifier of the present which was assigned to the winner.
     pub present_identifier: Option<String>,
+    /// The present which was assigned to the winner.
+    pub present_id: Option<i32>,
+    /// The draw which selected the participant as a winner (if it was recorded).
+    pub draw_id: Option<i32>,
 }
 
 pub async fn get_all_winners(
     db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    raffle_id: i32,
 ) -> Result<HashMap<String, Vec<Participant>>, ()> {
-    use crate::models::Participant as DatabaseParticipant;
-    use crate::schema::participants::dsl::{participants, won_on};
+    use crate::models::{Participant as DatabaseParticipant, Present, Win, WinStatus};
+    use crate::schema::participants::dsl::{participants, raffle_id as participant_raffle_id};
+    use crate::schema::presents::dsl::presents;
+    use crate::schema::wins::dsl::{status, wins, won_on};
     use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
     use log::error;
 
@@ -250,21 +734,28 @@ pub async fn get_all_winners(
         .read_only()
         .run::<_, diesel::result::Error, _>(|connection| {
             let mut result_map = HashMap::new();
-            match participants
-                .filter(won_on.is_not_null())
+            match wins
+                .inner_join(participants)
+                .left_join(presents)
+                .filter(participant_raffle_id.eq(raffle_id))
+                .filter(status.eq(WinStatus::Active.as_str()))
                 .order_by(won_on.asc())
-                .load::<DatabaseParticipant>(connection)
+                .load::<(Win, DatabaseParticipant, Option<Present>)>(connection)
             {
                 Ok(participants_won_dates) => {
-                    for current in participants_won_dates.iter() {
+                    for (win, current, present) in participants_won_dates.iter() {
                         result_map
-                            .entry(current.won_on.unwrap().to_string())
+                            .entry(win.won_on.to_string())
                             .or_insert(vec![])
                             .push(Participant {
                                 id: current.id,
                                 first_name: current.first_name.clone(),
                                 last_name: current.last_name.clone(),
-                                present_identifier: current.present_identifier.clone(),
+                                present_identifier: present
+                                    .as_ref()
+                                    .map(|present| present.identifier.clone()),
+                                present_id: win.present_id,
+                                draw_id: win.draw_id,
                             });
                     }
                     Ok(result_map)
@@ -282,10 +773,13 @@ pub async fn get_all_winners(
 
 pub async fn get_won_participants_on_day(
     db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    raffle_id: i32,
     date: NaiveDate,
 ) -> Result<Vec<Participant>, ()> {
-    use crate::models::Participant as DatabaseParticipant;
-    use crate::schema::participants::dsl::{participants, won_on};
+    use crate::models::{Participant as DatabaseParticipant, Present, Win, WinStatus};
+    use crate::schema::participants::dsl::{participants, raffle_id as participant_raffle_id};
+    use crate::schema::presents::dsl::presents;
+    use crate::schema::wins::dsl::{status, wins, won_on};
     use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
     use log::error;
 
@@ -306,17 +800,25 @@ pub async fn get_won_participants_on_day(
         .build_transaction()
         .read_only()
         .run::<_, diesel::result::Error, _>(move |connection| {
-            match participants
+            match wins
+                .inner_join(participants)
+                .left_join(presents)
+                .filter(participant_raffle_id.eq(raffle_id))
+                .filter(status.eq(WinStatus::Active.as_str()))
                 .filter(won_on.eq(date))
-                .load::<DatabaseParticipant>(connection)
+                .load::<(Win, DatabaseParticipant, Option<Present>)>(connection)
             {
                 Ok(participants_won_on_date) => Ok(participants_won_on_date
                     .iter()
-                    .map(|item| Participant {
+                    .map(|(win, item, present)| Participant {
                         id: item.id,
                         first_name: item.first_name.clone(),
                         last_name: item.last_name.clone(),
-                        present_identifier: item.present_identifier.clone(),
+                        present_identifier: present
+                            .as_ref()
+                            .map(|present| present.identifier.clone()),
+                        present_id: win.present_id,
+                        draw_id: win.draw_id,
                     })
                     .collect()),
                 Err(error) => Err(error),
@@ -330,8 +832,8 @@ pub async fn get_won_participants_on_day(
     Err(())
 }
 
-#[options("/participants/won")]
-pub async fn participants_won_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
+#[options("/raffles/<_raffle_id>/participants/won")]
+pub async fn participants_won_options<'r, 'o: 'r>(_raffle_id: i32) -> impl Responder<'r, 'o> {
     let mut options = cors_options();
     options.allowed_methods = vec![Method::Get, Method::Delete]
         .into_iter()
@@ -342,16 +844,23 @@ pub async fn participants_won_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
     cors.respond_owned(|guard| guard.responder(()))
 }
 
-#[delete("/participants/won/<participant_id>")]
+#[delete("/raffles/<raffle_id>/participants/won/<participant_id>")]
 pub async fn remove_participant_from_winner_list(
     db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    raffle_id: i32,
     participant_id: i32,
-    authenticated_user: AuthenticatedUser,
+    authenticated_user: RequireRole<Operator>,
 ) -> Status {
     use crate::log_action_rocket;
 
+    // the winners of archived raffles cannot be changed anymore
+    if let Err(status) = lookup_writable_raffle(db_connection_pool, raffle_id) {
+        return status;
+    }
+
     if mark_participant_as_not_won(
         db_connection_pool,
+        raffle_id,
         participant_id,
         authenticated_user.username.clone(),
     )
@@ -360,11 +869,11 @@ pub async fn remove_participant_from_winner_list(
     {
         log_action_rocket(
             db_connection_pool,
-            authenticated_user.username,
+            authenticated_user.username.clone(),
             Action::RemovedWinner,
             Some(format!(
-                "The participant with the id {} was marked removed from the list of winners",
-                participant_id
+                "The participant with the id {} was marked removed from the list of winners of the raffle {}",
+                participant_id, raffle_id
             )),
         )
         .await;
@@ -373,155 +882,174 @@ pub async fn remove_participant_from_winner_list(
     Status::NotFound
 }
 
-#[get("/participants/won")]
-pub async fn get_all_won_participants(
-    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
-    _authenticated_user: AuthenticatedUser,
-) -> Result<Json<HashMap<String, Vec<Participant>>>, Status> {
-    let maybe_all_winners = get_all_winners(db_connection_pool).await;
-    if let Ok(winners) = maybe_all_winners {
-        return Ok(Json(winners));
-    }
-    Err(Status::NotFound)
+#[derive(Deserialize)]
+pub struct ReplacementRequest {
+    /// The reason why the winner is replaced.
+    pub reason: crate::picking::ReplacementReason,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct NewPassword {
-    /// The new password the user wants to set.
-    first_time: String,
-    /// The repeated password from the user (check if it is the equal to `first_time`).
-    second_time: String,
+#[derive(Serialize)]
+pub struct ReplacementDetails {
+    /// The internally used id for the replacement.
+    pub id: i32,
+    /// The winner who was replaced.
+    pub participant_id: i32,
+    /// The day on which the replaced winner originally won.
+    pub won_on: NaiveDate,
+    /// The short identifier of the present which was assigned to the replaced winner (and taken
+    /// over by the substitute).
+    pub present_identifier: Option<String>,
+    /// The present which was assigned to the replaced winner.
+    pub present_id: Option<i32>,
+    /// The draw which originally selected the replaced winner.
+    pub draw_id: Option<i32>,
+    /// The reason why the winner was replaced (`declined`, `absent` or `duplicate`).
+    pub reason: String,
+    /// The time at which the winner was replaced.
+    pub replaced_at: NaiveDateTime,
+    /// The participant who won instead.
+    pub substitute_id: Option<i32>,
+    /// The draw which selected the substitute.
+    pub substitute_draw_id: Option<i32>,
 }
 
-#[put("/auth/password", data = "<new_password>")]
-pub async fn update_user_password(
-    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
-    authenticated_user: AuthenticatedUser,
-    new_password: Json<NewPassword>,
-) -> Status {
-    use crate::log_action_rocket;
-    use crate::schema::users::dsl::{password_hash, username, users};
-    use bcrypt::hash;
-    use diesel::{update, ExpressionMethods, QueryDsl, RunQueryDsl};
-    use log::{debug, error};
-
-    // check if the passwords are the same. If not, return an corresonding error
-    if new_password.first_time.ne(&new_password.second_time) {
-        return Status::UnprocessableEntity;
+impl ReplacementDetails {
+    /// Combine a replacement with the replaced win (and its present) and the win of the
+    /// substitute.
+    fn new(
+        replacement: &crate::models::WinnerReplacement,
+        win: &crate::models::Win,
+        present: Option<&crate::models::Present>,
+        substitute_win: Option<&crate::models::Win>,
+    ) -> Self {
+        ReplacementDetails {
+            id: replacement.id,
+            participant_id: win.participant_id,
+            won_on: win.won_on,
+            present_identifier: present.map(|present| present.identifier.clone()),
+            present_id: win.present_id,
+            draw_id: win.draw_id,
+            reason: replacement.reason.clone(),
+            replaced_at: replacement.replaced_at,
+            substitute_id: substitute_win.map(|win| win.participant_id),
+            substitute_draw_id: substitute_win.and_then(|win| win.draw_id),
+        }
     }
+}
 
-    // validate password strength using the passwords crate
-    use passwords::analyzer;
-    use passwords::scorer;
-
-    // enforce minimum length of 8 characters
-    if new_password.first_time.len() < 8 {
-        debug!("Password rejected: too short (minimum 8 characters)");
-        return Status::UnprocessableEntity;
-    }
+#[derive(Serialize)]
+pub struct ReplacedWinnerResponse {
+    /// The historical record of the replaced win.
+    pub replacement: ReplacementDetails,
+    /// The substitute who won instead.
+    pub substitute: Participant,
+}
 
-    // analyze password strength
-    let analyzed = analyzer::analyze(&new_password.first_time);
-    let score = scorer::score(&analyzed);
+#[options("/raffles/<_raffle_id>/participants/won/<_participant_id>/replace")]
+pub async fn replace_winner_options<'r, 'o: 'r>(
+    _raffle_id: i32,
+    _participant_id: i32,
+) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Post].into_iter().map(From::from).collect();
 
-    // require a minimum score of ~40 (roughly equivalent to zxcvbn score of 2)
-    // the passwords crate scores 0-100, zxcvbn scores 0-4
-    // score ~40 represents "Fair" strength
-    if score < 40.0 {
-        debug!(
-            "Password rejected: too weak (score: {}, minimum required: 40)",
-            score
-        );
-        return Status::UnprocessableEntity;
-    }
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
 
-    // create a hashed version of the password which we then can store in the database. if we fail, we
-    // return an error
-    let maybe_hashed_password = hash(&new_password.first_time, 10);
-    if maybe_hashed_password.is_err() {
-        error!(
-            "Could not generate an hash of a supplied password. The error was: {}",
-            maybe_hashed_password.unwrap_err()
-        );
-        return Status::InternalServerError;
-    }
-    let hashed_password = maybe_hashed_password.unwrap();
-    let current_user = authenticated_user.username.clone();
+/// Replace a winner by a substitute who is drawn for the same day. The original win is kept as
+/// history and the substitute takes over the present of the replaced winner.
+#[post(
+    "/raffles/<raffle_id>/participants/won/<participant_id>/replace",
+    data = "<replacement_request>"
+)]
+pub async fn replace_winner(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Operator>,
+    raffle_id: i32,
+    participant_id: i32,
+    replacement_request: Json<ReplacementRequest>,
+) -> Result<Json<ReplacedWinnerResponse>, Status> {
+    use crate::picking::replace_winner;
+    use log::{debug, error};
 
-    // get a connection to the database for dealing with the request
-    let db_connection = &mut match db_connection_pool.get() {
-        Ok(connection) => connection,
+    // the winners of archived raffles cannot be changed anymore
+    let raffle = lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    // release the winner and draw the substitute in a single step (the audit log is written
+    // within the same transaction)
+    let username = authenticated_user.username.clone();
+    let reason = replacement_request.reason;
+    let replaced_winner = match run_picking(db_connection_pool, move |db_connection| {
+        replace_winner(db_connection, &raffle, participant_id, reason, &username)
+    })
+    .await?
+    {
+        Ok(replaced_winner) => replaced_winner,
         Err(error) => {
             error!(
-                "Could not get a connection from the database connection pool. The error was: {}",
-                error
+                "The user {} tried to replace the winner with the id {} in the raffle {} but {}",
+                authenticated_user.username, participant_id, raffle_id, error
             );
-            return Status::InternalServerError;
+            return Err(pick_error_status(&error));
         }
     };
 
-    // update the corresponding row in the database
-    if let Err(error) = db_connection
-        .build_transaction()
-        .read_write()
-        .run::<_, diesel::result::Error, _>(move |connection| {
-            if let Ok(rows_updated) = update(users.filter(username.eq(authenticated_user.username.clone())))
-                .set(password_hash.eq(hashed_password))
-                .execute(connection)
-            {
-                if rows_updated != 1 {
-                    error!("Expected to update exactly one row but none or more than one row were updated. This should never happen!");
-                    return Err(diesel::result::Error::NotFound); // TODO: not the real error
-                }
-                return Ok(());
-            }
-            error!("Failed to update the corresponding entry");
-            Err(diesel::result::Error::NotFound) // TODO: not the real error
-        })
-    {
-        error!(
-            "Could not update the password in the database. The error was: {}",
-            error
-        );
-        return Status::InternalServerError;
-    }
-
-    // log that the user changed the own password
-    log_action_rocket(
-        db_connection_pool,
-        current_user,
-        Action::PasswordChanged,
-        None,
-    )
-    .await;
-
-    // if we get here, the password was successfully updated
-    debug!("Password was successfully updated",);
-    Status::NoContent
+    debug!(
+        "The user {} replaced the winner with the id {} by the participant with the id {} ({})",
+        authenticated_user.username,
+        participant_id,
+        replaced_winner.substitute.participant.id,
+        replacement_request.reason
+    );
+    Ok(Json(ReplacedWinnerResponse {
+        replacement: ReplacementDetails::new(
+            &replaced_winner.replacement,
+            &replaced_winner.replaced_win,
+            replaced_winner.present.as_ref(),
+            Some(&replaced_winner.substitute.win),
+        ),
+        substitute: Participant {
+            id: replaced_winner.substitute.participant.id,
+            first_name: replaced_winner.substitute.participant.first_name.clone(),
+            last_name: replaced_winner.substitute.participant.last_name.clone(),
+            present_identifier: replaced_winner
+                .present
+                .as_ref()
+                .map(|present| present.identifier.clone()),
+            present_id: replaced_winner.substitute.win.present_id,
+            draw_id: Some(replaced_winner.draw.id),
+        },
+    }))
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct NewPackageSelection {
-    /// The package the user should be assigned to.
-    package: String,
+#[options("/raffles/<_raffle_id>/replacements")]
+pub async fn replacements_options<'r, 'o: 'r>(_raffle_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
 }
 
-#[put(
-    "/participants/<current_participant_id>",
-    data = "<new_package_selection>"
-)]
-pub async fn update_participant_values(
+/// Get the history of all winners of a raffle who were replaced (the newest replacement first).
+#[get("/raffles/<raffle_id>/replacements")]
+pub async fn list_replacements(
     db_connection_pool: &State<AdventskalenderDatabaseConnection>,
-    authenticated_user: AuthenticatedUser,
-    current_participant_id: i32,
-    new_package_selection: Json<NewPackageSelection>,
-) -> Status {
-    use crate::log_action_rocket;
-    use crate::models::Participant as DatabaseParticipant;
-    use crate::schema::participants::dsl::{id, participants, present_identifier, won_on};
-    use diesel::{update, ExpressionMethods, QueryDsl, RunQueryDsl};
+    _authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
+) -> Result<Json<Vec<ReplacementDetails>>, Status> {
+    use crate::models::{Present, Win, WinnerReplacement};
+    use crate::schema::presents::dsl::presents;
+    use crate::schema::winner_replacements::dsl::{
+        id, raffle_id as replacement_raffle_id, winner_replacements,
+    };
+    use crate::schema::wins::dsl::{id as win_id, wins};
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
     use log::error;
 
+    lookup_raffle(db_connection_pool, raffle_id)?;
+
     // get a connection to the database for dealing with the request
     let db_connection = &mut match db_connection_pool.get() {
         Ok(connection) => connection,
@@ -530,172 +1058,244 @@ pub async fn update_participant_values(
                 "Could not get a connection from the database connection pool. The error was: {}",
                 error
             );
-            return Status::InternalServerError;
+            return Err(Status::InternalServerError);
         }
     };
 
-    // get the user on which the package should be selected on
-    let participant_won = match participants
-        .filter(id.eq(current_participant_id))
-        .load::<DatabaseParticipant>(db_connection)
-    {
-        Ok(users) => {
-            if users.len() != 1 {
-                error!("Tried to fetch the participant with the id {} from the database but got {} participants as a result", current_participant_id, users.len());
-                return Status::NotFound;
-            }
-            users.first().unwrap().clone()
-        }
+    // the replaced wins are joined directly, the wins of the substitutes are fetched afterwards
+    match db_connection
+        .build_transaction()
+        .read_only()
+        .run::<_, diesel::result::Error, _>(|connection| {
+            let replacements = winner_replacements
+                .inner_join(wins.left_join(presents))
+                .filter(replacement_raffle_id.eq(raffle_id))
+                .order_by(id.desc())
+                .load::<(WinnerReplacement, (Win, Option<Present>))>(connection)?;
+            let substitute_wins = wins
+                .filter(
+                    win_id.eq_any(
+                        replacements
+                            .iter()
+                            .filter_map(|(replacement, _)| replacement.substitute_win_id)
+                            .collect::<Vec<_>>(),
+                    ),
+                )
+                .load::<Win>(connection)?
+                .into_iter()
+                .map(|win| (win.id, win))
+                .collect::<HashMap<_, _>>();
+            Ok(replacements
+                .iter()
+                .map(|(replacement, (win, present))| {
+                    ReplacementDetails::new(
+                        replacement,
+                        win,
+                        present.as_ref(),
+                        replacement
+                            .substitute_win_id
+                            .and_then(|substitute_win_id| substitute_wins.get(&substitute_win_id)),
+                    )
+                })
+                .collect::<Vec<_>>())
+        }) {
+        Ok(replacements) => Ok(Json(replacements)),
         Err(error) => {
-            error!("Tried to fetch the participant with the id {} from the database but an error occurred. The error was: {}", current_participant_id, error);
-            return Status::InternalServerError;
+            error!(
+                "Could not get the replaced winners of the raffle {}. The error was: {}",
+                raffle_id, error
+            );
+            Err(Status::InternalServerError)
         }
-    };
-
-    //
-    if participant_won.won_on.is_none() {
-        error!("Tried to select a package for the participant with the id {} but the participant was not picked before", current_participant_id);
-        return Status::Conflict;
     }
-    let date_of_win = participant_won.won_on.unwrap();
+}
+
+#[derive(Serialize)]
+pub struct WinDetails {
+    /// The internally used id for the win.
+    pub id: i32,
+    /// The participant who won.
+    pub participant_id: i32,
+    /// The first name of the participant.
+    pub first_name: String,
+    /// The last name of the participant.
+    pub last_name: String,
+    /// The day for which the participant won.
+    pub won_on: NaiveDate,
+    /// The short identifier of the present which was assigned with this win.
+    pub present_identifier: Option<String>,
+    /// The present which was assigned with this win.
+    pub present_id: Option<i32>,
+    /// The state of the win (`active`, `revoked`, `replaced` or `superseded`).
+    pub status: String,
+    /// The time at which the participant was picked.
+    pub picked_at: Option<NaiveDateTime>,
+    /// The time at which the win stopped being the current one.
+    pub revoked_at: Option<NaiveDateTime>,
+    /// The draw which selected the participant (if it was recorded).
+    pub draw_id: Option<i32>,
+}
 
-    // variable to store a previously selected package
-    let mut old_package: Option<String> = None;
+#[options("/raffles/<_raffle_id>/wins")]
+pub async fn wins_options<'r, 'o: 'r>(_raffle_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get].into_iter().map(From::from).collect();
 
-    // get the already selected packages for the given date
-    let already_selected_packages: Vec<String> = match participants
-        .filter(won_on.eq(date_of_win))
-        .load::<DatabaseParticipant>(db_connection)
-    {
-        Ok(users) => users
-            .iter()
-            .filter(|user| {
-                if user.id == current_participant_id {
-                    old_package = user.present_identifier.clone();
-                }
-                user.present_identifier.is_some()
-            })
-            .map(|user| user.present_identifier.clone().unwrap())
-            .collect(),
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+/// Get the history of all wins of a raffle (including revoked, replaced and superseded ones),
+/// optionally restricted to a day and / or a present. A win which was superseded by a change of
+/// the present keeps the previous present, so this answers who held a present at which time.
+#[get("/raffles/<raffle_id>/wins?<date>&<present>")]
+pub async fn list_wins(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    _authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
+    date: Option<&str>,
+    present: Option<&str>,
+) -> Result<Json<Vec<WinDetails>>, Status> {
+    use crate::models::{Participant as DatabaseParticipant, Present, Win};
+    use crate::schema::participants::dsl::{participants, raffle_id as participant_raffle_id};
+    use crate::schema::presents::dsl::{identifier, presents};
+    use crate::schema::wins::dsl::{id, wins, won_on};
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use log::error;
+    use std::str::FromStr;
+
+    // if we cannot parse the input date, we received a bad parameter and we have to react to it
+    let date = match date.map(NaiveDate::from_str).transpose() {
+        Ok(date) => date,
+        Err(_) => return Err(Status::BadRequest),
+    };
+
+    lookup_raffle(db_connection_pool, raffle_id)?;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
         Err(error) => {
-            error!("Tried to fetch the already selected sub-packages for the date {} from the database but an error occurred. The error was: {}", date_of_win, error);
-            return Status::InternalServerError;
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
         }
     };
 
-    // check if the package was already assigned to another user
-    if already_selected_packages.contains(&new_package_selection.package) {
-        error!("Tried to select the package {} for the user {} but the package was already assigned to another user for the date of {}", new_package_selection.package, current_participant_id, date_of_win);
-        return Status::Conflict;
+    let mut query = wins
+        .inner_join(participants)
+        .left_join(presents)
+        .filter(participant_raffle_id.eq(raffle_id))
+        .order_by((won_on.asc(), id.asc()))
+        .into_boxed();
+    if let Some(date) = date {
+        query = query.filter(won_on.eq(date));
+    }
+    if let Some(present) = present {
+        query = query.filter(identifier.eq(present));
     }
 
-    // set the selected package for a user
-    match update(participants.filter(id.eq(current_participant_id)))
-        .set(present_identifier.eq(new_package_selection.package.clone()))
-        .execute(db_connection)
-    {
-        // TODO: use enum?!
-        Ok(rows_updated) => {
-            if rows_updated != 1 {
-                error!("Tried to save the sub-packages {} for the user {} on {} but it failed and {} rows were updated", new_package_selection.package, current_participant_id, date_of_win, rows_updated);
-                return Status::InternalServerError;
-            }
-        }
+    match query.load::<(Win, DatabaseParticipant, Option<Present>)>(db_connection) {
+        Ok(found_wins) => Ok(Json(
+            found_wins
+                .into_iter()
+                .map(|(win, participant, present)| WinDetails {
+                    id: win.id,
+                    participant_id: participant.id,
+                    first_name: participant.first_name,
+                    last_name: participant.last_name,
+                    won_on: win.won_on,
+                    present_identifier: present.map(|present| present.identifier),
+                    present_id: win.present_id,
+                    status: win.status,
+                    picked_at: win.picked_at,
+                    revoked_at: win.revoked_at,
+                    draw_id: win.draw_id,
+                })
+                .collect(),
+        )),
         Err(error) => {
-            error!("Tried to save the sub-packages {} for the user {} on {} but it failed. The error was: {}", new_package_selection.package, current_participant_id, date_of_win, error);
-            return Status::InternalServerError;
+            error!(
+                "Could not get the wins of the raffle {}. The error was: {}",
+                raffle_id, error
+            );
+            Err(Status::InternalServerError)
         }
     }
-
-    // if we get here we successfully selected a package
-    if old_package.is_none() {
-        log_action_rocket(
-            db_connection_pool,
-            authenticated_user.username.clone(),
-            Action::PackageSelected,
-            Some(format!(
-                "The participant with the id {} was assigned to package {}",
-                current_participant_id, new_package_selection.package,
-            )),
-        )
-        .await;
-    } else {
-        log_action_rocket(
-            db_connection_pool,
-            authenticated_user.username.clone(),
-            Action::PackageChanged,
-            Some(format!(
-                "The participant with the id {} was assigned a new package {}. The previous package was {}",
-                current_participant_id, new_package_selection.package, old_package.unwrap()
-            )),
-        )
-            .await;
-    }
-    Status::NoContent
 }
 
-#[get("/participants/won/<date_as_str>")]
-pub async fn get_won_participants_on_day_route(
-    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
-    _authenticated_user: AuthenticatedUser,
-    date_as_str: &str,
-) -> Result<Json<Vec<Participant>>, Status> {
-    use std::str::FromStr;
+#[derive(Serialize)]
+pub struct PresentDetails {
+    /// The internally used id for the present.
+    pub id: i32,
+    /// The day on which the present is handed out.
+    pub available_on: NaiveDate,
+    /// The short identifier of the present which is shown in the calendar.
+    pub identifier: String,
+    /// The name of the present.
+    pub name: String,
+    /// An optional description of the present.
+    pub description: Option<String>,
+    /// The (optional) sponsor of the present.
+    pub sponsor: Option<String>,
+    /// The number of pieces which can be handed out.
+    pub quantity: i32,
+    /// The number of pieces which are assigned to winners.
+    pub assigned: i64,
+    /// The number of pieces which can still be assigned.
+    pub remaining: i64,
+}
 
-    // if we cannot parse the input date, we received a bad parameter and we have to react to it
-    let maybe_date = NaiveDate::from_str(date_as_str);
-    if maybe_date.is_err() {
-        return Err(Status::BadRequest);
+impl PresentDetails {
+    fn new(present: &crate::models::Present, assigned: i64) -> Self {
+        PresentDetails {
+            id: present.id,
+            available_on: present.available_on,
+            identifier: present.identifier.clone(),
+            name: present.name.clone(),
+            description: present.description.clone(),
+            sponsor: present.sponsor.clone(),
+            quantity: present.quantity,
+            assigned,
+            remaining: (i64::from(present.quantity) - assigned).max(0),
+        }
     }
+}
 
-    // try to fetch the information and construct the corresponding data structure we want to return
-    let maybe_result = get_won_participants_on_day(db_connection_pool, maybe_date.unwrap()).await;
-
-    // if we got a result, count the participants and return the amount
-    if let Ok(result) = maybe_result {
-        return Ok(Json(result));
-    }
+#[options("/raffles/<_raffle_id>/presents")]
+pub async fn presents_options<'r, 'o: 'r>(_raffle_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get, Method::Post]
+        .into_iter()
+        .map(From::from)
+        .collect();
 
-    // it seems that we could not gather the requested information
-    Err(Status::InternalServerError)
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
 }
 
-#[get("/participants/won/<date_as_str>/count")]
-pub async fn count_won_participants_on_day(
+/// Get the inventory of a raffle (optionally just the presents of a single day) together with the
+/// number of pieces which are still available.
+#[get("/raffles/<raffle_id>/presents?<date>")]
+pub async fn list_presents(
     db_connection_pool: &State<AdventskalenderDatabaseConnection>,
-    authenticated_user: AuthenticatedUser,
-    date_as_str: &str,
-) -> Result<Json<usize>, Status> {
-    use log::debug;
+    _authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
+    date: Option<&str>,
+) -> Result<Json<Vec<PresentDetails>>, Status> {
+    use crate::presents::load_presents;
+    use log::error;
     use std::str::FromStr;
 
     // if we cannot parse the input date, we received a bad parameter and we have to react to it
-    let maybe_date = NaiveDate::from_str(date_as_str);
-    if maybe_date.is_err() {
-        return Err(Status::BadRequest);
-    }
-
-    // try to fetch the information and construct the corresponding data structure we want to return
-    let maybe_result = get_won_participants_on_day(db_connection_pool, maybe_date.unwrap()).await;
-
-    // if we got a result, count the participants and return the amount
-    if let Ok(winners) = maybe_result {
-        debug!("The user {} queried the number of winners for the {}. The answer is: {} participants won on that day so far", authenticated_user.username, date_as_str, winners.len());
-        return Ok(Json(winners.len()));
-    }
-
-    // it seems that we could not gather the requested information
-    Err(Status::InternalServerError)
-}
+    let date = match date.map(NaiveDate::from_str).transpose() {
+        Ok(date) => date,
+        Err(_) => return Err(Status::BadRequest),
+    };
 
-pub async fn pick_random_participants_from_database(
-    db_connection_pool: &AdventskalenderDatabaseConnection,
-    count: usize,
-) -> Option<Vec<Participant>> {
-    use crate::models::Participant as DatabaseParticipant;
-    use crate::schema::participants::dsl::{participants, won_on};
-    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
-    use log::error;
+    lookup_raffle(db_connection_pool, raffle_id)?;
 
     // get a connection to the database for dealing with the request
     let db_connection = &mut match db_connection_pool.get() {
@@ -705,137 +1305,4120 @@ pub async fn pick_random_participants_from_database(
                 "Could not get a connection from the database connection pool. The error was: {}",
                 error
             );
-            return None;
+            return Err(Status::InternalServerError);
         }
     };
 
-    //
-    let maybe_result = db_connection
+    match db_connection
         .build_transaction()
         .read_only()
-        .run::<_, diesel::result::Error, _>(move |connection| {
-            match participants
-                .filter(won_on.is_null())
-                .load::<DatabaseParticipant>(connection)
-            {
-                Ok(participants_in_raffle) => {
-                    let mut participants_vec = vec![];
-                    for current_participant in
-                        participants_in_raffle.choose_multiple(&mut rand::rng(), count)
-                    {
-                        participants_vec.push(Participant {
-                            id: current_participant.id,
-                            first_name: current_participant.first_name.clone(),
-                            last_name: current_participant.last_name.clone(),
-                            present_identifier: None,
-                        });
-                    }
-                    Ok(participants_vec)
-                }
-                Err(error) => Err(error),
-            }
-        });
-
-    //
-    if let Ok(result) = maybe_result {
-        return Some(result);
+        .run(|connection| load_presents(connection, raffle_id, date))
+    {
+        Ok(found_presents) => Ok(Json(
+            found_presents
+                .iter()
+                .map(|(present, assigned)| PresentDetails::new(present, *assigned))
+                .collect(),
+        )),
+        Err(error) => {
+            error!(
+                "Could not get the presents of the raffle {}. The error was: {}",
+                raffle_id, error
+            );
+            Err(Status::InternalServerError)
+        }
     }
-    None
 }
 
-#[get("/participants/pick/<count>/for/<date>")]
-pub async fn pick_multiple_random_participant_from_raffle_list(
+#[post("/raffles/<raffle_id>/presents", data = "<present_values>")]
+pub async fn create_present(
     db_connection_pool: &State<AdventskalenderDatabaseConnection>,
-    authenticated_user: AuthenticatedUser,
-    count: usize,
+    authenticated_user: RequireRole<Operator>,
+    raffle_id: i32,
+    present_values: Json<crate::presents::PresentValues>,
+) -> Result<Created<Json<PresentDetails>>, Status> {
+    use crate::log_action_rocket;
+    use crate::presents::create_present;
+    use log::error;
+
+    // ensure that the supplied values can be stored in the database
+    let values = match present_values.validated() {
+        Some(values) => values,
+        None => return Err(Status::UnprocessableEntity),
+    };
+
+    // the inventory of archived raffles cannot be changed anymore
+    let raffle = lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let created_present = match create_present(db_connection, &raffle, &values) {
+        Ok(present) => present,
+        Err(error) => {
+            error!(
+                "The user {} tried to add the present '{}' for the {} to the raffle {} but {}",
+                authenticated_user.username,
+                values.identifier,
+                values.available_on,
+                raffle_id,
+                error
+            );
+            return Err(present_error_status(&error));
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::PresentCreated,
+        Some(format!(
+            "The present '{}' ({}, {} piece(s)) was added for the {} to the raffle {} with the id {}",
+            created_present.identifier,
+            created_present.name,
+            created_present.quantity,
+            created_present.available_on,
+            raffle_id,
+            created_present.id
+        )),
+    )
+    .await;
+
+    Ok(Created::new(format!(
+        "/v1/raffles/{}/presents/{}",
+        raffle_id, created_present.id
+    ))
+    .body(Json(PresentDetails::new(&created_present, 0))))
+}
+
+#[options("/raffles/<_raffle_id>/presents/<_present_id>")]
+pub async fn present_options<'r, 'o: 'r>(
+    _raffle_id: i32,
+    _present_id: i32,
+) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Put, Method::Delete]
+        .into_iter()
+        .map(From::from)
+        .collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+/// Change a present of the inventory. The quantity cannot be reduced below the number of pieces
+/// which are assigned to winners and assigned presents cannot be moved to another day.
+#[put(
+    "/raffles/<raffle_id>/presents/<present_id>",
+    data = "<present_values>"
+)]
+pub async fn update_present(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Operator>,
+    raffle_id: i32,
+    present_id: i32,
+    present_values: Json<crate::presents::PresentValues>,
+) -> Result<Json<PresentDetails>, Status> {
+    use crate::log_action_rocket;
+    use crate::presents::{change_present, count_assigned};
+    use log::error;
+
+    // ensure that the supplied values can be stored in the database
+    let values = match present_values.validated() {
+        Some(values) => values,
+        None => return Err(Status::UnprocessableEntity),
+    };
+
+    // the inventory of archived raffles cannot be changed anymore
+    let raffle = lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let (old_present, changed_present) =
+        match change_present(db_connection, &raffle, present_id, &values) {
+            Ok(presents_before_and_after) => presents_before_and_after,
+            Err(error) => {
+                error!(
+                "The user {} tried to change the present with the id {} in the raffle {} but {}",
+                authenticated_user.username, present_id, raffle_id, error
+            );
+                return Err(present_error_status(&error));
+            }
+        };
+    let assigned = match count_assigned(db_connection, present_id) {
+        Ok(assigned) => assigned,
+        Err(error) => {
+            error!(
+                "Could not count the assigned pieces of the present with the id {}. The error was: {}",
+                present_id, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::PresentChanged,
+        Some(format!(
+            "The present with the id {} was changed from '{}' ({}, {} piece(s), {}) to '{}' ({}, {} piece(s), {})",
+            present_id,
+            old_present.identifier,
+            old_present.name,
+            old_present.quantity,
+            old_present.available_on,
+            changed_present.identifier,
+            changed_present.name,
+            changed_present.quantity,
+            changed_present.available_on
+        )),
+    )
+    .await;
+
+    Ok(Json(PresentDetails::new(&changed_present, assigned)))
+}
+
+/// Remove a present from the inventory. Presents which are (or were) assigned to a winner are kept
+/// for the history of the wins.
+#[delete("/raffles/<raffle_id>/presents/<present_id>")]
+pub async fn delete_present(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Operator>,
+    raffle_id: i32,
+    present_id: i32,
+) -> Status {
+    use crate::log_action_rocket;
+    use crate::presents::delete_present;
+    use log::error;
+
+    // the inventory of archived raffles cannot be changed anymore
+    if let Err(status) = lookup_writable_raffle(db_connection_pool, raffle_id) {
+        return status;
+    }
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    let deleted_present = match delete_present(db_connection, raffle_id, present_id) {
+        Ok(present) => present,
+        Err(error) => {
+            error!(
+                "The user {} tried to delete the present with the id {} in the raffle {} but {}",
+                authenticated_user.username, present_id, raffle_id, error
+            );
+            return present_error_status(&error);
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::PresentDeleted,
+        Some(format!(
+            "The present '{}' ({}) for the {} was removed from the raffle {}",
+            deleted_present.identifier,
+            deleted_present.name,
+            deleted_present.available_on,
+            raffle_id
+        )),
+    )
+    .await;
+
+    Status::NoContent
+}
+
+pub enum WinnerList {
+    /// All winners grouped by the day on which they won.
+    Grouped(Json<HashMap<String, Vec<Participant>>>),
+    /// A streamed export of all winners (e.g. as CSV).
+    Export(ExportResponse),
+}
+
+impl<'r> Responder<'r, 'r> for WinnerList {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'r> {
+        match self {
+            WinnerList::Grouped(winners) => winners.respond_to(request),
+            WinnerList::Export(export) => export.respond_to(request),
+        }
+    }
+}
+
+#[get("/raffles/<raffle_id>/participants/won")]
+pub async fn get_all_won_participants(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    _authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
+    accept: Option<&Accept>,
+) -> Result<WinnerList, Status> {
+    // ensure that the requested raffle exists at all
+    lookup_raffle(db_connection_pool, raffle_id)?;
+
+    // if the client explicitly asked for an export format, stream the winners in that format
+    if let Some(export_format) = ExportFormat::from_accept(accept) {
+        return ExportResponse::new(
+            db_connection_pool.inner().clone(),
+            raffle_id,
+            true,
+            export_format,
+            "winners",
+        )
+        .await
+        .map(WinnerList::Export)
+        .map_err(|()| Status::InternalServerError);
+    }
+
+    let maybe_all_winners = get_all_winners(db_connection_pool, raffle_id).await;
+    if let Ok(winners) = maybe_all_winners {
+        return Ok(WinnerList::Grouped(Json(winners)));
+    }
+    Err(Status::NotFound)
+}
+
+#[get("/raffles/<raffle_id>/participants/export")]
+pub async fn export_participants(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    _authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
+    accept: Option<&Accept>,
+) -> Result<ExportResponse, Status> {
+    // ensure that the requested raffle exists at all
+    lookup_raffle(db_connection_pool, raffle_id)?;
+
+    ExportResponse::new(
+        db_connection_pool.inner().clone(),
+        raffle_id,
+        false,
+        ExportFormat::from_accept(accept).unwrap_or(ExportFormat::Csv),
+        "participants",
+    )
+    .await
+    .map_err(|()| Status::InternalServerError)
+}
+
+/// The maximum number of characters the database allows for the first and the last name.
+pub const MAX_PARTICIPANT_NAME_LENGTH: usize = 32;
+
+/// The number of participants returned per page if the caller did not request a specific size.
+const DEFAULT_PARTICIPANTS_PER_PAGE: i64 = 50;
+
+/// The maximum number of participants which can be requested with a single page.
+const MAX_PARTICIPANTS_PER_PAGE: i64 = 500;
+
+#[derive(Serialize, Deserialize)]
+pub struct ParticipantName {
+    /// The first name of the participant.
+    pub first_name: String,
+    /// The last name of the participant.
+    pub last_name: String,
+}
+
+impl ParticipantName {
+    /// Get a copy of the name with surrounding whitespace removed or `None` if one of the parts
+    /// is empty or does not fit into the corresponding database column.
+    pub fn normalized(&self) -> Option<ParticipantName> {
+        let first_name = self.first_name.trim();
+        let last_name = self.last_name.trim();
+        if first_name.is_empty()
+            || last_name.is_empty()
+            || first_name.chars().count() > MAX_PARTICIPANT_NAME_LENGTH
+            || last_name.chars().count() > MAX_PARTICIPANT_NAME_LENGTH
+        {
+            return None;
+        }
+        Some(ParticipantName {
+            first_name: first_name.to_string(),
+            last_name: last_name.to_string(),
+        })
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct ParticipantDetails {
+    /// The internally used id for the participant.
+    pub id: i32,
+    /// The first name of the participant.
+    pub first_name: String,
+    /// The last name of the participant.
+    pub last_name: String,
+    /// The date on which the participant won (if the participant won at all).
+    pub won_on: Option<NaiveDate>,
+    /// The short identifier of the present which was assigned to the winner.
+    pub present_identifier: Option<String>,
+    /// The present which was assigned to the winner.
+    pub present_id: Option<i32>,
+    /// The draw which selected the participant as a winner (if it was recorded).
+    pub draw_id: Option<i32>,
+    /// The number of tickets of the participant (used if the raffle is weighted by tickets).
+    pub tickets: i32,
+    /// The department the participant belongs to.
+    pub department: Option<String>,
+    /// A flag which indicates that the participant must not win.
+    pub blocked: bool,
+    /// The identifiers of the presents the participant would like to get (most preferred first).
+    pub present_preferences: Vec<String>,
+}
+
+impl ParticipantDetails {
+    /// Combine a participant with its active win and the assigned present (if the participant won
+    /// at all).
+    fn new(participant: &crate::models::Participant, win: Option<&ActiveWin>) -> Self {
+        let (win, present) = match win {
+            Some((win, present)) => (Some(win), present.as_ref()),
+            None => (None, None),
+        };
+        ParticipantDetails {
+            id: participant.id,
+            first_name: participant.first_name.clone(),
+            last_name: participant.last_name.clone(),
+            won_on: win.map(|win| win.won_on),
+            present_identifier: present.map(|present| present.identifier.clone()),
+            present_id: win.and_then(|win| win.present_id),
+            draw_id: win.and_then(|win| win.draw_id),
+            tickets: participant.tickets,
+            department: participant.department.clone(),
+            blocked: participant.blocked,
+            present_preferences: participant.present_preferences.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ParticipantPage {
+    /// The number of the returned page (starting with 1).
+    pub page: i64,
+    /// The maximum number of participants on a single page.
+    pub per_page: i64,
+    /// The number of participants matching the filter over all pages.
+    pub total: i64,
+    /// The participants on the requested page.
+    pub participants: Vec<ParticipantDetails>,
+}
+
+/// An active win together with the present which was assigned to the winner.
+type ActiveWin = (crate::models::Win, Option<crate::models::Present>);
+
+/// Load the active wins of the supplied participants (indexed by the id of the participant).
+fn load_active_wins(
+    connection: &mut diesel::PgConnection,
+    participant_ids: &[i32],
+) -> Result<HashMap<i32, ActiveWin>, diesel::result::Error> {
+    use crate::models::{Present, Win, WinStatus};
+    use crate::schema::presents::dsl::presents;
+    use crate::schema::wins::dsl::{participant_id, status, wins};
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    Ok(wins
+        .left_join(presents)
+        .filter(participant_id.eq_any(participant_ids))
+        .filter(status.eq(WinStatus::Active.as_str()))
+        .load::<(Win, Option<Present>)>(connection)?
+        .into_iter()
+        .map(|active_win| (active_win.0.participant_id, active_win))
+        .collect())
+}
+
+/// Check if the supplied error was caused by the unique index on the participant names.
+fn is_unique_violation(error: &diesel::result::Error) -> bool {
+    use diesel::result::{DatabaseErrorKind, Error};
+
+    matches!(
+        error,
+        Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)
+    )
+}
+
+#[options("/raffles/<_raffle_id>/participants")]
+pub async fn participants_options<'r, 'o: 'r>(_raffle_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get, Method::Post]
+        .into_iter()
+        .map(From::from)
+        .collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[get("/raffles/<raffle_id>/participants?<page>&<per_page>&<won>")]
+pub async fn list_participants(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    _authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
+    page: Option<i64>,
+    per_page: Option<i64>,
+    won: Option<bool>,
+) -> Result<Json<ParticipantPage>, Status> {
+    use crate::models::Participant as DatabaseParticipant;
+    use crate::picking::active_winner_ids;
+    use crate::schema::participants::dsl::{
+        first_name, id, last_name, participants, raffle_id as participant_raffle_id,
+    };
+    use diesel::dsl::count_star;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // ensure the paging parameters are in a sensible range before we query anything
+    let page = page.unwrap_or(1);
+    let per_page = per_page.unwrap_or(DEFAULT_PARTICIPANTS_PER_PAGE);
+    if page < 1 || !(1..=MAX_PARTICIPANTS_PER_PAGE).contains(&per_page) {
+        return Err(Status::BadRequest);
+    }
+
+    // ensure that the requested raffle exists at all
+    lookup_raffle(db_connection_pool, raffle_id)?;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // fetch the requested page and the overall number of matching participants
+    let maybe_result = db_connection
+        .build_transaction()
+        .read_only()
+        .run::<_, diesel::result::Error, _>(move |connection| {
+            let mut count_query = participants
+                .filter(participant_raffle_id.eq(raffle_id))
+                .select(count_star())
+                .into_boxed();
+            let mut page_query = participants
+                .filter(participant_raffle_id.eq(raffle_id))
+                .into_boxed();
+            match won {
+                Some(true) => {
+                    count_query = count_query.filter(id.eq_any(active_winner_ids()));
+                    page_query = page_query.filter(id.eq_any(active_winner_ids()));
+                }
+                Some(false) => {
+                    count_query = count_query.filter(id.ne_all(active_winner_ids()));
+                    page_query = page_query.filter(id.ne_all(active_winner_ids()));
+                }
+                None => {}
+            }
+
+            let total = count_query.first::<i64>(connection)?;
+            let found_participants = page_query
+                .order_by((last_name.asc(), first_name.asc(), id.asc()))
+                .offset((page - 1) * per_page)
+                .limit(per_page)
+                .load::<DatabaseParticipant>(connection)?;
+            let active_wins = load_active_wins(
+                connection,
+                &found_participants
+                    .iter()
+                    .map(|participant| participant.id)
+                    .collect::<Vec<_>>(),
+            )?;
+
+            Ok(ParticipantPage {
+                page,
+                per_page,
+                total,
+                participants: found_participants
+                    .iter()
+                    .map(|participant| {
+                        ParticipantDetails::new(participant, active_wins.get(&participant.id))
+                    })
+                    .collect(),
+            })
+        });
+
+    match maybe_result {
+        Ok(result) => Ok(Json(result)),
+        Err(error) => {
+            error!(
+                "Could not fetch the list of participants. The error was: {}",
+                error
+            );
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+#[post("/raffles/<raffle_id>/participants", data = "<new_participant>")]
+pub async fn create_participant(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    raffle_id: i32,
+    new_participant: Json<ParticipantName>,
+) -> Result<Created<Json<ParticipantDetails>>, Status> {
+    use crate::log_action_rocket;
+    use crate::models::{NewParticipant, Participant as DatabaseParticipant};
+    use crate::schema::participants::dsl::participants;
+    use diesel::{insert_into, RunQueryDsl};
+    use log::error;
+
+    // ensure that the supplied name can be stored in the database
+    let name = match new_participant.normalized() {
+        Some(name) => name,
+        None => return Err(Status::UnprocessableEntity),
+    };
+
+    // participants can just be changed in raffles which are not archived
+    lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // try to insert the new participant
+    let participant_to_insert = NewParticipant {
+        first_name: name.first_name,
+        last_name: name.last_name,
+        raffle_id,
+    };
+    let created_participant = match db_connection
+        .build_transaction()
+        .read_write()
+        .run::<_, diesel::result::Error, _>(|connection| {
+        insert_into(participants)
+            .values(&participant_to_insert)
+            .get_result::<DatabaseParticipant>(connection)
+    }) {
+        Ok(participant) => participant,
+        Err(error) if is_unique_violation(&error) => {
+            error!(
+                "The user {} tried to add the participant '{} {}' but a participant with the same name already exists",
+                authenticated_user.username, participant_to_insert.first_name, participant_to_insert.last_name
+            );
+            return Err(Status::Conflict);
+        }
+        Err(error) => {
+            error!(
+                "Could not add a new participant to the database. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::ParticipantCreated,
+        Some(format!(
+            "The participant '{} {}' was added to the raffle {} with the id {}",
+            created_participant.first_name,
+            created_participant.last_name,
+            raffle_id,
+            created_participant.id
+        )),
+    )
+    .await;
+
+    Ok(Created::new(format!(
+        "/v1/raffles/{}/participants/{}",
+        raffle_id, created_participant.id
+    ))
+    .body(Json(ParticipantDetails::new(&created_participant, None))))
+}
+
+#[put(
+    "/raffles/<raffle_id>/participants/<participant_id>/name",
+    data = "<new_name>"
+)]
+pub async fn rename_participant(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    raffle_id: i32,
+    participant_id: i32,
+    new_name: Json<ParticipantName>,
+) -> Result<Json<ParticipantDetails>, Status> {
+    use crate::log_action_rocket;
+    use crate::models::{Participant as DatabaseParticipant, ParticipantRenaming};
+    use crate::schema::participants::dsl::{id, participants, raffle_id as participant_raffle_id};
+    use diesel::{update, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // ensure that the supplied name can be stored in the database
+    let name = match new_name.normalized() {
+        Some(name) => name,
+        None => return Err(Status::UnprocessableEntity),
+    };
+
+    // participants can just be changed in raffles which are not archived
+    lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // fetch the old name (for the audit log) and store the new one
+    let changed_name = ParticipantRenaming {
+        first_name: name.first_name,
+        last_name: name.last_name,
+    };
+    let (old_participant, (renamed_participant, active_win)) = match db_connection
+        .build_transaction()
+        .read_write()
+        .run::<_, diesel::result::Error, _>(|connection| {
+            let old_participant = participants
+                .filter(id.eq(participant_id))
+                .filter(participant_raffle_id.eq(raffle_id))
+                .for_update()
+                .first::<DatabaseParticipant>(connection)
+                .optional()?;
+            let old_participant = match old_participant {
+                Some(participant) => participant,
+                None => return Ok(None),
+            };
+            let renamed_participant = update(participants.filter(id.eq(participant_id)))
+                .set(&changed_name)
+                .get_result::<DatabaseParticipant>(connection)?;
+            let active_win =
+                load_active_wins(connection, &[participant_id])?.remove(&participant_id);
+            Ok(Some((old_participant, (renamed_participant, active_win))))
+        }) {
+        Ok(Some(participants_before_and_after)) => participants_before_and_after,
+        Ok(None) => return Err(Status::NotFound),
+        Err(error) if is_unique_violation(&error) => {
+            error!(
+                "The user {} tried to rename the participant with the id {} to '{} {}' but a participant with the same name already exists",
+                authenticated_user.username, participant_id, changed_name.first_name, changed_name.last_name
+            );
+            return Err(Status::Conflict);
+        }
+        Err(error) => {
+            error!(
+                "Could not rename the participant with the id {}. The error was: {}",
+                participant_id, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::ParticipantRenamed,
+        Some(format!(
+            "The participant with the id {} was renamed from '{} {}' to '{} {}'",
+            participant_id,
+            old_participant.first_name,
+            old_participant.last_name,
+            renamed_participant.first_name,
+            renamed_participant.last_name
+        )),
+    )
+    .await;
+
+    Ok(Json(ParticipantDetails::new(
+        &renamed_participant,
+        active_win.as_ref(),
+    )))
+}
+
+#[delete("/raffles/<raffle_id>/participants/<participant_id>")]
+pub async fn delete_participant(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    raffle_id: i32,
+    participant_id: i32,
+) -> Status {
+    use crate::log_action_rocket;
+    use crate::models::Participant as DatabaseParticipant;
+    use crate::schema::participants::dsl::{id, participants, raffle_id as participant_raffle_id};
+    use crate::schema::wins::dsl::{participant_id as win_participant_id, wins};
+    use diesel::dsl::{exists, select};
+    use diesel::{delete, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // participants can just be changed in raffles which are not archived
+    if let Err(status) = lookup_writable_raffle(db_connection_pool, raffle_id) {
+        return status;
+    }
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    // participants who won at any time cannot be deleted since this would remove their wins (even
+    // the revoked and replaced ones) from the history of the raffle
+    let maybe_deleted_participant = db_connection
+        .build_transaction()
+        .read_write()
+        .run::<_, diesel::result::Error, _>(|connection| {
+        let participant = match participants
+            .filter(id.eq(participant_id))
+            .filter(participant_raffle_id.eq(raffle_id))
+            .for_update()
+            .first::<DatabaseParticipant>(connection)
+            .optional()?
+        {
+            Some(participant) => participant,
+            None => return Ok(Err(Status::NotFound)),
+        };
+        if select(exists(wins.filter(win_participant_id.eq(participant_id))))
+            .get_result::<bool>(connection)?
+        {
+            return Ok(Err(Status::Conflict));
+        }
+        delete(participants.filter(id.eq(participant_id))).execute(connection)?;
+        Ok(Ok(participant))
+    });
+
+    let deleted_participant = match maybe_deleted_participant {
+        Ok(Ok(participant)) => participant,
+        Ok(Err(status)) => {
+            error!(
+                "The user {} tried to delete the participant with the id {} but it either does not exist or won at some time",
+                authenticated_user.username, participant_id
+            );
+            return status;
+        }
+        Err(error) => {
+            error!(
+                "Could not delete the participant with the id {}. The error was: {}",
+                participant_id, error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::ParticipantDeleted,
+        Some(format!(
+            "The participant '{} {}' with the id {} was deleted",
+            deleted_participant.first_name, deleted_participant.last_name, participant_id
+        )),
+    )
+    .await;
+
+    Status::NoContent
+}
+
+#[derive(Deserialize)]
+pub struct ParticipantEligibilityUpdate {
+    /// The number of tickets of the participant (used if the raffle is weighted by tickets).
+    pub tickets: i32,
+    /// The department the participant belongs to.
+    #[serde(default)]
+    pub department: Option<String>,
+    /// A flag which indicates that the participant must not win.
+    #[serde(default)]
+    pub blocked: bool,
+}
+
+impl ParticipantEligibilityUpdate {
+    /// Get the values in the form in which they can be stored in the database or `None` if the
+    /// number of tickets is not positive or the department does not fit into the database column.
+    fn validated(&self) -> Option<crate::models::ParticipantEligibility> {
+        use crate::eligibility::normalize_department;
+
+        if self.tickets < 1 {
+            return None;
+        }
+        Some(crate::models::ParticipantEligibility {
+            tickets: self.tickets,
+            department: normalize_department(self.department.as_deref())?,
+            blocked: self.blocked,
+        })
+    }
+}
+
+#[options("/raffles/<_raffle_id>/participants/<_participant_id>/eligibility")]
+pub async fn participant_eligibility_options<'r, 'o: 'r>(
+    _raffle_id: i32,
+    _participant_id: i32,
+) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Put].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[put(
+    "/raffles/<raffle_id>/participants/<participant_id>/eligibility",
+    data = "<eligibility>"
+)]
+pub async fn update_participant_eligibility(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    raffle_id: i32,
+    participant_id: i32,
+    eligibility: Json<ParticipantEligibilityUpdate>,
+) -> Result<Json<ParticipantDetails>, Status> {
+    use crate::log_action_rocket;
+    use crate::models::Participant as DatabaseParticipant;
+    use crate::schema::participants::dsl::{id, participants, raffle_id as participant_raffle_id};
+    use diesel::{update, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // ensure that the supplied values can be stored in the database
+    let changed_eligibility = match eligibility.validated() {
+        Some(changed_eligibility) => changed_eligibility,
+        None => return Err(Status::UnprocessableEntity),
+    };
+
+    // participants can just be changed in raffles which are not archived
+    lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let changed_participant = match update(
+        participants
+            .filter(id.eq(participant_id))
+            .filter(participant_raffle_id.eq(raffle_id)),
+    )
+    .set(&changed_eligibility)
+    .get_result::<DatabaseParticipant>(db_connection)
+    .optional()
+    {
+        Ok(Some(participant)) => participant,
+        Ok(None) => return Err(Status::NotFound),
+        Err(error) => {
+            error!(
+                "Could not change the eligibility of the participant with the id {}. The error was: {}",
+                participant_id, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+    let active_win = match load_active_wins(db_connection, &[participant_id]) {
+        Ok(mut active_wins) => active_wins.remove(&participant_id),
+        Err(error) => {
+            error!(
+                "Could not get the win of the participant with the id {}. The error was: {}",
+                participant_id, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::ParticipantEligibilityChanged,
+        Some(format!(
+            "The participant with the id {} now has {} ticket(s), the department {:?} and is {}",
+            participant_id,
+            changed_participant.tickets,
+            changed_participant.department,
+            if changed_participant.blocked {
+                "blocked"
+            } else {
+                "not blocked"
+            }
+        )),
+    )
+    .await;
+
+    Ok(Json(ParticipantDetails::new(
+        &changed_participant,
+        active_win.as_ref(),
+    )))
+}
+
+#[derive(Deserialize)]
+pub struct ParticipantPreferencesUpdate {
+    /// The identifiers of the presents the participant would like to get (most preferred first).
+    pub present_preferences: Vec<String>,
+}
+
+#[options("/raffles/<_raffle_id>/participants/<_participant_id>/preferences")]
+pub async fn participant_preferences_options<'r, 'o: 'r>(
+    _raffle_id: i32,
+    _participant_id: i32,
+) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Put].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+/// Set the presents a participant would like to get. The preferences are used if the raffle
+/// assigns the presents by preference when the winners are picked.
+#[put(
+    "/raffles/<raffle_id>/participants/<participant_id>/preferences",
+    data = "<preferences>"
+)]
+pub async fn update_participant_preferences(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    raffle_id: i32,
+    participant_id: i32,
+    preferences: Json<ParticipantPreferencesUpdate>,
+) -> Result<Json<ParticipantDetails>, Status> {
+    use crate::log_action_rocket;
+    use crate::models::{Participant as DatabaseParticipant, ParticipantPresentPreferences};
+    use crate::presents::normalize_preferences;
+    use crate::schema::participants::dsl::{id, participants, raffle_id as participant_raffle_id};
+    use diesel::{update, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // ensure that the supplied identifiers can be stored in the database
+    let present_preferences = match normalize_preferences(&preferences.present_preferences) {
+        Some(present_preferences) => present_preferences,
+        None => return Err(Status::UnprocessableEntity),
+    };
+
+    // participants can just be changed in raffles which are not archived
+    lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let changed_participant = match update(
+        participants
+            .filter(id.eq(participant_id))
+            .filter(participant_raffle_id.eq(raffle_id)),
+    )
+    .set(&ParticipantPresentPreferences {
+        present_preferences,
+    })
+    .get_result::<DatabaseParticipant>(db_connection)
+    .optional()
+    {
+        Ok(Some(participant)) => participant,
+        Ok(None) => return Err(Status::NotFound),
+        Err(error) => {
+            error!(
+                "Could not change the preferences of the participant with the id {}. The error was: {}",
+                participant_id, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+    let active_win = match load_active_wins(db_connection, &[participant_id]) {
+        Ok(mut active_wins) => active_wins.remove(&participant_id),
+        Err(error) => {
+            error!(
+                "Could not get the win of the participant with the id {}. The error was: {}",
+                participant_id, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::ParticipantPreferencesChanged,
+        Some(format!(
+            "The participant with the id {} now prefers the presents {:?}",
+            participant_id, changed_participant.present_preferences
+        )),
+    )
+    .await;
+
+    Ok(Json(ParticipantDetails::new(
+        &changed_participant,
+        active_win.as_ref(),
+    )))
+}
+
+#[derive(Serialize, Clone)]
+pub struct AbsenceDetails {
+    /// The internally used id for the absence.
+    pub id: i32,
+    /// The participant who is absent.
+    pub participant_id: i32,
+    /// The first day on which the participant is absent.
+    pub start_date: NaiveDate,
+    /// The last day on which the participant is absent.
+    pub end_date: NaiveDate,
+}
+
+impl From<&crate::models::ParticipantAbsence> for AbsenceDetails {
+    fn from(absence: &crate::models::ParticipantAbsence) -> Self {
+        AbsenceDetails {
+            id: absence.id,
+            participant_id: absence.participant_id,
+            start_date: absence.start_date,
+            end_date: absence.end_date,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AbsencePeriod {
+    /// The first day on which the participant is absent.
+    pub start_date: NaiveDate,
+    /// The last day on which the participant is absent.
+    pub end_date: NaiveDate,
+}
+
+/// Check if the participant with the supplied id belongs to the raffle.
+fn participant_belongs_to_raffle(
+    connection: &mut diesel::PgConnection,
+    raffle_id: i32,
+    participant_id: i32,
+) -> Result<bool, diesel::result::Error> {
+    use crate::schema::participants::dsl::{id, participants, raffle_id as participant_raffle_id};
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    let count = participants
+        .filter(id.eq(participant_id))
+        .filter(participant_raffle_id.eq(raffle_id))
+        .count()
+        .get_result::<i64>(connection)?;
+    Ok(count > 0)
+}
+
+#[options("/raffles/<_raffle_id>/participants/<_participant_id>/absences")]
+pub async fn participant_absences_options<'r, 'o: 'r>(
+    _raffle_id: i32,
+    _participant_id: i32,
+) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get, Method::Post]
+        .into_iter()
+        .map(From::from)
+        .collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[get(
+    "/raffles/<raffle_id>/participants/<participant_id>/absences",
+    rank = 2
+)]
+pub async fn list_participant_absences(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    _authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
+    participant_id: i32,
+) -> Result<Json<Vec<AbsenceDetails>>, Status> {
+    use crate::models::ParticipantAbsence;
+    use crate::schema::participant_absences::dsl::{
+        participant_absences, participant_id as absence_participant_id, start_date,
+    };
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    match db_connection
+        .build_transaction()
+        .read_only()
+        .run::<_, diesel::result::Error, _>(|connection| {
+            if !participant_belongs_to_raffle(connection, raffle_id, participant_id)? {
+                return Ok(None);
+            }
+            participant_absences
+                .filter(absence_participant_id.eq(participant_id))
+                .order_by(start_date.asc())
+                .load::<ParticipantAbsence>(connection)
+                .map(Some)
+        }) {
+        Ok(Some(absences)) => Ok(Json(absences.iter().map(AbsenceDetails::from).collect())),
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            error!(
+                "Could not get the absences of the participant with the id {}. The error was: {}",
+                participant_id, error
+            );
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+#[post(
+    "/raffles/<raffle_id>/participants/<participant_id>/absences",
+    data = "<period>"
+)]
+pub async fn add_participant_absence(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    raffle_id: i32,
+    participant_id: i32,
+    period: Json<AbsencePeriod>,
+) -> Result<Created<Json<AbsenceDetails>>, Status> {
+    use crate::log_action_rocket;
+    use crate::models::{NewParticipantAbsence, ParticipantAbsence};
+    use crate::schema::participant_absences::dsl::participant_absences;
+    use diesel::{insert_into, RunQueryDsl};
+    use log::error;
+
+    if period.start_date > period.end_date {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    // participants can just be changed in raffles which are not archived
+    lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let new_absence = NewParticipantAbsence {
+        participant_id,
+        start_date: period.start_date,
+        end_date: period.end_date,
+    };
+    let created_absence = match db_connection
+        .build_transaction()
+        .read_write()
+        .run::<_, diesel::result::Error, _>(|connection| {
+            if !participant_belongs_to_raffle(connection, raffle_id, participant_id)? {
+                return Ok(None);
+            }
+            insert_into(participant_absences)
+                .values(&new_absence)
+                .get_result::<ParticipantAbsence>(connection)
+                .map(Some)
+        }) {
+        Ok(Some(absence)) => absence,
+        Ok(None) => return Err(Status::NotFound),
+        Err(error) => {
+            error!(
+                "Could not add an absence for the participant with the id {}. The error was: {}",
+                participant_id, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::ParticipantAbsenceAdded,
+        Some(format!(
+            "The participant with the id {} is absent from {} to {} (absence id {})",
+            participant_id,
+            created_absence.start_date,
+            created_absence.end_date,
+            created_absence.id
+        )),
+    )
+    .await;
+
+    Ok(Created::new(format!(
+        "/v1/raffles/{}/participants/{}/absences/{}",
+        raffle_id, participant_id, created_absence.id
+    ))
+    .body(Json(AbsenceDetails::from(&created_absence))))
+}
+
+#[options(
+    "/raffles/<_raffle_id>/participants/<_participant_id>/absences/<_absence_id>",
+    rank = 2
+)]
+pub async fn participant_absence_options<'r, 'o: 'r>(
+    _raffle_id: i32,
+    _participant_id: i32,
+    _absence_id: i32,
+) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Delete].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[delete("/raffles/<raffle_id>/participants/<participant_id>/absences/<absence_id>")]
+pub async fn remove_participant_absence(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    raffle_id: i32,
+    participant_id: i32,
+    absence_id: i32,
+) -> Status {
+    use crate::log_action_rocket;
+    use crate::models::ParticipantAbsence;
+    use crate::schema::participant_absences::dsl::{
+        id, participant_absences, participant_id as absence_participant_id,
+    };
+    use diesel::{delete, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // participants can just be changed in raffles which are not archived
+    if let Err(status) = lookup_writable_raffle(db_connection_pool, raffle_id) {
+        return status;
+    }
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    let removed_absence = match db_connection
+        .build_transaction()
+        .read_write()
+        .run::<_, diesel::result::Error, _>(|connection| {
+            if !participant_belongs_to_raffle(connection, raffle_id, participant_id)? {
+                return Ok(None);
+            }
+            delete(
+                participant_absences
+                    .filter(id.eq(absence_id))
+                    .filter(absence_participant_id.eq(participant_id)),
+            )
+            .get_result::<ParticipantAbsence>(connection)
+            .optional()
+        }) {
+        Ok(Some(absence)) => absence,
+        Ok(None) => return Status::NotFound,
+        Err(error) => {
+            error!(
+                "Could not remove the absence with the id {}. The error was: {}",
+                absence_id, error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::ParticipantAbsenceRemoved,
+        Some(format!(
+            "The absence of the participant with the id {} from {} to {} was removed",
+            participant_id, removed_absence.start_date, removed_absence.end_date
+        )),
+    )
+    .await;
+
+    Status::NoContent
+}
+
+#[post(
+    "/raffles/<raffle_id>/participants/import?<dry_run>",
+    data = "<import_data>"
+)]
+pub async fn import_participants(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    raffle_id: i32,
+    content_type: &ContentType,
+    dry_run: Option<&str>,
+    import_data: Data<'_>,
+) -> Result<Custom<Json<ImportReport>>, Status> {
+    use crate::import::{store_participants, ImportFormat};
+    use crate::log_action_rocket;
+    use log::{error, info};
+    use rocket::data::ToByteUnit;
+
+    let dry_run = is_dry_run(dry_run);
+
+    // select the parser based on the content type of the request
+    let import_format = if content_type.is_json() {
+        ImportFormat::Json
+    } else if content_type.is_csv() || content_type.is_plain() {
+        ImportFormat::Csv
+    } else {
+        return Err(Status::UnsupportedMediaType);
+    };
+
+    // participants can just be changed in raffles which are not archived
+    lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    // read the whole import into memory (the default limit for strings is too small for a few
+    // hundred participants)
+    let import_data = match import_data.open(2.mebibytes()).into_string().await {
+        Ok(data) if data.is_complete() => data.into_inner(),
+        Ok(_) => return Err(Status::PayloadTooLarge),
+        Err(error) => {
+            error!(
+                "Could not read the supplied import data. The error was: {}",
+                error
+            );
+            return Err(Status::BadRequest);
+        }
+    };
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // validate and insert all participants in a single transaction. if a single row is invalid,
+    // nothing gets inserted at all
+    let maybe_report = store_participants(
+        db_connection,
+        raffle_id,
+        &import_data,
+        import_format,
+        dry_run,
+    );
+
+    let import_report = match maybe_report {
+        Ok(import_report) => import_report,
+        Err(error) if is_unique_violation(&error) => {
+            error!(
+                "The import of the user {} collided with participants which were added in parallel",
+                authenticated_user.username
+            );
+            return Err(Status::Conflict);
+        }
+        Err(error) => {
+            error!(
+                "Could not import the supplied participants. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // if the data contained errors, we just report them back to the caller
+    if !import_report.errors.is_empty() {
+        info!(
+            "The import of {} participants by {} contained {} invalid rows",
+            import_report.total_rows,
+            authenticated_user.username,
+            import_report.errors.len()
+        );
+        return Ok(Custom(
+            if dry_run {
+                Status::Ok
+            } else {
+                Status::UnprocessableEntity
+            },
+            Json(import_report),
+        ));
+    }
+
+    if !dry_run {
+        log_action_rocket(
+            db_connection_pool,
+            authenticated_user.username.clone(),
+            Action::ParticipantsImported,
+            Some(format!(
+                "{} participants were added to the raffle {} by a bulk import",
+                import_report.imported, raffle_id
+            )),
+        )
+        .await;
+    }
+
+    Ok(Custom(Status::Ok, Json(import_report)))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewPassword {
+    /// The new password the user wants to set.
+    first_time: String,
+    /// The repeated password from the user (check if it is the equal to `first_time`).
+    second_time: String,
+}
+
+#[put("/auth/password", data = "<new_password>")]
+pub async fn update_user_password(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: AuthenticatedUser,
+    new_password: Json<NewPassword>,
+) -> Status {
+    use crate::log_action_rocket;
+    use crate::schema::users::dsl::{id, must_change_password, password_hash, username, users};
+    use crate::sessions::revoke_sessions;
+    use crate::users::{check_new_password, hash_password};
+    use diesel::{update, ExpressionMethods, QueryDsl, RunQueryDsl};
+    use log::{debug, error};
+
+    // check if the passwords are the same and if the password is strong enough
+    if let Err(rejection) = check_new_password(&new_password.first_time, &new_password.second_time)
+    {
+        debug!("Password rejected: {}", rejection);
+        return Status::UnprocessableEntity;
+    }
+
+    // create a hashed version of the password which we then can store in the database. if we fail, we
+    // return an error
+    let hashed_password = match hash_password(&new_password.first_time) {
+        Ok(hashed_password) => hashed_password,
+        Err(error) => {
+            error!(
+                "Could not generate an hash of a supplied password. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+    let current_user = authenticated_user.username.clone();
+    let current_session_id = authenticated_user.session_id.clone();
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    // update the corresponding row in the database and revoke all other sessions of the user (the
+    // old password may have been used by someone else to log in)
+    let revoked_sessions = match db_connection
+        .build_transaction()
+        .read_write()
+        .run::<_, diesel::result::Error, _>(move |connection| {
+            if let Ok(rows_updated) = update(users.filter(username.eq(authenticated_user.username.clone())))
+                .set((password_hash.eq(hashed_password), must_change_password.eq(false)))
+                .execute(connection)
+            {
+                if rows_updated != 1 {
+                    error!("Expected to update exactly one row but none or more than one row were updated. This should never happen!");
+                    return Err(diesel::result::Error::NotFound); // TODO: not the real error
+                }
+                let user_id = users
+                    .filter(username.eq(authenticated_user.username.clone()))
+                    .select(id)
+                    .first::<i32>(connection)?;
+                return revoke_sessions(connection, user_id, None, Some(&current_session_id));
+            }
+            error!("Failed to update the corresponding entry");
+            Err(diesel::result::Error::NotFound) // TODO: not the real error
+        }) {
+        Ok(revoked_sessions) => revoked_sessions,
+        Err(error) => {
+            error!(
+                "Could not update the password in the database. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    // log that the user changed the own password
+    log_action_rocket(
+        db_connection_pool,
+        current_user,
+        Action::PasswordChanged,
+        Some(format!(
+            "The other {} sessions of the user were revoked",
+            revoked_sessions
+        )),
+    )
+    .await;
+
+    // if we get here, the password was successfully updated
+    debug!("Password was successfully updated",);
+    Status::NoContent
+}
+
+#[options("/users")]
+pub async fn users_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get, Method::Post]
+        .into_iter()
+        .map(From::from)
+        .collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[options("/users/<_user_id>")]
+pub async fn user_options<'r, 'o: 'r>(_user_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Put, Method::Delete]
+        .into_iter()
+        .map(From::from)
+        .collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[options("/users/<_user_id>/password")]
+pub async fn user_password_options<'r, 'o: 'r>(_user_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Put].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[get("/users")]
+pub async fn list_users(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    _authenticated_user: RequireRole<Admin>,
+) -> Result<Json<Vec<UserSummary>>, Status> {
+    use crate::schema::users::dsl::{username, users};
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    match users.order_by(username.asc()).load::<User>(db_connection) {
+        Ok(found_users) => Ok(Json(found_users.iter().map(UserSummary::from).collect())),
+        Err(error) => {
+            error!("Could not load the users. The error was: {}", error);
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewUserAccount {
+    /// The name the new user logs in with.
+    username: String,
+    /// The role of the new user (viewer if not supplied).
+    #[serde(default)]
+    role: Role,
+    /// The initial password of the new user.
+    password: NewPassword,
+}
+
+#[post("/users", data = "<new_user_account>")]
+pub async fn create_user(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    new_user_account: Json<NewUserAccount>,
+) -> Result<Created<Json<UserSummary>>, Status> {
+    use crate::log_action_rocket;
+    use crate::users::{check_new_password, hash_password, insert_user, is_valid_username};
+    use log::{debug, error};
+
+    if !is_valid_username(&new_user_account.username) {
+        return Err(Status::UnprocessableEntity);
+    }
+    if let Err(rejection) = check_new_password(
+        &new_user_account.password.first_time,
+        &new_user_account.password.second_time,
+    ) {
+        debug!("Password rejected: {}", rejection);
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let hashed_password = match hash_password(&new_user_account.password.first_time) {
+        Ok(hashed_password) => hashed_password,
+        Err(error) => {
+            error!(
+                "Could not generate an hash of a supplied password. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let created_user = match insert_user(
+        db_connection,
+        &new_user_account.username,
+        hashed_password,
+        new_user_account.role,
+    ) {
+        Ok(user) => user,
+        Err(error) if is_unique_violation(&error) => {
+            error!(
+                "The user {} tried to add the user '{}' but a user with the same name already exists",
+                authenticated_user.username, new_user_account.username
+            );
+            return Err(Status::Conflict);
+        }
+        Err(error) => {
+            error!(
+                "Could not add a new user to the database. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::UserCreated,
+        Some(format!(
+            "The user '{}' was created with the id {} and the role {}",
+            created_user.username, created_user.id, created_user.role
+        )),
+    )
+    .await;
+
+    Ok(Created::new(format!("/v1/users/{}", created_user.id))
+        .body(Json(UserSummary::from(&created_user))))
+}
+
+#[derive(Deserialize)]
+pub struct UserChanges {
+    /// The new role of the user (unchanged if not supplied).
+    role: Option<Role>,
+    /// Whether the user should be disabled or enabled again (unchanged if not supplied).
+    disabled: Option<bool>,
+}
+
+#[put("/users/<user_id>", data = "<user_changes>")]
+pub async fn update_user(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    user_id: i32,
+    user_changes: Json<UserChanges>,
+) -> Result<Json<UserSummary>, Status> {
+    use crate::log_action_rocket;
+    use crate::models::UserChangeset;
+    use crate::schema::users::dsl::users;
+    use crate::sessions::revoke_sessions;
+    use diesel::{update, Connection, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    if user_changes.role.is_none() && user_changes.disabled.is_none() {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let old_user = match users.find(user_id).first::<User>(db_connection) {
+        Ok(user) => user,
+        Err(diesel::result::Error::NotFound) => return Err(Status::NotFound),
+        Err(error) => {
+            error!(
+                "Could not look up the user with the id {}. The error was: {}",
+                user_id, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // the administrators must not lock themselves out
+    if old_user.username == authenticated_user.username {
+        error!(
+            "The user {} tried to change the own role or to disable the own account",
+            authenticated_user.username
+        );
+        return Err(Status::Conflict);
+    }
+
+    // the logins of a disabled user are revoked in the same transaction, so enabling the user
+    // again does not bring them back
+    let updated_user =
+        match db_connection.transaction::<_, diesel::result::Error, _>(|connection| {
+            let updated_user = update(users.find(user_id))
+                .set(&UserChangeset {
+                    role: user_changes.role.map(|role| role.as_str().to_string()),
+                    disabled: user_changes.disabled,
+                })
+                .get_result::<User>(connection)?;
+            if updated_user.disabled && !old_user.disabled {
+                revoke_sessions(connection, user_id, None, None)?;
+            }
+            Ok(updated_user)
+        }) {
+            Ok(user) => user,
+            Err(error) => {
+                error!(
+                    "Could not update the user with the id {}. The error was: {}",
+                    user_id, error
+                );
+                return Err(Status::InternalServerError);
+            }
+        };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::UserUpdated,
+        Some(format!(
+            "The user '{}' with the id {} was changed from (role: {}, disabled: {}) to (role: {}, disabled: {})",
+            updated_user.username,
+            user_id,
+            old_user.role,
+            old_user.disabled,
+            updated_user.role,
+            updated_user.disabled
+        )),
+    )
+    .await;
+
+    Ok(Json(UserSummary::from(&updated_user)))
+}
+
+#[delete("/users/<user_id>")]
+pub async fn delete_user(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    user_id: i32,
+) -> Status {
+    use crate::log_action_rocket;
+    use crate::schema::users::dsl::users;
+    use diesel::result::{DatabaseErrorKind, Error};
+    use diesel::{delete, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    let old_user = match users.find(user_id).first::<User>(db_connection) {
+        Ok(user) => user,
+        Err(Error::NotFound) => return Status::NotFound,
+        Err(error) => {
+            error!(
+                "Could not look up the user with the id {}. The error was: {}",
+                user_id, error
+            );
+            return Status::InternalServerError;
+        }
+    };
+    if old_user.username == authenticated_user.username {
+        error!(
+            "The user {} tried to delete the own account",
+            authenticated_user.username
+        );
+        return Status::Conflict;
+    }
+
+    // users who are referenced by the history (e.g. the audit log or picked winners) cannot be
+    // deleted, they have to be disabled instead
+    match delete(users.find(user_id)).execute(db_connection) {
+        Ok(_) => {}
+        Err(Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _)) => {
+            error!(
+                "The user {} tried to delete the user '{}' who is still referenced by the history",
+                authenticated_user.username, old_user.username
+            );
+            return Status::Conflict;
+        }
+        Err(error) => {
+            error!(
+                "Could not delete the user with the id {}. The error was: {}",
+                user_id, error
+            );
+            return Status::InternalServerError;
+        }
+    }
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::UserDeleted,
+        Some(format!(
+            "The user '{}' with the id {} was deleted",
+            old_user.username, user_id
+        )),
+    )
+    .await;
+
+    Status::NoContent
+}
+
+#[put("/users/<user_id>/password", data = "<new_password>")]
+pub async fn reset_user_password(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    user_id: i32,
+    new_password: Json<NewPassword>,
+) -> Status {
+    use crate::log_action_rocket;
+    use crate::users::{check_new_password, hash_password, reset_password};
+    use log::{debug, error};
+
+    if let Err(rejection) = check_new_password(&new_password.first_time, &new_password.second_time)
+    {
+        debug!("Password rejected: {}", rejection);
+        return Status::UnprocessableEntity;
+    }
+
+    let hashed_password = match hash_password(&new_password.first_time) {
+        Ok(hashed_password) => hashed_password,
+        Err(error) => {
+            error!(
+                "Could not generate an hash of a supplied password. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    // the administrator knows the new password, so the user has to replace it after the next login
+    // and every session which is still open is revoked
+    let updated_user = match reset_password(db_connection, user_id, hashed_password) {
+        Ok(user) => user,
+        Err(diesel::result::Error::NotFound) => return Status::NotFound,
+        Err(error) => {
+            error!(
+                "Could not reset the password of the user with the id {}. The error was: {}",
+                user_id, error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::PasswordReset,
+        Some(format!(
+            "The password of the user '{}' with the id {} was reset",
+            updated_user.username, user_id
+        )),
+    )
+    .await;
+
+    Status::NoContent
+}
+
+#[options("/users/<_user_id>/totp")]
+pub async fn user_second_factor_options<'r, 'o: 'r>(_user_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Delete].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[delete("/users/<user_id>/totp")]
+pub async fn reset_user_second_factor(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    user_id: i32,
+) -> Status {
+    use crate::log_action_rocket;
+    use crate::schema::users::dsl::users;
+    use crate::two_factor::remove_second_factor;
+    use diesel::result::Error;
+    use diesel::{QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    let user = match users.find(user_id).first::<User>(db_connection) {
+        Ok(user) => user,
+        Err(Error::NotFound) => return Status::NotFound,
+        Err(error) => {
+            error!(
+                "Could not look up the user with the id {}. The error was: {}",
+                user_id, error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    // the user sets up a new second factor with the next login (if the role requires one)
+    match remove_second_factor(db_connection, user_id) {
+        Ok(true) => {}
+        Ok(false) => return Status::NotFound,
+        Err(error) => {
+            error!(
+                "Could not remove the second factor of the user with the id {}. The error was: {}",
+                user_id, error
+            );
+            return Status::InternalServerError;
+        }
+    }
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::SecondFactorRemoved,
+        Some(format!(
+            "The second factor of the user '{}' with the id {} was reset",
+            user.username, user_id
+        )),
+    )
+    .await;
+
+    Status::NoContent
+}
+
+#[derive(Serialize)]
+pub struct SigningKeyInformation {
+    /// The id of the key (the `kid` in the header of the tokens).
+    pub id: String,
+    /// When the key was created.
+    pub created_at: DateTime<Utc>,
+}
+
+#[options("/keys/rotate")]
+pub async fn rotate_key_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Post].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[post("/keys/rotate")]
+pub async fn rotate_signing_key(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Admin>,
+    config: &State<BackendConfiguration>,
+) -> Result<Json<SigningKeyInformation>, Status> {
+    use crate::keys::key_retention;
+    use crate::log_action_rocket;
+    use log::{error, info};
+
+    // the previous keys are kept, so the tokens they signed stay valid until they expire
+    let rotation = config.keys.write().map_err(|_| ()).and_then(|mut keys| {
+        let key = keys.rotate().map_err(|error| {
+            error!("Could not rotate the signing key. The error was: {}", error);
+        })?;
+        match keys.prune(key_retention(config.token_lifetime_in_seconds)) {
+            Ok(removed_keys) => {
+                for key_id in removed_keys {
+                    info!("Removed the retired key {}", key_id);
+                }
+            }
+            Err(error) => error!(
+                "Could not remove the retired keys. The error was: {}",
+                error
+            ),
+        }
+        Ok(key)
+    });
+    let key = match rotation {
+        Ok(key) => key,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::KeyRotated,
+        Some(format!("The new signing key is {}", key.id)),
+    )
+    .await;
+
+    Ok(Json(SigningKeyInformation {
+        id: key.id,
+        created_at: key.created_at,
+    }))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewPackageSelection {
+    /// The present of the day which should be assigned to the winner (or `None` to remove the
+    /// assignment).
+    present_id: Option<i32>,
+}
+
+#[put(
+    "/raffles/<raffle_id>/participants/<current_participant_id>",
+    data = "<new_package_selection>"
+)]
+pub async fn update_participant_values(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Operator>,
+    raffle_id: i32,
+    current_participant_id: i32,
+    new_package_selection: Json<NewPackageSelection>,
+) -> Status {
+    use crate::log_action_rocket;
+    use crate::presents::assign_present;
+    use log::error;
+
+    // the winners of archived raffles cannot be changed anymore
+    if let Err(status) = lookup_writable_raffle(db_connection_pool, raffle_id) {
+        return status;
+    }
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    // assign the present (the stock of the present is checked within the same transaction)
+    let assignment = match assign_present(
+        db_connection,
+        raffle_id,
+        current_participant_id,
+        new_package_selection.present_id,
+    ) {
+        Ok(assignment) => assignment,
+        Err(error) => {
+            error!(
+                "The user {} tried to assign the present {:?} to the participant with the id {} but {}",
+                authenticated_user.username, new_package_selection.present_id, current_participant_id, error
+            );
+            return present_error_status(&error);
+        }
+    };
+
+    // if we get here we successfully selected a package
+    let describe = |present: &Option<crate::models::Present>| match present {
+        Some(present) => format!(
+            "{} ({}, id {})",
+            present.identifier, present.name, present.id
+        ),
+        None => "none".to_string(),
+    };
+    if assignment.previous.is_none() {
+        log_action_rocket(
+            db_connection_pool,
+            authenticated_user.username.clone(),
+            Action::PackageSelected,
+            Some(format!(
+                "The participant with the id {} was assigned to package {}",
+                current_participant_id,
+                describe(&assignment.present),
+            )),
+        )
+        .await;
+    } else {
+        log_action_rocket(
+            db_connection_pool,
+            authenticated_user.username.clone(),
+            Action::PackageChanged,
+            Some(format!(
+                "The participant with the id {} was assigned a new package {}. The previous package was {}",
+                current_participant_id,
+                describe(&assignment.present),
+                describe(&assignment.previous)
+            )),
+        )
+        .await;
+    }
+    Status::NoContent
+}
+
+/// Get the status which is returned to the caller if the inventory could not be changed or a
+/// present could not be assigned.
+fn present_error_status(error: &crate::presents::PresentError) -> Status {
+    use crate::presents::PresentError;
+
+    match error {
+        PresentError::UnknownPresent | PresentError::UnknownParticipant => Status::NotFound,
+        PresentError::NotAWinner
+        | PresentError::OutOfStock
+        | PresentError::InUse
+        | PresentError::DuplicateIdentifier => Status::Conflict,
+        PresentError::WrongDay => Status::UnprocessableEntity,
+        PresentError::Database(_) => Status::InternalServerError,
+    }
+}
+
+#[get("/raffles/<raffle_id>/participants/won/<date_as_str>")]
+pub async fn get_won_participants_on_day_route(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    _authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
+    date_as_str: &str,
+) -> Result<Json<Vec<Participant>>, Status> {
+    use std::str::FromStr;
+
+    // if we cannot parse the input date, we received a bad parameter and we have to react to it
+    let maybe_date = NaiveDate::from_str(date_as_str);
+    if maybe_date.is_err() {
+        return Err(Status::BadRequest);
+    }
+
+    // ensure that the requested raffle exists at all
+    lookup_raffle(db_connection_pool, raffle_id)?;
+
+    // try to fetch the information and construct the corresponding data structure we want to return
+    let maybe_result =
+        get_won_participants_on_day(db_connection_pool, raffle_id, maybe_date.unwrap()).await;
+
+    // if we got a result, count the participants and return the amount
+    if let Ok(result) = maybe_result {
+        return Ok(Json(result));
+    }
+
+    // it seems that we could not gather the requested information
+    Err(Status::InternalServerError)
+}
+
+#[get("/raffles/<raffle_id>/participants/won/<date_as_str>/count")]
+pub async fn count_won_participants_on_day(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
+    date_as_str: &str,
+) -> Result<Json<usize>, Status> {
+    use log::debug;
+    use std::str::FromStr;
+
+    // if we cannot parse the input date, we received a bad parameter and we have to react to it
+    let maybe_date = NaiveDate::from_str(date_as_str);
+    if maybe_date.is_err() {
+        return Err(Status::BadRequest);
+    }
+
+    // ensure that the requested raffle exists at all
+    lookup_raffle(db_connection_pool, raffle_id)?;
+
+    // try to fetch the information and construct the corresponding data structure we want to return
+    let maybe_result =
+        get_won_participants_on_day(db_connection_pool, raffle_id, maybe_date.unwrap()).await;
+
+    // if we got a result, count the participants and return the amount
+    if let Ok(winners) = maybe_result {
+        debug!("The user {} queried the number of winners of the raffle {} for the {}. The answer is: {} participants won on that day so far", authenticated_user.username, raffle_id, date_as_str, winners.len());
+        return Ok(Json(winners.len()));
+    }
+
+    // it seems that we could not gather the requested information
+    Err(Status::InternalServerError)
+}
+
+/// The response to a request for picking winners.
+#[derive(rocket::Responder)]
+pub enum PickResponse {
+    /// The winners which were picked.
+    Picked(Json<Vec<Participant>>),
+    /// The report of a dry run, nobody was marked as won.
+    Preview(Json<crate::picking::PickPreview>),
+}
+
+/// Check if the `dry_run` parameter of a request asks for a dry run. Every value besides the ones
+/// which clearly turn it off (e.g. `false` or `0`) does, so a misspelled value can never perform
+/// the actual changes.
+fn is_dry_run(dry_run: Option<&str>) -> bool {
+    match dry_run {
+        Some(value) => !matches!(
+            value.trim().to_lowercase().as_str(),
+            "false" | "off" | "no" | "0"
+        ),
+        None => false,
+    }
+}
+
+/// Pick winners for a day. With `dry_run`, the report of [`preview_pick_of_random_participants`]
+/// is returned instead and nobody is marked as won.
+#[get("/raffles/<raffle_id>/participants/pick/<count>/for/<date>?<dry_run>")]
+pub async fn pick_multiple_random_participant_from_raffle_list(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: AuthenticatedUser,
+    raffle_id: i32,
+    count: usize,
     date: &str,
+    dry_run: Option<&str>,
+) -> Result<PickResponse, Status> {
+    use log::error;
+    use std::str::FromStr;
+
+    // if we cannot parse the input date, we received a bad parameter and we have to react to it
+    let picked_for_date = match NaiveDate::from_str(date) {
+        Ok(picked_for_date) if count > 0 => picked_for_date,
+        _ => return Err(Status::BadRequest),
+    };
+
+    // every user can check how a pick would turn out, but just operators can pick the winners
+    if is_dry_run(dry_run) {
+        return preview_pick_of_random_participants(
+            db_connection_pool,
+            &authenticated_user.username,
+            raffle_id,
+            picked_for_date,
+            count,
+        )
+        .map(PickResponse::Preview);
+    }
+    if authenticated_user.role < Role::Operator {
+        error!(
+            "The user {} with the role {} tried to pick winners in the raffle {}",
+            authenticated_user.username, authenticated_user.role, raffle_id
+        );
+        return Err(Status::Forbidden);
+    }
+
+    // winners can just be picked for raffles which are not archived
+    let raffle = lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    pick_and_log_winners(
+        db_connection_pool,
+        &authenticated_user.username,
+        &raffle,
+        picked_for_date,
+        count,
+        None,
+    )
+    .await
+    .map(PickResponse::Picked)
+}
+
+/// Check how picking winners would turn out without marking anyone as won. The report contains the
+/// number of eligible participants and all reasons why the pick would be refused.
+fn preview_pick_of_random_participants(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    username: &str,
+    raffle_id: i32,
+    picked_for_date: NaiveDate,
+    count: usize,
+) -> Result<Json<crate::picking::PickPreview>, Status> {
+    use crate::picking::preview_pick;
+    use log::{debug, error};
+
+    let raffle = lookup_raffle(db_connection_pool, raffle_id)?;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    match preview_pick(db_connection, &raffle, picked_for_date, count) {
+        Ok(preview) => {
+            debug!(
+                "The user {} previewed picking {} winners for the {} in the raffle {}: {:?}",
+                username, count, picked_for_date, raffle_id, preview.problems
+            );
+            Ok(Json(preview))
+        }
+        Err(error) => {
+            error!(
+                "Could not preview picking {} winners for the {} in the raffle {}. The error was: {}",
+                count, picked_for_date, raffle_id, error
+            );
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+/// Pick the winners (with the seed of a published commitment if `commitment_id` is set), log them
+/// and convert them into the response of the routes which pick winners.
+async fn pick_and_log_winners(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    username: &str,
+    raffle: &Raffle,
+    picked_for_date: NaiveDate,
+    count: usize,
+    commitment_id: Option<i32>,
+) -> Result<Json<Vec<Participant>>, Status> {
+    use crate::log_action_rocket;
+    use crate::picking::pick_winners;
+    use log::{debug, error};
+
+    // select the winners and mark them as won in a single step, so parallel picks cannot interfere
+    let picking_raffle = raffle.clone();
+    let picking_user = username.to_string();
+    let drawn_winners = match run_picking(db_connection_pool, move |db_connection| {
+        pick_winners(
+            db_connection,
+            &picking_raffle,
+            picked_for_date,
+            count,
+            &picking_user,
+            commitment_id,
+        )
+    })
+    .await?
+    {
+        Ok(winners) => winners,
+        Err(error) => {
+            error!(
+                "The user {} tried to pick {} winners for the {} in the raffle {} but {}",
+                username, count, picked_for_date, raffle.id, error
+            );
+            return Err(pick_error_status(&error));
+        }
+    };
+
+    // log the picked winners and return them
+    let draw_id = drawn_winners.draw.id;
+    let won_participant_ids: Vec<i32> = drawn_winners
+        .winners
+        .iter()
+        .map(|winner| winner.participant.id)
+        .collect();
+    for current_participant_id in won_participant_ids.clone() {
+        log_action_rocket(
+            db_connection_pool,
+            username.to_string(),
+            Action::PickedWinner,
+            Some(format!(
+                "The participant with the id {} was marked as won in the raffle {} by the draw {}",
+                current_participant_id, raffle.id, draw_id
+            )),
+        )
+        .await;
+    }
+    debug!(
+        "The user {} picked the participants with the ids {:?} as new winners with the draw {}",
+        username, won_participant_ids, draw_id
+    );
+    Ok(Json(
+        drawn_winners
+            .winners
+            .iter()
+            .map(|winner| Participant {
+                id: winner.participant.id,
+                first_name: winner.participant.first_name.clone(),
+                last_name: winner.participant.last_name.clone(),
+                present_identifier: winner
+                    .present
+                    .as_ref()
+                    .map(|present| present.identifier.clone()),
+                present_id: winner.win.present_id,
+                draw_id: winner.win.draw_id,
+            })
+            .collect(),
+    ))
+}
+
+/// Run an operation which picks winners (or commits to a draw) on a thread which may block, since
+/// it waits for the picking lock of the raffle. The outer error is the status which is returned to
+/// the caller if the operation could not be run at all.
+async fn run_picking<T, F>(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    operation: F,
+) -> Result<Result<T, crate::picking::PickError>, Status>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut diesel::PgConnection) -> Result<T, crate::picking::PickError> + Send + 'static,
+{
+    use log::error;
+
+    let db_connection_pool = db_connection_pool.inner().clone();
+    let result = rocket::tokio::task::spawn_blocking(move || {
+        // get a connection to the database for dealing with the request
+        let db_connection = &mut match db_connection_pool.get() {
+            Ok(connection) => connection,
+            Err(error) => {
+                error!(
+                    "Could not get a connection from the database connection pool. The error was: {}",
+                    error
+                );
+                return Err(Status::InternalServerError);
+            }
+        };
+        Ok(operation(db_connection))
+    })
+    .await;
+    match result {
+        Ok(result) => result,
+        Err(error) => {
+            error!("Picking the winners failed. The error was: {}", error);
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+/// Get the status which is returned to the caller if picking winners (or committing to a draw)
+/// failed.
+fn pick_error_status(error: &crate::picking::PickError) -> Status {
+    use crate::picking::PickError;
+
+    match error {
+        PickError::Refused(PickRefusal::QuotaExceeded { .. }) => Status::Conflict,
+        PickError::Refused(_) => Status::UnprocessableEntity,
+        // if there are not enough winners left, it seems that all participants where picked at
+        // some point. Return NOT FOUND to indicate that
+        PickError::NotEnoughParticipants
+        | PickError::UnknownCommitment
+        | PickError::UnknownWinner => Status::NotFound,
+        PickError::CommitmentMismatch
+        | PickError::CandidatesChanged
+        | PickError::OpenCommitment => Status::Conflict,
+        PickError::Busy => Status::ServiceUnavailable,
+        PickError::UnknownUser | PickError::NoRandomness | PickError::Database(_) => {
+            Status::InternalServerError
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DrawDetails {
+    /// The internally used id for the draw.
+    pub id: i32,
+    /// The raffle for which the winners were drawn.
+    pub raffle_id: i32,
+    /// The day for which the winners were drawn.
+    pub drawn_for: NaiveDate,
+    /// The time at which the winners were drawn.
+    pub drawn_at: NaiveDateTime,
+    /// The version of the algorithm which was used for the draw.
+    pub algorithm_version: i32,
+    /// The seed of the random number generator (as hex string).
+    pub seed: String,
+    /// The ids of all participants who could have been drawn (in the order used for the draw).
+    pub candidate_ids: Vec<i32>,
+    /// The SHA-256 hash of the ordered candidates which was recorded with the draw.
+    pub candidates_hash: String,
+    /// The ids of the drawn winners in the order in which they were drawn.
+    pub winner_ids: Vec<i32>,
+    /// The weights of the candidates (not recorded for draws of the first algorithm version).
+    pub candidate_weights: Option<Vec<i32>>,
+    /// The indexes of the departments of the candidates whose number of winners was limited.
+    pub candidate_groups: Option<Vec<Option<i32>>>,
+    /// The number of winners which could be drawn per department.
+    pub group_capacities: Option<Vec<i32>>,
+    /// The eligibility rules which were applied when selecting the candidates.
+    pub rules: Option<DrawRules>,
+}
+
+impl From<&crate::models::Draw> for DrawDetails {
+    fn from(draw: &crate::models::Draw) -> Self {
+        use crate::draw::to_hex;
+
+        DrawDetails {
+            id: draw.id,
+            raffle_id: draw.raffle_id,
+            drawn_for: draw.drawn_for,
+            drawn_at: draw.drawn_at,
+            algorithm_version: draw.algorithm_version,
+            seed: to_hex(&draw.seed),
+            candidate_ids: draw.candidate_ids.clone(),
+            candidates_hash: draw.candidates_hash.clone(),
+            winner_ids: draw.winner_ids.clone(),
+            candidate_weights: draw.candidate_weights.clone(),
+            candidate_groups: draw.candidate_groups.clone(),
+            group_capacities: draw.group_capacities.clone(),
+            rules: draw
+                .rules
+                .as_deref()
+                .and_then(|rules| rocket::serde::json::from_str(rules).ok()),
+        }
+    }
+}
+
+/// Replay a recorded draw with its recorded seed, weighted candidates and limits per department.
+/// Draws without recorded weights (first algorithm version) are replayed with equal weights.
+fn replay_draw(draw: &crate::models::Draw) -> DrawVerification {
+    use crate::draw::{verify_draw, Candidate};
+
+    let candidates = draw
+        .candidate_ids
+        .iter()
+        .enumerate()
+        .map(|(index, candidate_id)| Candidate {
+            id: *candidate_id,
+            weight: draw
+                .candidate_weights
+                .as_ref()
+                .and_then(|weights| weights.get(index))
+                .map_or(1, |weight| u32::try_from(*weight).unwrap_or(0)),
+            group: draw
+                .candidate_groups
+                .as_ref()
+                .and_then(|groups| groups.get(index).copied().flatten())
+                .and_then(|group| usize::try_from(group).ok()),
+        })
+        .collect::<Vec<Candidate>>();
+    let group_capacities = draw
+        .group_capacities
+        .iter()
+        .flatten()
+        .map(|capacity| usize::try_from(*capacity).unwrap_or(0))
+        .collect::<Vec<usize>>();
+    verify_draw(
+        draw.algorithm_version,
+        &draw.seed,
+        &candidates,
+        &group_capacities,
+        &draw.candidates_hash,
+        &draw.winner_ids,
+    )
+}
+
+#[derive(Serialize)]
+pub struct DrawVerificationReport {
+    /// The recorded draw which was replayed.
+    pub draw: DrawDetails,
+    /// The result of replaying the draw.
+    pub verification: DrawVerification,
+}
+
+/// Look up a recorded draw. If the draw does not exist (or cannot be looked up), the status which
+/// should be returned to the caller is returned as an error.
+fn lookup_draw(
+    db_connection_pool: &AdventskalenderDatabaseConnection,
+    draw_id: i32,
+) -> Result<crate::models::Draw, Status> {
+    use crate::models::Draw;
+    use crate::schema::draws::dsl::draws;
+    use diesel::{OptionalExtension, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    match draws.find(draw_id).first::<Draw>(db_connection).optional() {
+        Ok(Some(draw)) => Ok(draw),
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            error!(
+                "Could not look up the draw with the id {}. The error was: {}",
+                draw_id, error
+            );
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+#[options("/draws/<_draw_id>")]
+pub async fn draw_options<'r, 'o: 'r>(_draw_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[get("/draws/<draw_id>")]
+pub async fn get_draw(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    _authenticated_user: AuthenticatedUser,
+    draw_id: i32,
+) -> Result<Json<DrawDetails>, Status> {
+    let draw = lookup_draw(db_connection_pool, draw_id)?;
+    Ok(Json(DrawDetails::from(&draw)))
+}
+
+#[options("/draws/<_draw_id>/verify")]
+pub async fn verify_draw_options<'r, 'o: 'r>(_draw_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[get("/draws/<draw_id>/verify")]
+pub async fn verify_recorded_draw(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: AuthenticatedUser,
+    draw_id: i32,
+) -> Result<Json<DrawVerificationReport>, Status> {
+    use log::{debug, error};
+
+    let draw = lookup_draw(db_connection_pool, draw_id)?;
+
+    // replay the draw with the recorded seed and candidates
+    let verification = replay_draw(&draw);
+    if verification.verified {
+        debug!(
+            "The user {} verified the draw {} successfully",
+            authenticated_user.username, draw_id
+        );
+    } else {
+        error!(
+            "The user {} tried to verify the draw {} but the replayed winners {:?} do not match the recorded winners {:?}",
+            authenticated_user.username, draw_id, verification.replayed_winner_ids, draw.winner_ids
+        );
+    }
+
+    Ok(Json(DrawVerificationReport {
+        draw: DrawDetails::from(&draw),
+        verification,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct CommitmentRequest {
+    /// The day for which the winners will be drawn.
+    pub date: NaiveDate,
+    /// The number of winners which will be drawn.
+    pub count: usize,
+}
+
+#[derive(Serialize)]
+pub struct CommitmentDetails {
+    /// The internally used id for the commitment.
+    pub id: i32,
+    /// The raffle for which the winners will be drawn.
+    pub raffle_id: i32,
+    /// The day for which the winners will be drawn.
+    pub drawn_for: NaiveDate,
+    /// The number of winners which will be drawn.
+    pub winner_count: i32,
+    /// The SHA-256 hash of the seed which will be used for the draw.
+    pub seed_hash: String,
+    /// The SHA-256 hash of the ordered candidate ids at the time of the commitment.
+    pub candidates_hash: String,
+    /// The time at which the commitment was published.
+    pub committed_at: NaiveDateTime,
+    /// The time at which the seed was revealed by performing the draw.
+    pub revealed_at: Option<NaiveDateTime>,
+    /// The seed of the draw (as hex string). It is just included after it was revealed.
+    pub seed: Option<String>,
+    /// A flag which indicates that the revealed draw used the committed seed and candidates.
+    pub draw_matches_commitment: Option<bool>,
+    /// The revealed draw and the result of replaying it.
+    pub draw: Option<DrawVerificationReport>,
+    /// All commitments which were published for the same raffle and day (including this one), so
+    /// everybody can see if a day was committed to more than once.
+    pub commitments_for_day: Option<Vec<PublishedCommitment>>,
+}
+
+/// The public information about one of the commitments of a day.
+#[derive(Serialize)]
+pub struct PublishedCommitment {
+    /// The internally used id for the commitment.
+    pub id: i32,
+    /// The number of winners which will be drawn.
+    pub winner_count: i32,
+    /// The SHA-256 hash of the seed which will be used for the draw.
+    pub seed_hash: String,
+    /// The time at which the commitment was published.
+    pub committed_at: NaiveDateTime,
+    /// The time at which the seed was revealed by performing the draw.
+    pub revealed_at: Option<NaiveDateTime>,
+    /// The draw which revealed the commitment.
+    pub draw_id: Option<i32>,
+}
+
+impl From<&crate::models::DrawCommitment> for CommitmentDetails {
+    fn from(commitment: &crate::models::DrawCommitment) -> Self {
+        CommitmentDetails {
+            id: commitment.id,
+            raffle_id: commitment.raffle_id,
+            drawn_for: commitment.drawn_for,
+            winner_count: commitment.winner_count,
+            seed_hash: commitment.seed_hash.clone(),
+            candidates_hash: commitment.candidates_hash.clone(),
+            committed_at: commitment.committed_at,
+            revealed_at: commitment.revealed_at,
+            seed: None,
+            draw_matches_commitment: None,
+            draw: None,
+            commitments_for_day: None,
+        }
+    }
+}
+
+/// Look up a commitment to a draw. If the commitment does not exist (or cannot be looked up), the
+/// status which should be returned to the caller is returned as an error.
+fn lookup_commitment(
+    db_connection_pool: &AdventskalenderDatabaseConnection,
+    commitment_id: i32,
+) -> Result<crate::models::DrawCommitment, Status> {
+    use crate::models::DrawCommitment;
+    use crate::schema::draw_commitments::dsl::draw_commitments;
+    use diesel::{OptionalExtension, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    match draw_commitments
+        .find(commitment_id)
+        .first::<DrawCommitment>(db_connection)
+        .optional()
+    {
+        Ok(Some(commitment)) => Ok(commitment),
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            error!(
+                "Could not look up the commitment with the id {}. The error was: {}",
+                commitment_id, error
+            );
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+/// Look up all commitments of a raffle for the supplied day (ordered by the time they were
+/// published).
+fn lookup_commitments_of_day(
+    db_connection_pool: &AdventskalenderDatabaseConnection,
+    raffle_id: i32,
+    date: NaiveDate,
+) -> Result<Vec<crate::models::DrawCommitment>, Status> {
+    use crate::models::DrawCommitment;
+    use crate::schema::draw_commitments::dsl::{
+        draw_commitments, drawn_for, id, raffle_id as commitment_raffle_id,
+    };
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    draw_commitments
+        .filter(commitment_raffle_id.eq(raffle_id))
+        .filter(drawn_for.eq(date))
+        .order_by(id.asc())
+        .load::<DrawCommitment>(db_connection)
+        .map_err(|error| {
+            error!(
+                "Could not look up the commitments of the {} in the raffle {}. The error was: {}",
+                date, raffle_id, error
+            );
+            Status::InternalServerError
+        })
+}
+
+#[options("/raffles/<_raffle_id>/commitments")]
+pub async fn commitments_options<'r, 'o: 'r>(_raffle_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Post].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[post("/raffles/<raffle_id>/commitments", data = "<commitment_request>")]
+pub async fn commit_to_draw(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Operator>,
+    raffle_id: i32,
+    commitment_request: Json<CommitmentRequest>,
+) -> Result<Created<Json<CommitmentDetails>>, Status> {
+    use crate::log_action_rocket;
+    use crate::picking::commit_draw;
+    use log::error;
+
+    if commitment_request.count == 0 {
+        return Err(Status::BadRequest);
+    }
+
+    // winners can just be picked for raffles which are not archived
+    let raffle = lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    let username = authenticated_user.username.clone();
+    let (date, count) = (commitment_request.date, commitment_request.count);
+    let commitment = match run_picking(db_connection_pool, move |db_connection| {
+        commit_draw(db_connection, &raffle, date, count, &username)
+    })
+    .await?
+    {
+        Ok(commitment) => commitment,
+        Err(error) => {
+            error!(
+                "The user {} tried to commit to a draw of {} winners for the {} in the raffle {} but {}",
+                authenticated_user.username, commitment_request.count, commitment_request.date, raffle_id, error
+            );
+            return Err(pick_error_status(&error));
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::DrawCommitted,
+        Some(format!(
+            "The commitment {} to a draw of {} winners for the {} in the raffle {} was published with the seed hash {}",
+            commitment.id, commitment.winner_count, commitment.drawn_for, raffle_id, commitment.seed_hash
+        )),
+    )
+    .await;
+
+    Ok(Created::new(format!("/v1/commitments/{}", commitment.id))
+        .body(Json(CommitmentDetails::from(&commitment))))
+}
+
+#[options("/raffles/<_raffle_id>/commitments/<_commitment_id>/reveal")]
+pub async fn reveal_commitment_options<'r, 'o: 'r>(
+    _raffle_id: i32,
+    _commitment_id: i32,
+) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Post].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[post("/raffles/<raffle_id>/commitments/<commitment_id>/reveal")]
+pub async fn reveal_commitment(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: RequireRole<Operator>,
+    raffle_id: i32,
+    commitment_id: i32,
 ) -> Result<Json<Vec<Participant>>, Status> {
+    // the commitment defines the day and the number of winners of the draw
+    let commitment = lookup_commitment(db_connection_pool, commitment_id)?;
+    if commitment.raffle_id != raffle_id {
+        return Err(Status::NotFound);
+    }
+
+    // winners can just be picked for raffles which are not archived
+    let raffle = lookup_writable_raffle(db_connection_pool, raffle_id)?;
+
+    pick_and_log_winners(
+        db_connection_pool,
+        &authenticated_user.username,
+        &raffle,
+        commitment.drawn_for,
+        commitment.winner_count as usize,
+        Some(commitment_id),
+    )
+    .await
+}
+
+#[options("/commitments/<_commitment_id>")]
+pub async fn public_commitment_options<'r, 'o: 'r>(_commitment_id: i32) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+/// The public (unauthenticated) view of a commitment. Before the draw it just contains the hashes
+/// which were published, afterwards it also contains the revealed seed and the replayed draw.
+#[get("/commitments/<commitment_id>")]
+pub async fn get_public_commitment(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    commitment_id: i32,
+) -> Result<Json<CommitmentDetails>, Status> {
+    use crate::draw::{seed_hash, to_hex};
+
+    let commitment = lookup_commitment(db_connection_pool, commitment_id)?;
+    let mut details = CommitmentDetails::from(&commitment);
+    details.commitments_for_day = Some(
+        lookup_commitments_of_day(
+            db_connection_pool,
+            commitment.raffle_id,
+            commitment.drawn_for,
+        )?
+        .iter()
+        .map(|commitment| PublishedCommitment {
+            id: commitment.id,
+            winner_count: commitment.winner_count,
+            seed_hash: commitment.seed_hash.clone(),
+            committed_at: commitment.committed_at,
+            revealed_at: commitment.revealed_at,
+            draw_id: commitment.draw_id,
+        })
+        .collect(),
+    );
+
+    // the seed (and the draw) are just public after the commitment was revealed
+    if let Some(draw_id) = commitment.draw_id {
+        let draw = lookup_draw(db_connection_pool, draw_id)?;
+        details.seed = Some(to_hex(&draw.seed));
+        details.draw_matches_commitment = Some(
+            seed_hash(&draw.seed) == commitment.seed_hash
+                && draw.candidates_hash == commitment.candidates_hash,
+        );
+        details.draw = Some(DrawVerificationReport {
+            verification: replay_draw(&draw),
+            draw: DrawDetails::from(&draw),
+        });
+    }
+
+    Ok(Json(details))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PickingInformation {
+    /// The participant who was picked as a winner
+    participant_id: i32,
+    /// The date for which the winner was picked
+    picked_for_date: NaiveDate,
+}
+
+pub async fn mark_participant_as_not_won(
+    db_connection_pool: &AdventskalenderDatabaseConnection,
+    raffle_id: i32,
+    participant_id: i32,
+    user_who_unpicked: String,
+) -> Result<(), ()> {
+    use crate::models::{WinRevocation, WinStatus};
+    use crate::schema::participants::dsl::{id, participants, raffle_id as participant_raffle_id};
+    use crate::schema::wins::dsl::{participant_id as win_participant_id, status, wins};
+    use chrono::Utc;
+    use diesel::{update, ExpressionMethods, QueryDsl, RunQueryDsl};
+    use log::{debug, error};
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(());
+        }
+    };
+
+    let maybe_result = db_connection
+        .build_transaction()
+        .read_write()
+        .run::<_, diesel::result::Error, _>(move |connection| {
+            // the win is kept as history, it is just marked as revoked
+            let participant_info = WinRevocation {
+                status: WinStatus::Revoked.as_str().to_string(),
+                revoked_at: Some(Utc::now().naive_utc()),
+            };
+
+            // do the actual update of the database
+            match update(
+                wins.filter(win_participant_id.eq(participant_id))
+                    .filter(status.eq(WinStatus::Active.as_str()))
+                    .filter(win_participant_id.eq_any(
+                        participants
+                            .filter(participant_raffle_id.eq(raffle_id))
+                            .select(id),
+                    )),
+            )
+            .set(&participant_info)
+                .execute(connection)
+            {
+                Ok(rows_updated) =>
+
+                    {
+                        // ensure that the expected row was updated, if we did not exactly update one row, something went wrong
+                        if rows_updated != 1 {
+                            error!("There should be 1 row updates but {} rows were actually updated. The following ID should not be marked as NOT won: {:?}", rows_updated, participant_id);
+                            return Err(diesel::result::Error::NotFound); // TODO: not really the correct error code
+                        }
+
+                        debug!("The user {} marked the user with the id {} as NOT won", user_who_unpicked, participant_id);
+                        Ok(())
+                    }
+                Err(error) => {
+                    error!("The user {} tried to mark the user with the id {} as NOT won but we failed to do so. The error was: {}", user_who_unpicked, participant_id, error);
+                    Err(error)
+                }
+            }
+        });
+
+    //
+    if maybe_result.is_err() {
+        return Err(());
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LoginInformation {
+    /// The username of the user.
+    username: String,
+    /// The password for the login request.
+    password: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenResponse {
+    /// The access token to use for API requests.
+    access_token: String,
+}
+
+/// The second step of a login whose password was accepted.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecondFactorChallenge {
+    /// The id of the login which has to be supplied together with the second factor.
+    challenge: String,
+    /// Whether the user has to set up a second factor before the login can be finished.
+    enrollment_required: bool,
+}
+
+/// The response to a login with the password.
+#[derive(rocket::Responder)]
+pub enum LoginResponse {
+    /// The user is logged in and the tokens are stored in the cookies.
+    LoggedIn(NoContent),
+    /// The password was accepted, but the user has to supply a second factor.
+    SecondFactorRequired(Custom<Json<SecondFactorChallenge>>),
+}
+
+#[options("/auth/token")]
+pub async fn get_login_token_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Post].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[post("/auth/token", data = "<login_information>")]
+pub async fn get_login_token(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    login_information: Json<LoginInformation>,
+    config: &State<BackendConfiguration>,
+    remote_addr: Option<std::net::SocketAddr>,
+    user_agent: UserAgent,
+    cookies: &CookieJar<'_>,
+) -> Result<LoginResponse, Status> {
+    use crate::log_action_rocket;
+    use crate::rate_limiter::{is_rate_limited, reset_rate_limit};
+    use crate::schema::users::dsl::{username, users};
+    use bcrypt::verify;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use log::{error, warn};
+
+    let rate_limit_config = &config.login_rate_limit;
+
+    if let Some(addr) = remote_addr {
+        let ip_key = format!("ip:{}", addr.ip());
+        if is_rate_limited(&ip_key, rate_limit_config) {
+            warn!("Rate limit exceeded for IP: {}", addr.ip());
+            return Err(Status::TooManyRequests);
+        }
+    }
+
+    let username_key = format!("user:{}", login_information.username);
+    if is_rate_limited(&username_key, rate_limit_config) {
+        warn!(
+            "Rate limit exceeded for username: {}",
+            login_information.username
+        );
+        return Err(Status::TooManyRequests);
+    }
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // try to get the user record for the supplied username
+    let supplied_username = login_information.username.clone();
+    let maybe_user_result = db_connection
+        .build_transaction()
+        .read_only()
+        .run::<_, diesel::result::Error, _>(move |connection| {
+            if let Ok(found_users) = users
+                .filter(username.eq(supplied_username))
+                .load::<User>(connection)
+            {
+                // if we did not get exactly one user, return an 'error'
+                if found_users.len() != 1 {
+                    return Err(diesel::result::Error::NotFound);
+                }
+
+                // return the found user
+                return Ok(found_users[0].clone());
+            }
+
+            //
+            Err(diesel::result::Error::NotFound) // TODO: not the real error
+        });
+
+    // try to get the actual user object or delay a bit and then return with the corresponding error
+    let user = match maybe_user_result {
+        Ok(user) => user,
+        Err(_) => {
+            // ensure that we know what happened
+            error!(
+                "Could not get the user record for '{}'",
+                login_information.username
+            );
+
+            // just slow down the process to prevent easy checking if a username exists or not
+            let _ = verify(
+                "some_password",
+                "$2y$12$7xMzqvnHyizkumZYpIRXheGMAqDKVo8HKtpmQSn51JUfY0N2VN4ua",
+            );
+
+            // log the failed attempt (use anonymous to avoid username enumeration)
+            log_action_rocket(
+                db_connection_pool,
+                "anonymous".to_string(),
+                Action::FailedLogin,
+                Some(format!(
+                    "Failed login attempt - invalid credentials from IP: {}",
+                    remote_addr
+                        .map(|addr| addr.ip().to_string())
+                        .unwrap_or("unknown".to_string())
+                )),
+            )
+            .await;
+
+            // finally we can tell teh user that he/she is not authorized
+            return Err(Status::Unauthorized);
+        }
+    };
+
+    // users which were created for the login through the OpenID Connect provider have no password
+    if user.password_hash.is_empty() {
+        log_action_rocket(
+            db_connection_pool,
+            user.username.clone(),
+            Action::FailedLogin,
+            Some(format!(
+                "Failed login attempt - the user has no password and has to log in through the OpenID Connect provider, from IP: {}",
+                remote_addr
+                    .map(|addr| addr.ip().to_string())
+                    .unwrap_or("unknown".to_string())
+            )),
+        )
+        .await;
+        return Err(Status::Unauthorized);
+    }
+
+    // check if the supplied password matches the one we stored in the database using the same bcrypt
+    // parameters
+    match verify(&login_information.password, user.password_hash.as_str()) {
+        Ok(is_password_correct) => {
+            if !is_password_correct {
+                log_action_rocket(
+                    db_connection_pool,
+                    user.username.clone(),
+                    Action::FailedLogin,
+                    Some(format!(
+                        "Failed login attempt - invalid credentials from IP: {}",
+                        remote_addr
+                            .map(|addr| addr.ip().to_string())
+                            .unwrap_or("unknown".to_string())
+                    )),
+                )
+                .await;
+                return Err(Status::Unauthorized);
+            }
+        }
+        Err(error) => {
+            error!("Could not verify the supplied password with the one stored in the database. The error was: {}", error);
+            return Err(Status::InternalServerError);
+        }
+    }
+
+    // disabled users must not get a new token (even with the correct password)
+    if user.disabled {
+        log_action_rocket(
+            db_connection_pool,
+            user.username.clone(),
+            Action::FailedLogin,
+            Some(format!(
+                "Failed login attempt - the user is disabled, from IP: {}",
+                remote_addr
+                    .map(|addr| addr.ip().to_string())
+                    .unwrap_or("unknown".to_string())
+            )),
+        )
+        .await;
+        return Err(Status::Unauthorized);
+    }
+
+    // users with a second factor (or whose role requires one) have to supply a TOTP code before
+    // they get their tokens
+    if let Some(challenge) = second_factor_challenge(db_connection, config, &user)? {
+        return Ok(LoginResponse::SecondFactorRequired(Custom(
+            Status::Accepted,
+            Json(challenge),
+        )));
+    }
+
+    // if we get here, we ensured that the user is known and that the supplied password was valid,
+    // so we start a new session with a refresh token which is used until the user logs out
+    start_login_session(
+        db_connection,
+        config,
+        &user,
+        user_agent.0.as_deref(),
+        remote_addr.map(|addr| addr.ip().to_string()),
+        cookies,
+    )?;
+
+    if let Some(addr) = remote_addr {
+        reset_rate_limit(&format!("ip:{}", addr.ip()));
+    }
+    reset_rate_limit(&format!("user:{}", login_information.username));
+
+    log_action_rocket(
+        db_connection_pool,
+        login_information.username.clone(),
+        Action::SuccessfulLogin,
+        Some(format!(
+            "Successfully logged in user '{}' with a token",
+            login_information.username
+        )),
+    )
+    .await;
+
+    Ok(LoginResponse::LoggedIn(NoContent))
+}
+
+/// Start the second step of the login if the user has a second factor (or the role of the user
+/// requires one). `None` is returned if the user can be logged in right away.
+fn second_factor_challenge(
+    db_connection: &mut diesel::PgConnection,
+    config: &BackendConfiguration,
+    user: &User,
+) -> Result<Option<SecondFactorChallenge>, Status> {
+    use crate::two_factor::{create_challenge, has_confirmed_second_factor};
+    use log::error;
+
+    let Some(totp) = &config.totp else {
+        return Ok(None);
+    };
+    let has_second_factor = match has_confirmed_second_factor(db_connection, user.id) {
+        Ok(has_second_factor) => has_second_factor,
+        Err(error) => {
+            error!(
+                "Could not look up the second factor of '{}'. The error was: {}",
+                user.username, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+    let is_required = totp.required_roles.contains(&Role::from_name(&user.role));
+    if !has_second_factor && !is_required {
+        return Ok(None);
+    }
+    let challenge = match create_challenge(user.id, &user.username) {
+        Ok(challenge) => challenge,
+        Err(_) => {
+            error!(
+                "Could not generate the challenge for the second factor of '{}'",
+                user.username
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+    Ok(Some(SecondFactorChallenge {
+        challenge,
+        enrollment_required: !has_second_factor,
+    }))
+}
+
+/// Start a new session for the login of the user and store its access token and its first refresh
+/// token in the cookies.
+fn start_login_session(
+    db_connection: &mut diesel::PgConnection,
+    config: &BackendConfiguration,
+    user: &User,
+    user_agent: Option<&str>,
+    ip_address: Option<String>,
+    cookies: &CookieJar<'_>,
+) -> Result<(), Status> {
+    use crate::get_token_for_user;
+    use crate::refresh_tokens::issue_refresh_token;
+    use crate::sessions::create_session;
+    use log::error;
+
+    let session_and_refresh_token = create_session(
+        db_connection,
+        user.id,
+        user_agent,
+        ip_address,
+        config.refresh_token_lifetime_in_seconds,
+    )
+    .and_then(|session_id| {
+        issue_refresh_token(
+            db_connection,
+            user.id,
+            &session_id,
+            config.refresh_token_lifetime_in_seconds,
+        )
+        .map(|refresh_token| (session_id, refresh_token))
+    });
+    let (session_id, refresh_token) = match session_and_refresh_token {
+        Ok(session_and_refresh_token) => session_and_refresh_token,
+        Err(error) => {
+            error!(
+                "Could not store the session of '{}'. The error was: {}",
+                user.username, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // generate a new access token for the session with the active key and return it to the
+    // calling party
+    let signing_key = match config.keys.read().ok().and_then(|keys| keys.active_key()) {
+        Some(signing_key) => signing_key,
+        None => {
+            error!("There is no key available for signing the tokens");
+            return Err(Status::InternalServerError);
+        }
+    };
+    match get_token_for_user(
+        &user.username,
+        Role::from_name(&user.role),
+        &session_id,
+        config.token_audience.clone(),
+        config.api_host.clone(),
+        config.token_lifetime_in_seconds,
+        &signing_key,
+    ) {
+        Some(token) => {
+            add_token_cookies(cookies, config, token, refresh_token);
+            Ok(())
+        }
+        // it seems that we failed to generate a valid token. This should never happen, something
+        // seems REALLY wrong
+        None => Err(Status::InternalServerError),
+    }
+}
+
+/// The name of the cookie with the access token.
+const ACCESS_TOKEN_COOKIE: &str = "auth_token";
+
+/// The name of the cookie with the refresh token.
+const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
+
+/// Store the access token and the refresh token in httpOnly cookies which expire together with the
+/// tokens.
+fn add_token_cookies(
+    cookies: &CookieJar<'_>,
+    config: &BackendConfiguration,
+    access_token: String,
+    refresh_token: String,
+) {
+    for (name, value, lifetime_in_seconds) in [
+        (
+            ACCESS_TOKEN_COOKIE,
+            access_token,
+            config.token_lifetime_in_seconds,
+        ),
+        (
+            REFRESH_TOKEN_COOKIE,
+            refresh_token,
+            config.refresh_token_lifetime_in_seconds,
+        ),
+    ] {
+        let mut cookie = Cookie::new(name, value);
+        cookie.set_http_only(true);
+        cookie.set_same_site(SameSite::Strict);
+        cookie.set_path("/");
+
+        // only set secure-flag if we're using HTTPS (check API host)
+        if config.api_host.starts_with("https://") {
+            cookie.set_secure(true);
+        }
+
+        // set expiration to match the expiration of the token
+        let expiration =
+            time::OffsetDateTime::now_utc() + time::Duration::seconds(lifetime_in_seconds as i64);
+        cookie.set_expires(expiration);
+
+        cookies.add(cookie);
+    }
+}
+
+fn remove_token_cookies(cookies: &CookieJar<'_>) {
+    cookies.remove(Cookie::from(ACCESS_TOKEN_COOKIE));
+    cookies.remove(Cookie::from(REFRESH_TOKEN_COOKIE));
+}
+
+/// The name of the cookie which binds a login through the OpenID Connect provider to the browser
+/// it was started in.
+const EXTERNAL_LOGIN_STATE_COOKIE: &str = "oidc_state";
+
+/// Get the URL the OpenID Connect provider sends the user back to after the login.
+fn external_login_callback_url(config: &BackendConfiguration) -> String {
+    use crate::discovery::API_BASE_PATH;
+    use rocket::uri;
+
+    format!(
+        "{}{}{}",
+        config.api_host.trim_end_matches('/'),
+        API_BASE_PATH,
+        uri!(finish_external_login(_, _, _))
+    )
+}
+
+#[get("/auth/oidc/login")]
+pub async fn start_external_login(
+    config: &State<BackendConfiguration>,
+    cookies: &CookieJar<'_>,
+) -> Result<Redirect, Status> {
+    use crate::oidc::{discover_provider, start_login};
+    use log::error;
+
+    let settings = match &config.external_login {
+        Some(settings) => settings.clone(),
+        None => return Err(Status::NotFound),
+    };
+
+    // the discovery document is read for each login, so changes of the provider are picked up
+    let callback_url = external_login_callback_url(config);
+    let login = rocket::tokio::task::spawn_blocking(move || {
+        let provider = discover_provider(&settings.issuer)?;
+        start_login(&settings, provider, &callback_url)
+    })
+    .await;
+    let (state, authorization_url) = match login {
+        Ok(Ok(login)) => login,
+        Ok(Err(error)) => {
+            error!(
+                "Could not start the login through the OpenID Connect provider. The error was: {}",
+                error
+            );
+            return Err(Status::BadGateway);
+        }
+        Err(error) => {
+            error!(
+                "Could not start the login through the OpenID Connect provider. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // the provider redirects back from another site, so the cookie must not be strict
+    let mut cookie = Cookie::new(EXTERNAL_LOGIN_STATE_COOKIE, state);
+    cookie.set_http_only(true);
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_path("/");
+    if config.api_host.starts_with("https://") {
+        cookie.set_secure(true);
+    }
+    cookie.set_expires(time::OffsetDateTime::now_utc() + time::Duration::minutes(10));
+    cookies.add(cookie);
+
+    Ok(Redirect::to(authorization_url))
+}
+
+#[get("/auth/oidc/callback?<code>&<state>&<error>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn finish_external_login(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    config: &State<BackendConfiguration>,
+    remote_addr: Option<std::net::SocketAddr>,
+    user_agent: UserAgent,
+    cookies: &CookieJar<'_>,
+    code: Option<&str>,
+    state: Option<&str>,
+    error: Option<&str>,
+) -> Result<Redirect, Status> {
     use crate::log_action_rocket;
-    use log::{debug, error};
-    use std::str::FromStr;
+    use crate::oidc::finish_login;
+    use crate::users::{user_for_external_identity, ExternalUserMatch};
+    use log::{error, warn};
+    use url::Url;
+
+    let settings = match &config.external_login {
+        Some(settings) => settings.clone(),
+        None => return Err(Status::NotFound),
+    };
+    let remote_ip = remote_addr
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or("unknown".to_string());
+
+    // the login has to be finished in the browser it was started in
+    let expected_state = cookies
+        .get(EXTERNAL_LOGIN_STATE_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+    cookies.remove(Cookie::from(EXTERNAL_LOGIN_STATE_COOKIE));
+    if let Some(error) = error {
+        warn!(
+            "The OpenID Connect provider refused the login from IP {}: {}",
+            remote_ip, error
+        );
+        return Err(Status::Unauthorized);
+    }
+    let (code, state) = match (code, state) {
+        (Some(code), Some(state)) if expected_state.as_deref() == Some(state) => {
+            (code.to_string(), state.to_string())
+        }
+        _ => {
+            warn!(
+                "The login through the OpenID Connect provider from IP {} does not belong to this browser",
+                remote_ip
+            );
+            return Err(Status::BadRequest);
+        }
+    };
+
+    let callback_url = external_login_callback_url(config);
+    let login_settings = settings.clone();
+    let identity = match rocket::tokio::task::spawn_blocking(move || {
+        finish_login(&login_settings, &state, &code, &callback_url)
+    })
+    .await
+    {
+        Ok(Ok(identity)) => identity,
+        Ok(Err(error)) => {
+            log_action_rocket(
+                db_connection_pool,
+                "anonymous".to_string(),
+                Action::FailedLogin,
+                Some(format!(
+                    "Failed login attempt through the OpenID Connect provider from IP {}: {}",
+                    remote_ip, error
+                )),
+            )
+            .await;
+            return Err(Status::Unauthorized);
+        }
+        Err(error) => {
+            error!(
+                "Could not finish the login through the OpenID Connect provider. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let (user, user_match) = match user_for_external_identity(
+        db_connection,
+        &identity,
+        settings.default_role,
+    ) {
+        Ok(Some(user_and_match)) => user_and_match,
+        Ok(None) => {
+            log_action_rocket(
+                    db_connection_pool,
+                    "anonymous".to_string(),
+                    Action::FailedLogin,
+                    Some(format!(
+                        "Failed login attempt through the OpenID Connect provider - the subject '{}' is not mapped to a user, from IP: {}",
+                        identity.subject, remote_ip
+                    )),
+                )
+                .await;
+            return Err(Status::Forbidden);
+        }
+        Err(error) => {
+            error!(
+                "Could not look up the user of the subject '{}'. The error was: {}",
+                identity.subject, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // disabled users must not get a new token (even if the provider still knows them)
+    if user.disabled {
+        log_action_rocket(
+            db_connection_pool,
+            user.username.clone(),
+            Action::FailedLogin,
+            Some(format!(
+                "Failed login attempt through the OpenID Connect provider - the user is disabled, from IP: {}",
+                remote_ip
+            )),
+        )
+        .await;
+        return Err(Status::Forbidden);
+    }
+    if user_match == ExternalUserMatch::Provisioned {
+        log_action_rocket(
+            db_connection_pool,
+            user.username.clone(),
+            Action::UserProvisioned,
+            Some(format!(
+                "Created the user '{}' with the role {} for the subject '{}'",
+                user.username, user.role, identity.subject
+            )),
+        )
+        .await;
+    }
+
+    // the second factor is asked for by the web frontend, which gets the challenge in the fragment
+    // of the redirect (so it is not sent to any server)
+    if let Some(challenge) = second_factor_challenge(db_connection, config, &user)? {
+        let mut login_redirect = match Url::parse(&settings.login_redirect) {
+            Ok(login_redirect) => login_redirect,
+            Err(error) => {
+                error!(
+                    "The redirect after the login through the OpenID Connect provider is invalid. The error was: {}",
+                    error
+                );
+                return Err(Status::InternalServerError);
+            }
+        };
+        let fragment = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("challenge", &challenge.challenge)
+            .append_pair(
+                "enrollmentRequired",
+                &challenge.enrollment_required.to_string(),
+            )
+            .finish();
+        login_redirect.set_fragment(Some(&fragment));
+        return Ok(Redirect::to(login_redirect.to_string()));
+    }
+
+    start_login_session(
+        db_connection,
+        config,
+        &user,
+        user_agent.0.as_deref(),
+        remote_addr.map(|addr| addr.ip().to_string()),
+        cookies,
+    )?;
+    log_action_rocket(
+        db_connection_pool,
+        user.username.clone(),
+        Action::SuccessfulLogin,
+        Some(format!(
+            "Successfully logged in user '{}' through the OpenID Connect provider",
+            user.username
+        )),
+    )
+    .await;
+
+    Ok(Redirect::to(settings.login_redirect))
+}
+
+#[derive(Deserialize)]
+pub struct SecondFactorLogin {
+    /// The id of the login returned after the password was accepted.
+    challenge: String,
+    /// A TOTP code of the authenticator app or an unused recovery code.
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub struct SecondFactorEnrollmentRequest {
+    /// The id of the login returned after the password was accepted.
+    challenge: String,
+}
+
+#[derive(Deserialize)]
+pub struct SecondFactorCode {
+    /// A TOTP code of the authenticator app (or an unused recovery code).
+    code: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodes {
+    /// The codes which can be used once instead of a TOTP code (e.g. if the device with the
+    /// authenticator app was lost). They are just shown once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recovery_codes: Option<Vec<String>>,
+}
+
+#[options("/auth/totp")]
+pub async fn second_factor_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Post, Method::Put, Method::Delete]
+        .into_iter()
+        .map(From::from)
+        .collect();
 
-    // if we cannot parse the input date, we received a bad parameter and we have to react to it
-    let maybe_date = NaiveDate::from_str(date);
-    if maybe_date.is_err() {
-        return Err(Status::BadRequest);
-    }
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[options("/auth/totp/login")]
+pub async fn second_factor_login_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Post].into_iter().map(From::from).collect();
 
-    // try to get the number of random picks from the database (they are not marked as won after this call!!!)
-    let maybe_result = pick_random_participants_from_database(db_connection_pool, count).await;
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
 
-    // if we could fetch a result from the database, return the requested information
-    if let Some(result) = maybe_result {
-        // ensure that we got exactly one result for this call. Otherwise something went wrong
-        if result.len() != count {
-            error!("Got {} participants who won from the database but we expected to receive {} winners", result.len(), count);
-            return Err(Status::InternalServerError);
-        }
+#[options("/auth/totp/login/enrollment")]
+pub async fn second_factor_login_enrollment_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Post].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+/// Check the TOTP code (or recovery code) of a user with the rate limit for second factors. Wrong
+/// codes are logged, so attacks on the second factor show up in the audit log.
+async fn verify_second_factor(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    db_connection: &mut diesel::PgConnection,
+    config: &BackendConfiguration,
+    user: &User,
+    code: &str,
+    remote_addr: Option<std::net::SocketAddr>,
+) -> Result<SecondFactorCheck, Status> {
+    use crate::log_action_rocket;
+    use crate::rate_limiter::{is_rate_limited, reset_rate_limit};
+    use crate::two_factor::check_second_factor;
+    use log::{error, warn};
+
+    let Some(totp) = &config.totp else {
+        return Err(Status::NotFound);
+    };
+    let ip_address = remote_addr
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or("unknown".to_string());
 
-        // after we have all participants we wanted to select, we have to mark them as won before we can
-        // return them
-        let won_participant_ids: Vec<i32> = result.iter().map(|p| p.id).collect();
-        if mark_participant_as_won(
+    let rate_limit_key = format!("totp:{}", user.username);
+    if is_rate_limited(&rate_limit_key, &config.login_rate_limit) {
+        warn!(
+            "Rate limit exceeded for the second factor of: {}",
+            user.username
+        );
+        log_action_rocket(
             db_connection_pool,
-            won_participant_ids.clone(),
-            maybe_date.unwrap(),
-            authenticated_user.username.clone(),
+            user.username.clone(),
+            Action::FailedSecondFactor,
+            Some(format!(
+                "Too many wrong codes for the second factor, from IP: {}",
+                ip_address
+            )),
         )
-        .await
-        .is_err()
-        {
-            error!("Failed to mark all picked participants as won. Returning an error since it is not guaranteed that the pick would be genuine.");
-            return Err(Status::InternalServerError);
-        }
+        .await;
+        return Err(Status::TooManyRequests);
+    }
 
-        // log the picked winners and return them
-        for current_participant_id in won_participant_ids.clone() {
+    match check_second_factor(db_connection, &totp.cipher, user.id, code) {
+        Ok(SecondFactorCheck::Rejected) => {
             log_action_rocket(
                 db_connection_pool,
-                authenticated_user.username.clone(),
-                Action::PickedWinner,
+                user.username.clone(),
+                Action::FailedSecondFactor,
                 Some(format!(
-                    "The participant with the id {} was marked as won",
-                    current_participant_id
+                    "Wrong or already used code for the second factor, from IP: {}",
+                    ip_address
                 )),
             )
             .await;
+            Ok(SecondFactorCheck::Rejected)
+        }
+        Ok(check) => {
+            reset_rate_limit(&rate_limit_key);
+            Ok(check)
+        }
+        Err(error) => {
+            error!(
+                "Could not check the second factor of '{}'. The error was: {}",
+                user.username, error
+            );
+            Err(Status::InternalServerError)
         }
-        debug!(
-            "The user {} picked the participants with the ids {:?} as new winners",
-            authenticated_user.username, won_participant_ids
-        );
-        return Ok(Json(result.clone()));
     }
+}
 
-    // if we could not get a result, it seems that all participants where picked at some point. Return
-    // NOT FOUND to indicate that
-    error!(
-        "The user {} tried to pick a new winner but we could not find one",
-        authenticated_user.username
-    );
-    Err(Status::NotFound)
+/// Log that the user confirmed a new second factor (if the check confirmed it).
+async fn log_second_factor_enrollment(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    username: &str,
+    check: &SecondFactorCheck,
+) {
+    use crate::log_action_rocket;
+
+    if let SecondFactorCheck::Totp {
+        recovery_codes: Some(recovery_codes),
+    } = check
+    {
+        log_action_rocket(
+            db_connection_pool,
+            username.to_string(),
+            Action::SecondFactorEnrolled,
+            Some(format!(
+                "The TOTP second factor was confirmed and {} recovery codes were issued",
+                recovery_codes.len()
+            )),
+        )
+        .await;
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct PickingInformation {
-    /// The participant who was picked as a winner
-    participant_id: i32,
-    /// The date for which the winner was picked
-    picked_for_date: NaiveDate,
+#[post("/auth/totp/login", data = "<second_factor>")]
+pub async fn login_with_second_factor(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    second_factor: Json<SecondFactorLogin>,
+    config: &State<BackendConfiguration>,
+    remote_addr: Option<std::net::SocketAddr>,
+    user_agent: UserAgent,
+    cookies: &CookieJar<'_>,
+) -> Result<Json<RecoveryCodes>, Status> {
+    use crate::log_action_rocket;
+    use crate::rate_limiter::reset_rate_limit;
+    use crate::schema::users::dsl::users;
+    use crate::two_factor::{challenge_user, finish_challenge, record_failed_attempt};
+    use diesel::{QueryDsl, RunQueryDsl};
+    use log::error;
+
+    // the challenge proves that the password was accepted a few minutes ago
+    let Some((user_id, username)) = challenge_user(&second_factor.challenge) else {
+        log_action_rocket(
+            db_connection_pool,
+            "anonymous".to_string(),
+            Action::FailedSecondFactor,
+            Some(format!(
+                "Second factor for an unknown or expired login, from IP: {}",
+                remote_addr
+                    .map(|addr| addr.ip().to_string())
+                    .unwrap_or("unknown".to_string())
+            )),
+        )
+        .await;
+        return Err(Status::Unauthorized);
+    };
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    // the user may have been disabled since the password was checked
+    let user = match users.find(user_id).first::<User>(db_connection) {
+        Ok(user) if !user.disabled => user,
+        Ok(_) | Err(diesel::result::Error::NotFound) => {
+            finish_challenge(&second_factor.challenge);
+            return Err(Status::Unauthorized);
+        }
+        Err(error) => {
+            error!(
+                "Could not look up the user '{}'. The error was: {}",
+                username, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let check = verify_second_factor(
+        db_connection_pool,
+        db_connection,
+        config,
+        &user,
+        &second_factor.code,
+        remote_addr,
+    )
+    .await?;
+    let login_method = match &check {
+        SecondFactorCheck::Totp { .. } => "a TOTP code",
+        SecondFactorCheck::RecoveryCode => "a recovery code",
+        SecondFactorCheck::Rejected => {
+            record_failed_attempt(&second_factor.challenge);
+            return Err(Status::Unauthorized);
+        }
+    };
+    finish_challenge(&second_factor.challenge);
+    log_second_factor_enrollment(db_connection_pool, &user.username, &check).await;
+
+    start_login_session(
+        db_connection,
+        config,
+        &user,
+        user_agent.0.as_deref(),
+        remote_addr.map(|addr| addr.ip().to_string()),
+        cookies,
+    )?;
+
+    if let Some(addr) = remote_addr {
+        reset_rate_limit(&format!("ip:{}", addr.ip()));
+    }
+    reset_rate_limit(&format!("user:{}", user.username));
+
+    log_action_rocket(
+        db_connection_pool,
+        user.username.clone(),
+        Action::SuccessfulLogin,
+        Some(format!(
+            "Successfully logged in user '{}' with a token and {}",
+            user.username, login_method
+        )),
+    )
+    .await;
+
+    Ok(Json(RecoveryCodes {
+        recovery_codes: match check {
+            SecondFactorCheck::Totp { recovery_codes } => recovery_codes,
+            _ => None,
+        },
+    }))
 }
 
-pub async fn mark_participant_as_won(
-    db_connection_pool: &AdventskalenderDatabaseConnection,
-    participant_ids: Vec<i32>,
-    picked_for_date: NaiveDate,
-    user_who_picked: String,
-) -> Result<(), ()> {
-    use crate::lookup_user_by_name;
-    use crate::models::ParticipantPicking;
-    use crate::schema::participants::dsl::{id, participants};
-    use chrono::Utc;
-    use diesel::{update, ExpressionMethods, QueryDsl, RunQueryDsl};
-    use log::{debug, error};
+#[post("/auth/totp/login/enrollment", data = "<enrollment_request>")]
+pub async fn start_second_factor_enrollment_for_login(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    enrollment_request: Json<SecondFactorEnrollmentRequest>,
+    config: &State<BackendConfiguration>,
+) -> Result<Json<TotpEnrollment>, Status> {
+    use crate::two_factor::{challenge_user, start_enrollment};
+    use log::error;
+
+    let Some(totp) = &config.totp else {
+        return Err(Status::NotFound);
+    };
+    let Some((user_id, username)) = challenge_user(&enrollment_request.challenge) else {
+        return Err(Status::Unauthorized);
+    };
 
     // get a connection to the database for dealing with the request
     let db_connection = &mut match db_connection_pool.get() {
@@ -845,67 +5428,78 @@ pub async fn mark_participant_as_won(
                 "Could not get a connection from the database connection pool. The error was: {}",
                 error
             );
-            return Err(());
+            return Err(Status::InternalServerError);
         }
     };
 
-    //
-    let maybe_result= db_connection
-        .build_transaction()
-        .read_write()
-            .run::<_, diesel::result::Error, _>(move |connection| {
-                // look up the user object who initiated the call
-                match lookup_user_by_name(connection, user_who_picked.clone()) {
-                Ok(user_obj) =>
-                    {
-                        // create the struct with the update information for the picked user
-                        let participant_info = ParticipantPicking {
-                            won_on: Some(picked_for_date),
-                            picking_time: Some(Utc::now().naive_utc()),
-                            picked_by: Some(user_obj.id),
-                            present_identifier: None
-                        };
-
-                        // do the actual update of the database
-                        if let Ok(rows_updated) = update(participants.filter(id.eq_any(participant_ids.clone())))
-                            .set(&participant_info)
-                            .execute(connection)
-                        {
-                            // ensure that all rows were successfully updated. If not, we have to assume an error and log it before exit here
-                            if rows_updated != participant_ids.len() {
-                                error!("There should be {} row updates but {} rows were actually updated. The following IDs should not be marked as won: {:?}", participant_ids.len(), rows_updated, participant_ids);
-                                return Err(diesel::result::Error::NotFound); // TODO: not the actual error
-                            }
-
-                            debug!("The user {} marked the users with the ids {:?} as 'won on {}'", user_who_picked, participant_ids, picked_for_date);
-                            return Ok(());
-                        }
-                        error!("The user {} tried to mark the users with the ids {:?} as 'won on {}' but we failed to do so", user_who_picked, participant_ids, picked_for_date);
-                        Err(diesel::result::Error::NotFound) // TODO: not the actual error
-                    },
-                    Err(_) => {
-                        // it seems that we could not look up the user who initiated the call
-                        Err(diesel::result::Error::NotFound) // TODO: not the actual error
-                    }
-                }
-            });
+    // a confirmed second factor can just be replaced after the user logged in with it
+    match start_enrollment(db_connection, &totp.cipher, user_id, &username) {
+        Ok(Some(enrollment)) => Ok(Json(enrollment)),
+        Ok(None) => Err(Status::Conflict),
+        Err(error) => {
+            error!(
+                "Could not start the enrollment of a second factor for '{}'. The error was: {}",
+                username, error
+            );
+            Err(Status::InternalServerError)
+        }
+    }
+}
 
-    //
-    if maybe_result.is_err() {
-        return Err(());
+#[post("/auth/totp")]
+pub async fn start_second_factor_enrollment(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: AuthenticatedUser,
+    config: &State<BackendConfiguration>,
+) -> Result<Json<TotpEnrollment>, Status> {
+    use crate::lookup_user_by_name;
+    use crate::two_factor::start_enrollment;
+    use log::error;
+
+    let Some(totp) = &config.totp else {
+        return Err(Status::NotFound);
+    };
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let user = match lookup_user_by_name(db_connection, authenticated_user.username.clone()) {
+        Ok(user) => user,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    match start_enrollment(db_connection, &totp.cipher, user.id, &user.username) {
+        Ok(Some(enrollment)) => Ok(Json(enrollment)),
+        Ok(None) => Err(Status::Conflict),
+        Err(error) => {
+            error!(
+                "Could not start the enrollment of a second factor for '{}'. The error was: {}",
+                user.username, error
+            );
+            Err(Status::InternalServerError)
+        }
     }
-    Ok(())
 }
 
-pub async fn mark_participant_as_not_won(
-    db_connection_pool: &AdventskalenderDatabaseConnection,
-    participant_id: i32,
-    user_who_unpicked: String,
-) -> Result<(), ()> {
-    use crate::models::ParticipantPicking;
-    use crate::schema::participants::dsl::{id, participants};
-    use diesel::{update, ExpressionMethods, QueryDsl, RunQueryDsl};
-    use log::{debug, error};
+#[put("/auth/totp", data = "<second_factor>")]
+pub async fn confirm_second_factor(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: AuthenticatedUser,
+    second_factor: Json<SecondFactorCode>,
+    config: &State<BackendConfiguration>,
+    remote_addr: Option<std::net::SocketAddr>,
+) -> Result<Json<RecoveryCodes>, Status> {
+    use crate::lookup_user_by_name;
+    use crate::two_factor::second_factor_of;
+    use log::error;
 
     // get a connection to the database for dealing with the request
     let db_connection = &mut match db_connection_pool.get() {
@@ -915,70 +5509,137 @@ pub async fn mark_participant_as_not_won(
                 "Could not get a connection from the database connection pool. The error was: {}",
                 error
             );
-            return Err(());
+            return Err(Status::InternalServerError);
         }
     };
 
-    let maybe_result = db_connection
-        .build_transaction()
-        .read_write()
-        .run::<_, diesel::result::Error, _>(move |connection| {
-            // set all fields to none
-            let participant_info = ParticipantPicking {
-                won_on: None,
-                picking_time: None,
-                picked_by: None,
-                present_identifier: None
-            };
+    let user = match lookup_user_by_name(db_connection, authenticated_user.username.clone()) {
+        Ok(user) => user,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    match second_factor_of(db_connection, user.id) {
+        Ok(Some(second_factor)) if second_factor.confirmed_at.is_none() => {}
+        Ok(Some(_)) => return Err(Status::Conflict),
+        Ok(None) => return Err(Status::NotFound),
+        Err(error) => {
+            error!(
+                "Could not look up the second factor of '{}'. The error was: {}",
+                user.username, error
+            );
+            return Err(Status::InternalServerError);
+        }
+    }
 
-            // do the actual update of the database
-            match update(participants.filter(id.eq(participant_id)))
-                .set(&participant_info)
-                .execute(connection)
-            {
-                Ok(rows_updated) =>
+    let check = verify_second_factor(
+        db_connection_pool,
+        db_connection,
+        config,
+        &user,
+        &second_factor.code,
+        remote_addr,
+    )
+    .await?;
+    log_second_factor_enrollment(db_connection_pool, &user.username, &check).await;
+    match check {
+        SecondFactorCheck::Totp { recovery_codes } => Ok(Json(RecoveryCodes { recovery_codes })),
+        _ => Err(Status::UnprocessableEntity),
+    }
+}
 
-                    {
-                        // ensure that the expected row was updated, if we did not exactly update one row, something went wrong
-                        if rows_updated != 1 {
-                            error!("There should be 1 row updates but {} rows were actually updated. The following ID should not be marked as NOT won: {:?}", rows_updated, participant_id);
-                            return Err(diesel::result::Error::NotFound); // TODO: not really the correct error code
-                        }
+#[delete("/auth/totp", data = "<second_factor>")]
+pub async fn remove_own_second_factor(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: AuthenticatedUser,
+    second_factor: Json<SecondFactorCode>,
+    config: &State<BackendConfiguration>,
+    remote_addr: Option<std::net::SocketAddr>,
+) -> Status {
+    use crate::log_action_rocket;
+    use crate::lookup_user_by_name;
+    use crate::two_factor::{has_confirmed_second_factor, remove_second_factor};
+    use log::error;
 
-                        debug!("The user {} marked the user with the id {} as NOT won", user_who_unpicked, participant_id);
-                        Ok(())
-                    }
-                Err(error) => {
-                    error!("The user {} tried to mark the user with the id {} as NOT won but we failed to do so. The error was: {}", user_who_unpicked, participant_id, error);
-                    Err(error)
-                }
+    let Some(totp) = &config.totp else {
+        return Status::NotFound;
+    };
+
+    // users whose role requires a second factor cannot remove it (an administrator can reset it,
+    // so a new one is set up with the next login)
+    if totp.required_roles.contains(&authenticated_user.role) {
+        return Status::Conflict;
+    }
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    let user = match lookup_user_by_name(db_connection, authenticated_user.username.clone()) {
+        Ok(user) => user,
+        Err(_) => return Status::InternalServerError,
+    };
+
+    // a confirmed second factor can just be removed with a valid code, so a stolen session is not
+    // enough to get rid of it
+    match has_confirmed_second_factor(db_connection, user.id) {
+        Ok(true) => {
+            match verify_second_factor(
+                db_connection_pool,
+                db_connection,
+                config,
+                &user,
+                &second_factor.code,
+                remote_addr,
+            )
+            .await
+            {
+                Ok(SecondFactorCheck::Rejected) => return Status::UnprocessableEntity,
+                Ok(_) => {}
+                Err(status) => return status,
             }
-        });
+        }
+        Ok(false) => {}
+        Err(error) => {
+            error!(
+                "Could not look up the second factor of '{}'. The error was: {}",
+                user.username, error
+            );
+            return Status::InternalServerError;
+        }
+    }
 
-    //
-    if maybe_result.is_err() {
-        return Err(());
+    match remove_second_factor(db_connection, user.id) {
+        Ok(true) => {}
+        Ok(false) => return Status::NotFound,
+        Err(error) => {
+            error!(
+                "Could not remove the second factor of '{}'. The error was: {}",
+                user.username, error
+            );
+            return Status::InternalServerError;
+        }
     }
-    Ok(())
-}
 
-#[derive(Serialize, Deserialize)]
-pub struct LoginInformation {
-    /// The username of the user.
-    username: String,
-    /// The password for the login request.
-    password: String,
-}
+    log_action_rocket(
+        db_connection_pool,
+        user.username.clone(),
+        Action::SecondFactorRemoved,
+        Some("The user removed the own second factor".to_string()),
+    )
+    .await;
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct TokenResponse {
-    /// The access token to use for API requests.
-    access_token: String,
+    Status::NoContent
 }
 
-#[options("/auth/token")]
-pub async fn get_login_token_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
+#[options("/auth/refresh")]
+pub async fn refresh_login_token_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
     let mut options = cors_options();
     options.allowed_methods = vec![Method::Post].into_iter().map(From::from).collect();
 
@@ -986,40 +5647,28 @@ pub async fn get_login_token_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
     cors.respond_owned(|guard| guard.responder(()))
 }
 
-#[post("/auth/token", data = "<login_information>")]
-pub async fn get_login_token(
+#[post("/auth/refresh")]
+pub async fn refresh_login_token(
     db_connection_pool: &State<AdventskalenderDatabaseConnection>,
-    login_information: Json<LoginInformation>,
     config: &State<BackendConfiguration>,
-    remote_addr: Option<std::net::SocketAddr>,
     cookies: &CookieJar<'_>,
 ) -> Result<NoContent, Status> {
     use crate::get_token_for_user;
     use crate::log_action_rocket;
-    use crate::rate_limiter::{is_rate_limited, reset_rate_limit, RateLimitConfig};
-    use crate::schema::users::dsl::{username, users};
-    use bcrypt::verify;
-    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use crate::refresh_tokens::{rotate_refresh_token, RefreshOutcome};
     use log::{error, warn};
 
-    let rate_limit_config = RateLimitConfig::default();
-
-    if let Some(addr) = remote_addr {
-        let ip_key = format!("ip:{}", addr.ip());
-        if is_rate_limited(&ip_key, &rate_limit_config) {
-            warn!("Rate limit exceeded for IP: {}", addr.ip());
-            return Err(Status::TooManyRequests);
+    let presented_token = match cookies.get(REFRESH_TOKEN_COOKIE) {
+        Some(cookie) => cookie.value().to_string(),
+        None => return Err(Status::Unauthorized),
+    };
+    let signing_key = match config.keys.read().ok().and_then(|keys| keys.active_key()) {
+        Some(signing_key) => signing_key,
+        None => {
+            error!("There is no key available for signing the tokens");
+            return Err(Status::InternalServerError);
         }
-    }
-
-    let username_key = format!("user:{}", login_information.username);
-    if is_rate_limited(&username_key, &rate_limit_config) {
-        warn!(
-            "Rate limit exceeded for username: {}",
-            login_information.username
-        );
-        return Err(Status::TooManyRequests);
-    }
+    };
 
     // get a connection to the database for dealing with the request
     let db_connection = &mut match db_connection_pool.get() {
@@ -1033,154 +5682,254 @@ pub async fn get_login_token(
         }
     };
 
-    // try to get the user record for the supplied username
-    let supplied_username = login_information.username.clone();
-    let maybe_user_result = db_connection
-        .build_transaction()
-        .read_only()
-        .run::<_, diesel::result::Error, _>(move |connection| {
-            if let Ok(found_users) = users
-                .filter(username.eq(supplied_username))
-                .load::<User>(connection)
-            {
-                // if we did not get exactly one user, return an 'error'
-                if found_users.len() != 1 {
-                    return Err(diesel::result::Error::NotFound);
-                }
-
-                // return the found user
-                return Ok(found_users[0].clone());
+    // exchange the refresh token for a new one and issue a new access token for its user
+    match rotate_refresh_token(
+        db_connection,
+        &presented_token,
+        config.refresh_token_lifetime_in_seconds,
+    ) {
+        Ok(RefreshOutcome::Rotated {
+            user,
+            session_id,
+            refresh_token,
+        }) => match get_token_for_user(
+            &user.username,
+            Role::from_name(&user.role),
+            &session_id,
+            config.token_audience.clone(),
+            config.api_host.clone(),
+            config.token_lifetime_in_seconds,
+            &signing_key,
+        ) {
+            Some(access_token) => {
+                add_token_cookies(cookies, config, access_token, refresh_token);
+                Ok(NoContent)
             }
-
-            //
-            Err(diesel::result::Error::NotFound) // TODO: not the real error
-        });
-
-    // try to get the actual user object or delay a bit and then return with the corresponding error
-    let user = match maybe_user_result {
-        Ok(user) => user,
-        Err(_) => {
-            // ensure that we know what happened
-            error!(
-                "Could not get the user record for '{}'",
-                login_information.username
-            );
-
-            // just slow down the process to prevent easy checking if a username exists or not
-            let _ = verify(
-                "some_password",
-                "$2y$12$7xMzqvnHyizkumZYpIRXheGMAqDKVo8HKtpmQSn51JUfY0N2VN4ua",
+            None => Err(Status::InternalServerError),
+        },
+        // the browser got the new tokens with the response to the parallel request, so its cookies
+        // are kept
+        Ok(RefreshOutcome::Superseded) => Err(Status::Conflict),
+        Ok(RefreshOutcome::Reused { user }) => {
+            warn!(
+                "The refresh token of '{}' was used a second time, revoking all tokens of the login",
+                user.username
             );
-
-            // log the failed attempt (use anonymous to avoid username enumeration)
             log_action_rocket(
                 db_connection_pool,
-                "anonymous".to_string(),
-                Action::FailedLogin,
+                user.username.clone(),
+                Action::RefreshTokenReused,
                 Some(format!(
-                    "Failed login attempt - invalid credentials from IP: {}",
-                    remote_addr
-                        .map(|addr| addr.ip().to_string())
-                        .unwrap_or("unknown".to_string())
+                    "A refresh token of '{}' was used a second time, all tokens of the login were revoked",
+                    user.username
                 )),
             )
             .await;
-
-            // finally we can tell teh user that he/she is not authorized
-            return Err(Status::Unauthorized);
+            remove_token_cookies(cookies);
+            Err(Status::Unauthorized)
         }
-    };
-
-    // check if the supplied password matches the one we stored in the database using the same bcrypt
-    // parameters
-    match verify(&login_information.password, user.password_hash.as_str()) {
-        Ok(is_password_correct) => {
-            if !is_password_correct {
-                log_action_rocket(
-                    db_connection_pool,
-                    user.username.clone(),
-                    Action::FailedLogin,
-                    Some(format!(
-                        "Failed login attempt - invalid credentials from IP: {}",
-                        remote_addr
-                            .map(|addr| addr.ip().to_string())
-                            .unwrap_or("unknown".to_string())
-                    )),
-                )
-                .await;
-                return Err(Status::Unauthorized);
-            }
+        Ok(RefreshOutcome::Rejected) => {
+            remove_token_cookies(cookies);
+            Err(Status::Unauthorized)
         }
         Err(error) => {
-            error!("Could not verify the supplied password with the one stored in the database. The error was: {}", error);
-            return Err(Status::InternalServerError);
+            error!(
+                "Could not exchange the refresh token. The error was: {}",
+                error
+            );
+            Err(Status::InternalServerError)
         }
     }
+}
 
-    // if we get here, we ensured that the user is known and that the supplied password
-    // was valid, we can generate a new access token and return it to the calling party
-    if let Some(token) = get_token_for_user(
-        &login_information.username,
-        config.token_audience.clone(),
-        config.api_host.clone(),
-        &config.encoding_key.clone().unwrap(),
-    ) {
-        if let Some(addr) = remote_addr {
-            reset_rate_limit(&format!("ip:{}", addr.ip()));
+#[post("/auth/logout")]
+pub fn logout(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    cookies: &CookieJar<'_>,
+) -> NoContent {
+    use crate::refresh_tokens::revoke_session_of_refresh_token;
+    use log::error;
+
+    // revoke the session of the login, so neither its access token nor its refresh tokens can be
+    // used anymore
+    if let Some(cookie) = cookies.get(REFRESH_TOKEN_COOKIE) {
+        let revocation = db_connection_pool
+            .get()
+            .map_err(|error| error.to_string())
+            .and_then(|mut connection| {
+                revoke_session_of_refresh_token(&mut connection, cookie.value())
+                    .map_err(|error| error.to_string())
+            });
+        if let Err(error) = revocation {
+            error!(
+                "Could not revoke the session on logout. The error was: {}",
+                error
+            );
         }
-        reset_rate_limit(&format!("user:{}", login_information.username));
+    }
 
-        log_action_rocket(
-            db_connection_pool,
-            login_information.username.clone(),
-            Action::SuccessfulLogin,
-            Some(format!(
-                "Successfully logged in user '{}' with a token",
-                login_information.username
-            )),
-        )
-        .await;
+    remove_token_cookies(cookies);
+    NoContent
+}
 
-        // create httpOnly cookie with the token
-        let mut cookie = Cookie::new("auth_token", token);
-        cookie.set_http_only(true);
-        cookie.set_same_site(SameSite::Strict);
-        cookie.set_path("/");
+#[options("/auth/sessions")]
+pub async fn sessions_options<'r, 'o: 'r>() -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Get, Method::Delete]
+        .into_iter()
+        .map(From::from)
+        .collect();
 
-        // only set secure-flag if we're using HTTPS (check API host)
-        if config.api_host.starts_with("https://") {
-            cookie.set_secure(true);
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[options("/auth/sessions/<_session_id>")]
+pub async fn session_options<'r, 'o: 'r>(_session_id: &str) -> impl Responder<'r, 'o> {
+    let mut options = cors_options();
+    options.allowed_methods = vec![Method::Delete].into_iter().map(From::from).collect();
+
+    let cors = options.to_cors()?;
+    cors.respond_owned(|guard| guard.responder(()))
+}
+
+#[get("/auth/sessions")]
+pub async fn list_sessions(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: AuthenticatedUser,
+) -> Result<Json<Vec<SessionSummary>>, Status> {
+    use crate::lookup_user_by_name;
+    use crate::sessions::active_sessions;
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Err(Status::InternalServerError);
         }
+    };
 
-        // set expiration to match JWT expiration (1 hour)
-        let expiration = time::OffsetDateTime::now_utc() + time::Duration::hours(1);
-        cookie.set_expires(expiration);
+    let user = match lookup_user_by_name(db_connection, authenticated_user.username.clone()) {
+        Ok(user) => user,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    match active_sessions(db_connection, user.id) {
+        Ok(sessions) => Ok(Json(
+            sessions
+                .into_iter()
+                .map(|session| SessionSummary::new(session, &authenticated_user.session_id))
+                .collect(),
+        )),
+        Err(error) => {
+            error!("Could not load the sessions. The error was: {}", error);
+            Err(Status::InternalServerError)
+        }
+    }
+}
 
-        cookies.add(cookie);
+/// Revoke one session (or all of them) of the authenticated user. The cookies are removed if the
+/// session of the request itself is revoked.
+async fn revoke_own_sessions(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: AuthenticatedUser,
+    session_id: Option<&str>,
+    cookies: &CookieJar<'_>,
+) -> Status {
+    use crate::log_action_rocket;
+    use crate::lookup_user_by_name;
+    use crate::sessions::revoke_sessions;
+    use log::error;
+
+    // get a connection to the database for dealing with the request
+    let db_connection = &mut match db_connection_pool.get() {
+        Ok(connection) => connection,
+        Err(error) => {
+            error!(
+                "Could not get a connection from the database connection pool. The error was: {}",
+                error
+            );
+            return Status::InternalServerError;
+        }
+    };
+
+    let user = match lookup_user_by_name(db_connection, authenticated_user.username.clone()) {
+        Ok(user) => user,
+        Err(_) => return Status::InternalServerError,
+    };
+    let revoked_sessions = match revoke_sessions(db_connection, user.id, session_id, None) {
+        Ok(0) if session_id.is_some() => return Status::NotFound,
+        Ok(revoked_sessions) => revoked_sessions,
+        Err(error) => {
+            error!("Could not revoke the sessions. The error was: {}", error);
+            return Status::InternalServerError;
+        }
+    };
+
+    log_action_rocket(
+        db_connection_pool,
+        authenticated_user.username.clone(),
+        Action::SessionsRevoked,
+        Some(match session_id {
+            Some(session_id) => format!("The session {} was revoked", session_id),
+            None => format!("All {} sessions were revoked", revoked_sessions),
+        }),
+    )
+    .await;
 
-        return Ok(NoContent);
+    if session_id.map_or(true, |session_id| {
+        session_id == authenticated_user.session_id
+    }) {
+        remove_token_cookies(cookies);
     }
+    Status::NoContent
+}
 
-    // it seems that we failed to generate a valid token. This should never happen, something
-    // seems REALLY wrong
-    Err(Status::InternalServerError)
+#[delete("/auth/sessions/<session_id>")]
+pub async fn revoke_session(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: AuthenticatedUser,
+    session_id: &str,
+    cookies: &CookieJar<'_>,
+) -> Status {
+    revoke_own_sessions(
+        db_connection_pool,
+        authenticated_user,
+        Some(session_id),
+        cookies,
+    )
+    .await
 }
 
-#[post("/auth/logout")]
-pub fn logout(cookies: &CookieJar<'_>) -> NoContent {
-    cookies.remove(Cookie::from("auth_token"));
-    NoContent
+#[delete("/auth/sessions")]
+pub async fn revoke_all_sessions(
+    db_connection_pool: &State<AdventskalenderDatabaseConnection>,
+    authenticated_user: AuthenticatedUser,
+    cookies: &CookieJar<'_>,
+) -> Status {
+    revoke_own_sessions(db_connection_pool, authenticated_user, None, cookies).await
 }
 
 #[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct UserInfo {
     pub username: String,
+    /// The role which defines what the user is allowed to do.
+    pub role: Role,
+    /// Whether the user has to change the password before anything else can be done.
+    pub password_change_required: bool,
 }
 
 #[get("/auth/me")]
 pub fn get_current_user(user: AuthenticatedUser) -> Json<UserInfo> {
     Json(UserInfo {
         username: user.username,
+        role: user.role,
+        password_change_required: user.must_change_password,
     })
 }
 
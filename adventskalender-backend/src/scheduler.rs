@@ -0,0 +1,329 @@
+use crate::fairings::AdventskalenderDatabaseConnection;
+use crate::models::Raffle;
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use diesel::PgConnection;
+use std::collections::HashSet;
+
+/// The local time (and its time zone) at which the winners of the raffles with scheduled draws
+/// are picked every day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrawSchedule {
+    /// The local time at which the winners are picked.
+    pub time: NaiveTime,
+    /// The time zone in which the time is given (e.g. `Europe/Berlin`).
+    pub time_zone: Tz,
+}
+
+impl DrawSchedule {
+    /// Parse the time (`HH:MM` or `HH:MM:SS`) and the name of the time zone of a schedule. `None`
+    /// is returned if one of them is invalid.
+    pub fn parse(time: &str, time_zone: &str) -> Option<DrawSchedule> {
+        let time = NaiveTime::parse_from_str(time.trim(), "%H:%M")
+            .or_else(|_| NaiveTime::parse_from_str(time.trim(), "%H:%M:%S"))
+            .ok()?;
+        let time_zone = time_zone.trim().parse::<Tz>().ok()?;
+        Some(DrawSchedule { time, time_zone })
+    }
+
+    /// Get the last (local) day whose draw is due at `now`. If the time of the draw has not been
+    /// reached on the current day so far, this is the previous day.
+    pub fn last_due_day(&self, now: DateTime<Utc>) -> NaiveDate {
+        let local_now = now.with_timezone(&self.time_zone);
+        if local_now.time() >= self.time {
+            return local_now.date_naive();
+        }
+        local_now.date_naive() - Days::new(1)
+    }
+
+    /// Get the first (local) day whose draw is due after `time`.
+    pub fn first_day_due_after(&self, time: DateTime<Utc>) -> NaiveDate {
+        self.last_due_day(time) + Days::new(1)
+    }
+
+    /// Get the first point in time after `now` at which the next draw is due.
+    pub fn next_run_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.with_timezone(&self.time_zone).date_naive();
+        let mut day = today;
+        loop {
+            if let Some(run) = self.run_on(day) {
+                if run > now {
+                    return run;
+                }
+            }
+            day = day + Days::new(1);
+        }
+    }
+
+    /// Get the point in time at which the draw of the supplied day is due. If the local time does
+    /// not exist on that day (since the clocks are moved forward), the draw is due an hour later.
+    fn run_on(&self, day: NaiveDate) -> Option<DateTime<Utc>> {
+        let local_time = day.and_time(self.time);
+        local_time
+            .and_local_timezone(self.time_zone)
+            .earliest()
+            .or_else(|| {
+                (local_time + TimeDelta::hours(1))
+                    .and_local_timezone(self.time_zone)
+                    .earliest()
+            })
+            .map(|run| run.with_timezone(&Utc))
+    }
+}
+
+/// Perform the scheduled draws of all raffles which are not archived and have scheduled draws
+/// enabled. Every day of the raffles whose draw is due at `now` (but was not due before the
+/// scheduled draws of the raffle were enabled) and which can pick winners but was not drawn so far
+/// is drawn, so draws which were missed (e.g. since the server was not running) are caught up. Days
+/// which were drawn before (even if their winners were removed since) are skipped, so the draws can
+/// be repeated safely. The result of each draw is logged as a system action.
+pub fn run_scheduled_draws(
+    connection: &mut PgConnection,
+    schedule: &DrawSchedule,
+    now: DateTime<Utc>,
+) {
+    use crate::calendar::RaffleCalendar;
+    use crate::schema::raffles::dsl::{archived, id, raffles, scheduled_draws};
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use log::error;
+
+    let scheduled_raffles = match raffles
+        .filter(archived.eq(false))
+        .filter(scheduled_draws.eq(true))
+        .order_by(id.asc())
+        .load::<Raffle>(connection)
+    {
+        Ok(scheduled_raffles) => scheduled_raffles,
+        Err(error) => {
+            error!(
+                "Could not load the raffles with scheduled draws. The error was: {}",
+                error
+            );
+            return;
+        }
+    };
+
+    let last_due_day = schedule.last_due_day(now);
+    for raffle in &scheduled_raffles {
+        let drawn_days = match lookup_drawn_days(connection, raffle.id) {
+            Ok(drawn_days) => drawn_days,
+            Err(error) => {
+                error!(
+                    "Could not look up the drawn days of the raffle {}. The error was: {}",
+                    raffle.id, error
+                );
+                continue;
+            }
+        };
+
+        // days whose draw was already due when the scheduled draws were enabled are left to the
+        // operators (a raffle without the point in time just gets the draw of the last due day)
+        let first_due_day = raffle
+            .scheduled_draws_enabled_at
+            .map_or(last_due_day, |enabled_at| {
+                schedule.first_day_due_after(enabled_at.and_utc())
+            });
+        for day in RaffleCalendar::from(raffle)
+            .valid_days()
+            .into_iter()
+            .filter(|day| {
+                *day >= first_due_day && *day <= last_due_day && !drawn_days.contains(day)
+            })
+        {
+            run_scheduled_draw(connection, raffle, day);
+        }
+    }
+}
+
+/// Get all days of a raffle which were drawn before or which got winners in any other way (even
+/// if the winners were removed since).
+fn lookup_drawn_days(
+    connection: &mut PgConnection,
+    raffle_id: i32,
+) -> diesel::QueryResult<HashSet<NaiveDate>> {
+    use crate::schema::draws::dsl::{drawn_for, draws, raffle_id as draw_raffle_id};
+    use crate::schema::participants::dsl::{participants, raffle_id as participant_raffle_id};
+    use crate::schema::wins::dsl::{wins, won_on};
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    let mut drawn_days = draws
+        .filter(draw_raffle_id.eq(raffle_id))
+        .select(drawn_for)
+        .distinct()
+        .load::<NaiveDate>(connection)?
+        .into_iter()
+        .collect::<HashSet<NaiveDate>>();
+    drawn_days.extend(
+        wins.inner_join(participants)
+            .filter(participant_raffle_id.eq(raffle_id))
+            .select(won_on)
+            .distinct()
+            .load::<NaiveDate>(connection)?,
+    );
+    Ok(drawn_days)
+}
+
+/// Perform the scheduled draw of a single day of a raffle and log its result.
+fn run_scheduled_draw(connection: &mut PgConnection, raffle: &Raffle, date: NaiveDate) {
+    use crate::picking::{pick_scheduled_winners, PickError};
+    use crate::{log_action, Action};
+    use log::{error, info};
+
+    let description = match pick_scheduled_winners(connection, raffle, date) {
+        Ok(Some(drawn_winners)) => {
+            let winner_ids = drawn_winners
+                .winners
+                .iter()
+                .map(|winner| winner.participant.id)
+                .collect::<Vec<i32>>();
+            for winner_id in &winner_ids {
+                log_action(
+                    connection,
+                    None,
+                    Action::PickedWinner,
+                    Some(format!(
+                        "The participant with the id {} was marked as won in the raffle {} by the draw {}",
+                        winner_id, raffle.id, drawn_winners.draw.id
+                    )),
+                );
+            }
+            let description = format!(
+                "The scheduled draw {} picked the participants with the ids {:?} as winners of the {} in the raffle {}",
+                drawn_winners.draw.id, winner_ids, date, raffle.id
+            );
+            info!("{}", description);
+            description
+        }
+        Ok(None) => {
+            let description = format!(
+                "Skipped the scheduled draw of the {} in the raffle {} since the day already has winners",
+                date, raffle.id
+            );
+            info!("{}", description);
+            description
+        }
+        // an operator is performing a draw with a published commitment which must not be
+        // preempted, so the day is tried again with the next scheduled draws
+        Err(PickError::OpenCommitment) => {
+            let description = format!(
+                "Skipped the scheduled draw of the {} in the raffle {} since a commitment to a draw of the day is still open",
+                date, raffle.id
+            );
+            info!("{}", description);
+            description
+        }
+        Err(error) => {
+            let description = format!(
+                "The scheduled draw of the {} in the raffle {} failed since {}",
+                date, raffle.id, error
+            );
+            error!("{}", description);
+            description
+        }
+    };
+    log_action(connection, None, Action::ScheduledDraw, Some(description));
+}
+
+/// Perform the scheduled draws whenever they are due. When the server starts, all draws which are
+/// due so far (including the ones of previous days which were missed) are performed immediately
+/// (but skipped for the days which were drawn before).
+pub async fn schedule_draws(
+    db_connection_pool: AdventskalenderDatabaseConnection,
+    schedule: DrawSchedule,
+) {
+    use log::{error, info};
+
+    loop {
+        let db_connection_pool = db_connection_pool.clone();
+        // the draws use blocking database calls, so they must not block the async runtime
+        let result = rocket::tokio::task::spawn_blocking(move || {
+            match db_connection_pool.get() {
+                Ok(mut connection) => run_scheduled_draws(&mut connection, &schedule, Utc::now()),
+                Err(error) => error!(
+                    "Could not get a connection from the database connection pool for the scheduled draws. The error was: {}",
+                    error
+                ),
+            }
+        })
+        .await;
+        if let Err(error) = result {
+            error!("The scheduled draws failed. The error was: {}", error);
+        }
+
+        let next_run = schedule.next_run_after(Utc::now());
+        info!("The next scheduled draws are performed at {}", next_run);
+        let wait_time = (next_run - Utc::now()).to_std().unwrap_or_default();
+        rocket::tokio::time::sleep(wait_time).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn berlin_at_seven() -> DrawSchedule {
+        DrawSchedule::parse("07:00", "Europe/Berlin").unwrap()
+    }
+
+    #[test]
+    fn schedules_are_parsed() {
+        assert_eq!(
+            DrawSchedule::parse(" 07:30:15 ", "UTC"),
+            Some(DrawSchedule {
+                time: NaiveTime::from_hms_opt(7, 30, 15).unwrap(),
+                time_zone: Tz::UTC,
+            })
+        );
+        assert_eq!(DrawSchedule::parse("25:00", "UTC"), None);
+        assert_eq!(DrawSchedule::parse("07:00", "Mars/Olympus_Mons"), None);
+    }
+
+    #[test]
+    fn draws_are_due_at_the_local_time() {
+        let schedule = berlin_at_seven();
+        // 05:59 UTC is 06:59 in Berlin during the winter
+        let before = Utc.with_ymd_and_hms(2026, 12, 3, 5, 59, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2026, 12, 3, 6, 0, 0).unwrap();
+        assert_eq!(
+            Some(schedule.last_due_day(before)),
+            NaiveDate::from_ymd_opt(2026, 12, 2)
+        );
+        assert_eq!(
+            Some(schedule.last_due_day(after)),
+            NaiveDate::from_ymd_opt(2026, 12, 3)
+        );
+        assert_eq!(
+            Some(schedule.first_day_due_after(before)),
+            NaiveDate::from_ymd_opt(2026, 12, 3)
+        );
+        assert_eq!(
+            Some(schedule.first_day_due_after(after)),
+            NaiveDate::from_ymd_opt(2026, 12, 4)
+        );
+        assert_eq!(schedule.next_run_after(before), after);
+        assert_eq!(
+            schedule.next_run_after(after),
+            Utc.with_ymd_and_hms(2026, 12, 4, 6, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn daylight_saving_time_is_respected() {
+        // 07:00 in Berlin is 05:00 UTC during the summer
+        let schedule = berlin_at_seven();
+        let now = Utc.with_ymd_and_hms(2026, 7, 1, 12, 0, 0).unwrap();
+        assert_eq!(
+            schedule.next_run_after(now),
+            Utc.with_ymd_and_hms(2026, 7, 2, 5, 0, 0).unwrap()
+        );
+
+        // 02:30 does not exist on the day the clocks are moved forward
+        let schedule = DrawSchedule::parse("02:30", "Europe/Berlin").unwrap();
+        let now = Utc.with_ymd_and_hms(2026, 3, 28, 12, 0, 0).unwrap();
+        assert_eq!(
+            schedule.next_run_after(now),
+            Utc.with_ymd_and_hms(2026, 3, 29, 1, 30, 0).unwrap()
+        );
+    }
+}
@@ -7,11 +7,59 @@ diesel::table! {
         first_name -> Varchar,
         #[max_length = 32]
         last_name -> Varchar,
-        won_on -> Nullable<Date>,
-        picked_by -> Nullable<Int4>,
-        picking_time -> Nullable<Timestamp>,
-        #[max_length = 1]
-        present_identifier -> Nullable<Varchar>,
+        raffle_id -> Int4,
+        tickets -> Int4,
+        #[max_length = 64]
+        department -> Nullable<Varchar>,
+        blocked -> Bool,
+        present_preferences -> Array<Varchar>,
+    }
+}
+
+diesel::table! {
+    participant_absences (id) {
+        id -> Int4,
+        participant_id -> Int4,
+        start_date -> Date,
+        end_date -> Date,
+    }
+}
+
+diesel::table! {
+    draw_commitments (id) {
+        id -> Int4,
+        raffle_id -> Int4,
+        drawn_for -> Date,
+        winner_count -> Int4,
+        seed -> Bytea,
+        #[max_length = 64]
+        seed_hash -> Varchar,
+        #[max_length = 64]
+        candidates_hash -> Varchar,
+        committed_at -> Timestamp,
+        committed_by -> Nullable<Int4>,
+        revealed_at -> Nullable<Timestamp>,
+        draw_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    draws (id) {
+        id -> Int4,
+        raffle_id -> Int4,
+        drawn_for -> Date,
+        drawn_at -> Timestamp,
+        drawn_by -> Nullable<Int4>,
+        algorithm_version -> Int4,
+        seed -> Bytea,
+        candidate_ids -> Array<Int4>,
+        #[max_length = 64]
+        candidates_hash -> Varchar,
+        winner_ids -> Array<Int4>,
+        candidate_weights -> Nullable<Array<Int4>>,
+        candidate_groups -> Nullable<Array<Nullable<Int4>>>,
+        group_capacities -> Nullable<Array<Int4>>,
+        rules -> Nullable<Text>,
     }
 }
 
@@ -26,6 +74,94 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    presents (id) {
+        id -> Int4,
+        raffle_id -> Int4,
+        available_on -> Date,
+        #[max_length = 8]
+        identifier -> Varchar,
+        #[max_length = 128]
+        name -> Varchar,
+        description -> Nullable<Text>,
+        #[max_length = 128]
+        sponsor -> Nullable<Varchar>,
+        quantity -> Int4,
+    }
+}
+
+diesel::table! {
+    raffles (id) {
+        id -> Int4,
+        #[max_length = 64]
+        name -> Varchar,
+        start_date -> Date,
+        end_date -> Date,
+        archived -> Bool,
+        exclude_weekends -> Bool,
+        excluded_dates -> Array<Date>,
+        winners_per_day -> Int4,
+        use_tickets -> Bool,
+        max_winners_per_department -> Nullable<Int4>,
+        #[max_length = 16]
+        present_assignment -> Varchar,
+        scheduled_draws -> Bool,
+        scheduled_draws_enabled_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    recovery_codes (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 64]
+        code_hash -> Varchar,
+        used_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 32]
+        family -> Varchar,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        issued_at -> Timestamp,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    second_factors (user_id) {
+        user_id -> Int4,
+        #[max_length = 128]
+        secret -> Varchar,
+        created_at -> Timestamp,
+        confirmed_at -> Nullable<Timestamp>,
+        last_used_step -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        #[max_length = 32]
+        id -> Varchar,
+        user_id -> Int4,
+        created_at -> Timestamp,
+        last_used_at -> Timestamp,
+        expires_at -> Timestamp,
+        #[max_length = 256]
+        user_agent -> Nullable<Varchar>,
+        #[max_length = 64]
+        ip_address -> Nullable<Varchar>,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Int4,
@@ -33,10 +169,77 @@ diesel::table! {
         username -> Varchar,
         #[max_length = 255]
         password_hash -> Varchar,
+        #[max_length = 16]
+        role -> Varchar,
+        disabled -> Bool,
+        #[max_length = 255]
+        external_subject -> Nullable<Varchar>,
+        must_change_password -> Bool,
+    }
+}
+
+diesel::table! {
+    winner_replacements (id) {
+        id -> Int4,
+        raffle_id -> Int4,
+        #[max_length = 16]
+        reason -> Varchar,
+        replaced_at -> Timestamp,
+        replaced_by -> Nullable<Int4>,
+        win_id -> Int4,
+        substitute_win_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    wins (id) {
+        id -> Int4,
+        participant_id -> Int4,
+        won_on -> Date,
+        picked_by -> Nullable<Int4>,
+        picked_at -> Nullable<Timestamp>,
+        #[max_length = 16]
+        status -> Varchar,
+        revoked_at -> Nullable<Timestamp>,
+        draw_id -> Nullable<Int4>,
+        present_id -> Nullable<Int4>,
     }
 }
 
-diesel::joinable!(participants -> users (picked_by));
+diesel::joinable!(draw_commitments -> draws (draw_id));
+diesel::joinable!(draw_commitments -> raffles (raffle_id));
+diesel::joinable!(draw_commitments -> users (committed_by));
+diesel::joinable!(draws -> raffles (raffle_id));
+diesel::joinable!(draws -> users (drawn_by));
+diesel::joinable!(participant_absences -> participants (participant_id));
+diesel::joinable!(participants -> raffles (raffle_id));
 diesel::joinable!(performed_actions -> users (user_id));
+diesel::joinable!(presents -> raffles (raffle_id));
+diesel::joinable!(recovery_codes -> users (user_id));
+diesel::joinable!(refresh_tokens -> sessions (family));
+diesel::joinable!(refresh_tokens -> users (user_id));
+diesel::joinable!(second_factors -> users (user_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(winner_replacements -> raffles (raffle_id));
+diesel::joinable!(winner_replacements -> wins (win_id));
+diesel::joinable!(wins -> draws (draw_id));
+diesel::joinable!(wins -> participants (participant_id));
+diesel::joinable!(wins -> presents (present_id));
+diesel::joinable!(wins -> users (picked_by));
 
-diesel::allow_tables_to_appear_in_same_query!(participants, performed_actions, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    draw_commitments,
+    draws,
+    participant_absences,
+    participants,
+    performed_actions,
+    presents,
+    raffles,
+    recovery_codes,
+    refresh_tokens,
+    second_factors,
+    sessions,
+    users,
+    winner_replacements,
+    wins,
+);
@@ -0,0 +1,221 @@
+use crate::models::Session;
+use chrono::NaiveDateTime;
+use diesel::result::Error as DieselError;
+use diesel::{PgConnection, QueryResult};
+use ring::error::Unspecified;
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+
+/// The number of random bytes of the identifiers of the sessions and the tokens.
+const IDENTIFIER_BYTES: usize = 16;
+
+/// The maximum number of characters of the user agent stored for a session.
+const MAX_USER_AGENT_LENGTH: usize = 256;
+
+/// The reasons why a session or one of its tokens could not be created.
+#[derive(Debug)]
+pub enum SessionError {
+    /// No random token could be generated.
+    NoRandomness,
+    /// The database failed to store or look up the session.
+    Database(DieselError),
+}
+
+impl Display for SessionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::NoRandomness => write!(f, "no random token could be generated"),
+            SessionError::Database(error) => write!(f, "the database failed with: {}", error),
+        }
+    }
+}
+
+impl From<DieselError> for SessionError {
+    fn from(error: DieselError) -> Self {
+        SessionError::Database(error)
+    }
+}
+
+impl From<Unspecified> for SessionError {
+    fn from(_: Unspecified) -> Self {
+        SessionError::NoRandomness
+    }
+}
+
+/// Generate a new random identifier (32 hex characters) for a session or a token.
+pub fn random_identifier() -> Result<String, Unspecified> {
+    use crate::draw::to_hex;
+    use ring::rand::{SecureRandom, SystemRandom};
+
+    let mut bytes = [0u8; IDENTIFIER_BYTES];
+    SystemRandom::new().fill(&mut bytes)?;
+    Ok(to_hex(&bytes))
+}
+
+/// Start a new session for a login of the user and return its id. The session expires together
+/// with the refresh tokens issued for it.
+pub fn create_session(
+    connection: &mut PgConnection,
+    user_id: i32,
+    user_agent: Option<&str>,
+    ip_address: Option<String>,
+    lifetime_in_seconds: usize,
+) -> Result<String, SessionError> {
+    use crate::models::NewSession;
+    use crate::schema::sessions::dsl;
+    use chrono::{Duration, Utc};
+    use diesel::{delete, insert_into, ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    // the expired sessions of the user are not needed anymore
+    let now = Utc::now().naive_utc();
+    delete(
+        dsl::sessions
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::expires_at.lt(now)),
+    )
+    .execute(connection)?;
+
+    let session_id = random_identifier()?;
+    insert_into(dsl::sessions)
+        .values(&NewSession {
+            id: session_id.clone(),
+            user_id,
+            expires_at: now + Duration::seconds(lifetime_in_seconds as i64),
+            user_agent: user_agent
+                .map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_LENGTH).collect()),
+            ip_address,
+        })
+        .execute(connection)?;
+    Ok(session_id)
+}
+
+/// Mark the session as used now and extend it by the supplied lifetime (e.g. when its refresh
+/// token was exchanged).
+pub fn extend_session(
+    connection: &mut PgConnection,
+    session_id: &str,
+    lifetime_in_seconds: usize,
+) -> QueryResult<usize> {
+    use crate::schema::sessions::dsl;
+    use chrono::{Duration, Utc};
+    use diesel::{update, ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    let now = Utc::now().naive_utc();
+    update(dsl::sessions.find(session_id))
+        .set((
+            dsl::last_used_at.eq(now),
+            dsl::expires_at.eq(now + Duration::seconds(lifetime_in_seconds as i64)),
+        ))
+        .execute(connection)
+}
+
+/// Check if the session belongs to the user and was neither revoked nor did it expire.
+pub fn is_session_active(
+    connection: &mut PgConnection,
+    session_id: &str,
+    user_id: i32,
+) -> QueryResult<bool> {
+    use crate::schema::sessions::dsl;
+    use chrono::Utc;
+    use diesel::dsl::{exists, select};
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    select(exists(
+        dsl::sessions
+            .filter(dsl::id.eq(session_id))
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::revoked_at.is_null())
+            .filter(dsl::expires_at.gt(Utc::now().naive_utc())),
+    ))
+    .get_result(connection)
+}
+
+/// Get the active sessions of the user, the most recently used one first.
+pub fn active_sessions(connection: &mut PgConnection, user_id: i32) -> QueryResult<Vec<Session>> {
+    use crate::schema::sessions::dsl;
+    use chrono::Utc;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    dsl::sessions
+        .filter(dsl::user_id.eq(user_id))
+        .filter(dsl::revoked_at.is_null())
+        .filter(dsl::expires_at.gt(Utc::now().naive_utc()))
+        .order(dsl::last_used_at.desc())
+        .load::<Session>(connection)
+}
+
+/// Revoke the sessions of the user (all of them if no session id is supplied) except the one
+/// which should be kept. The refresh tokens of the sessions are revoked as well. Returns the
+/// number of revoked sessions.
+pub fn revoke_sessions(
+    connection: &mut PgConnection,
+    user_id: i32,
+    session_id: Option<&str>,
+    keep_session_id: Option<&str>,
+) -> QueryResult<usize> {
+    use crate::schema::refresh_tokens::dsl as refresh_tokens;
+    use crate::schema::sessions::dsl;
+    use chrono::Utc;
+    use diesel::{update, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    let now = Utc::now().naive_utc();
+    connection.transaction(|connection| {
+        let mut query = dsl::sessions
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::revoked_at.is_null())
+            .select(dsl::id)
+            .into_boxed();
+        if let Some(session_id) = session_id {
+            query = query.filter(dsl::id.eq(session_id));
+        }
+        if let Some(keep_session_id) = keep_session_id {
+            query = query.filter(dsl::id.ne(keep_session_id));
+        }
+        let session_ids = query.load::<String>(connection)?;
+
+        update(dsl::sessions.filter(dsl::id.eq_any(&session_ids)))
+            .set(dsl::revoked_at.eq(now))
+            .execute(connection)?;
+        update(
+            refresh_tokens::refresh_tokens
+                .filter(refresh_tokens::family.eq_any(&session_ids))
+                .filter(refresh_tokens::revoked_at.is_null()),
+        )
+        .set(refresh_tokens::revoked_at.eq(now))
+        .execute(connection)?;
+        Ok(session_ids.len())
+    })
+}
+
+/// The information about a session which is shown to its user.
+#[derive(Serialize)]
+pub struct SessionSummary {
+    /// The id of the session.
+    pub id: String,
+    /// When the user logged in.
+    pub created_at: NaiveDateTime,
+    /// When the tokens of the session were renewed the last time.
+    pub last_used_at: NaiveDateTime,
+    /// When the session expires if its tokens are not renewed anymore.
+    pub expires_at: NaiveDateTime,
+    /// The user agent (e.g. the browser) used for the login.
+    pub user_agent: Option<String>,
+    /// The IP address the login came from.
+    pub ip_address: Option<String>,
+    /// Whether the session is the one of the requesting user.
+    pub current: bool,
+}
+
+impl SessionSummary {
+    pub fn new(session: Session, current_session_id: &str) -> Self {
+        SessionSummary {
+            current: session.id == current_session_id,
+            id: session.id,
+            created_at: session.created_at,
+            last_used_at: session.last_used_at,
+            expires_at: session.expires_at,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+        }
+    }
+}
@@ -0,0 +1,602 @@
+use crate::guards::Role;
+use crate::models::SecondFactor;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use diesel::result::Error as DieselError;
+use diesel::{PgConnection, QueryResult};
+use ring::error::Unspecified;
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+use std::sync::LazyLock;
+
+/// The number of seconds a TOTP code is valid (RFC 6238).
+const TOTP_STEP_SECONDS: i64 = 30;
+
+/// The number of digits of a TOTP code.
+const TOTP_DIGITS: u32 = 6;
+
+/// The number of random bytes of a TOTP secret (160 bits, as recommended by RFC 4226).
+const TOTP_SECRET_BYTES: usize = 20;
+
+/// The number of time steps a code may be off (to allow for clocks which are not in sync).
+const TOTP_ALLOWED_DRIFT: i64 = 1;
+
+/// The issuer which is shown in the authenticator apps.
+const TOTP_ISSUER: &str = "Adventskalender";
+
+/// The number of recovery codes which are issued with a confirmed enrollment.
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// The number of characters of a recovery code (without the separator).
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+/// The time a user has to supply the second factor after the password was accepted.
+const CHALLENGE_LIFETIME_IN_MINUTES: i64 = 5;
+
+/// The number of wrong codes which are accepted for one login before it has to be started again.
+const MAX_CHALLENGE_ATTEMPTS: u32 = 5;
+
+/// The alphabet of the base32 encoding (RFC 4648) used for the secrets in the authenticator apps.
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The reasons why a second factor could not be enrolled or checked.
+#[derive(Debug)]
+pub enum SecondFactorError {
+    /// No random secret (or recovery code) could be generated.
+    NoRandomness,
+    /// The database failed to store or look up the second factor.
+    Database(DieselError),
+}
+
+impl Display for SecondFactorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SecondFactorError::NoRandomness => {
+                write!(f, "no random secret could be generated")
+            }
+            SecondFactorError::Database(error) => write!(f, "the database failed with: {}", error),
+        }
+    }
+}
+
+impl From<DieselError> for SecondFactorError {
+    fn from(error: DieselError) -> Self {
+        SecondFactorError::Database(error)
+    }
+}
+
+impl From<Unspecified> for SecondFactorError {
+    fn from(_: Unspecified) -> Self {
+        SecondFactorError::NoRandomness
+    }
+}
+
+/// Encrypts the TOTP secrets before they are stored in the database (AES-256-GCM).
+#[derive(Clone)]
+pub struct SecretCipher {
+    key: [u8; 32],
+}
+
+impl SecretCipher {
+    /// Create the cipher from a key of 64 hex characters (e.g. generated with
+    /// `openssl rand -hex 32`).
+    pub fn from_hex(key: &str) -> Result<SecretCipher, String> {
+        let invalid_key = || "The key has to consist of 64 hex characters (32 bytes)".to_string();
+        if key.len() != 64 || !key.is_ascii() {
+            return Err(invalid_key());
+        }
+        let mut bytes = [0u8; 32];
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&key[index * 2..index * 2 + 2], 16)
+                .map_err(|_| invalid_key())?;
+        }
+        Ok(SecretCipher { key: bytes })
+    }
+
+    fn key(&self) -> ring::aead::LessSafeKey {
+        use ring::aead::{LessSafeKey, UnboundKey, AES_256_GCM};
+
+        LessSafeKey::new(
+            UnboundKey::new(&AES_256_GCM, &self.key).expect("the key has the length of AES-256"),
+        )
+    }
+
+    /// Encrypt the secret of the user. The id of the user is authenticated as well, so the secret
+    /// cannot be copied to another user.
+    pub fn encrypt(&self, user_id: i32, secret: &[u8]) -> Result<String, Unspecified> {
+        use base64::engine::general_purpose::STANDARD;
+        use base64::Engine;
+        use ring::aead::{Aad, Nonce, NONCE_LEN};
+        use ring::rand::{SecureRandom, SystemRandom};
+
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce)?;
+        let mut encrypted = secret.to_vec();
+        self.key().seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(user_id.to_be_bytes()),
+            &mut encrypted,
+        )?;
+        Ok(STANDARD.encode([nonce.as_slice(), &encrypted].concat()))
+    }
+
+    /// Decrypt the secret of the user (or `None` if it was encrypted with another key or for
+    /// another user).
+    pub fn decrypt(&self, user_id: i32, encrypted_secret: &str) -> Option<Vec<u8>> {
+        use base64::engine::general_purpose::STANDARD;
+        use base64::Engine;
+        use ring::aead::{Aad, Nonce, NONCE_LEN};
+
+        let bytes = STANDARD.decode(encrypted_secret).ok()?;
+        if bytes.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, encrypted) = bytes.split_at(NONCE_LEN);
+        let mut encrypted = encrypted.to_vec();
+        let secret = self
+            .key()
+            .open_in_place(
+                Nonce::try_assume_unique_for_key(nonce).ok()?,
+                Aad::from(user_id.to_be_bytes()),
+                &mut encrypted,
+            )
+            .ok()?;
+        Some(secret.to_vec())
+    }
+}
+
+/// The settings of the second factor for the login.
+#[derive(Clone)]
+pub struct TotpSettings {
+    /// The cipher for the secrets stored in the database.
+    pub cipher: SecretCipher,
+    /// The roles whose users have to use a second factor.
+    pub required_roles: Vec<Role>,
+}
+
+/// Encode bytes with base32 (RFC 4648, without padding).
+fn base32(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+fn random_bytes(length: usize) -> Result<Vec<u8>, Unspecified> {
+    use ring::rand::{SecureRandom, SystemRandom};
+
+    let mut bytes = vec![0u8; length];
+    SystemRandom::new().fill(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Calculate the TOTP code of the secret for the supplied time step (RFC 6238 with HMAC-SHA1).
+pub fn totp_code(secret: &[u8], step: i64) -> String {
+    use ring::hmac::{sign, Key, HMAC_SHA1_FOR_LEGACY_USE_ONLY};
+
+    let signature = sign(
+        &Key::new(HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret),
+        &step.to_be_bytes(),
+    );
+    let hash = signature.as_ref();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+/// Find the time step of a TOTP code which is valid at the supplied time. Codes of time steps
+/// which were already used are rejected, so a code cannot be replayed.
+fn matching_step(
+    secret: &[u8],
+    code: &str,
+    now: DateTime<Utc>,
+    last_used_step: Option<i64>,
+) -> Option<i64> {
+    let current_step = now.timestamp() / TOTP_STEP_SECONDS;
+    (current_step - TOTP_ALLOWED_DRIFT..=current_step + TOTP_ALLOWED_DRIFT)
+        .filter(|step| last_used_step.map_or(true, |last_used_step| *step > last_used_step))
+        .find(|step| totp_code(secret, *step) == code)
+}
+
+/// Get the URI which configures an authenticator app (usually shown as QR code).
+pub fn provisioning_uri(username: &str, secret: &[u8]) -> String {
+    use url::form_urlencoded::byte_serialize;
+
+    let label = format!("{}:{}", TOTP_ISSUER, username);
+    format!(
+        "otpauth://totp/{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        byte_serialize(label.as_bytes()).collect::<String>(),
+        base32(secret),
+        byte_serialize(TOTP_ISSUER.as_bytes()).collect::<String>(),
+        TOTP_DIGITS,
+        TOTP_STEP_SECONDS
+    )
+}
+
+/// Normalize a recovery code as it was typed by the user (e.g. without the separator) and
+/// calculate the hash which is stored instead of the code itself.
+fn hash_recovery_code(code: &str) -> String {
+    use crate::draw::to_hex;
+    use ring::digest::{digest, SHA256};
+
+    let normalized = code
+        .chars()
+        .filter(|character| character.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+    to_hex(digest(&SHA256, normalized.as_bytes()).as_ref())
+}
+
+/// Generate a new recovery code (e.g. `k3pq7-zx2ma`).
+fn generate_recovery_code() -> Result<String, Unspecified> {
+    let code = base32(&random_bytes(RECOVERY_CODE_LENGTH)?)[..RECOVERY_CODE_LENGTH].to_lowercase();
+    Ok(format!(
+        "{}-{}",
+        &code[..RECOVERY_CODE_LENGTH / 2],
+        &code[RECOVERY_CODE_LENGTH / 2..]
+    ))
+}
+
+/// The information an authenticator app needs to generate the codes of a user.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpEnrollment {
+    /// The secret (base32 encoded) to type into an authenticator app.
+    pub secret: String,
+    /// The `otpauth://` URI to show as QR code.
+    pub provisioning_uri: String,
+}
+
+/// Get the second factor of the user (or `None` if the user has none).
+pub fn second_factor_of(
+    connection: &mut PgConnection,
+    user_id: i32,
+) -> QueryResult<Option<SecondFactor>> {
+    use crate::schema::second_factors::dsl::second_factors;
+    use diesel::{OptionalExtension, QueryDsl, RunQueryDsl};
+
+    second_factors
+        .find(user_id)
+        .first::<SecondFactor>(connection)
+        .optional()
+}
+
+/// Check if the user has a confirmed second factor, i.e. it has to be used for the login.
+pub fn has_confirmed_second_factor(
+    connection: &mut PgConnection,
+    user_id: i32,
+) -> QueryResult<bool> {
+    Ok(second_factor_of(connection, user_id)?
+        .is_some_and(|second_factor| second_factor.confirmed_at.is_some()))
+}
+
+/// Create a new TOTP secret for the user. It replaces a secret which was not confirmed yet and is
+/// just used for the login after it was confirmed with a first code. `None` is returned if the
+/// user already has a confirmed second factor.
+pub fn start_enrollment(
+    connection: &mut PgConnection,
+    cipher: &SecretCipher,
+    user_id: i32,
+    username: &str,
+) -> Result<Option<TotpEnrollment>, SecondFactorError> {
+    use crate::models::NewSecondFactor;
+    use crate::schema::second_factors::dsl::{confirmed_at, second_factors};
+    use diesel::{delete, insert_into, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    connection.transaction(|connection| {
+        if has_confirmed_second_factor(connection, user_id)? {
+            return Ok(None);
+        }
+        delete(second_factors.find(user_id).filter(confirmed_at.is_null())).execute(connection)?;
+
+        let secret = random_bytes(TOTP_SECRET_BYTES)?;
+        insert_into(second_factors)
+            .values(&NewSecondFactor {
+                user_id,
+                secret: cipher.encrypt(user_id, &secret)?,
+            })
+            .execute(connection)?;
+        Ok(Some(TotpEnrollment {
+            secret: base32(&secret),
+            provisioning_uri: provisioning_uri(username, &secret),
+        }))
+    })
+}
+
+/// The result of checking the second factor of a login.
+#[derive(Debug, PartialEq)]
+pub enum SecondFactorCheck {
+    /// A valid TOTP code was supplied. If it confirmed the enrollment, the new recovery codes are
+    /// included (they are shown just once).
+    Totp { recovery_codes: Option<Vec<String>> },
+    /// An unused recovery code was supplied (it cannot be used again).
+    RecoveryCode,
+    /// The code is wrong, was already used or the user has no second factor.
+    Rejected,
+}
+
+/// Check the TOTP code or recovery code supplied by the user. A TOTP code for a secret which was
+/// not confirmed yet confirms the enrollment and replaces the recovery codes of the user.
+pub fn check_second_factor(
+    connection: &mut PgConnection,
+    cipher: &SecretCipher,
+    user_id: i32,
+    code: &str,
+) -> Result<SecondFactorCheck, SecondFactorError> {
+    use crate::models::NewRecoveryCode;
+    use crate::schema::recovery_codes::dsl as recovery_codes;
+    use crate::schema::second_factors::dsl::{confirmed_at, last_used_step, second_factors};
+    use diesel::{
+        delete, insert_into, update, BoolExpressionMethods, Connection, ExpressionMethods,
+        QueryDsl, RunQueryDsl,
+    };
+    use log::error;
+
+    connection.transaction(|connection| {
+        let Some(second_factor) = second_factor_of(connection, user_id)? else {
+            return Ok(SecondFactorCheck::Rejected);
+        };
+        let is_confirmed = second_factor.confirmed_at.is_some();
+
+        let code = code.trim();
+        if code.len() != TOTP_DIGITS as usize || !code.chars().all(|digit| digit.is_ascii_digit())
+        {
+            // recovery codes can just be used after the enrollment was confirmed
+            if !is_confirmed {
+                return Ok(SecondFactorCheck::Rejected);
+            }
+            let used_codes = update(
+                recovery_codes::recovery_codes
+                    .filter(recovery_codes::user_id.eq(user_id))
+                    .filter(recovery_codes::code_hash.eq(hash_recovery_code(code)))
+                    .filter(recovery_codes::used_at.is_null()),
+            )
+            .set(recovery_codes::used_at.eq(Utc::now().naive_utc()))
+            .execute(connection)?;
+            return Ok(match used_codes {
+                0 => SecondFactorCheck::Rejected,
+                _ => SecondFactorCheck::RecoveryCode,
+            });
+        }
+
+        let Some(secret) = cipher.decrypt(user_id, &second_factor.secret) else {
+            error!(
+                "The TOTP secret of the user {} cannot be decrypted, was the totp_encryption_key changed?",
+                user_id
+            );
+            return Ok(SecondFactorCheck::Rejected);
+        };
+        let Some(step) = matching_step(&secret, code, Utc::now(), second_factor.last_used_step)
+        else {
+            return Ok(SecondFactorCheck::Rejected);
+        };
+
+        // the step is stored at once, so parallel logins cannot use the same code
+        let is_unused = update(
+            second_factors.find(user_id).filter(
+                last_used_step
+                    .is_null()
+                    .or(last_used_step.lt(step)),
+            ),
+        )
+        .set((
+            last_used_step.eq(step),
+            confirmed_at.eq(second_factor
+                .confirmed_at
+                .unwrap_or(Utc::now().naive_utc())),
+        ))
+        .execute(connection)?
+            == 1;
+        if !is_unused {
+            return Ok(SecondFactorCheck::Rejected);
+        }
+        if is_confirmed {
+            return Ok(SecondFactorCheck::Totp {
+                recovery_codes: None,
+            });
+        }
+
+        // the enrollment is confirmed now, so the user gets a new set of recovery codes
+        delete(recovery_codes::recovery_codes.filter(recovery_codes::user_id.eq(user_id)))
+            .execute(connection)?;
+        let codes = (0..RECOVERY_CODE_COUNT)
+            .map(|_| generate_recovery_code())
+            .collect::<Result<Vec<String>, Unspecified>>()?;
+        insert_into(recovery_codes::recovery_codes)
+            .values(
+                codes
+                    .iter()
+                    .map(|code| NewRecoveryCode {
+                        user_id,
+                        code_hash: hash_recovery_code(code),
+                    })
+                    .collect::<Vec<NewRecoveryCode>>(),
+            )
+            .execute(connection)?;
+        Ok(SecondFactorCheck::Totp {
+            recovery_codes: Some(codes),
+        })
+    })
+}
+
+/// Remove the second factor and the recovery codes of the user. Returns `false` if the user had no
+/// second factor.
+pub fn remove_second_factor(connection: &mut PgConnection, user_id: i32) -> QueryResult<bool> {
+    use crate::schema::recovery_codes::dsl as recovery_codes;
+    use crate::schema::second_factors::dsl::second_factors;
+    use diesel::{delete, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    connection.transaction(|connection| {
+        delete(recovery_codes::recovery_codes.filter(recovery_codes::user_id.eq(user_id)))
+            .execute(connection)?;
+        Ok(delete(second_factors.find(user_id)).execute(connection)? > 0)
+    })
+}
+
+/// A login whose password was accepted and which waits for the second factor. It is identified by
+/// a random id which is returned instead of the tokens.
+struct PendingChallenge {
+    user_id: i32,
+    username: String,
+    started_at: DateTime<Utc>,
+    failed_attempts: u32,
+}
+
+static PENDING_CHALLENGES: LazyLock<DashMap<String, PendingChallenge>> =
+    LazyLock::new(DashMap::new);
+
+fn is_expired(challenge: &PendingChallenge, now: DateTime<Utc>) -> bool {
+    challenge.started_at + Duration::minutes(CHALLENGE_LIFETIME_IN_MINUTES) < now
+}
+
+/// Start the second step of the login of the user and return the id of the challenge.
+pub fn create_challenge(user_id: i32, username: &str) -> Result<String, Unspecified> {
+    use crate::sessions::random_identifier;
+
+    // the challenges which were never answered are not needed anymore
+    let now = Utc::now();
+    PENDING_CHALLENGES.retain(|_, challenge| !is_expired(challenge, now));
+
+    let challenge_id = random_identifier()?;
+    PENDING_CHALLENGES.insert(
+        challenge_id.clone(),
+        PendingChallenge {
+            user_id,
+            username: username.to_string(),
+            started_at: now,
+            failed_attempts: 0,
+        },
+    );
+    Ok(challenge_id)
+}
+
+/// Get the id and the name of the user of a challenge (or `None` if the challenge is unknown or
+/// expired).
+pub fn challenge_user(challenge_id: &str) -> Option<(i32, String)> {
+    let challenge = PENDING_CHALLENGES.get(challenge_id)?;
+    if is_expired(&challenge, Utc::now()) {
+        drop(challenge);
+        PENDING_CHALLENGES.remove(challenge_id);
+        return None;
+    }
+    Some((challenge.user_id, challenge.username.clone()))
+}
+
+/// Record a wrong code for the challenge. After too many wrong codes the challenge is dropped, so
+/// the login has to be started again with the password.
+pub fn record_failed_attempt(challenge_id: &str) {
+    let is_exhausted = match PENDING_CHALLENGES.get_mut(challenge_id) {
+        Some(mut challenge) => {
+            challenge.failed_attempts += 1;
+            challenge.failed_attempts >= MAX_CHALLENGE_ATTEMPTS
+        }
+        None => false,
+    };
+    if is_exhausted {
+        PENDING_CHALLENGES.remove(challenge_id);
+    }
+}
+
+/// Finish the challenge after the second factor was accepted, so it cannot be used again.
+pub fn finish_challenge(challenge_id: &str) {
+    PENDING_CHALLENGES.remove(challenge_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_follow_rfc_6238() {
+        // the SHA1 test vectors of appendix B of RFC 6238 (the last 6 of the 8 digits)
+        let secret = b"12345678901234567890";
+        assert_eq!(totp_code(secret, 59 / 30), "287082");
+        assert_eq!(totp_code(secret, 1111111109 / 30), "081804");
+        assert_eq!(totp_code(secret, 1234567890 / 30), "005924");
+        assert_eq!(totp_code(secret, 2000000000 / 30), "279037");
+    }
+
+    #[test]
+    fn used_and_outdated_codes_are_rejected() {
+        let secret = b"12345678901234567890";
+        let now = DateTime::from_timestamp(1234567890, 0).unwrap();
+        let step = 1234567890 / 30;
+
+        assert_eq!(matching_step(secret, "005924", now, None), Some(step));
+        assert_eq!(
+            matching_step(secret, &totp_code(secret, step - 1), now, None),
+            Some(step - 1)
+        );
+        assert_eq!(matching_step(secret, "005924", now, Some(step)), None);
+        assert_eq!(
+            matching_step(secret, &totp_code(secret, step - 2), now, None),
+            None
+        );
+    }
+
+    #[test]
+    fn secrets_are_encoded_for_authenticator_apps() {
+        assert_eq!(
+            base32(b"12345678901234567890"),
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+        );
+        assert_eq!(
+            provisioning_uri("jane", b"12345678901234567890"),
+            "otpauth://totp/Adventskalender%3Ajane?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Adventskalender&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn secrets_are_bound_to_the_key_and_the_user() {
+        let cipher = SecretCipher::from_hex(&"ab".repeat(32)).unwrap();
+        let encrypted = cipher.encrypt(42, b"secret").unwrap();
+        assert_eq!(cipher.decrypt(42, &encrypted), Some(b"secret".to_vec()));
+        assert_eq!(cipher.decrypt(43, &encrypted), None);
+        let other_cipher = SecretCipher::from_hex(&"cd".repeat(32)).unwrap();
+        assert_eq!(other_cipher.decrypt(42, &encrypted), None);
+        assert!(SecretCipher::from_hex("too short").is_err());
+    }
+
+    #[test]
+    fn recovery_codes_are_normalized() {
+        let code = generate_recovery_code().unwrap();
+        assert_eq!(code.len(), RECOVERY_CODE_LENGTH + 1);
+        assert_eq!(
+            hash_recovery_code(&code),
+            hash_recovery_code(&code.replace('-', "").to_uppercase())
+        );
+    }
+
+    #[test]
+    fn challenges_are_dropped_after_too_many_attempts() {
+        let challenge_id = create_challenge(42, "jane").unwrap();
+        assert_eq!(
+            challenge_user(&challenge_id),
+            Some((42, "jane".to_string()))
+        );
+        for _ in 0..MAX_CHALLENGE_ATTEMPTS {
+            record_failed_attempt(&challenge_id);
+        }
+        assert_eq!(challenge_user(&challenge_id), None);
+    }
+}
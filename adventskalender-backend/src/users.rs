@@ -0,0 +1,286 @@
+use crate::guards::Role;
+use crate::models::User;
+use crate::oidc::ExternalIdentity;
+use diesel::{PgConnection, QueryResult};
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+
+/// The cost factor used by bcrypt to hash the passwords of the users.
+pub const PASSWORD_HASH_COST: u32 = 10;
+
+/// The minimum number of characters of a password.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// The minimum score (0 to 100) a password has to reach with the scorer of the `passwords` crate.
+/// A score of 40 represents a "fair" password (roughly equivalent to a zxcvbn score of 2).
+pub const MIN_PASSWORD_SCORE: f64 = 40.0;
+
+/// The maximum length of the name of a user.
+pub const MAX_USERNAME_LENGTH: usize = 64;
+
+/// The reasons why a new password is refused.
+#[derive(Debug, PartialEq)]
+pub enum PasswordRejection {
+    /// The repeated password differs from the first one.
+    Mismatch,
+    /// The password has less than [`MIN_PASSWORD_LENGTH`] characters.
+    TooShort,
+    /// The password does not reach the [`MIN_PASSWORD_SCORE`] (the score is included).
+    TooWeak(f64),
+}
+
+impl Display for PasswordRejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordRejection::Mismatch => write!(f, "the repeated password does not match"),
+            PasswordRejection::TooShort => write!(
+                f,
+                "the password is too short (minimum {} characters)",
+                MIN_PASSWORD_LENGTH
+            ),
+            PasswordRejection::TooWeak(score) => write!(
+                f,
+                "the password is too weak (score: {}, minimum required: {})",
+                score, MIN_PASSWORD_SCORE
+            ),
+        }
+    }
+}
+
+/// Check if a new password (and its repetition) can be used as password of a user.
+pub fn check_new_password(first_time: &str, second_time: &str) -> Result<(), PasswordRejection> {
+    use passwords::analyzer;
+    use passwords::scorer;
+
+    if first_time != second_time {
+        return Err(PasswordRejection::Mismatch);
+    }
+    if first_time.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(PasswordRejection::TooShort);
+    }
+    let score = scorer::score(&analyzer::analyze(first_time));
+    if score < MIN_PASSWORD_SCORE {
+        return Err(PasswordRejection::TooWeak(score));
+    }
+    Ok(())
+}
+
+/// Hash a password with bcrypt so that it can be stored in the database.
+pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
+    bcrypt::hash(password, PASSWORD_HASH_COST)
+}
+
+/// Check if the supplied name can be used as name of a new user. The name must not be empty, must
+/// fit into the database and must not contain whitespace or control characters.
+pub fn is_valid_username(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_USERNAME_LENGTH
+        && !name
+            .chars()
+            .any(|character| character.is_whitespace() || character.is_control())
+        // this name is used for the failed logins of unknown users in the audit log
+        && name != "anonymous"
+}
+
+/// Store a new user with an already hashed password.
+pub fn insert_user(
+    connection: &mut PgConnection,
+    name: &str,
+    hashed_password: String,
+    role: Role,
+) -> QueryResult<User> {
+    use crate::models::NewUser;
+    use crate::schema::users::dsl::users;
+    use diesel::{insert_into, RunQueryDsl};
+
+    insert_into(users)
+        .values(&NewUser {
+            username: name.to_string(),
+            password_hash: hashed_password,
+            role: role.as_str().to_string(),
+            external_subject: None,
+        })
+        .get_result::<User>(connection)
+}
+
+/// Replace the password hash of the user with the supplied id. If `must_change_password` is set
+/// (e.g. since an administrator chose the password), the user cannot do anything but change the
+/// password after the next login.
+pub fn set_password_hash(
+    connection: &mut PgConnection,
+    user_id: i32,
+    hashed_password: String,
+    must_change_password: bool,
+) -> QueryResult<User> {
+    use crate::schema::users::dsl::{
+        must_change_password as password_change_required, password_hash, users,
+    };
+    use diesel::{update, ExpressionMethods, QueryDsl, RunQueryDsl};
+
+    update(users.find(user_id))
+        .set((
+            password_hash.eq(hashed_password),
+            password_change_required.eq(must_change_password),
+        ))
+        .get_result::<User>(connection)
+}
+
+/// Reset the password of the user with the supplied id to one which was chosen by someone else
+/// (e.g. an administrator). All sessions of the user are revoked in the same transaction, so
+/// nobody who is still logged in can pick the next password instead of the user. The user has to
+/// log in with the new password and change it before doing anything else.
+pub fn reset_password(
+    connection: &mut PgConnection,
+    user_id: i32,
+    hashed_password: String,
+) -> QueryResult<User> {
+    use crate::sessions::revoke_sessions;
+    use diesel::Connection;
+
+    connection.transaction(|connection| {
+        let user = set_password_hash(connection, user_id, hashed_password, true)?;
+        revoke_sessions(connection, user_id, None, None)?;
+        Ok(user)
+    })
+}
+
+/// How the local user of an external login was found.
+#[derive(Debug, PartialEq)]
+pub enum ExternalUserMatch {
+    /// The user logged in through the external provider before.
+    Known,
+    /// An existing user with the email address as name is linked to the external subject.
+    Linked,
+    /// A new user (without a password) was created for the external subject.
+    Provisioned,
+}
+
+/// Find the local user of a login through the external OpenID Connect provider. The user is
+/// looked up by the external subject first and by the email address (as name) second, which links
+/// the subject to this user. If there is no such user and a default role is supplied, a new user
+/// is created. `None` is returned if the login cannot be mapped to a user.
+pub fn user_for_external_identity(
+    connection: &mut PgConnection,
+    identity: &ExternalIdentity,
+    default_role: Option<Role>,
+) -> QueryResult<Option<(User, ExternalUserMatch)>> {
+    use crate::models::NewUser;
+    use crate::schema::users::dsl::{external_subject, username, users};
+    use diesel::{
+        insert_into, update, Connection, ExpressionMethods, OptionalExtension, QueryDsl,
+        RunQueryDsl,
+    };
+
+    connection.transaction(|connection| {
+        if let Some(user) = users
+            .filter(external_subject.eq(&identity.subject))
+            .first::<User>(connection)
+            .optional()?
+        {
+            return Ok(Some((user, ExternalUserMatch::Known)));
+        }
+
+        // an email address is just used if the provider states that it was verified, otherwise
+        // anyone who can enter the address at the provider could take over the local user
+        let email = identity
+            .email
+            .as_ref()
+            .filter(|_| identity.email_verified == Some(true));
+        if let Some(email) = email {
+            if let Some(user) = users
+                .filter(username.eq(email))
+                .filter(external_subject.is_null())
+                .first::<User>(connection)
+                .optional()?
+            {
+                let user = update(users.find(user.id))
+                    .set(external_subject.eq(&identity.subject))
+                    .get_result::<User>(connection)?;
+                return Ok(Some((user, ExternalUserMatch::Linked)));
+            }
+        }
+
+        let Some(role) = default_role else {
+            return Ok(None);
+        };
+        let name = match email.or(identity.preferred_username.as_ref()) {
+            Some(name) if is_valid_username(name) => name.clone(),
+            _ => return Ok(None),
+        };
+        let name_is_taken = users
+            .filter(username.eq(&name))
+            .count()
+            .get_result::<i64>(connection)?
+            > 0;
+        if name_is_taken {
+            return Ok(None);
+        }
+        let user = insert_into(users)
+            .values(&NewUser {
+                username: name,
+                password_hash: String::new(),
+                role: role.as_str().to_string(),
+                external_subject: Some(identity.subject.clone()),
+            })
+            .get_result::<User>(connection)?;
+        Ok(Some((user, ExternalUserMatch::Provisioned)))
+    })
+}
+
+/// The information about a user which is shown to the administrators (without the password hash).
+#[derive(Serialize)]
+pub struct UserSummary {
+    /// The id of the user.
+    pub id: i32,
+    /// The name the user logs in with.
+    pub username: String,
+    /// The role which defines what the user is allowed to do.
+    pub role: Role,
+    /// Whether the user is prevented from logging in and from using issued tokens.
+    pub disabled: bool,
+}
+
+impl From<&User> for UserSummary {
+    fn from(user: &User) -> Self {
+        UserSummary {
+            id: user.id,
+            username: user.username.clone(),
+            role: Role::from_name(&user.role),
+            disabled: user.disabled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weak_passwords_are_rejected() {
+        assert_eq!(
+            check_new_password("Correct-Horse-42", "Correct-Horse-43"),
+            Err(PasswordRejection::Mismatch)
+        );
+        assert_eq!(
+            check_new_password("aB3$xY", "aB3$xY"),
+            Err(PasswordRejection::TooShort)
+        );
+        assert!(matches!(
+            check_new_password("aaaaaaaa", "aaaaaaaa"),
+            Err(PasswordRejection::TooWeak(_))
+        ));
+        assert_eq!(
+            check_new_password("Correct-Horse-42", "Correct-Horse-42"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn usernames_are_validated() {
+        assert!(is_valid_username("jane.doe"));
+        assert!(!is_valid_username(""));
+        assert!(!is_valid_username("jane doe"));
+        assert!(!is_valid_username("anonymous"));
+        assert!(!is_valid_username(&"a".repeat(MAX_USERNAME_LENGTH + 1)));
+    }
+}
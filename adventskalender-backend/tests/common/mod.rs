@@ -0,0 +1,153 @@
+//! The fixtures which are shared by the tests which need a PostgreSQL database. The tests are skipped if
+//! `ADVENTSKALENDER_TEST_DB_CONNECTION` is not set.
+// every test crate uses just some of the fixtures
+#![allow(dead_code)]
+
+use adventskalender_backend::fairings::BackendConfiguration;
+use adventskalender_backend::guards::Role;
+use adventskalender_backend::models::User;
+use adventskalender_backend::users::insert_user;
+use adventskalender_backend::MIGRATIONS;
+use diesel::sql_types::Integer;
+use diesel::{sql_query, Connection, PgConnection, RunQueryDsl};
+use diesel_migrations::MigrationHarness;
+use rocket::http::Cookie;
+use rocket::local::blocking::Client;
+use rocket::Route;
+
+/// The lifetime of the sessions and tokens which are created for the requests of the tests.
+const TOKEN_LIFETIME_IN_SECONDS: usize = 60;
+
+/// Open another connection to the test database (e.g. for a parallel thread).
+pub fn connect(database_url: &str) -> PgConnection {
+    PgConnection::establish(database_url).expect("could not connect to the test database")
+}
+
+/// Connect to the test database and apply all migrations. The URL of the database is returned as well, so the
+/// test can open more connections. `None` is returned if no test database is configured.
+pub fn test_database() -> Option<(PgConnection, String)> {
+    let database_url = match std::env::var("ADVENTSKALENDER_TEST_DB_CONNECTION") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("ADVENTSKALENDER_TEST_DB_CONNECTION is not set, skipping the test");
+            return None;
+        }
+    };
+    let mut connection = connect(&database_url);
+    connection.run_pending_migrations(MIGRATIONS).unwrap();
+    Some((connection, database_url))
+}
+
+/// Create a user with the supplied role which is just used by one test. The name of the user is the name of the
+/// test followed by the id of the process.
+pub fn create_user(connection: &mut PgConnection, test_name: &str, role: Role) -> User {
+    insert_user(
+        connection,
+        &format!("{}-{}", test_name, std::process::id()),
+        String::new(),
+        role,
+    )
+    .unwrap()
+}
+
+/// Connect to the test database and create a user with the supplied role which is just used by one test (or
+/// return `None` if no test database is configured).
+pub fn prepare_user(test_name: &str, role: Role) -> Option<(PgConnection, User)> {
+    let (mut connection, _) = test_database()?;
+    let user = create_user(&mut connection, test_name, role);
+    Some((connection, user))
+}
+
+/// Delete a user which was created by the test (and the entries of the audit log which reference the user).
+pub fn delete_user(connection: &mut PgConnection, user_id: i32) {
+    sql_query("DELETE FROM performed_actions WHERE user_id = $1")
+        .bind::<Integer, _>(user_id)
+        .execute(connection)
+        .unwrap();
+    sql_query("DELETE FROM users WHERE id = $1")
+        .bind::<Integer, _>(user_id)
+        .execute(connection)
+        .unwrap();
+}
+
+/// Delete a raffle which was created by the test together with its participants and their wins (which are kept if
+/// just a participant is deleted).
+pub fn delete_raffle(connection: &mut PgConnection, raffle_id: i32) {
+    sql_query(
+        "DELETE FROM wins USING participants WHERE participants.id = wins.participant_id AND participants.raffle_id = $1",
+    )
+    .bind::<Integer, _>(raffle_id)
+    .execute(connection)
+    .unwrap();
+    sql_query("DELETE FROM raffles WHERE id = $1")
+        .bind::<Integer, _>(raffle_id)
+        .execute(connection)
+        .unwrap();
+}
+
+/// Start a local instance of the backend which just serves the supplied routes (without a prefix) and uses the
+/// test database. The keys for signing the tokens are stored in a temporary directory of the test.
+pub fn test_client(database_url: &str, test_name: &str, routes: Vec<Route>) -> Client {
+    use adventskalender_backend::discovery::OpenIdConfiguration;
+    use adventskalender_backend::fairings::{
+        connect_to_database, AdventskalenderDatabaseConnection,
+    };
+    use adventskalender_backend::keys::KeyStore;
+    use std::sync::{Arc, RwLock};
+
+    let api_host = "http://localhost:5479";
+    let key_directory = std::env::temp_dir().join(format!(
+        "adventskalender-{}-{}",
+        test_name,
+        std::process::id()
+    ));
+    let backend_config = BackendConfiguration {
+        api_host: api_host.to_string(),
+        keys: Arc::new(RwLock::new(
+            KeyStore::load_or_create(&key_directory).unwrap(),
+        )),
+        openid_configuration: Arc::new(OpenIdConfiguration::for_api_host(api_host).unwrap()),
+        healthcheck_project: String::new(),
+        token_audience: [api_host.to_string()].into(),
+        token_lifetime_in_seconds: TOKEN_LIFETIME_IN_SECONDS,
+        refresh_token_lifetime_in_seconds: TOKEN_LIFETIME_IN_SECONDS,
+        login_rate_limit: Default::default(),
+        external_login: None,
+        totp: None,
+    };
+    let database_connection =
+        AdventskalenderDatabaseConnection::from(connect_to_database(database_url, 4, 1).unwrap());
+
+    let rocket = rocket::build()
+        .manage(backend_config)
+        .manage(database_connection)
+        .mount("/", routes);
+    Client::untracked(rocket).unwrap()
+}
+
+/// Log the user in, i.e. start a new session and get the cookie with an access token of the session for the
+/// requests of the client.
+pub fn login_cookie(
+    client: &Client,
+    connection: &mut PgConnection,
+    user: &User,
+) -> Cookie<'static> {
+    use adventskalender_backend::get_token_for_user;
+    use adventskalender_backend::sessions::create_session;
+
+    let backend_config = client.rocket().state::<BackendConfiguration>().unwrap();
+    let session_id =
+        create_session(connection, user.id, None, None, TOKEN_LIFETIME_IN_SECONDS).unwrap();
+    let signing_key = backend_config.keys.read().unwrap().active_key().unwrap();
+    let token = get_token_for_user(
+        &user.username,
+        Role::from_name(&user.role),
+        &session_id,
+        backend_config.token_audience.clone(),
+        backend_config.api_host.clone(),
+        TOKEN_LIFETIME_IN_SECONDS,
+        &signing_key,
+    )
+    .unwrap();
+    Cookie::new("auth_token", token)
+}
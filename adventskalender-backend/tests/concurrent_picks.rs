@@ -0,0 +1,329 @@
+//! Regression tests for picking winners and assigning presents in parallel. They need a PostgreSQL database which can be
+//! used for testing and are skipped if `ADVENTSKALENDER_TEST_DB_CONNECTION` is not set, e.g.:
+//!
+//! `ADVENTSKALENDER_TEST_DB_CONNECTION=postgres://adventskalender@localhost/adventskalender cargo test`
+mod common;
+
+use adventskalender_backend::calendar::PickRefusal;
+use adventskalender_backend::guards::Role;
+use adventskalender_backend::models::Raffle;
+use adventskalender_backend::picking::{
+    commit_draw, pick_scheduled_winners, pick_winners, PickError,
+};
+use adventskalender_backend::presents::{assign_present, PresentError};
+use chrono::NaiveDate;
+use common::{connect, create_user, delete_raffle, delete_user, test_database};
+use diesel::sql_types::{Date, Integer, Text};
+use diesel::{sql_query, QueryableByName, RunQueryDsl};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+/// The number of days of the test raffle for which winners are picked.
+const DAYS: u32 = 12;
+
+/// The number of winners which can be picked per day.
+const WINNERS_PER_DAY: usize = 3;
+
+#[derive(QueryableByName)]
+struct InsertedId {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+}
+
+#[test]
+fn parallel_picks_never_select_a_participant_twice() {
+    let Some((mut connection, database_url)) = test_database() else {
+        return;
+    };
+
+    // prepare a separate raffle (and user) which is just used by this test
+    let connection = &mut connection;
+    let test_name = format!("concurrent-picks-{}", std::process::id());
+    let first_day = NaiveDate::from_ymd_opt(2099, 12, 1).unwrap();
+    let last_day = NaiveDate::from_ymd_opt(2099, 12, 24).unwrap();
+    let raffle_id = sql_query(
+        "INSERT INTO raffles (name, start_date, end_date, winners_per_day) VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+    .bind::<Text, _>(&test_name)
+    .bind::<Date, _>(first_day)
+    .bind::<Date, _>(last_day)
+    .bind::<Integer, _>(WINNERS_PER_DAY as i32)
+    .get_result::<InsertedId>(connection)
+    .unwrap()
+    .id;
+    let user = create_user(connection, "concurrent-picks", Role::Operator);
+    sql_query(
+        "INSERT INTO participants (first_name, last_name, raffle_id) SELECT 'Participant', number::TEXT, $1 FROM generate_series(1, 40) AS number",
+    )
+    .bind::<Integer, _>(raffle_id)
+    .execute(connection)
+    .unwrap();
+    let raffle = Raffle {
+        id: raffle_id,
+        name: test_name.clone(),
+        start_date: first_day,
+        end_date: last_day,
+        archived: false,
+        exclude_weekends: false,
+        excluded_dates: vec![],
+        winners_per_day: WINNERS_PER_DAY as i32,
+        use_tickets: false,
+        max_winners_per_department: None,
+        present_assignment: "manual".to_string(),
+        scheduled_draws: false,
+        scheduled_draws_enabled_at: None,
+    };
+
+    // fill the quota of every day with single picks and try to pick some more for the first day
+    let picks = (0..DAYS as usize * WINNERS_PER_DAY + 4)
+        .map(|pick| first_day + chrono::Days::new((pick as u64) % u64::from(DAYS)))
+        .collect::<Vec<NaiveDate>>();
+    let barrier = Arc::new(Barrier::new(picks.len()));
+    let handles = picks
+        .into_iter()
+        .map(|date| {
+            let barrier = barrier.clone();
+            let database_url = database_url.clone();
+            let raffle = raffle.clone();
+            let test_name = test_name.clone();
+            thread::spawn(move || {
+                let connection = &mut connect(&database_url);
+                barrier.wait();
+                (
+                    date,
+                    pick_winners(connection, &raffle, date, 1, &test_name, None),
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    let results = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+
+    // clean up before checking the results, so a failed test does not leave anything behind
+    delete_raffle(connection, raffle_id);
+    delete_user(connection, user.id);
+
+    let mut winners = HashSet::new();
+    let mut winners_per_day = HashMap::new();
+    let mut refused_picks = 0;
+    for (date, result) in results {
+        match result {
+            Ok(picked) => {
+                assert_eq!(picked.winners.len(), 1);
+                assert_eq!(
+                    picked.draw.winner_ids,
+                    vec![picked.winners[0].participant.id]
+                );
+                assert!(
+                    winners.insert(picked.winners[0].participant.id),
+                    "picked a participant twice"
+                );
+                *winners_per_day.entry(date).or_insert(0) += 1;
+            }
+            Err(PickError::Refused(PickRefusal::QuotaExceeded { remaining: 0 })) => {
+                refused_picks += 1
+            }
+            Err(error) => panic!("unexpected error while picking: {}", error),
+        }
+    }
+    assert_eq!(winners.len(), DAYS as usize * WINNERS_PER_DAY);
+    assert!(winners_per_day
+        .values()
+        .all(|count| *count == WINNERS_PER_DAY));
+    assert_eq!(refused_picks, 4);
+}
+
+#[test]
+fn parallel_assignments_never_exceed_the_stock_of_a_present() {
+    let Some((mut connection, database_url)) = test_database() else {
+        return;
+    };
+
+    // prepare a separate raffle with ten winners of a single day and a present with two pieces
+    let connection = &mut connection;
+    let test_name = format!("concurrent-presents-{}", std::process::id());
+    let day = NaiveDate::from_ymd_opt(2099, 12, 1).unwrap();
+    let raffle_id = sql_query(
+        "INSERT INTO raffles (name, start_date, end_date, winners_per_day) VALUES ($1, $2, $2, 10) RETURNING id",
+    )
+    .bind::<Text, _>(&test_name)
+    .bind::<Date, _>(day)
+    .get_result::<InsertedId>(connection)
+    .unwrap()
+    .id;
+    sql_query(
+        "INSERT INTO participants (first_name, last_name, raffle_id) SELECT 'Winner', number::TEXT, $1 FROM generate_series(1, 10) AS number",
+    )
+    .bind::<Integer, _>(raffle_id)
+    .execute(connection)
+    .unwrap();
+    sql_query(
+        "INSERT INTO wins (participant_id, won_on) SELECT id, $2 FROM participants WHERE raffle_id = $1",
+    )
+    .bind::<Integer, _>(raffle_id)
+    .bind::<Date, _>(day)
+    .execute(connection)
+    .unwrap();
+    let present_id = sql_query(
+        "INSERT INTO presents (raffle_id, available_on, identifier, name, quantity) VALUES ($1, $2, 'A', 'Present', 2) RETURNING id",
+    )
+    .bind::<Integer, _>(raffle_id)
+    .bind::<Date, _>(day)
+    .get_result::<InsertedId>(connection)
+    .unwrap()
+    .id;
+    let participant_ids = sql_query("SELECT id FROM participants WHERE raffle_id = $1")
+        .bind::<Integer, _>(raffle_id)
+        .load::<InsertedId>(connection)
+        .unwrap()
+        .into_iter()
+        .map(|participant| participant.id)
+        .collect::<Vec<_>>();
+
+    // let all winners try to get the present at the same time
+    let barrier = Arc::new(Barrier::new(participant_ids.len()));
+    let handles = participant_ids
+        .into_iter()
+        .map(|participant_id| {
+            let barrier = barrier.clone();
+            let database_url = database_url.clone();
+            thread::spawn(move || {
+                let connection = &mut connect(&database_url);
+                barrier.wait();
+                assign_present(connection, raffle_id, participant_id, Some(present_id))
+            })
+        })
+        .collect::<Vec<_>>();
+    let results = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+
+    // clean up before checking the results, so a failed test does not leave anything behind
+    delete_raffle(connection, raffle_id);
+
+    let mut assigned = 0;
+    let mut out_of_stock = 0;
+    for result in results {
+        match result {
+            Ok(assignment) => {
+                assert_eq!(assignment.win.present_id, Some(present_id));
+                assigned += 1;
+            }
+            Err(PresentError::OutOfStock) => out_of_stock += 1,
+            Err(error) => panic!("unexpected error while assigning: {}", error),
+        }
+    }
+    assert_eq!(assigned, 2);
+    assert_eq!(out_of_stock, 8);
+}
+
+#[test]
+fn open_commitments_cannot_be_re_rolled() {
+    let Some((mut connection, _)) = test_database() else {
+        return;
+    };
+
+    // prepare a separate raffle (and user) which is just used by this test
+    let connection = &mut connection;
+    let test_name = format!("open-commitments-{}", std::process::id());
+    let day = NaiveDate::from_ymd_opt(2099, 12, 1).unwrap();
+    let raffle_id = sql_query(
+        "INSERT INTO raffles (name, start_date, end_date, winners_per_day) VALUES ($1, $2, $2, $3) RETURNING id",
+    )
+    .bind::<Text, _>(&test_name)
+    .bind::<Date, _>(day)
+    .bind::<Integer, _>(WINNERS_PER_DAY as i32)
+    .get_result::<InsertedId>(connection)
+    .unwrap()
+    .id;
+    let user = create_user(connection, "open-commitments", Role::Operator);
+    sql_query(
+        "INSERT INTO participants (first_name, last_name, raffle_id) SELECT 'Participant', number::TEXT, $1 FROM generate_series(1, 10) AS number",
+    )
+    .bind::<Integer, _>(raffle_id)
+    .execute(connection)
+    .unwrap();
+    let raffle = Raffle {
+        id: raffle_id,
+        name: test_name.clone(),
+        start_date: day,
+        end_date: day,
+        archived: false,
+        exclude_weekends: false,
+        excluded_dates: vec![],
+        winners_per_day: WINNERS_PER_DAY as i32,
+        use_tickets: false,
+        max_winners_per_department: None,
+        present_assignment: "manual".to_string(),
+        scheduled_draws: false,
+        scheduled_draws_enabled_at: None,
+    };
+
+    // while the commitment is open, the day can neither be committed to again nor be picked
+    // without revealing the commitment
+    let commitment = commit_draw(connection, &raffle, day, 1, &test_name).unwrap();
+    let second_commitment = commit_draw(connection, &raffle, day, 1, &test_name);
+    let direct_pick = pick_winners(connection, &raffle, day, 1, &test_name, None);
+    let scheduled_pick = pick_scheduled_winners(connection, &raffle, day);
+    let revealed = pick_winners(connection, &raffle, day, 1, &test_name, Some(commitment.id));
+    let pick_after_reveal = pick_winners(connection, &raffle, day, 1, &test_name, None);
+
+    delete_raffle(connection, raffle_id);
+    delete_user(connection, user.id);
+
+    assert!(matches!(second_commitment, Err(PickError::OpenCommitment)));
+    assert!(matches!(direct_pick, Err(PickError::OpenCommitment)));
+    assert!(matches!(scheduled_pick, Err(PickError::OpenCommitment)));
+    assert_eq!(revealed.unwrap().draw.seed, commitment.seed);
+    assert!(pick_after_reveal.is_ok());
+}
+
+#[test]
+fn picks_are_refused_as_busy_while_the_raffle_is_locked() {
+    let Some((mut connection, database_url)) = test_database() else {
+        return;
+    };
+
+    // hold the picking lock of a raffle (which does not need to exist) in another session
+    let connection = &mut connection;
+    let day = NaiveDate::from_ymd_opt(2099, 12, 1).unwrap();
+    let raffle = Raffle {
+        id: -(std::process::id() as i32),
+        name: format!("busy-picks-{}", std::process::id()),
+        start_date: day,
+        end_date: day,
+        archived: false,
+        exclude_weekends: false,
+        excluded_dates: vec![],
+        winners_per_day: 1,
+        use_tickets: false,
+        max_winners_per_department: None,
+        present_assignment: "manual".to_string(),
+        scheduled_draws: false,
+        scheduled_draws_enabled_at: None,
+    };
+    sql_query("SELECT pg_advisory_lock(x'5049434b'::INT4, $1)")
+        .bind::<Integer, _>(raffle.id)
+        .execute(connection)
+        .unwrap();
+
+    let result = pick_winners(
+        &mut connect(&database_url),
+        &raffle,
+        day,
+        1,
+        "busy-picks",
+        None,
+    );
+
+    sql_query("SELECT pg_advisory_unlock(x'5049434b'::INT4, $1)")
+        .bind::<Integer, _>(raffle.id)
+        .execute(connection)
+        .unwrap();
+
+    assert!(matches!(result, Err(PickError::Busy)));
+}
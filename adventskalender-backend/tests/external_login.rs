@@ -0,0 +1,331 @@
+//! Tests for the login through an external OpenID Connect provider. The provider is mocked by a small HTTP server on
+//! the loopback interface. The tests which map the logins to users need a PostgreSQL database and are skipped if
+//! `ADVENTSKALENDER_TEST_DB_CONNECTION` is not set, e.g.:
+//!
+//! `ADVENTSKALENDER_TEST_DB_CONNECTION=postgres://adventskalender@localhost/adventskalender cargo test`
+mod common;
+
+use adventskalender_backend::guards::Role;
+use adventskalender_backend::oidc::{
+    code_challenge, discover_provider, finish_login, start_login, ExternalIdentity, OidcSettings,
+};
+use adventskalender_backend::users::{insert_user, user_for_external_identity, ExternalUserMatch};
+use common::{delete_user, test_database};
+use rocket::serde::json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+/// The id of the application at the mocked provider.
+const CLIENT_ID: &str = "adventskalender";
+
+/// The URL the mocked provider would send the user back to.
+const REDIRECT_URI: &str = "http://localhost:5479/v1/auth/oidc/callback";
+
+/// The code the mocked provider accepts in exchange for an ID token.
+const AUTHORIZATION_CODE: &str = "mock-code";
+
+/// What the mocked provider knows about the login which is currently running.
+#[derive(Default)]
+struct MockLogin {
+    /// The PKCE challenge sent with the authorization request.
+    code_challenge: String,
+    /// The nonce which is put into the ID token.
+    nonce: String,
+}
+
+/// An OpenID Connect provider which serves the discovery document, its keys and a token endpoint.
+struct MockProvider {
+    issuer: String,
+    login: Arc<Mutex<MockLogin>>,
+}
+
+impl MockProvider {
+    fn start() -> MockProvider {
+        use ring::rand::SystemRandom;
+        use ring::signature::Ed25519KeyPair;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let key_pair = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .unwrap()
+            .as_ref()
+            .to_vec();
+        let login = Arc::new(Mutex::new(MockLogin::default()));
+
+        let provider_issuer = issuer.clone();
+        let provider_login = login.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                handle_request(stream, &provider_issuer, &key_pair, &provider_login);
+            }
+        });
+        MockProvider { issuer, login }
+    }
+
+    fn settings(&self) -> OidcSettings {
+        OidcSettings {
+            issuer: self.issuer.clone(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: Some("secret".to_string()),
+            default_role: None,
+            login_redirect: "http://localhost:5173".to_string(),
+        }
+    }
+
+    /// Start a login and let the mocked provider remember the parameters of the authorization request (as if the
+    /// user logged in there). The state of the login is returned.
+    fn authorize(&self) -> String {
+        let settings = self.settings();
+        let provider = discover_provider(&settings.issuer).unwrap();
+        let (state, authorization_url) = start_login(&settings, provider, REDIRECT_URI).unwrap();
+
+        let parameters = url::Url::parse(&authorization_url)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect::<HashMap<String, String>>();
+        assert!(authorization_url.starts_with(&format!("{}/authorize?", self.issuer)));
+        assert_eq!(parameters["client_id"], CLIENT_ID);
+        assert_eq!(parameters["redirect_uri"], REDIRECT_URI);
+        assert_eq!(parameters["state"], state);
+        assert_eq!(parameters["code_challenge_method"], "S256");
+
+        let mut login = self.login.lock().unwrap();
+        login.code_challenge = parameters["code_challenge"].clone();
+        login.nonce = parameters["nonce"].clone();
+        state
+    }
+}
+
+fn handle_request(mut stream: TcpStream, issuer: &str, key_pair: &[u8], login: &Mutex<MockLogin>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap();
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (status, response) = match path {
+        "/.well-known/openid-configuration" => (
+            "200 OK",
+            json!({
+                "issuer": issuer,
+                "authorization_endpoint": format!("{}/authorize", issuer),
+                "token_endpoint": format!("{}/token", issuer),
+                "jwks_uri": format!("{}/jwks", issuer),
+            }),
+        ),
+        "/jwks" => ("200 OK", keys(key_pair)),
+        "/token" => exchange_code(&body, issuer, key_pair, login),
+        _ => ("404 Not Found", json!({})),
+    };
+
+    let response = response.to_string();
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        response.len(),
+        response
+    );
+}
+
+fn keys(key_pair: &[u8]) -> Value {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    let public_key = Ed25519KeyPair::from_pkcs8(key_pair)
+        .unwrap()
+        .public_key()
+        .as_ref()
+        .to_vec();
+    json!({
+        "keys": [{
+            "kty": "OKP",
+            "use": "sig",
+            "kid": "mock-key",
+            "alg": "EdDSA",
+            "crv": "Ed25519",
+            "x": URL_SAFE_NO_PAD.encode(public_key),
+        }]
+    })
+}
+
+/// Issue an ID token if the code and the PKCE code verifier match the running login.
+fn exchange_code(
+    body: &[u8],
+    issuer: &str,
+    key_pair: &[u8],
+    login: &Mutex<MockLogin>,
+) -> (&'static str, Value) {
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+
+    let parameters = url::form_urlencoded::parse(body)
+        .into_owned()
+        .collect::<HashMap<String, String>>();
+    let login = login.lock().unwrap();
+    let is_valid = parameters.get("grant_type").map(String::as_str) == Some("authorization_code")
+        && parameters.get("code").map(String::as_str) == Some(AUTHORIZATION_CODE)
+        && parameters.get("redirect_uri").map(String::as_str) == Some(REDIRECT_URI)
+        && parameters
+            .get("code_verifier")
+            .is_some_and(|verifier| code_challenge(verifier) == login.code_challenge);
+    if !is_valid {
+        return ("400 Bad Request", json!({"error": "invalid_grant"}));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let mut header = Header::new(Algorithm::EdDSA);
+    header.kid = Some("mock-key".to_string());
+    let id_token = encode(
+        &header,
+        &json!({
+            "iss": issuer,
+            "sub": "external-user",
+            "aud": CLIENT_ID,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": login.nonce,
+            "email": "jane.doe@example.com",
+        }),
+        &EncodingKey::from_ed_der(key_pair),
+    )
+    .unwrap();
+    (
+        "200 OK",
+        json!({"access_token": "mock", "token_type": "Bearer", "id_token": id_token}),
+    )
+}
+
+#[test]
+fn logins_are_finished_with_a_validated_id_token() {
+    let provider = MockProvider::start();
+    let state = provider.authorize();
+
+    let identity = finish_login(
+        &provider.settings(),
+        &state,
+        AUTHORIZATION_CODE,
+        REDIRECT_URI,
+    )
+    .unwrap();
+    assert_eq!(identity.subject, "external-user");
+    assert_eq!(identity.email.as_deref(), Some("jane.doe@example.com"));
+
+    // each login can just be finished once
+    assert!(finish_login(
+        &provider.settings(),
+        &state,
+        AUTHORIZATION_CODE,
+        REDIRECT_URI
+    )
+    .is_err());
+}
+
+#[test]
+fn id_tokens_of_other_logins_are_rejected() {
+    let provider = MockProvider::start();
+    let state = provider.authorize();
+
+    // the provider returns the nonce of another login
+    provider.login.lock().unwrap().nonce = "another-nonce".to_string();
+    let error = finish_login(
+        &provider.settings(),
+        &state,
+        AUTHORIZATION_CODE,
+        REDIRECT_URI,
+    )
+    .unwrap_err();
+    assert!(error.contains("nonce"), "{}", error);
+}
+
+#[test]
+fn wrong_codes_are_rejected() {
+    let provider = MockProvider::start();
+    let state = provider.authorize();
+
+    assert!(finish_login(&provider.settings(), &state, "wrong-code", REDIRECT_URI).is_err());
+}
+
+fn identity(subject: &str, email: &str) -> ExternalIdentity {
+    ExternalIdentity {
+        subject: subject.to_string(),
+        email: Some(email.to_string()),
+        email_verified: Some(true),
+        preferred_username: None,
+    }
+}
+
+#[test]
+fn external_logins_are_mapped_to_users() {
+    let Some((mut connection, _)) = test_database() else {
+        return;
+    };
+    let connection = &mut connection;
+    let id = std::process::id();
+
+    // an existing user is linked by the email address and found by the subject afterwards
+    let email = format!("linked-{}@example.com", id);
+    let existing_user = insert_user(connection, &email, String::new(), Role::Operator).unwrap();
+    let linked_identity = identity(&format!("linked-{}", id), &email);
+
+    // an email address which was not verified by the provider is not linked to the user
+    for email_verified in [None, Some(false)] {
+        let unverified_identity = ExternalIdentity {
+            email_verified,
+            ..linked_identity.clone()
+        };
+        assert!(
+            user_for_external_identity(connection, &unverified_identity, None)
+                .unwrap()
+                .is_none()
+        );
+    }
+    let (user, user_match) = user_for_external_identity(connection, &linked_identity, None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (user.id, user_match),
+        (existing_user.id, ExternalUserMatch::Linked)
+    );
+    let (user, user_match) = user_for_external_identity(connection, &linked_identity, None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (user.id, user_match),
+        (existing_user.id, ExternalUserMatch::Known)
+    );
+
+    // unknown users are just created if a default role is configured
+    let new_identity = identity(&format!("new-{}", id), &format!("new-{}@example.com", id));
+    assert!(user_for_external_identity(connection, &new_identity, None)
+        .unwrap()
+        .is_none());
+    let (user, user_match) =
+        user_for_external_identity(connection, &new_identity, Some(Role::Viewer))
+            .unwrap()
+            .unwrap();
+    assert_eq!(user_match, ExternalUserMatch::Provisioned);
+    assert_eq!(user.username, format!("new-{}@example.com", id));
+    assert_eq!(user.role, "viewer");
+    assert!(user.password_hash.is_empty());
+
+    delete_user(connection, existing_user.id);
+    delete_user(connection, user.id);
+}
@@ -0,0 +1,172 @@
+//! Tests for managing the participants of a raffle through the routes. They need a PostgreSQL database which can be
+//! used for testing and are skipped if `ADVENTSKALENDER_TEST_DB_CONNECTION` is not set, e.g.:
+//!
+//! `ADVENTSKALENDER_TEST_DB_CONNECTION=postgres://adventskalender@localhost/adventskalender cargo test`
+mod common;
+
+use adventskalender_backend::guards::Role;
+use adventskalender_backend::routes::{
+    delete_participant, pick_multiple_random_participant_from_raffle_list,
+};
+use chrono::NaiveDate;
+use common::{create_user, delete_raffle, delete_user, login_cookie, test_client, test_database};
+use diesel::sql_types::{Date, Integer, Text};
+use diesel::{sql_query, QueryableByName, RunQueryDsl};
+use rocket::http::Status;
+use rocket::routes;
+
+#[derive(QueryableByName)]
+struct InsertedId {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+}
+
+#[test]
+fn participants_who_ever_won_cannot_be_deleted() {
+    let Some((mut connection, database_url)) = test_database() else {
+        return;
+    };
+
+    // prepare a separate raffle with a participant whose win was revoked and one who never won
+    let connection = &mut connection;
+    let test_name = format!("delete-participants-{}", std::process::id());
+    let day = NaiveDate::from_ymd_opt(2099, 12, 1).unwrap();
+    let raffle_id = sql_query(
+        "INSERT INTO raffles (name, start_date, end_date, winners_per_day) VALUES ($1, $2, $2, 1) RETURNING id",
+    )
+    .bind::<Text, _>(&test_name)
+    .bind::<Date, _>(day)
+    .get_result::<InsertedId>(connection)
+    .unwrap()
+    .id;
+    let mut add_participant = |last_name: &str| {
+        sql_query(
+            "INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Participant', $1, $2) RETURNING id",
+        )
+        .bind::<Text, _>(last_name)
+        .bind::<Integer, _>(raffle_id)
+        .get_result::<InsertedId>(connection)
+        .unwrap()
+        .id
+    };
+    let former_winner_id = add_participant("Former Winner");
+    let participant_id = add_participant("Never Won");
+    sql_query(
+        "INSERT INTO wins (participant_id, won_on, status, revoked_at) VALUES ($1, $2, 'revoked', now())",
+    )
+    .bind::<Integer, _>(former_winner_id)
+    .bind::<Date, _>(day)
+    .execute(connection)
+    .unwrap();
+    let administrator = create_user(connection, "delete-participants", Role::Admin);
+    let client = test_client(
+        &database_url,
+        "delete-participants",
+        routes![delete_participant],
+    );
+    let cookie = login_cookie(&client, connection, &administrator);
+
+    let delete = |participant_id: i32| {
+        client
+            .delete(format!(
+                "/raffles/{}/participants/{}",
+                raffle_id, participant_id
+            ))
+            .cookie(cookie.clone())
+            .dispatch()
+            .status()
+    };
+    let former_winner_status = delete(former_winner_id);
+    let participant_status = delete(participant_id);
+    let remaining_wins = sql_query("SELECT id FROM wins WHERE participant_id = $1")
+        .bind::<Integer, _>(former_winner_id)
+        .load::<InsertedId>(connection)
+        .unwrap()
+        .len();
+
+    delete_raffle(connection, raffle_id);
+    delete_user(connection, administrator.id);
+
+    assert_eq!(former_winner_status, Status::Conflict);
+    assert_eq!(participant_status, Status::NoContent);
+    assert_eq!(remaining_wins, 1);
+}
+
+#[test]
+fn a_dry_run_of_the_pick_never_marks_winners() {
+    let Some((mut connection, database_url)) = test_database() else {
+        return;
+    };
+
+    // prepare a separate raffle with a single participant who could be picked
+    let connection = &mut connection;
+    let test_name = format!("dry-run-picks-{}", std::process::id());
+    let day = NaiveDate::from_ymd_opt(2099, 12, 1).unwrap();
+    let raffle_id = sql_query(
+        "INSERT INTO raffles (name, start_date, end_date, winners_per_day) VALUES ($1, $2, $2, 1) RETURNING id",
+    )
+    .bind::<Text, _>(&test_name)
+    .bind::<Date, _>(day)
+    .get_result::<InsertedId>(connection)
+    .unwrap()
+    .id;
+    sql_query("INSERT INTO participants (first_name, last_name, raffle_id) VALUES ('Participant', 'Dry Run', $1)")
+        .bind::<Integer, _>(raffle_id)
+        .execute(connection)
+        .unwrap();
+    let operator = create_user(connection, "dry-run-picks", Role::Operator);
+    let viewer = create_user(connection, "dry-run-picks-viewer", Role::Viewer);
+    let client = test_client(
+        &database_url,
+        "dry-run-picks",
+        routes![pick_multiple_random_participant_from_raffle_list],
+    );
+    let operator_cookie = login_cookie(&client, connection, &operator);
+    let viewer_cookie = login_cookie(&client, connection, &viewer);
+
+    // every spelling of a dry run just reports how the pick would turn out, even for viewers
+    let pick = |query: &str, cookie: &rocket::http::Cookie<'static>| {
+        client
+            .get(format!(
+                "/raffles/{}/participants/pick/1/for/{}{}",
+                raffle_id, day, query
+            ))
+            .cookie(cookie.clone())
+            .dispatch()
+            .status()
+    };
+    let dry_run_statuses = [
+        "?dry_run=true",
+        "?dry_run=True",
+        "?dry_run=1",
+        "?dry_run",
+        "?dry_run=",
+    ]
+    .map(|query| (query, pick(query, &operator_cookie)));
+    let viewer_dry_run_status = pick("?dry_run=true", &viewer_cookie);
+    let viewer_pick_status = pick("?dry_run=false", &viewer_cookie);
+    let count_rows = |connection: &mut diesel::PgConnection, query: &str| {
+        sql_query(query)
+            .bind::<Integer, _>(raffle_id)
+            .load::<InsertedId>(connection)
+            .unwrap()
+            .len()
+    };
+    let wins = count_rows(
+        connection,
+        "SELECT wins.id FROM wins JOIN participants ON participants.id = wins.participant_id WHERE participants.raffle_id = $1",
+    );
+    let draws = count_rows(connection, "SELECT id FROM draws WHERE raffle_id = $1");
+
+    delete_raffle(connection, raffle_id);
+    delete_user(connection, operator.id);
+    delete_user(connection, viewer.id);
+
+    for (query, status) in dry_run_statuses {
+        assert_eq!(status, Status::Ok, "unexpected status for {}", query);
+    }
+    assert_eq!(viewer_dry_run_status, Status::Ok);
+    assert_eq!(viewer_pick_status, Status::Forbidden);
+    assert_eq!(wins, 0);
+    assert_eq!(draws, 0);
+}
@@ -0,0 +1,113 @@
+//! Tests for changing the settings of a raffle through the routes. They need a PostgreSQL database which can be used
+//! for testing and are skipped if `ADVENTSKALENDER_TEST_DB_CONNECTION` is not set, e.g.:
+//!
+//! `ADVENTSKALENDER_TEST_DB_CONNECTION=postgres://adventskalender@localhost/adventskalender cargo test`
+mod common;
+
+use adventskalender_backend::guards::Role;
+use adventskalender_backend::routes::update_raffle;
+use chrono::NaiveDate;
+use common::{create_user, delete_raffle, delete_user, login_cookie, test_client, test_database};
+use diesel::sql_types::{Date, Integer, Text};
+use diesel::{sql_query, QueryableByName, RunQueryDsl};
+use rocket::http::{ContentType, Status};
+use rocket::routes;
+use rocket::serde::json::{json, Value};
+
+#[derive(QueryableByName)]
+struct InsertedId {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+}
+
+#[derive(QueryableByName)]
+struct LoggedAction {
+    #[diesel(sql_type = Text)]
+    description: String,
+}
+
+#[test]
+fn changes_which_do_not_fit_the_stored_winners_are_refused() {
+    let Some((mut connection, database_url)) = test_database() else {
+        return;
+    };
+
+    // prepare a separate raffle with two winners on its second day
+    let connection = &mut connection;
+    let test_name = format!("update-raffles-{}", std::process::id());
+    let first_day = NaiveDate::from_ymd_opt(2099, 12, 1).unwrap();
+    let day_with_winners = NaiveDate::from_ymd_opt(2099, 12, 2).unwrap();
+    let last_day = NaiveDate::from_ymd_opt(2099, 12, 3).unwrap();
+    let raffle_id = sql_query(
+        "INSERT INTO raffles (name, start_date, end_date, winners_per_day) VALUES ($1, $2, $3, 2) RETURNING id",
+    )
+    .bind::<Text, _>(&test_name)
+    .bind::<Date, _>(first_day)
+    .bind::<Date, _>(last_day)
+    .get_result::<InsertedId>(connection)
+    .unwrap()
+    .id;
+    sql_query(
+        "INSERT INTO participants (first_name, last_name, raffle_id) SELECT 'Participant', number::TEXT, $1 FROM generate_series(1, 2) AS number",
+    )
+    .bind::<Integer, _>(raffle_id)
+    .execute(connection)
+    .unwrap();
+    sql_query(
+        "INSERT INTO wins (participant_id, won_on, status) SELECT id, $2, 'active' FROM participants WHERE raffle_id = $1",
+    )
+    .bind::<Integer, _>(raffle_id)
+    .bind::<Date, _>(day_with_winners)
+    .execute(connection)
+    .unwrap();
+    let administrator = create_user(connection, "update-raffles", Role::Admin);
+    let client = test_client(&database_url, "update-raffles", routes![update_raffle]);
+    let cookie = login_cookie(&client, connection, &administrator);
+
+    let settings = json!({
+        "name": test_name,
+        "start_date": first_day,
+        "end_date": last_day,
+        "winners_per_day": 2,
+    });
+    let update = |changes: Value| {
+        let mut changed_settings = settings.clone();
+        changed_settings
+            .as_object_mut()
+            .unwrap()
+            .extend(changes.as_object().unwrap().clone());
+        client
+            .put(format!("/raffles/{}", raffle_id))
+            .header(ContentType::JSON)
+            .cookie(cookie.clone())
+            .body(changed_settings.to_string())
+            .dispatch()
+            .status()
+    };
+    let shrunk_status = update(json!({ "end_date": first_day }));
+    let excluded_status = update(json!({ "excluded_dates": [day_with_winners] }));
+    let lowered_quota_status = update(json!({ "winners_per_day": 1 }));
+    let fitting_status = update(json!({ "end_date": day_with_winners, "use_tickets": true }));
+    let archived_status = update(json!({ "archived": true }));
+    let changed_archived_status = update(json!({ "archived": true, "use_tickets": true }));
+    let logged_changes = sql_query(
+        "SELECT description FROM performed_actions WHERE user_id = $1 AND action = 'raffle_updated' ORDER BY id",
+    )
+    .bind::<Integer, _>(administrator.id)
+    .load::<LoggedAction>(connection)
+    .unwrap();
+
+    delete_raffle(connection, raffle_id);
+    delete_user(connection, administrator.id);
+
+    assert_eq!(shrunk_status, Status::Conflict);
+    assert_eq!(excluded_status, Status::Conflict);
+    assert_eq!(lowered_quota_status, Status::Conflict);
+    assert_eq!(fitting_status, Status::Ok);
+    assert_eq!(archived_status, Status::Ok);
+    assert_eq!(changed_archived_status, Status::Conflict);
+    assert_eq!(logged_changes.len(), 2);
+    assert!(logged_changes[0].description.contains(
+        "2 winners per day, weekends excluded: false, excluded dates: [], tickets: true"
+    ));
+}
@@ -0,0 +1,107 @@
+//! Tests for catching up the scheduled draws. They need a PostgreSQL database which can be used for testing and are
+//! skipped if `ADVENTSKALENDER_TEST_DB_CONNECTION` is not set, e.g.:
+//!
+//! `ADVENTSKALENDER_TEST_DB_CONNECTION=postgres://adventskalender@localhost/adventskalender cargo test`
+mod common;
+
+use adventskalender_backend::scheduler::{run_scheduled_draws, DrawSchedule};
+use chrono::{Days, NaiveDate, Utc};
+use common::{delete_raffle, test_database};
+use diesel::sql_types::{Date, Integer, Text, Timestamp};
+use diesel::{sql_query, PgConnection, QueryableByName, RunQueryDsl};
+
+#[derive(QueryableByName)]
+struct InsertedId {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+}
+
+#[derive(QueryableByName)]
+struct DrawnDay {
+    #[diesel(sql_type = Date)]
+    drawn_for: NaiveDate,
+}
+
+/// Get the days of the raffle which were drawn (in ascending order).
+fn drawn_days(connection: &mut PgConnection, raffle_id: i32) -> Vec<NaiveDate> {
+    sql_query("SELECT drawn_for FROM draws WHERE raffle_id = $1 ORDER BY drawn_for")
+        .bind::<Integer, _>(raffle_id)
+        .load::<DrawnDay>(connection)
+        .unwrap()
+        .into_iter()
+        .map(|day| day.drawn_for)
+        .collect()
+}
+
+#[test]
+fn just_the_days_due_after_enabling_the_scheduled_draws_are_caught_up() {
+    let Some((mut connection, _)) = test_database() else {
+        return;
+    };
+
+    // prepare a separate raffle whose last four days are due and which has scheduled draws enabled just now
+    let connection = &mut connection;
+    let test_name = format!("scheduled-draws-{}", std::process::id());
+    let schedule = DrawSchedule::parse("00:00", "UTC").unwrap();
+    let now = Utc::now();
+    let today = now.date_naive();
+    let first_day = today - Days::new(3);
+    let raffle_id = sql_query(
+        "INSERT INTO raffles (name, start_date, end_date, winners_per_day, scheduled_draws, scheduled_draws_enabled_at) VALUES ($1, $2, $3, 1, true, $4) RETURNING id",
+    )
+    .bind::<Text, _>(&test_name)
+    .bind::<Date, _>(first_day)
+    .bind::<Date, _>(today)
+    .bind::<Timestamp, _>(now.naive_utc())
+    .get_result::<InsertedId>(connection)
+    .unwrap()
+    .id;
+    sql_query(
+        "INSERT INTO participants (first_name, last_name, raffle_id) SELECT 'Participant', number::TEXT, $1 FROM generate_series(1, 10) AS number",
+    )
+    .bind::<Integer, _>(raffle_id)
+    .execute(connection)
+    .unwrap();
+
+    // the draws of all days were due before the scheduled draws were enabled
+    run_scheduled_draws(connection, &schedule, now);
+    let days_drawn_after_enabling = drawn_days(connection, raffle_id);
+
+    // once the scheduled draws were enabled before the raffle started, the missed days are caught up
+    // except for a day which was drawn before and whose winner was removed since
+    let removed_winner_day = first_day + Days::new(1);
+    sql_query("UPDATE raffles SET scheduled_draws_enabled_at = $1 WHERE id = $2")
+        .bind::<Timestamp, _>((now - Days::new(10)).naive_utc())
+        .bind::<Integer, _>(raffle_id)
+        .execute(connection)
+        .unwrap();
+    sql_query(
+        "INSERT INTO draws (raffle_id, drawn_for, algorithm_version, seed, candidate_ids, candidates_hash, winner_ids) SELECT $1, $2, 1, '\\x00', ARRAY[MIN(id)], '', ARRAY[MIN(id)] FROM participants WHERE raffle_id = $1",
+    )
+    .bind::<Integer, _>(raffle_id)
+    .bind::<Date, _>(removed_winner_day)
+    .execute(connection)
+    .unwrap();
+    sql_query(
+        "INSERT INTO wins (participant_id, won_on, status, revoked_at) SELECT MIN(id), $2, 'revoked', now() FROM participants WHERE raffle_id = $1",
+    )
+    .bind::<Integer, _>(raffle_id)
+    .bind::<Date, _>(removed_winner_day)
+    .execute(connection)
+    .unwrap();
+    run_scheduled_draws(connection, &schedule, now);
+    let days_drawn_after_catching_up = drawn_days(connection, raffle_id);
+
+    // repeating the draws never draws a day again
+    run_scheduled_draws(connection, &schedule, now);
+    let days_drawn_after_repeating = drawn_days(connection, raffle_id);
+
+    delete_raffle(connection, raffle_id);
+
+    assert_eq!(days_drawn_after_enabling, vec![]);
+    assert_eq!(
+        days_drawn_after_catching_up,
+        vec![first_day, removed_winner_day, today - Days::new(1), today]
+    );
+    assert_eq!(days_drawn_after_repeating, days_drawn_after_catching_up);
+}
@@ -0,0 +1,115 @@
+//! Tests for the enrollment and the use of TOTP second factors and recovery codes. They need a PostgreSQL database
+//! which can be used for testing and are skipped if `ADVENTSKALENDER_TEST_DB_CONNECTION` is not set, e.g.:
+//!
+//! `ADVENTSKALENDER_TEST_DB_CONNECTION=postgres://adventskalender@localhost/adventskalender cargo test`
+mod common;
+
+use adventskalender_backend::guards::Role;
+use adventskalender_backend::models::User;
+use adventskalender_backend::two_factor::{
+    check_second_factor, has_confirmed_second_factor, remove_second_factor, second_factor_of,
+    start_enrollment, totp_code, SecondFactorCheck, SecretCipher,
+};
+use common::{delete_user, prepare_user};
+use diesel::PgConnection;
+
+fn cipher() -> SecretCipher {
+    SecretCipher::from_hex(&"5a".repeat(32)).unwrap()
+}
+
+/// Get the code the authenticator app of the user shows for the supplied time step offset.
+fn current_code(connection: &mut PgConnection, user: &User, step_offset: i64) -> String {
+    let second_factor = second_factor_of(connection, user.id).unwrap().unwrap();
+    let secret = cipher().decrypt(user.id, &second_factor.secret).unwrap();
+    totp_code(&secret, chrono::Utc::now().timestamp() / 30 + step_offset)
+}
+
+#[test]
+fn enrollments_are_confirmed_with_a_code() {
+    let Some((mut connection, user)) = prepare_user("totp-enrollment", Role::Operator) else {
+        return;
+    };
+    let connection = &mut connection;
+
+    let enrollment = start_enrollment(connection, &cipher(), user.id, &user.username)
+        .unwrap()
+        .unwrap();
+    assert!(enrollment.provisioning_uri.contains(&enrollment.secret));
+    assert!(!has_confirmed_second_factor(connection, user.id).unwrap());
+
+    // the secret is stored encrypted
+    let second_factor = second_factor_of(connection, user.id).unwrap().unwrap();
+    assert!(!second_factor.secret.contains(&enrollment.secret));
+
+    let code = current_code(connection, &user, -1);
+    let recovery_codes = match check_second_factor(connection, &cipher(), user.id, &code).unwrap() {
+        SecondFactorCheck::Totp {
+            recovery_codes: Some(recovery_codes),
+        } => recovery_codes,
+        check => panic!("the enrollment was not confirmed: {:?}", check),
+    };
+    assert_eq!(recovery_codes.len(), 10);
+    assert!(has_confirmed_second_factor(connection, user.id).unwrap());
+
+    // a confirmed second factor is not replaced by a new enrollment and codes cannot be replayed
+    assert!(
+        start_enrollment(connection, &cipher(), user.id, &user.username)
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(
+        check_second_factor(connection, &cipher(), user.id, &code).unwrap(),
+        SecondFactorCheck::Rejected
+    );
+    let code = current_code(connection, &user, 0);
+    assert_eq!(
+        check_second_factor(connection, &cipher(), user.id, &code).unwrap(),
+        SecondFactorCheck::Totp {
+            recovery_codes: None
+        }
+    );
+
+    delete_user(connection, user.id);
+}
+
+#[test]
+fn recovery_codes_can_be_used_once() {
+    let Some((mut connection, user)) = prepare_user("totp-recovery", Role::Operator) else {
+        return;
+    };
+    let connection = &mut connection;
+
+    start_enrollment(connection, &cipher(), user.id, &user.username).unwrap();
+    let code = current_code(connection, &user, 0);
+    let SecondFactorCheck::Totp {
+        recovery_codes: Some(recovery_codes),
+    } = check_second_factor(connection, &cipher(), user.id, &code).unwrap()
+    else {
+        panic!("the enrollment was not confirmed");
+    };
+
+    // the codes are accepted without the separator and in upper case as well
+    let recovery_code = recovery_codes[0].replace('-', "").to_uppercase();
+    assert_eq!(
+        check_second_factor(connection, &cipher(), user.id, &recovery_code).unwrap(),
+        SecondFactorCheck::RecoveryCode
+    );
+    assert_eq!(
+        check_second_factor(connection, &cipher(), user.id, &recovery_codes[0]).unwrap(),
+        SecondFactorCheck::Rejected
+    );
+    assert_eq!(
+        check_second_factor(connection, &cipher(), user.id, "wrong-code").unwrap(),
+        SecondFactorCheck::Rejected
+    );
+
+    // without the second factor the recovery codes are gone as well
+    assert!(remove_second_factor(connection, user.id).unwrap());
+    assert!(!remove_second_factor(connection, user.id).unwrap());
+    assert_eq!(
+        check_second_factor(connection, &cipher(), user.id, &recovery_codes[1]).unwrap(),
+        SecondFactorCheck::Rejected
+    );
+
+    delete_user(connection, user.id);
+}
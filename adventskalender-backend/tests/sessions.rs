@@ -0,0 +1,254 @@
+//! Tests for exchanging refresh tokens and revoking sessions. They need a PostgreSQL database which can be used for
+//! testing and are skipped if `ADVENTSKALENDER_TEST_DB_CONNECTION` is not set, e.g.:
+//!
+//! `ADVENTSKALENDER_TEST_DB_CONNECTION=postgres://adventskalender@localhost/adventskalender cargo test`
+mod common;
+
+use adventskalender_backend::guards::Role;
+use adventskalender_backend::models::User;
+use adventskalender_backend::refresh_tokens::{
+    issue_refresh_token, revoke_session_of_refresh_token, rotate_refresh_token, RefreshOutcome,
+};
+use adventskalender_backend::sessions::{
+    active_sessions, create_session, is_session_active, revoke_sessions,
+};
+use common::{create_user, delete_user, login_cookie, prepare_user, test_client, test_database};
+use diesel::PgConnection;
+
+/// The lifetime of the sessions and refresh tokens created by the tests.
+const LIFETIME_IN_SECONDS: usize = 60;
+
+/// Log the user in, i.e. start a new session and issue its first refresh token.
+fn login(connection: &mut PgConnection, user: &User) -> (String, String) {
+    let session_id = create_session(connection, user.id, None, None, LIFETIME_IN_SECONDS).unwrap();
+    let refresh_token =
+        issue_refresh_token(connection, user.id, &session_id, LIFETIME_IN_SECONDS).unwrap();
+    (session_id, refresh_token)
+}
+
+fn rotated_token(outcome: RefreshOutcome) -> String {
+    match outcome {
+        RefreshOutcome::Rotated { refresh_token, .. } => refresh_token,
+        RefreshOutcome::Superseded => panic!("the token was treated as exchanged in parallel"),
+        RefreshOutcome::Reused { .. } => panic!("the token was treated as reused"),
+        RefreshOutcome::Rejected => panic!("the token was rejected"),
+    }
+}
+
+#[test]
+fn reusing_a_refresh_token_revokes_its_session() {
+    let (mut connection, user) = match prepare_user("refresh-tokens", Role::Viewer) {
+        Some(prepared) => prepared,
+        None => return,
+    };
+    let connection = &mut connection;
+
+    // each token can be exchanged once for a new token of the same session
+    let (session_id, first_token) = login(connection, &user);
+    let second_token =
+        rotated_token(rotate_refresh_token(connection, &first_token, LIFETIME_IN_SECONDS).unwrap());
+
+    // a parallel request with the token which was just exchanged is refused, but keeps the session
+    assert!(matches!(
+        rotate_refresh_token(connection, &first_token, LIFETIME_IN_SECONDS).unwrap(),
+        RefreshOutcome::Superseded
+    ));
+    let third_token = rotated_token(
+        rotate_refresh_token(connection, &second_token, LIFETIME_IN_SECONDS).unwrap(),
+    );
+
+    // presenting an older exchanged token again revokes the session including its newest token
+    assert!(matches!(
+        rotate_refresh_token(connection, &first_token, LIFETIME_IN_SECONDS).unwrap(),
+        RefreshOutcome::Reused { .. }
+    ));
+    assert!(matches!(
+        rotate_refresh_token(connection, &third_token, LIFETIME_IN_SECONDS).unwrap(),
+        RefreshOutcome::Rejected
+    ));
+    assert!(!is_session_active(connection, &session_id, user.id).unwrap());
+
+    // logging out revokes the session of that login only
+    let (_, other_login) = login(connection, &user);
+    let (logged_out_session, logged_out) = login(connection, &user);
+    assert_eq!(
+        revoke_session_of_refresh_token(connection, &logged_out).unwrap(),
+        1
+    );
+    assert!(!is_session_active(connection, &logged_out_session, user.id).unwrap());
+    assert!(matches!(
+        rotate_refresh_token(connection, &logged_out, LIFETIME_IN_SECONDS).unwrap(),
+        RefreshOutcome::Rejected
+    ));
+    rotated_token(rotate_refresh_token(connection, &other_login, LIFETIME_IN_SECONDS).unwrap());
+
+    delete_user(connection, user.id);
+}
+
+#[test]
+fn other_sessions_can_be_revoked() {
+    let (mut connection, user) = match prepare_user("sessions", Role::Viewer) {
+        Some(prepared) => prepared,
+        None => return,
+    };
+    let connection = &mut connection;
+
+    let (current_session, current_token) = login(connection, &user);
+    let (other_session, other_token) = login(connection, &user);
+    let (_, third_token) = login(connection, &user);
+    assert_eq!(active_sessions(connection, user.id).unwrap().len(), 3);
+
+    // a session of someone else cannot be revoked
+    assert_eq!(
+        revoke_sessions(connection, user.id + 1, Some(&other_session), None).unwrap(),
+        0
+    );
+    assert_eq!(
+        revoke_sessions(connection, user.id, Some(&other_session), None).unwrap(),
+        1
+    );
+    assert!(matches!(
+        rotate_refresh_token(connection, &other_token, LIFETIME_IN_SECONDS).unwrap(),
+        RefreshOutcome::Rejected
+    ));
+
+    // e.g. after changing the password, just the current session is kept
+    assert_eq!(
+        revoke_sessions(connection, user.id, None, Some(&current_session)).unwrap(),
+        1
+    );
+    assert!(matches!(
+        rotate_refresh_token(connection, &third_token, LIFETIME_IN_SECONDS).unwrap(),
+        RefreshOutcome::Rejected
+    ));
+    let remaining_sessions = active_sessions(connection, user.id).unwrap();
+    assert_eq!(remaining_sessions.len(), 1);
+    assert_eq!(remaining_sessions[0].id, current_session);
+    rotated_token(rotate_refresh_token(connection, &current_token, LIFETIME_IN_SECONDS).unwrap());
+
+    delete_user(connection, user.id);
+}
+
+#[test]
+fn resetting_the_password_revokes_the_sessions_and_requires_a_new_password() {
+    use adventskalender_backend::routes::{
+        get_current_user, reset_user_password, update_user_password,
+    };
+    use rocket::http::{ContentType, Status};
+    use rocket::routes;
+
+    let Some((mut connection, database_url)) = test_database() else {
+        return;
+    };
+    let connection = &mut connection;
+    let administrator = create_user(connection, "password-reset-admin", Role::Admin);
+    let user = create_user(connection, "password-reset", Role::Viewer);
+    let client = test_client(
+        &database_url,
+        "password-reset",
+        routes![get_current_user, reset_user_password, update_user_password],
+    );
+    let administrator_cookie = login_cookie(&client, connection, &administrator);
+    let old_cookie = login_cookie(&client, connection, &user);
+    let new_password =
+        r#"{"first_time": "Correct-Horse-Battery-7", "second_time": "Correct-Horse-Battery-7"}"#;
+
+    let reset_status = client
+        .put(format!("/users/{}/password", user.id))
+        .cookie(administrator_cookie)
+        .header(ContentType::JSON)
+        .body(new_password)
+        .dispatch()
+        .status();
+
+    // whoever is still logged in as the user can neither use the session nor choose the next password
+    let old_session_status = client
+        .get("/auth/me")
+        .cookie(old_cookie.clone())
+        .dispatch()
+        .status();
+    let password_change_status = client
+        .put("/auth/password")
+        .cookie(old_cookie)
+        .header(ContentType::JSON)
+        .body(new_password)
+        .dispatch()
+        .status();
+
+    // after logging in with the new password, the user can just change it
+    let new_cookie = login_cookie(&client, connection, &user);
+    let new_session = client.get("/auth/me").cookie(new_cookie.clone()).dispatch();
+    let new_session_status = new_session.status();
+    let user_information = new_session.into_string().unwrap_or_default();
+    let changed_password_status = client
+        .put("/auth/password")
+        .cookie(new_cookie.clone())
+        .header(ContentType::JSON)
+        .body(new_password)
+        .dispatch()
+        .status();
+    let changed_user_information = client
+        .get("/auth/me")
+        .cookie(new_cookie)
+        .dispatch()
+        .into_string()
+        .unwrap_or_default();
+
+    delete_user(connection, user.id);
+    delete_user(connection, administrator.id);
+
+    assert_eq!(reset_status, Status::NoContent);
+    assert_eq!(old_session_status, Status::Forbidden);
+    assert_eq!(password_change_status, Status::Forbidden);
+    assert_eq!(new_session_status, Status::Ok);
+    assert!(user_information.contains(r#""passwordChangeRequired":true"#));
+    assert_eq!(changed_password_status, Status::NoContent);
+    assert!(changed_user_information.contains(r#""passwordChangeRequired":false"#));
+}
+
+#[test]
+fn disabling_a_user_revokes_the_sessions_for_good() {
+    use adventskalender_backend::routes::{get_current_user, update_user};
+    use rocket::http::{ContentType, Status};
+    use rocket::routes;
+
+    let Some((mut connection, database_url)) = test_database() else {
+        return;
+    };
+    let connection = &mut connection;
+    let administrator = create_user(connection, "disable-user-admin", Role::Admin);
+    let user = create_user(connection, "disable-user", Role::Viewer);
+    let client = test_client(
+        &database_url,
+        "disable-user",
+        routes![get_current_user, update_user],
+    );
+    let administrator_cookie = login_cookie(&client, connection, &administrator);
+    let user_cookie = login_cookie(&client, connection, &user);
+
+    let change_user = |disabled: bool| {
+        client
+            .put(format!("/users/{}", user.id))
+            .cookie(administrator_cookie.clone())
+            .header(ContentType::JSON)
+            .body(format!(r#"{{"disabled": {}}}"#, disabled))
+            .dispatch()
+            .status()
+    };
+    let disable_status = change_user(true);
+    let enable_status = change_user(false);
+
+    // enabling the user again must not bring back the login from before
+    let old_session_status = client
+        .get("/auth/me")
+        .cookie(user_cookie)
+        .dispatch()
+        .status();
+
+    delete_user(connection, user.id);
+    delete_user(connection, administrator.id);
+
+    assert_eq!(disable_status, Status::Ok);
+    assert_eq!(enable_status, Status::Ok);
+    assert_eq!(old_session_status, Status::Forbidden);
+}
@@ -1,3 +1,5 @@
+import { PresentArraySchema } from './schemas';
+
 const determineApiUrl = () => {
     if (window.location && import.meta.env.PROD) {
         const locationValue = `${window.location.protocol}//${window.location.host}`;
@@ -8,6 +10,13 @@ const determineApiUrl = () => {
 
 export const API_BACKEND_URL = `${determineApiUrl()}/v1`;
 
+export const RAFFLE_ID = Number(import.meta.env.VITE_RAFFLE_ID ?? 1);
+
+// the login through the OpenID Connect provider of the backend (if it is configured there)
+export const IS_SSO_ENABLED = import.meta.env.VITE_SSO_ENABLED === 'true';
+
+export const API_RAFFLE_URL = `${API_BACKEND_URL}/raffles/${RAFFLE_ID}`;
+
 export const MAX_WINNERS_PER_DAY = 5;
 
 export interface ParticipantCount {
@@ -21,6 +30,7 @@ export interface Participant {
     first_name: string;
     last_name: string;
     present_identifier?: string | null;
+    present_id?: number | null;
 }
 
 export interface WinnerInformation {
@@ -28,6 +38,7 @@ export interface WinnerInformation {
     firstName: string;
     lastName: string;
     presentIdentifier?: string | null;
+    presentId?: number | null;
 }
 
 export interface AccessToken {
@@ -45,3 +56,30 @@ export interface VersionInformation {
 export interface AuditEventCount {
     count: number;
 }
+
+export interface Present {
+    id: number;
+    available_on: string;
+    identifier: string;
+    name: string;
+    description?: string | null;
+    sponsor?: string | null;
+    quantity: number;
+    assigned: number;
+    remaining: number;
+}
+
+// fetch the presents which can be assigned to the winners of the given day (yyyy-MM-dd)
+export const fetchPresentsOfDay = async (date: string): Promise<Present[]> => {
+    const res = await fetch(`${API_RAFFLE_URL}/presents?date=${date}`, {
+        method: 'GET',
+        headers: {
+            'Content-type': 'application/json; charset=UTF-8',
+        },
+        credentials: 'include',
+    });
+    if (res.status !== 200) {
+        return Promise.reject(res.status);
+    }
+    return PresentArraySchema.parse(await res.json());
+};
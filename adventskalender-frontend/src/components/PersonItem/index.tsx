@@ -4,8 +4,10 @@ import { useState } from 'react';
 import { DeleteWinnerDialog } from '../../dialogs/DeleteWinnerDialog';
 import { Button } from '@/components/ui/button';
 import { cn } from '@/lib/utils';
+import { useAuthentication } from '../../hooks/useAuthentication';
 
 export const PersonItem = ({ winner, winningDay, updateWinnerList }: { winner: WinnerInformation; winningDay: number; numberOfMaxSubPackages: number; updateWinnerList?: () => void }) => {
+    const auth = useAuthentication();
     const [deleteDialogOpen, setDeleteDialogOpen] = useState<boolean>(false);
     const [userToDelete, setUserToDelete] = useState<WinnerInformation>({ firstName: '', lastName: '', id: -1, presentIdentifier: '' });
 
@@ -37,9 +39,11 @@ export const PersonItem = ({ winner, winningDay, updateWinnerList }: { winner: W
                     &nbsp;
                     <p className="font-bold">{`${winningDay}${winner.presentIdentifier ? winner.presentIdentifier : ''}`}</p>
                 </div>
-                <Button variant={'destructive'} className="rounded-[20px] text-xs" onClick={handleDeleteClick(winner)}>
-                    <LocalizedText translationKey={'calendar.cards.winners.button_remove'} />
-                </Button>
+                {auth.canOperate && (
+                    <Button variant={'destructive'} className="rounded-[20px] text-xs" onClick={handleDeleteClick(winner)}>
+                        <LocalizedText translationKey={'calendar.cards.winners.button_remove'} />
+                    </Button>
+                )}
             </div>
         </>
     );
@@ -19,5 +19,10 @@ export const RequireAuthentication = ({ children }: { children: ReactNode }) =>
         return <Navigate to="/login" state={{ from: location }} />;
     }
 
+    // users whose password was reset by an administrator cannot do anything but change it
+    if (auth.passwordChangeRequired && location.pathname !== '/settings') {
+        return <Navigate to="/settings" replace />;
+    }
+
     return children;
 };
@@ -8,6 +8,7 @@ import { PersonItem } from '../PersonItem';
 import { EditWinnerDialog } from '../../dialogs/EditWinnerDialog';
 import { ViewWinnersDialog } from '../../dialogs/ViewWinnersDialog';
 import { Button } from '@/components/ui/button';
+import { useAuthentication } from '../../hooks/useAuthentication';
 
 interface Props {
     winningDate: string;
@@ -17,6 +18,7 @@ interface Props {
 }
 
 export const WinnerCard = (props: Props) => {
+    const auth = useAuthentication();
     const [editDialogOpen, setEditDialogOpen] = useState<boolean>(false);
     const [viewDialogOpen, setViewDialogOpen] = useState<boolean>(false);
     const [packageSelections, setPackageSelections] = useState<{ [key: string]: string }>({});
@@ -24,7 +26,7 @@ export const WinnerCard = (props: Props) => {
     useEffect(() => {
         const newPackageSelections: { [key: string]: string } = {};
         props.listOfWinner.forEach((winner) => {
-            newPackageSelections[winner.id] = winner.presentIdentifier ? winner.presentIdentifier : '';
+            newPackageSelections[winner.id] = winner.presentId ? winner.presentId.toString() : '';
         });
         setPackageSelections(newPackageSelections);
     }, [props.listOfWinner]);
@@ -73,9 +75,9 @@ export const WinnerCard = (props: Props) => {
         <>
             <EditWinnerDialog
                 listOfWinner={props.listOfWinner}
+                winningDate={props.winningDate}
                 isOpen={editDialogOpen}
                 setDialogOpenStateFunction={setEditDialogOpen}
-                numberOfMaxSubPackages={props.numberOfMaxSubPackages}
                 packageSelections={packageSelections}
             />
             <ViewWinnersDialog winningDate={props.winningDate} listOfWinner={props.listOfWinner} isOpen={viewDialogOpen} onClose={() => setViewDialogOpen(false)} />
@@ -91,7 +93,7 @@ export const WinnerCard = (props: Props) => {
                                 <Button variant={'outline'} size="icon" className="rounded-full h-8 w-8" onClick={handleViewClick}>
                                     <Eye className="h-4 w-4" />
                                 </Button>
-                                {import.meta.env.DEV && (
+                                {import.meta.env.DEV && auth.canOperate && (
                                     <Button variant={'outline'} size="icon" className="rounded-full h-8 w-8" onClick={handleEditClick}>
                                         <Edit className="h-4 w-4" />
                                     </Button>
@@ -2,7 +2,7 @@ import { Dialog, DialogContent, DialogDescription, DialogFooter, DialogHeader, D
 import { Button } from '@/components/ui/button';
 import { LocalizedText } from '../../components/LocalizedText';
 import { Dispatch, SetStateAction, useContext } from 'react';
-import { API_BACKEND_URL, WinnerInformation } from '../../api.ts';
+import { API_RAFFLE_URL, WinnerInformation } from '../../api.ts';
 import { LocalizationContext } from '../../provider/LocalizationContext';
 import { useAuthentication } from '../../hooks/useAuthentication';
 import { useNavigate } from 'react-router-dom';
@@ -30,7 +30,7 @@ export const DeleteWinnerDialog = (props: Props) => {
         }
 
         // since we have a token, we can query the backend for the winner count for the selected day
-        fetch(`${API_BACKEND_URL}/participants/won/${props.userToDelete.id}`, {
+        fetch(`${API_RAFFLE_URL}/participants/won/${props.userToDelete.id}`, {
             method: 'DELETE',
             headers: {
                 'Content-type': 'application/json; charset=UTF-8',
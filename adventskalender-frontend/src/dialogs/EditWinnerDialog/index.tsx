@@ -4,16 +4,16 @@ import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from '@
 import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '@/components/ui/select';
 import { Label } from '@/components/ui/label';
 import { LocalizedText } from '../../components/LocalizedText';
-import { API_BACKEND_URL, WinnerInformation } from '../../api.ts';
-import { Dispatch, ReactNode, SetStateAction, useState } from 'react';
+import { API_RAFFLE_URL, fetchPresentsOfDay, Present, WinnerInformation } from '../../api.ts';
+import { Dispatch, ReactNode, SetStateAction, useEffect, useState } from 'react';
 import { useAuthentication } from '../../hooks/useAuthentication';
 import { useNavigate } from 'react-router-dom';
 import { cn } from '@/lib/utils';
 
 interface Props {
     listOfWinner: WinnerInformation[];
+    winningDate: string;
     isOpen: boolean;
-    numberOfMaxSubPackages: number;
     setDialogOpenStateFunction: Dispatch<SetStateAction<boolean>>;
     packageSelections: StringMap;
 }
@@ -23,23 +23,41 @@ export const EditWinnerDialog = (props: Props) => {
     const auth = useAuthentication();
     const navigate = useNavigate();
     const [packageSelections, setPackageSelections] = useState<{ [key: string]: string }>({});
+    const [presents, setPresents] = useState<Present[]>([]);
 
-    const getPossiblePackageMenuItems = (userId: number, maxPackageCount: number) => {
-        const alphabet = Array.from(Array(maxPackageCount))
-            .map((_, i) => i + 65)
-            .map((x) => String.fromCharCode(x));
+    const updatePresents = () => {
+        fetchPresentsOfDay(props.winningDate)
+            .then(setPresents)
+            .catch(() => setPresents([]));
+    };
+
+    useEffect(() => {
+        // the stock of the presents might have changed since the dialog was opened the last time
+        if (props.isOpen && auth.isAuthenticated) {
+            setPackageSelections(props.packageSelections);
+            updatePresents();
+        }
+        // eslint-disable-next-line react-hooks/exhaustive-deps
+    }, [props.isOpen, props.winningDate]);
+
+    const getPossiblePackageMenuItems = (userId: number) => {
         const items: ReactNode[] = [];
 
         items.push(
-            <SelectItem key={`menu-item-not-selected-for-user-${userId}`} value={''}>
+            <SelectItem key={`menu-item-not-selected-for-user-${userId}`} value="__none__">
                 -
             </SelectItem>
         );
 
-        alphabet.map((character) => {
+        // presents without remaining pieces can just be kept by the winner who already got them
+        presents.map((present) => {
             items.push(
-                <SelectItem key={`menu-item-${character}-for-user-${userId}`} value={character}>
-                    {character}
+                <SelectItem
+                    key={`menu-item-${present.id}-for-user-${userId}`}
+                    value={present.id.toString()}
+                    disabled={present.remaining === 0 && packageSelections[userId] !== present.id.toString()}
+                >
+                    {present.identifier} ({present.name}, {present.remaining}/{present.quantity})
                 </SelectItem>
             );
         });
@@ -53,14 +71,17 @@ export const EditWinnerDialog = (props: Props) => {
             return;
         }
 
+        // the "__none__" sentinel value removes the assignment of the present
+        const presentId = selectedPackage === '__none__' ? null : Number(selectedPackage);
+
         // since we have a token, we can update the package selection for the given user id
-        fetch(`${API_BACKEND_URL}/participants/${userId}`, {
+        fetch(`${API_RAFFLE_URL}/participants/${userId}`, {
             method: 'PUT',
             headers: {
                 'Content-type': 'application/json; charset=UTF-8',
             },
             credentials: 'include',
-            body: JSON.stringify({ package: selectedPackage }),
+            body: JSON.stringify({ present_id: presentId }),
         })
             .then((res) => {
                 // if we got a valid response from the backend, it should be JSON. We can convert it to a valid JSON
@@ -83,8 +104,9 @@ export const EditWinnerDialog = (props: Props) => {
             .then(() => {
                 // update the value the user successfully set for the package
                 const previousSelection = Object.assign({}, packageSelections); // recreate the json object so React sees a change
-                previousSelection[userId] = selectedPackage;
+                previousSelection[userId] = presentId === null ? '' : selectedPackage;
                 setPackageSelections(previousSelection);
+                updatePresents();
 
                 // clear a previous error state, if it existed
                 const previousErrorStates = Object.assign({}, packageSelectionErrorStates); // recreate the json object so React sees a change
@@ -134,7 +156,7 @@ export const EditWinnerDialog = (props: Props) => {
                                                 <Label htmlFor={`winner-${currentWinner.id}-package-selection`} className="sr-only">
                                                     <LocalizedText translationKey={'calendar.dialogs.edit_participant.table.select.package_label'} />
                                                 </Label>
-                                                <Select value={packageSelections[currentWinner.id] || ''} onValueChange={(value) => selectPackageForUser(currentWinner.id, value)}>
+                                                <Select value={packageSelections[currentWinner.id] || '__none__'} onValueChange={(value) => selectPackageForUser(currentWinner.id, value)}>
                                                     <SelectTrigger
                                                         id={`winner-${currentWinner.id}-package-selection`}
                                                         className={cn('w-full', packageSelectionErrorStates[currentWinner.id] && 'border-destructive focus-visible:ring-destructive')}
@@ -142,7 +164,7 @@ export const EditWinnerDialog = (props: Props) => {
                                                     >
                                                         <SelectValue placeholder="-" />
                                                     </SelectTrigger>
-                                                    <SelectContent>{getPossiblePackageMenuItems(currentWinner.id, props.numberOfMaxSubPackages)}</SelectContent>
+                                                    <SelectContent>{getPossiblePackageMenuItems(currentWinner.id)}</SelectContent>
                                                 </Select>
                                                 {packageSelectionErrorStates[currentWinner.id] && (
                                                     <p className="text-sm text-destructive" role="alert">
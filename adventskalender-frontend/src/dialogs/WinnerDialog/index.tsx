@@ -4,10 +4,10 @@ import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from '@
 import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '@/components/ui/select';
 import { Label } from '@/components/ui/label';
 import { LocalizedText } from '../../components/LocalizedText';
-import { ReactNode, useContext, useState } from 'react';
+import { ReactNode, useContext, useEffect, useState } from 'react';
 import { useAuthentication } from '../../hooks/useAuthentication';
 import { useNavigate } from 'react-router-dom';
-import { API_BACKEND_URL, MAX_WINNERS_PER_DAY, WinnerInformation } from '../../api.ts';
+import { API_RAFFLE_URL, fetchPresentsOfDay, MAX_WINNERS_PER_DAY, Present, WinnerInformation } from '../../api.ts';
 import { LocalizationContext } from '../../provider/LocalizationContext';
 import { format, parse } from 'date-fns';
 import { de } from 'date-fns/locale';
@@ -45,9 +45,22 @@ export const WinnerDialog = (props: Props) => {
     const [fetchedWinners, setFetchedWinners] = useState<WinnerInformation2[]>([]);
     const [fetchError, setFetchError] = useState<boolean>(false);
     const [packageSelectionErrorStates, setPackageSelectionErrorStates] = useState<BooleanMap>({});
-    const USABLE_PACKAGE_ALPHABET = Array.from(Array(MAX_WINNERS_PER_DAY))
-        .map((_, i) => i + 65)
-        .map((x) => String.fromCharCode(x));
+    const [presents, setPresents] = useState<Present[]>([]);
+
+    const updatePresents = () => {
+        fetchPresentsOfDay(props.date)
+            .then(setPresents)
+            .catch(() => setPresents([]));
+    };
+
+    useEffect(() => {
+        if (props.isOpen && auth.isAuthenticated) {
+            updatePresents();
+        }
+        // eslint-disable-next-line react-hooks/exhaustive-deps
+    }, [props.isOpen, props.date]);
+
+    const identifierOfPresent = (presentId: string | undefined) => presents.find((present) => present.id.toString() === presentId)?.identifier;
 
     const allSubPackagesAreSelected = () => {
         // ensure we have selected a package for each winner
@@ -67,7 +80,7 @@ export const WinnerDialog = (props: Props) => {
         const deepCheckErrorValues = Object.keys(packageSelectionErrorStates).map((key) => {
             const isErrorState = packageSelectionErrorStates[key];
             if (isErrorState) {
-                return packageSelections[key].length == 1 && USABLE_PACKAGE_ALPHABET.includes(packageSelections[key]);
+                return presents.some((present) => present.id.toString() === packageSelections[key]);
             }
             return false;
         });
@@ -107,11 +120,8 @@ export const WinnerDialog = (props: Props) => {
         unselectAllWinnersForDay();
     };
 
-    const getPossiblePackageMenuItems = (userId: number, maxPackageCount: number) => {
+    const getPossiblePackageMenuItems = (userId: number) => {
         const items: ReactNode[] = [];
-        const alphabet = Array.from(Array(maxPackageCount))
-            .map((_, i) => i + 65)
-            .map((x) => String.fromCharCode(x));
 
         items.push(
             <SelectItem key={`menu-item-not-selected-for-user-${userId}`} value="__none__">
@@ -119,10 +129,15 @@ export const WinnerDialog = (props: Props) => {
             </SelectItem>
         );
 
-        alphabet.map((character) => {
+        // presents without remaining pieces can just be kept by the winner who already got them
+        presents.map((present) => {
             items.push(
-                <SelectItem key={`menu-item-${character}-for-user-${userId}`} value={character}>
-                    {character}
+                <SelectItem
+                    key={`menu-item-${present.id}-for-user-${userId}`}
+                    value={present.id.toString()}
+                    disabled={present.remaining === 0 && packageSelections[userId] !== present.id.toString()}
+                >
+                    {present.identifier} ({present.name}, {present.remaining}/{present.quantity})
                 </SelectItem>
             );
         });
@@ -133,11 +148,16 @@ export const WinnerDialog = (props: Props) => {
     const handleFillPackageSelectionAutomatically = () => {
         if (import.meta.env.DEV) {
             const selectedPackages: StringMap = {};
-            let currentAlphabetIdx = 0;
+            // hand out the pieces of the presents in the order they were created
+            const availablePresents = presents.flatMap((present) => Array(present.remaining).fill(present.id.toString()));
+            let currentPresentIdx = 0;
             props.winner
                 .map((currentWinner) => currentWinner.id)
                 .forEach((id) => {
-                    selectedPackages[id.toString()] = USABLE_PACKAGE_ALPHABET[currentAlphabetIdx++];
+                    if (currentPresentIdx >= availablePresents.length) {
+                        return;
+                    }
+                    selectedPackages[id.toString()] = availablePresents[currentPresentIdx++];
                     selectPackageForUser(id, selectedPackages[id.toString()], false);
                 });
             setPackageSelections(selectedPackages);
@@ -160,7 +180,7 @@ export const WinnerDialog = (props: Props) => {
         // since we have a token, we can now unselect all participants
         Promise.all(
             winnerIds.map(async (winnerId) => {
-                const res = await fetch(`${API_BACKEND_URL}/participants/won/${winnerId}`, {
+                const res = await fetch(`${API_RAFFLE_URL}/participants/won/${winnerId}`, {
                     method: 'DELETE',
                     headers: {
                         'Content-type': 'application/json; charset=UTF-8',
@@ -189,17 +209,17 @@ export const WinnerDialog = (props: Props) => {
             return;
         }
 
-        // Convert "__none__" sentinel value to empty string for the API
-        const packageValue = selectedPackage === '__none__' ? '' : selectedPackage;
+        // the "__none__" sentinel value removes the assignment of the present
+        const presentId = selectedPackage === '__none__' ? null : Number(selectedPackage);
 
         // since we have a token, we can update the package selection for the given user id
-        fetch(`${API_BACKEND_URL}/participants/${userId}`, {
+        fetch(`${API_RAFFLE_URL}/participants/${userId}`, {
             method: 'PUT',
             headers: {
                 'Content-type': 'application/json; charset=UTF-8',
             },
             credentials: 'include',
-            body: JSON.stringify({ package: packageValue }),
+            body: JSON.stringify({ present_id: presentId }),
         })
             .then((res) => {
                 // if we got a valid response from the backend, it should be JSON. We can convert it to a valid JSON
@@ -225,6 +245,7 @@ export const WinnerDialog = (props: Props) => {
                     const previousSelection = Object.assign({}, packageSelections); // recreate the json object so React sees a change
                     previousSelection[userId] = selectedPackage;
                     setPackageSelections(previousSelection);
+                    updatePresents();
 
                     // clear a previous error state, if it existed
                     const previousErrorStates = Object.assign({}, packageSelectionErrorStates); // recreate the json object so React sees a change
@@ -244,7 +265,7 @@ export const WinnerDialog = (props: Props) => {
     const prepareWinnerData = (refetchWinners: boolean) => {
         if (refetchWinners) {
             // Fetch winners from backend to get all winners for the day
-            fetch(`${API_BACKEND_URL}/participants/won/${props.date}`, {
+            fetch(`${API_RAFFLE_URL}/participants/won/${props.date}`, {
                 method: 'GET',
                 headers: {
                     'Content-type': 'application/json; charset=UTF-8',
@@ -304,8 +325,8 @@ export const WinnerDialog = (props: Props) => {
                       return packageA > packageB ? 1 : -1;
                   })
                 : [...props.winner].sort((a, b) => {
-                      const packageA = packageSelections[a.id] || '?';
-                      const packageB = packageSelections[b.id] || '?';
+                      const packageA = identifierOfPresent(packageSelections[a.id]) || '?';
+                      const packageB = identifierOfPresent(packageSelections[b.id]) || '?';
                       return packageA > packageB ? 1 : -1;
                   });
 
@@ -328,7 +349,7 @@ export const WinnerDialog = (props: Props) => {
                         const w = winner as WinnerInformation;
                         firstName = w.firstName;
                         lastName = w.lastName;
-                        presentId = packageSelections[w.id];
+                        presentId = identifierOfPresent(packageSelections[w.id]);
                     }
 
                     return (
@@ -390,7 +411,7 @@ export const WinnerDialog = (props: Props) => {
                                                         >
                                                             <SelectValue placeholder="-" />
                                                         </SelectTrigger>
-                                                        <SelectContent>{getPossiblePackageMenuItems(currentWinner.id, props.numberOfMaxSubPackages)}</SelectContent>
+                                                        <SelectContent>{getPossiblePackageMenuItems(currentWinner.id)}</SelectContent>
                                                     </Select>
                                                     {packageSelectionErrorStates[currentWinner.id] && (
                                                         <p className="text-sm text-destructive" role="alert">
@@ -1,9 +1,40 @@
 import { createContext, useContext } from 'react';
 
+// the roles of the users, each role includes the permissions of the roles before it
+export type UserRole = 'viewer' | 'operator' | 'admin';
+
+// the second step of a login whose password was accepted
+export interface SecondFactorChallenge {
+    challenge: string;
+    // the user has to set up an authenticator app before the login can be finished
+    enrollmentRequired: boolean;
+}
+
+// the information an authenticator app needs to generate the codes of the user
+export interface TotpEnrollment {
+    secret: string;
+    provisioningUri: string;
+}
+
 interface AuthContextType {
     isAuthenticated: boolean;
     isLoading: boolean;
-    signin: (user: string, password: string, successCallback: VoidFunction, failCallback: VoidFunction, rateLimitCallback?: (waitTime: number) => void) => void;
+    role: UserRole;
+    // operators (and admins) can pick, remove and replace winners and assign presents
+    canOperate: boolean;
+    // a password which was reset by an administrator has to be changed before anything else can be done
+    passwordChangeRequired: boolean;
+    passwordChanged: VoidFunction;
+    signin: (
+        user: string,
+        password: string,
+        successCallback: VoidFunction,
+        failCallback: VoidFunction,
+        rateLimitCallback?: (waitTime: number) => void,
+        secondFactorCallback?: (challenge: SecondFactorChallenge) => void
+    ) => void;
+    startSecondFactorEnrollment: (challenge: SecondFactorChallenge) => Promise<TotpEnrollment>;
+    verifySecondFactor: (challenge: SecondFactorChallenge, code: string, successCallback: (recoveryCodes?: string[]) => void, failCallback: VoidFunction) => void;
     signout: (callback: VoidFunction) => void;
 }
 
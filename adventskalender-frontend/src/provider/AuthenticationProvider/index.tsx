@@ -1,26 +1,51 @@
 import { useState, useEffect } from 'react';
 import * as React from 'react';
-import { AuthenticationContext } from '../../hooks/useAuthentication';
+import { AuthenticationContext, SecondFactorChallenge, TotpEnrollment, UserRole } from '../../hooks/useAuthentication';
 import { API_BACKEND_URL } from '../../api';
 import { rateLimiter } from '../../utils/RateLimiter';
 
+// the access tokens are short-lived, so they are renewed with the refresh token before they expire
+const REFRESH_INTERVAL_IN_MILLISECONDS = 5 * 60 * 1000;
+
 export const AuthenticationProvider = ({ children }: { children: React.ReactNode }) => {
     // Remove token state - authentication is now server-side via httpOnly cookies
     const [isAuthenticated, setIsAuthenticated] = useState<boolean>(false);
     const [isLoading, setIsLoading] = useState<boolean>(true);
+    const [role, setRole] = useState<UserRole>('viewer');
+    const [passwordChangeRequired, setPasswordChangeRequired] = useState<boolean>(false);
 
-    // Check authentication status on mount by calling /auth/me
-    useEffect(() => {
-        fetch(`${API_BACKEND_URL}/auth/me`, {
+    // fetch the information about the logged-in user (e.g. the role) from the backend
+    const fetchCurrentUser = () => {
+        return fetch(`${API_BACKEND_URL}/auth/me`, {
             method: 'GET',
             credentials: 'include', // Include httpOnly cookie
-        })
-            .then((response) => {
-                if (response.ok) {
-                    setIsAuthenticated(true);
-                } else {
-                    setIsAuthenticated(false);
-                }
+        }).then(async (response) => {
+            if (!response.ok) {
+                return false;
+            }
+            const user = await response.json();
+            setRole(user.role ?? 'viewer');
+            setPasswordChangeRequired(user.passwordChangeRequired ?? false);
+            return true;
+        });
+    };
+
+    // exchange the refresh token (stored as httpOnly cookie) for a new access token. A conflict means
+    // that a parallel request (e.g. of another tab) already got the new tokens
+    const refreshToken = () => {
+        return fetch(`${API_BACKEND_URL}/auth/refresh`, {
+            method: 'POST',
+            credentials: 'include',
+        }).then((response) => response.status === 204 || response.status === 409);
+    };
+
+    // Check authentication status on mount by calling /auth/me (the access token may have expired
+    // while the refresh token is still valid)
+    useEffect(() => {
+        fetchCurrentUser()
+            .then((isLoggedIn) => (isLoggedIn ? true : refreshToken().then((isRefreshed) => isRefreshed && fetchCurrentUser())))
+            .then((isLoggedIn) => {
+                setIsAuthenticated(isLoggedIn);
             })
             .catch(() => {
                 setIsAuthenticated(false);
@@ -30,7 +55,33 @@ export const AuthenticationProvider = ({ children }: { children: React.ReactNode
             });
     }, []);
 
-    const signin = (username: string, password: string, successCallback: VoidFunction, failCallback: VoidFunction, rateLimitCallback?: (waitTime: number) => void) => {
+    // keep the access token fresh as long as the user is logged in
+    useEffect(() => {
+        if (!isAuthenticated) {
+            return;
+        }
+        const interval = setInterval(() => {
+            refreshToken()
+                .then((isRefreshed) => {
+                    if (!isRefreshed) {
+                        setIsAuthenticated(false);
+                    }
+                })
+                .catch(() => {
+                    // the backend may just be unreachable for a moment, try again later
+                });
+        }, REFRESH_INTERVAL_IN_MILLISECONDS);
+        return () => clearInterval(interval);
+    }, [isAuthenticated]);
+
+    const signin = (
+        username: string,
+        password: string,
+        successCallback: VoidFunction,
+        failCallback: VoidFunction,
+        rateLimitCallback?: (waitTime: number) => void,
+        secondFactorCallback?: (challenge: SecondFactorChallenge) => void
+    ) => {
         const key = `login:${username}`;
 
         // Check if rate limit allows this attempt
@@ -55,16 +106,21 @@ export const AuthenticationProvider = ({ children }: { children: React.ReactNode
             },
             credentials: 'include', // CRITICAL: Send cookies with request
         })
-            .then((response) => {
+            .then(async (response) => {
+                // the password was accepted, but the user has to supply a second factor
+                if (response.status === 202 && secondFactorCallback) {
+                    rateLimiter.recordAttempt(key, true);
+                    secondFactorCallback(await response.json());
+                    return;
+                }
                 if (response.status !== 204) {
                     return Promise.reject();
                 }
-                return response;
-            })
-            .then(() => {
+
                 // Success - clear rate limit tracking
                 rateLimiter.recordAttempt(key, true);
                 setIsAuthenticated(true);
+                await fetchCurrentUser().catch(() => false);
                 successCallback();
             })
             .catch(() => {
@@ -75,6 +131,48 @@ export const AuthenticationProvider = ({ children }: { children: React.ReactNode
             });
     };
 
+    // get a new secret for the authenticator app of a user who has to set up a second factor
+    const startSecondFactorEnrollment = (challenge: SecondFactorChallenge): Promise<TotpEnrollment> => {
+        return fetch(`${API_BACKEND_URL}/auth/totp/login/enrollment`, {
+            method: 'POST',
+            body: JSON.stringify({ challenge: challenge.challenge }),
+            headers: {
+                'Content-type': 'application/json; charset=UTF-8',
+            },
+            credentials: 'include',
+        }).then((response) => {
+            if (!response.ok) {
+                return Promise.reject();
+            }
+            return response.json();
+        });
+    };
+
+    // finish the login with a TOTP code (or a recovery code), the recovery codes are returned once after
+    // a new second factor was confirmed
+    const verifySecondFactor = (challenge: SecondFactorChallenge, code: string, successCallback: (recoveryCodes?: string[]) => void, failCallback: VoidFunction) => {
+        fetch(`${API_BACKEND_URL}/auth/totp/login`, {
+            method: 'POST',
+            body: JSON.stringify({ challenge: challenge.challenge, code }),
+            headers: {
+                'Content-type': 'application/json; charset=UTF-8',
+            },
+            credentials: 'include',
+        })
+            .then(async (response) => {
+                if (!response.ok) {
+                    return Promise.reject();
+                }
+                const result = await response.json();
+                setIsAuthenticated(true);
+                await fetchCurrentUser().catch(() => false);
+                successCallback(result.recoveryCodes);
+            })
+            .catch(() => {
+                failCallback();
+            });
+    };
+
     const signout = (callback: VoidFunction) => {
         fetch(`${API_BACKEND_URL}/auth/logout`, {
             method: 'POST',
@@ -91,7 +189,9 @@ export const AuthenticationProvider = ({ children }: { children: React.ReactNode
             });
     };
 
-    const value = { isAuthenticated, isLoading, signin, signout };
+    const canOperate = role === 'operator' || role === 'admin';
+    const passwordChanged = () => setPasswordChangeRequired(false);
+    const value = { isAuthenticated, isLoading, role, canOperate, passwordChangeRequired, passwordChanged, signin, startSecondFactorEnrollment, verifySecondFactor, signout };
 
     return <AuthenticationContext.Provider value={value}>{children}</AuthenticationContext.Provider>;
 };
@@ -13,6 +13,7 @@ export const ParticipantSchema = z.object({
     first_name: z.string().min(1).max(100),
     last_name: z.string().min(1).max(100),
     present_identifier: z.string().max(10).nullable().optional(),
+    present_id: z.number().int().positive().nullable().optional(),
 });
 
 // Schema for winner information
@@ -21,6 +22,7 @@ export const WinnerInformationSchema = z.object({
     firstName: z.string().min(1).max(100),
     lastName: z.string().min(1).max(100),
     presentIdentifier: z.string().max(10).nullable().optional(),
+    presentId: z.number().int().positive().nullable().optional(),
 });
 
 // Schema for access token (Note: with httpOnly cookies, this may not be used)
@@ -57,7 +59,24 @@ export const WinnerInformation2Schema = z.object({
     first_name: z.string().min(1).max(100),
     last_name: z.string().min(1).max(100),
     present_identifier: z.string().max(10).nullable().optional(),
+    present_id: z.number().int().positive().nullable().optional(),
 });
 
 // Schema for WinnersOnDateMap - a map of dates to arrays of winners
 export const WinnersOnDateMapSchema = z.record(z.string(), z.array(WinnerInformation2Schema));
+
+// Schema for a present of the inventory of a day
+export const PresentSchema = z.object({
+    id: z.number().int().positive(),
+    available_on: z.string().min(1),
+    identifier: z.string().min(1).max(10),
+    name: z.string().min(1).max(128),
+    description: z.string().nullable().optional(),
+    sponsor: z.string().max(128).nullable().optional(),
+    quantity: z.number().int().nonnegative(),
+    assigned: z.number().int().nonnegative(),
+    remaining: z.number().int().nonnegative(),
+});
+
+// Schema for arrays of presents
+export const PresentArraySchema = z.array(PresentSchema);
@@ -6,7 +6,7 @@ import { LocalizedText } from '../../components/LocalizedText';
 import { LocalizationContext } from '../../provider/LocalizationContext';
 import { sessionManager } from '../../utils/SessionManager';
 import { SessionExpiryWarningDialog } from '../../dialogs/SessionExpiryWarningDialog';
-import { API_BACKEND_URL } from '../../api';
+import { API_RAFFLE_URL } from '../../api';
 import { NavigationDrawer } from '../../components/NavigationDrawer';
 import { AppHeader } from '../../components/AppHeader';
 import { cn } from '../../lib/utils';
@@ -58,7 +58,7 @@ export const AuthenticatedView = (props: Props) => {
 
         // Make a request to the backend to refresh the session cookie
         // Any authenticated API call will refresh the httpOnly cookie
-        fetch(`${API_BACKEND_URL}/participants/count`, {
+        fetch(`${API_RAFFLE_URL}/participants/count`, {
             method: 'GET',
             credentials: 'include',
         })
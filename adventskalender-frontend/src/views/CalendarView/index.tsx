@@ -1,5 +1,5 @@
 import { useEffect, useState } from 'react';
-import { API_BACKEND_URL, MAX_WINNERS_PER_DAY } from '../../api';
+import { API_RAFFLE_URL, MAX_WINNERS_PER_DAY } from '../../api';
 import { useAuthentication } from '../../hooks/useAuthentication';
 import { useNavigate } from 'react-router-dom';
 import { WinnerCard } from '../../components/WinnerCard';
@@ -21,7 +21,7 @@ export const CalendarView = () => {
         }
 
         // since we have a token, we can query the backend for the winner count for the selected day
-        fetch(`${API_BACKEND_URL}/participants/won`, {
+        fetch(`${API_RAFFLE_URL}/participants/won`, {
             method: 'GET',
             headers: {
                 'Content-type': 'application/json; charset=UTF-8',
@@ -84,6 +84,7 @@ export const CalendarView = () => {
                     firstName: winnerEntry.first_name,
                     lastName: winnerEntry.last_name,
                     presentIdentifier: winnerEntry.present_identifier,
+                    presentId: winnerEntry.present_id,
                 };
             });
             elements.push(
@@ -1,7 +1,7 @@
 import { OutlinedCard } from '../../components/OutlinedCard';
 import { WinningDaySelector } from '../../components/WinningDaySelector';
 import { PickNewWinner } from '../../components/PickNewWinner';
-import { API_BACKEND_URL, MAX_WINNERS_PER_DAY, ParticipantCount, WinnerInformation } from '../../api';
+import { API_RAFFLE_URL, MAX_WINNERS_PER_DAY, ParticipantCount, WinnerInformation } from '../../api';
 import { useContext, useEffect, useState } from 'react';
 import { useAuthentication } from '../../hooks/useAuthentication';
 import { useNavigate } from 'react-router-dom';
@@ -53,7 +53,7 @@ export const DashboardView = () => {
         }
 
         // since we are authenticated, we can query the backend for the winner count for the selected day
-        fetch(`${API_BACKEND_URL}/participants/won/${getSelectedDateAsString()}/count`, {
+        fetch(`${API_RAFFLE_URL}/participants/won/${getSelectedDateAsString()}/count`, {
             method: 'GET',
             headers: {
                 'Content-type': 'application/json; charset=UTF-8',
@@ -99,7 +99,7 @@ export const DashboardView = () => {
         }
 
         // since we are authenticated, we can query the backend for the participant count
-        fetch(`${API_BACKEND_URL}/participants/count`, {
+        fetch(`${API_RAFFLE_URL}/participants/count`, {
             method: 'GET',
             headers: {
                 'Content-type': 'application/json; charset=UTF-8',
@@ -173,7 +173,7 @@ export const DashboardView = () => {
         }
 
         // try to pick a new winner from the backend
-        fetch(`${API_BACKEND_URL}/participants/pick/${actualWinnersToPick}/for/${getSelectedDateAsString()}`, {
+        fetch(`${API_RAFFLE_URL}/participants/pick/${actualWinnersToPick}/for/${getSelectedDateAsString()}`, {
             method: 'GET',
             headers: {
                 'Content-type': 'application/json; charset=UTF-8',
@@ -214,7 +214,7 @@ export const DashboardView = () => {
 
                 // setup the dialog for the picked winners and return ensure the dialog is shown to the user, ...
                 const mappedWinners = validated.map((element) => {
-                    return { id: element.id, firstName: element.first_name, lastName: element.last_name, presentIdentifier: element.present_identifier };
+                    return { id: element.id, firstName: element.first_name, lastName: element.last_name, presentIdentifier: element.present_identifier, presentId: element.present_id };
                 });
                 setLastWinners(mappedWinners);
                 setIsLastWinnerDialogOpen(true);
@@ -276,7 +276,7 @@ export const DashboardView = () => {
                         <div className="flex flex-col gap-4 my-4">
                             <WinningDaySelector label={localizationContext.translate('dashboard.day_selection')} selectedDay={selectedDay} changeHandler={handleDateSelectionChange} />
                             <PickNewWinner
-                                isEnabled={getNumberOfWinnersToPick(winnersOnSelectedDay) > 0}
+                                isEnabled={auth.canOperate && getNumberOfWinnersToPick(winnersOnSelectedDay) > 0}
                                 isLoadingNewWinner={loadingNewWinner}
                                 onRequestWinner={checkPicking}
                                 label={localizationContext.translate('dashboard.pick_winner_button')}
@@ -5,11 +5,12 @@ import { Label } from '@/components/ui/label';
 import { Card, CardContent } from '@/components/ui/card';
 import { LockKeyhole } from 'lucide-react';
 import { useLocation, useNavigate } from 'react-router-dom';
-import { useAuthentication } from '../../hooks/useAuthentication';
+import { SecondFactorChallenge, TotpEnrollment, useAuthentication } from '../../hooks/useAuthentication';
 import { toast } from 'sonner';
 import { LocalizedText } from '../../components/LocalizedText';
 import { LocalizationContext } from '../../provider/LocalizationContext';
 import { useTheme } from '../../provider/ThemeProvider';
+import { API_BACKEND_URL, IS_SSO_ENABLED } from '../../api';
 
 export const LoginView = () => {
     const { theme } = useTheme();
@@ -33,6 +34,39 @@ export const LoginView = () => {
 
     const usernameField = useRef<HTMLInputElement>(null);
     const passwordField = useRef<HTMLInputElement>(null);
+    const codeField = useRef<HTMLInputElement>(null);
+
+    // the second step of the login (if the user has or needs a second factor)
+    const [challenge, setChallenge] = useState<SecondFactorChallenge | null>(null);
+    const [enrollment, setEnrollment] = useState<TotpEnrollment | null>(null);
+    const [recoveryCodes, setRecoveryCodes] = useState<string[] | null>(null);
+
+    const showFailedLogin = () =>
+        toast.error(localizationContext.translate('login.alerts.failed_login.message'), {
+            duration: 6000,
+        });
+
+    // a login through the single sign-on which needs a second factor returns with the challenge in the fragment
+    useEffect(() => {
+        const fragment = new URLSearchParams((state?.from?.hash || location.hash).replace(/^#/, ''));
+        const externalChallenge = fragment.get('challenge');
+        if (externalChallenge) {
+            setChallenge({ challenge: externalChallenge, enrollmentRequired: fragment.get('enrollmentRequired') === 'true' });
+        }
+    }, []);
+
+    // users who have to set up a second factor get the secret for their authenticator app first
+    useEffect(() => {
+        if (!challenge?.enrollmentRequired) {
+            return;
+        }
+        auth.startSecondFactorEnrollment(challenge)
+            .then((newEnrollment) => setEnrollment(newEnrollment))
+            .catch(() => {
+                setChallenge(null);
+                showFailedLogin();
+            });
+    }, [challenge]);
 
     const requestAuthorizationToken = (event: FormEvent) => {
         // ensure that we do not handle the actual submit event anymore
@@ -51,10 +85,7 @@ export const LoginView = () => {
                 // user experience.
                 navigate(from, { replace: true });
             },
-            () =>
-                toast.error(localizationContext.translate('login.alerts.failed_login.message'), {
-                    duration: 6000,
-                }),
+            showFailedLogin,
             (waitTime: number) => {
                 // Rate limit hit - show user how long to wait
                 const waitSeconds = Math.ceil(waitTime / 1000);
@@ -62,7 +93,33 @@ export const LoginView = () => {
                 toast.warning(rateLimitMessage, {
                     duration: 10000,
                 });
-            }
+            },
+            (newChallenge: SecondFactorChallenge) => setChallenge(newChallenge)
+        );
+    };
+
+    const verifySecondFactor = (event: FormEvent) => {
+        event.preventDefault();
+        if (!challenge) {
+            return;
+        }
+
+        auth.verifySecondFactor(
+            challenge,
+            codeField?.current?.value || '',
+            (newRecoveryCodes?: string[]) => {
+                // the recovery codes of a new second factor are just shown once, so the user has to
+                // confirm that they were stored before we continue
+                if (newRecoveryCodes) {
+                    setRecoveryCodes(newRecoveryCodes);
+                    return;
+                }
+                navigate(from, { replace: true });
+            },
+            () =>
+                toast.error(localizationContext.translate('login.alerts.failed_second_factor.message'), {
+                    duration: 6000,
+                })
         );
     };
 
@@ -96,7 +153,46 @@ export const LoginView = () => {
                                 <LocalizedText translationKey={'login.headlines.signin'} />
                             </h1>
                         </div>
-                        <form noValidate onSubmit={requestAuthorizationToken} className="space-y-4">
+                        {recoveryCodes && (
+                            <div className="space-y-4">
+                                <p className="text-sm text-muted-foreground">
+                                    <LocalizedText translationKey={'login.second_factor.recovery_codes_hint'} />
+                                </p>
+                                <ul className="grid grid-cols-2 gap-2 font-mono text-sm">
+                                    {recoveryCodes.map((recoveryCode) => (
+                                        <li key={recoveryCode}>{recoveryCode}</li>
+                                    ))}
+                                </ul>
+                                <Button type="button" variant="default" className="w-full mt-6" onClick={() => navigate(from, { replace: true })}>
+                                    <LocalizedText translationKey={'login.second_factor.continue_button'} />
+                                </Button>
+                            </div>
+                        )}
+                        {challenge && !recoveryCodes && (
+                            <form noValidate onSubmit={verifySecondFactor} className="space-y-4">
+                                {challenge.enrollmentRequired && enrollment && (
+                                    <div className="space-y-2 text-sm">
+                                        <p className="text-muted-foreground">
+                                            <LocalizedText translationKey={'login.second_factor.enrollment_hint'} />
+                                        </p>
+                                        <p className="font-mono break-all">{enrollment.secret}</p>
+                                        <a href={enrollment.provisioningUri} className="underline">
+                                            <LocalizedText translationKey={'login.second_factor.open_authenticator_link'} />
+                                        </a>
+                                    </div>
+                                )}
+                                <div className="space-y-2">
+                                    <Label htmlFor="code">
+                                        <LocalizedText translationKey={'login.second_factor.code_field_label'} /> *
+                                    </Label>
+                                    <Input id="code" name="code" type="text" required autoComplete="one-time-code" autoFocus ref={codeField} className="w-full" />
+                                </div>
+                                <Button type="submit" variant="default" className="w-full mt-6">
+                                    <LocalizedText translationKey={'login.second_factor.verify_button'} />
+                                </Button>
+                            </form>
+                        )}
+                        <form noValidate onSubmit={requestAuthorizationToken} className={challenge ? 'hidden' : 'space-y-4'}>
                             <div className="space-y-2">
                                 <Label htmlFor="username">
                                     <LocalizedText translationKey={'login.form.username_field_label'} /> *
@@ -113,6 +209,13 @@ export const LoginView = () => {
                                 <LocalizedText translationKey={'login.form.sign_in_button'} />
                             </Button>
                         </form>
+                        {IS_SSO_ENABLED && !challenge && (
+                            <Button asChild variant="outline" className="w-full mt-4">
+                                <a href={`${API_BACKEND_URL}/auth/oidc/login`}>
+                                    <LocalizedText translationKey={'login.form.sso_button'} />
+                                </a>
+                            </Button>
+                        )}
                     </CardContent>
                 </Card>
             </div>
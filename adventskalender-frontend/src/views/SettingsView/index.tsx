@@ -11,9 +11,11 @@ import { PasswordNotChangedDialog } from '../../dialogs/PasswordNotChangedDialog
 import { PasswordStrengthMeter } from '../../components/PasswordStrengthMeter';
 import { cn } from '@/lib/utils';
 import zxcvbn from 'zxcvbn';
+import { useAuthentication } from '../../hooks/useAuthentication';
 
 export const SettingsView = () => {
     const localizationContext = useContext(LocalizationContext);
+    const auth = useAuthentication();
     const [firstPassword, setFirstPassword] = useState<string>('');
     const [secondPassword, setSecondPassword] = useState<string>('');
     const [isPasswordChangedDialogOpen, setIsPasswordChangedDialogOpen] = useState<boolean>(false);
@@ -75,6 +77,7 @@ export const SettingsView = () => {
                 return response;
             })
             .then(() => {
+                auth.passwordChanged();
                 setIsPasswordChangedDialogOpen(true);
             })
             .catch(() => {
@@ -1,5 +1,10 @@
 /// <reference types="vite/client" />
 
+interface ImportMetaEnv {
+    readonly VITE_RAFFLE_ID?: string;
+    readonly VITE_SSO_ENABLED?: string;
+}
+
 declare const __BUILD_DATE__: string;
 
 interface WinnerInformation2 {
@@ -7,6 +12,7 @@ interface WinnerInformation2 {
     first_name: string;
     last_name: string;
     present_identifier?: string | null;
+    present_id?: number | null;
 }
 
 type StringMap = { [key: string]: string };